@@ -6,240 +6,43 @@ use crate::Reasoners::{
 };
 use clap::{Arg, Command};
 use colored::*;
-use phf::phf_map;
-use shapiro::models::datalog::{Atom, SugaredAtom, SugaredRule, Term, Ty, TypedValue};
+use shapiro::models::datalog::{SugaredAtom, SugaredProgram};
 use shapiro::models::index::{
     BTreeIndex, HashMapIndex, ImmutableVectorIndex, SpineIndex, VecIndex,
 };
-use shapiro::models::reasoner::{Diff, Materializer, UntypedRow};
+use shapiro::models::reasoner::{Diff, Materializer};
+use shapiro::models::schema::Schema;
+use shapiro::parsers::datalog::parse_program;
+use shapiro::parsers::loader::{
+    atom_to_diff, open_data_file, read_facts, FactParser, LubmParser, NQuadsParser,
+    NTriplesParser, SpaceSeparatedParser, TurtleParser,
+};
+use shapiro::parsers::rdf::Prefixes;
 use shapiro::reasoning::algorithms::constant_specialization::specialize_to_constants;
 use shapiro::reasoning::reasoners::chibi::ChibiDatalog;
 use shapiro::reasoning::reasoners::differential::DifferentialDatalog;
 use shapiro::reasoning::reasoners::relational::RelationalDatalog;
 use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-static OWL: phf::Map<&'static str, &'static str> = phf_map! {
-    "http://www.w3.org/1999/02/22-rdf-syntax-ns#type" => "rdf:type",
-    "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest" => "rdf:rest",
-    "http://www.w3.org/1999/02/22-rdf-syntax-ns#first" =>"rdf:first",
-    "http://www.w3.org/1999/02/22-rdf-syntax-ns#nil" =>"rdf:nil",
-    "http://www.w3.org/1999/02/22-rdf-syntax-ns#Property" =>"rdf:Property",
-    "http://www.w3.org/2000/01/rdf-schema#subClassOf" =>"rdfs:subClassOf",
-    "http://www.w3.org/2000/01/rdf-schema#subPropertyOf" =>"rdfs:subPropertyOf",
-    "http://www.w3.org/2000/01/rdf-schema#domain" =>"rdfs:domain",
-    "http://www.w3.org/2000/01/rdf-schema#range" =>"rdfs:range",
-    "http://www.w3.org/2000/01/rdf-schema#comment" =>"rdfs:comment",
-    "http://www.w3.org/2000/01/rdf-schema#label" =>"rdfs:label",
-    "http://www.w3.org/2000/01/rdf-schema#Literal" =>"rdfs:Literal",
-    "http://www.w3.org/2002/07/owl#TransitiveProperty" =>"owl:TransitiveProperty",
-    "http://www.w3.org/2002/07/owl#inverseOf" =>"owl:inverseOf",
-    "http://www.w3.org/2002/07/owl#Thing" =>"owl:Thing",
-    "http://www.w3.org/2002/07/owl#maxQualifiedCardinality" =>"owl:maxQualifiedCardinality",
-    "http://www.w3.org/2002/07/owl#someValuesFrom" =>"owl:someValuesFrom",
-    "http://www.w3.org/2002/07/owl#equivalentClass" =>"owl:equivalentClass",
-    "http://www.w3.org/2002/07/owl#intersectionOf" =>"owl:intersectionOf",
-    "http://www.w3.org/2002/07/owl#members" =>"owl:members",
-    "http://www.w3.org/2002/07/owl#equivalentProperty" =>"owl:equivalentProperty",
-    "http://www.w3.org/2002/07/owl#onProperty" =>"owl:onProperty",
-    "http://www.w3.org/2002/07/owl#propertyChainAxiom" =>"owl:propertyChainAxiom",
-    "http://www.w3.org/2002/07/owl#disjointWith" =>"owl:disjointWith",
-    "http://www.w3.org/2002/07/owl#propertyDisjointWith" =>"owl:propertyDisjointWith",
-    "http://www.w3.org/2002/07/owl#unionOf" =>"owl:unionOf",
-    "http://www.w3.org/2002/07/owl#hasKey" =>"owl:hasKey",
-    "http://www.w3.org/2002/07/owl#allValuesFrom" =>"owl:allValuesFrom",
-    "http://www.w3.org/2002/07/owl#complementOf" =>"owl:complementOf",
-    "http://www.w3.org/2002/07/owl#onClass" =>"owl:onClass",
-    "http://www.w3.org/2002/07/owl#distinctMembers" =>"owl:distinctMembers",
-    "http://www.w3.org/2002/07/owl#FunctionalProperty" =>"owl:FunctionalProperty",
-    "http://www.w3.org/2002/07/owl#NamedIndividual" =>"owl:NamedIndividual",
-    "http://www.w3.org/2002/07/owl#ObjectProperty" =>"owl:ObjectProperty",
-    "http://www.w3.org/2002/07/owl#Class" =>"owl:Class",
-    "http://www.w3.org/2002/07/owl#AllDisjointClasses" =>"owl:AllDisjointClasses",
-    "http://www.w3.org/2002/07/owl#Restriction" =>"owl:Restriction",
-    "http://www.w3.org/2002/07/owl#DatatypeProperty" =>"owl:DatatypeProperty",
-    "http://www.w3.org/2002/07/owl#Ontology" =>"owl:Ontology",
-    "http://www.w3.org/2002/07/owl#AsymmetricProperty" =>"owl:AsymmetricProperty",
-    "http://www.w3.org/2002/07/owl#SymmetricProperty" =>"owl:SymmetricProperty",
-    "http://www.w3.org/2002/07/owl#IrreflexiveProperty" =>"owl:IrreflexiveProperty",
-    "http://www.w3.org/2002/07/owl#AllDifferent" =>"owl:AllDifferent",
-    "http://www.w3.org/2002/07/owl#InverseFunctionalProperty" =>"owl:InverseFunctionalProperty",
-    "http://www.w3.org/2002/07/owl#sameAs" =>"owl:sameAs",
-    "http://www.w3.org/2002/07/owl#hasValue" =>"owl:hasValue",
-    "http://www.w3.org/2002/07/owl#Nothing" =>"owl:Nothing",
-    "http://www.w3.org/2002/07/owl#oneOf" =>"owl:oneOf",
-};
-
-trait SugaredAtomParser {
-    fn parse_line(&self, line: &str) -> Option<SugaredAtom>;
-}
-
-pub struct NTripleParser;
-
-impl SugaredAtomParser for NTripleParser {
-    fn parse_line(&self, line: &str) -> Option<SugaredAtom> {
-        let split_line: String = line
-            .replace("<", "")
-            .replace(">", "");
-
-        let clean_split_line: Vec<_> = split_line
-            .split_whitespace()
-            .filter(|c| !c.is_empty() && !(*c == "."))
-            .collect();
-
-        let mut digit_one = clean_split_line[0].clone();
-        let mut digit_two = clean_split_line[1].clone();
-        let mut digit_three = clean_split_line[2..].join(" ");
+use std::fs::read_to_string;
 
-        if let Some(alias) = OWL.get(&digit_one) {
-            digit_one = alias;
+fn read_fact_file(filename: &str, fact_parser: &mut dyn FactParser) -> Vec<SugaredAtom> {
+    return match open_data_file(filename).and_then(|reader| read_facts(reader, fact_parser)) {
+        Ok(facts) => facts,
+        Err(e) => {
+            panic!("{}", e)
         }
-        if let Some(alias) = OWL.get(&digit_two) {
-            digit_two = alias;
-        }
-        if let Some(alias) = OWL.get(&digit_three) {
-            digit_three = alias.to_string();
-        }
-
-        let terms = vec![
-            Term::Constant(TypedValue::Str(digit_one.to_string())),
-            Term::Constant(TypedValue::Str(digit_two.to_string())),
-            Term::Constant(TypedValue::Str(digit_three.to_string())),
-        ];
-
-        return Some(SugaredAtom {
-            terms,
-            symbol: "T".to_string(),
-            positive: true,
-        });
-    }
-}
-
-pub struct LUBMTboxSpecificParser;
-
-impl SugaredAtomParser for LUBMTboxSpecificParser {
-    fn parse_line(&self, line: &str) -> Option<SugaredAtom> {
-        let split_line: String = line
-            .replace("<", "")
-            .replace(">", "");
-
-        let clean_split_line: Vec<_> = split_line
-            .split_whitespace()
-            .filter(|c| !c.is_empty() && !(*c == "."))
-            .collect();
-
-        let mut digit_one = clean_split_line[0].clone();
-        let mut digit_two = clean_split_line[1].clone();
-        let mut digit_three = clean_split_line[2..].join(" ");
-
-        if let Some(alias) = OWL.get(&digit_one) {
-            digit_one = alias;
-        }
-        if let Some(alias) = OWL.get(&digit_two) {
-            digit_two = alias;
-        }
-        if let Some(alias) = OWL.get(&digit_three) {
-            digit_three = alias.to_string();
-        }
-
-        let mut atom = Some(SugaredAtom::default());
-        let prefix = "http://www.lehigh.edu/~zhp2/2004/0401/univ-bench.owl#";
-        match digit_two {
-            "rdf:type" => {
-                let mut sym = digit_three.to_string();
-                if let Some(prefix_striped_sym) = sym.strip_prefix(prefix) {
-                    sym = prefix_striped_sym.to_string()
-                }
-
-                atom = Some(SugaredAtom {
-                    terms: vec![Term::Constant(TypedValue::Str(digit_one.to_string()))],
-                    symbol: sym,
-                    positive: true,
-                });
-            }
-            possiblyProperty => {
-                if possiblyProperty.contains(prefix) {
-                    if let Some(property) = possiblyProperty.strip_prefix(prefix) {
-                        atom = Some(SugaredAtom {
-                            terms: vec![Term::Constant(TypedValue::Str(digit_one.to_string())), Term::Constant(TypedValue::Str(digit_three.to_string()))],
-                            symbol: property.to_string(),
-                            positive: true,
-                        });
-                    };
-                } else {
-                    atom = None
-                }
-            }
-        };
-
-        return atom
-    }
-}
-
-pub struct SpaceSepParser;
-
-impl SugaredAtomParser for SpaceSepParser {
-    fn parse_line(&self, line: &str) -> Option<SugaredAtom> {
-        let raw_terms: Vec<&str> = line.split([' ', '\t']).filter(|c| !c.is_empty()).collect();
-
-        if raw_terms.len() == 0 {
-            return None;
-        }
-
-        let symbol = raw_terms[raw_terms.len() - 1];
-
-        let terms = raw_terms[..raw_terms.len() - 1]
-            .into_iter()
-            .map(|term| Term::Constant(term.to_typed_value()))
-            .collect();
-
-        return Some(SugaredAtom {
-            terms,
-            symbol: symbol.to_string(),
-            positive: true,
-        });
-    }
-}
-
-struct Parser {
-    atom_parser: Box<dyn SugaredAtomParser>,
-}
-
-fn read_file(filename: &str) -> Result<impl Iterator<Item = String>, &'static str> {
-    return if let Ok(file) = File::open(filename) {
-        let buffer = BufReader::new(file);
-
-        Ok(buffer.lines().filter_map(|line| line.ok()))
-    } else {
-        Err("fail to open file")
     };
 }
 
-impl Parser {
-    fn read_fact_file(&self, filename: &str) -> impl Iterator<Item = SugaredAtom> + '_ {
-        match read_file(filename) {
-            Ok(file) => {
-                return file
-                    .into_iter()
-                    .filter_map(|line| self.atom_parser.parse_line(&line));
-            }
-            Err(e) => {
-                panic!("{}", e)
-            }
-        }
-    }
-    fn read_datalog_file(&self, filename: &str) -> impl Iterator<Item = SugaredRule> + '_ {
-        match read_file(filename) {
-            Ok(file) => {
-                return file
-                    .into_iter()
-                    .map(|line| SugaredRule::from(line.as_str()))
-            }
-            Err(e) => {
-                panic!("{}", e)
-            }
+fn read_datalog_file(filename: &str) -> (SugaredProgram, Vec<SugaredAtom>, Schema) {
+    match read_to_string(filename) {
+        Ok(document) => match parse_program(&document) {
+            Ok(program_facts_and_schema) => return program_facts_and_schema,
+            Err(e) => panic!("{}", e.render(&document)),
+        },
+        Err(e) => {
+            panic!("{}", e)
         }
     }
 }
@@ -280,7 +83,7 @@ fn main() {
     let matches = Command::new("shapiro-bencher")
         .version("0.7.0")
         .about(
-            "Benches the time taken to reason over relational space-separated facts or .nt files",
+            "Benches the time taken to reason over relational space-separated facts, or .nt, .nq or .ttl files, which may be zstd or gzip compressed",
         )
         .arg(
             Arg::new("DATA_PATH")
@@ -320,7 +123,7 @@ fn main() {
         )
         .arg(
             Arg::new("PARSER")
-                .help("Sets the parser, nt or free")
+                .help("Sets the parser, nt, nq, ttl, lubm or free")
                 .required(true)
                 .index(7),
         )
@@ -330,6 +133,13 @@ fn main() {
                 .required(true)
                 .index(8),
         )
+        .arg(
+            Arg::new("PREFIX")
+                .help("Abbreviates the IRIs of a namespace, as in ub=http://www.lehigh.edu/~zhp2/2004/0401/univ-bench.owl#")
+                .long("prefix")
+                .takes_value(true)
+                .multiple_occurrences(true),
+        )
         .get_matches();
 
     let data_path: String = matches.value_of("DATA_PATH").unwrap().to_string();
@@ -356,16 +166,34 @@ fn main() {
         .unwrap()
         .parse::<f64>()
         .unwrap();
-    let line_parser: Box<dyn SugaredAtomParser> = match matches.value_of("PARSER").unwrap() {
-        "nt" => Box::new(NTripleParser),
-        "lubm" => Box::new(LUBMTboxSpecificParser),
-        _ => Box::new(SpaceSepParser),
-    };
-    let parser = Parser {
-        atom_parser: line_parser,
+    let mut prefixes = Prefixes::default();
+    matches
+        .values_of("PREFIX")
+        .into_iter()
+        .flatten()
+        .for_each(|prefix| match prefix.split_once('=') {
+            Some((prefix, namespace)) => prefixes.insert(prefix, namespace),
+            None => panic!("prefixes are written as prefix=namespace: {}", prefix),
+        });
+    let mut fact_parser: Box<dyn FactParser> = match matches.value_of("PARSER").unwrap() {
+        "nt" => Box::new(NTriplesParser::new(prefixes)),
+        "nq" => Box::new(NQuadsParser::new(prefixes)),
+        "ttl" => Box::new(TurtleParser::new(prefixes)),
+        "lubm" => Box::new(LubmParser),
+        _ => Box::new(SpaceSeparatedParser),
     };
 
-    let facts: Vec<SugaredAtom> = parser.read_fact_file(&data_path).collect();
+    let (mut sugared_program, program_facts, schema) = read_datalog_file(&program_path);
+    let facts: Vec<SugaredAtom> = program_facts
+        .into_iter()
+        .chain(read_fact_file(&data_path, fact_parser.as_mut()))
+        .collect();
+    // Facts of the program document are already checked by the parser, but not the data file's
+    facts.iter().for_each(|fact| {
+        if let Err(e) = schema.check_atom(fact) {
+            panic!("{}", e)
+        }
+    });
     let cutoff: usize = (facts.len() as f64 * batch_size) as usize;
 
     let batch_size: usize = {
@@ -381,7 +209,6 @@ fn main() {
     //     data_path, program_path, parallel, intern, reasoner, batch_size
     // );
 
-    let mut sugared_program = parser.read_datalog_file(&program_path).collect();
     if specialize {
         sugared_program = specialize_to_constants(&sugared_program);
     }
@@ -391,39 +218,19 @@ fn main() {
     let mut negative_update: Vec<Diff> = vec![];
 
     facts.iter().enumerate().for_each(|(idx, atom)| {
-        let sym = atom.symbol.as_str();
-        let terms: UntypedRow = atom
-            .terms
-            .iter()
-            .map(|term| match term {
-                Term::Constant(inner) => return inner.clone().into(),
-                _ => unreachable!(),
-            })
-            .collect();
-
         if idx < batch_size {
-            initial_materialization.push((true, (sym, terms)))
+            initial_materialization.push(atom_to_diff(true, atom))
         } else {
-            positive_update.push((true, (sym, terms)));
-
-            let negative_terms: UntypedRow = atom
-                .terms
-                .iter()
-                .map(|term| match term {
-                    Term::Constant(inner) => return inner.clone().into(),
-                    _ => unreachable!(),
-                })
-                .collect();
-
-            negative_update.push((false, (sym, negative_terms)));
+            positive_update.push(atom_to_diff(true, atom));
+            negative_update.push(atom_to_diff(false, atom));
         }
     });
 
     let maybeboxmaterializer: Option<Box<dyn Materializer>> = match reasoner {
         Chibi => Some(Box::new(ChibiDatalog::new(parallel, intern, false))),
         ChibiIndexed => Some(Box::new(ChibiDatalog::new(parallel, intern, true))),
-        Differential => Some(Box::new(DifferentialDatalog::new(parallel, false))),
-        DifferentialIndexed => Some(Box::new(DifferentialDatalog::new(parallel, true))),
+        Differential => Some(Box::new(DifferentialDatalog::new(parallel, intern, false))),
+        DifferentialIndexed => Some(Box::new(DifferentialDatalog::new(parallel, intern, true))),
         RelationalHashMap => Some(Box::new(RelationalDatalog::<HashMapIndex>::new(parallel, intern))),
         RelationalBTree => Some(Box::new(RelationalDatalog::<BTreeIndex>::new(parallel, intern))),
         RelationalVec => Some(Box::new(RelationalDatalog::<VecIndex>::new(parallel, intern))),
@@ -435,7 +242,9 @@ fn main() {
     };
     match maybeboxmaterializer {
         Some(mut evaluator) => {
-            evaluator.materialize(&sugared_program);
+            if let Err(e) = evaluator.materialize(&sugared_program) {
+                panic!("{}", e)
+            }
             //println!("{}", "Initial materialization".purple());
             evaluator.update(initial_materialization);
             println!("triples: {}", evaluator.triple_count());
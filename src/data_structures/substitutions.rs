@@ -1,18 +1,18 @@
 use crate::models::datalog::TypedValue;
 use std::fmt::{Debug, Display, Formatter};
 
-pub type Substitution = (u8, TypedValue);
+pub type Substitution = (u32, TypedValue);
 
 #[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub struct Substitutions {
-    pub inner: Vec<(u8, TypedValue)>,
+    pub inner: Vec<(u32, TypedValue)>,
 }
 
 impl Substitutions {
     pub fn new() -> Self {
         return Self { inner: Vec::new() };
     }
-    pub fn get(&self, key: u8) -> Option<&TypedValue> {
+    pub fn get(&self, key: u32) -> Option<&TypedValue> {
         for sub in &self.inner {
             if sub.0 == key {
                 return Some(&sub.1);
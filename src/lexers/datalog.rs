@@ -1,17 +1,54 @@
-use logos::Logos;
+use logos::{Lexer, Logos};
 use ordered_float::OrderedFloat;
 
+// The contents of a quoted string, without its quotes, and with its escapes resolved
+fn unescape<'a>(lex: &mut Lexer<'a, DatalogToken<'a>>) -> Option<String> {
+    let slice = lex.slice();
+    let mut unescaped = String::with_capacity(slice.len());
+    let mut chars = slice[1..slice.len() - 1].chars();
+
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            unescaped.push(char);
+            continue;
+        }
+        match chars.next()? {
+            '"' => unescaped.push('"'),
+            '\\' => unescaped.push('\\'),
+            'n' => unescaped.push('\n'),
+            't' => unescaped.push('\t'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+
+    return Some(unescaped);
+}
+
+fn unbracket<'a>(lex: &mut Lexer<'a, DatalogToken<'a>>) -> &'a str {
+    let slice = lex.slice();
+
+    return &slice[1..slice.len() - 1];
+}
+
 #[derive(Logos, Debug, PartialEq, Hash, Eq, Clone)]
 pub enum DatalogToken<'a> {
     #[regex(r"\?[A-Za-z0-9]+")]
     Variable(&'a str),
     #[regex(r"[0-9]+", |lex| lex.slice().parse())]
-    UIntConst(u32),
-    #[regex(r"[A-Za-z:+\-_]+")]
+    UIntConst(u64),
+    // Digits are only allowed after a leading letter, such that "-4" is still an operator and a number
+    #[regex(r"[A-Za-z:+\-_]+|[A-Za-z_][A-Za-z0-9:+\-_]*")]
     Str(&'a str),
+    #[regex(r#""([^"\\]|\\.)*""#, unescape)]
+    QuotedStr(String),
+    // Angle brackets are not part of the IRI, just like when reading N-Triples
+    #[regex(r#"<[^<>"{}|^`\\\x00-\x20]*>"#, unbracket)]
+    Iri(&'a str),
     #[regex(r"(true|false)", |lex| lex.slice().parse())]
     BoolConst(bool),
-    #[regex(r"[-]?[0-9]*(\.[0-9]+)", |lex| lex.slice().parse())]
+    // Numbers are lexed without their sign, which the parser reads as unary minus
+    #[regex(r"[0-9]*(\.[0-9]+)", |lex| lex.slice().parse())]
     FloatConst(OrderedFloat<f64>),
     #[token("!")]
     Negation,
@@ -27,8 +64,28 @@ pub enum DatalogToken<'a> {
     RParen,
     #[token(",")]
     Comma,
+    #[token(".")]
+    Dot,
+    // "+" and "-" are already valid symbols, hence they arrive as Str
+    #[token("<")]
+    LessThan,
+    #[token("<=")]
+    LessThanOrEqual,
+    #[token(">")]
+    GreaterThan,
+    #[token(">=")]
+    GreaterThanOrEqual,
+    #[token("=")]
+    Equal,
+    #[token("!=")]
+    NotEqual,
+    #[token("*")]
+    Times,
+    #[token("/")]
+    Slash,
     #[error]
     #[regex(r"[ \t\n\f]+", logos::skip)]
+    #[regex(r"(%|//)[^\n]*", logos::skip)]
     Error,
 }
 
@@ -104,15 +161,72 @@ mod tests {
 
         assert_eq!(lex.next(), Some(DatalogToken::Comma));
 
+        assert_eq!(lex.next(), Some(DatalogToken::Str("-")));
         assert_eq!(
             lex.next(),
-            Some(DatalogToken::FloatConst(OrderedFloat(-4.1)))
+            Some(DatalogToken::FloatConst(OrderedFloat(4.1)))
         );
-        assert_eq!(lex.slice(), "-4.1");
+        assert_eq!(lex.slice(), "4.1");
 
         assert_eq!(lex.next(), Some(DatalogToken::Comma));
 
         assert_eq!(lex.next(), Some(DatalogToken::UIntConst(5)));
         assert_eq!(lex.slice(), "5");
     }
+
+    #[test]
+    fn test_lex_literals() {
+        let mut lex = DatalogToken::lexer(
+            r#"T(<http://example.org/#type>, "John \"Jr\" Smith", ub:Course10) <- [?x <= 2]"#,
+        );
+
+        assert_eq!(lex.next(), Some(DatalogToken::Str("T")));
+        assert_eq!(lex.next(), Some(DatalogToken::LParen));
+        assert_eq!(
+            lex.next(),
+            Some(DatalogToken::Iri("http://example.org/#type"))
+        );
+        assert_eq!(lex.next(), Some(DatalogToken::Comma));
+        assert_eq!(
+            lex.next(),
+            Some(DatalogToken::QuotedStr("John \"Jr\" Smith".to_string()))
+        );
+        assert_eq!(lex.next(), Some(DatalogToken::Comma));
+        assert_eq!(lex.next(), Some(DatalogToken::Str("ub:Course10")));
+        assert_eq!(lex.next(), Some(DatalogToken::RParen));
+        assert_eq!(lex.next(), Some(DatalogToken::HeadDirection));
+        assert_eq!(lex.next(), Some(DatalogToken::LBracket));
+        assert_eq!(lex.next(), Some(DatalogToken::Variable("?x")));
+        assert_eq!(lex.next(), Some(DatalogToken::LessThanOrEqual));
+        assert_eq!(lex.next(), Some(DatalogToken::UIntConst(2)));
+        assert_eq!(lex.next(), Some(DatalogToken::RBracket));
+
+        let mut lex = DatalogToken::lexer(r#""unknown \q escape""#);
+        assert_eq!(lex.next(), Some(DatalogToken::Error));
+    }
+
+    #[test]
+    fn test_lex_integers() {
+        let mut lex = DatalogToken::lexer("T(-5, 4294967296, -edge) <- [?x > -4.5]");
+
+        assert_eq!(lex.next(), Some(DatalogToken::Str("T")));
+        assert_eq!(lex.next(), Some(DatalogToken::LParen));
+        assert_eq!(lex.next(), Some(DatalogToken::Str("-")));
+        assert_eq!(lex.next(), Some(DatalogToken::UIntConst(5)));
+        assert_eq!(lex.next(), Some(DatalogToken::Comma));
+        assert_eq!(lex.next(), Some(DatalogToken::UIntConst(4294967296)));
+        assert_eq!(lex.next(), Some(DatalogToken::Comma));
+        assert_eq!(lex.next(), Some(DatalogToken::Str("-edge")));
+        assert_eq!(lex.next(), Some(DatalogToken::RParen));
+        assert_eq!(lex.next(), Some(DatalogToken::HeadDirection));
+        assert_eq!(lex.next(), Some(DatalogToken::LBracket));
+        assert_eq!(lex.next(), Some(DatalogToken::Variable("?x")));
+        assert_eq!(lex.next(), Some(DatalogToken::GreaterThan));
+        assert_eq!(lex.next(), Some(DatalogToken::Str("-")));
+        assert_eq!(
+            lex.next(),
+            Some(DatalogToken::FloatConst(OrderedFloat(4.5)))
+        );
+        assert_eq!(lex.next(), Some(DatalogToken::RBracket));
+    }
 }
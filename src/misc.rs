@@ -1,4 +1,6 @@
+pub mod export;
 pub mod helpers;
 pub mod joins;
 pub mod rule_graph;
 pub mod string_interning;
+pub mod validation;
@@ -0,0 +1,355 @@
+// Exports relations in a few line-based formats. Every row is written on its own line, and must
+// already be un-interned. Tabular formats start every line with the relation of the row, such that
+// several relations can be written at once.
+
+use crate::models::datalog::TypedValue;
+use crate::models::relational_algebra::Row;
+use crate::parsers::rdf::Prefixes;
+use phf::phf_map;
+use std::io::{Error, ErrorKind, Result, Write};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    // Unary rows are rdf:type triples, binary rows are triples with the relation as predicate and
+    // ternary rows are triples as they are. Prefixed names are expanded with the prefixes
+    NTriples(Prefixes),
+    Csv,
+    Tsv,
+    JsonLines,
+}
+
+// Expansions of the abbreviated IRIs that the parsers produce
+pub static OWL_INV: phf::Map<&'static str, &'static str> = phf_map! {
+    "rdf:type" => "<http://www.w3.org/1999/02/22-rdf-syntax-ns#type>",
+    "rdf:rest" => "<http://www.w3.org/1999/02/22-rdf-syntax-ns#rest>",
+    "rdf:first" => "<http://www.w3.org/1999/02/22-rdf-syntax-ns#first>",
+    "rdf:nil" => "<http://www.w3.org/1999/02/22-rdf-syntax-ns#nil>",
+    "rdf:Property" => "<http://www.w3.org/1999/02/22-rdf-syntax-ns#Property>",
+    "rdfs:subClassOf" => "<http://www.w3.org/2000/01/rdf-schema#subClassOf>",
+    "rdfs:subPropertyOf" => "<http://www.w3.org/2000/01/rdf-schema#subPropertyOf>",
+    "rdfs:domain" => "<http://www.w3.org/2000/01/rdf-schema#domain>",
+    "rdfs:range" => "<http://www.w3.org/2000/01/rdf-schema#range>",
+    "rdfs:comment" => "<http://www.w3.org/2000/01/rdf-schema#comment>",
+    "rdfs:label" => "<http://www.w3.org/2000/01/rdf-schema#label>",
+    "rdfs:Literal" => "<http://www.w3.org/2000/01/rdf-schema#Literal>",
+    "owl:TransitiveProperty" => "<http://www.w3.org/2002/07/owl#TransitiveProperty>",
+    "owl:inverseOf" => "<http://www.w3.org/2002/07/owl#inverseOf>",
+    "owl:Thing" => "<http://www.w3.org/2002/07/owl#Thing>",
+    "owl:maxQualifiedCardinality" => "<http://www.w3.org/2002/07/owl#maxQualifiedCardinality>",
+    "owl:maxCardinality" => "<http://www.w3.org/2002/07/owl#maxCardinality>",
+    "owl:someValuesFrom" => "<http://www.w3.org/2002/07/owl#someValuesFrom>",
+    "owl:equivalentClass" => "<http://www.w3.org/2002/07/owl#equivalentClass>",
+    "owl:intersectionOf" => "<http://www.w3.org/2002/07/owl#intersectionOf>",
+    "owl:members" => "<http://www.w3.org/2002/07/owl#members>",
+    "owl:equivalentProperty" => "<http://www.w3.org/2002/07/owl#equivalentProperty>",
+    "owl:onProperty" => "<http://www.w3.org/2002/07/owl#onProperty>",
+    "owl:propertyChainAxiom" => "<http://www.w3.org/2002/07/owl#propertyChainAxiom>",
+    "owl:disjointWith" => "<http://www.w3.org/2002/07/owl#disjointWith>",
+    "owl:propertyDisjointWith" => "<http://www.w3.org/2002/07/owl#propertyDisjointWith>",
+    "owl:unionOf" => "<http://www.w3.org/2002/07/owl#unionOf>",
+    "owl:hasKey" => "<http://www.w3.org/2002/07/owl#hasKey>",
+    "owl:allValuesFrom" => "<http://www.w3.org/2002/07/owl#allValuesFrom>",
+    "owl:complementOf" => "<http://www.w3.org/2002/07/owl#complementOf>",
+    "owl:onClass" => "<http://www.w3.org/2002/07/owl#onClass>",
+    "owl:distinctMembers" => "<http://www.w3.org/2002/07/owl#distinctMembers>",
+    "owl:FunctionalProperty" => "<http://www.w3.org/2002/07/owl#FunctionalProperty>",
+    "owl:NamedIndividual" => "<http://www.w3.org/2002/07/owl#NamedIndividual>",
+    "owl:ObjectProperty" => "<http://www.w3.org/2002/07/owl#ObjectProperty>",
+    "owl:Class" => "<http://www.w3.org/2002/07/owl#Class>",
+    "owl:AllDisjointClasses" => "<http://www.w3.org/2002/07/owl#AllDisjointClasses>",
+    "owl:Restriction" => "<http://www.w3.org/2002/07/owl#Restriction>",
+    "owl:DatatypeProperty" => "<http://www.w3.org/2002/07/owl#DatatypeProperty>",
+    "owl:Ontology" => "<http://www.w3.org/2002/07/owl#Ontology>",
+    "owl:AsymmetricProperty" => "<http://www.w3.org/2002/07/owl#AsymmetricProperty>",
+    "owl:SymmetricProperty" => "<http://www.w3.org/2002/07/owl#SymmetricProperty>",
+    "owl:IrreflexiveProperty" => "<http://www.w3.org/2002/07/owl#IrreflexiveProperty>",
+    "owl:AllDifferent" => "<http://www.w3.org/2002/07/owl#AllDifferent>",
+    "owl:InverseFunctionalProperty" => "<http://www.w3.org/2002/07/owl#InverseFunctionalProperty>",
+    "owl:sameAs" => "<http://www.w3.org/2002/07/owl#sameAs>",
+    "owl:hasValue" => "<http://www.w3.org/2002/07/owl#hasValue>",
+    "owl:Nothing" => "<http://www.w3.org/2002/07/owl#Nothing>",
+    "owl:oneOf" => "<http://www.w3.org/2002/07/owl#oneOf>",
+};
+
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+// Whether the string is an absolute IRI, a scheme followed by characters that IRIs may hold
+fn is_iri(string: &str) -> bool {
+    return match string.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+                && !string
+                    .chars()
+                    .any(|c| c.is_whitespace() || "<>\"{}|^`\\".contains(c))
+        }
+        None => false,
+    };
+}
+
+fn n_triples_literal(string: &str) -> String {
+    let mut literal = String::from("\"");
+    string.chars().for_each(|c| match c {
+        '"' => literal.push_str("\\\""),
+        '\\' => literal.push_str("\\\\"),
+        '\n' => literal.push_str("\\n"),
+        '\r' => literal.push_str("\\r"),
+        c => literal.push(c),
+    });
+    literal.push('"');
+
+    return literal;
+}
+
+// Strings that are neither abbreviated, nor absolute, IRIs, nor blank nodes, are literals
+fn rdf_term(typed_value: &TypedValue, prefixes: &Prefixes) -> String {
+    return match typed_value {
+        TypedValue::Str(inner) => {
+            if let Some(expansion) = OWL_INV.get(inner.as_str()) {
+                expansion.to_string()
+            // Literals with a language tag or datatype, and blank nodes, are kept as they were read
+            } else if inner.starts_with('"') || inner.starts_with("_:") {
+                inner.clone()
+            } else if let Some(iri) = prefixes.expand(inner) {
+                format!("<{}>", iri)
+            } else if is_iri(inner) {
+                format!("<{}>", inner)
+            } else {
+                n_triples_literal(inner)
+            }
+        }
+        TypedValue::Bool(inner) => format!("\"{}\"^^<{}boolean>", inner, XSD),
+        TypedValue::UInt(inner) => format!("\"{}\"^^<{}integer>", inner, XSD),
+        TypedValue::Int(inner) => format!("\"{}\"^^<{}integer>", inner, XSD),
+        TypedValue::UInt64(inner) => format!("\"{}\"^^<{}integer>", inner, XSD),
+        TypedValue::Float(inner) => format!("\"{}\"^^<{}double>", inner, XSD),
+        TypedValue::InternedStr(_) => unreachable!(),
+    };
+}
+
+fn plain_value(typed_value: &TypedValue) -> String {
+    return match typed_value {
+        TypedValue::Str(inner) => inner.clone(),
+        TypedValue::Bool(inner) => inner.to_string(),
+        TypedValue::UInt(inner) => inner.to_string(),
+        TypedValue::Int(inner) => inner.to_string(),
+        TypedValue::UInt64(inner) => inner.to_string(),
+        TypedValue::Float(inner) => inner.to_string(),
+        TypedValue::InternedStr(_) => unreachable!(),
+    };
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", field.replace('"', "\"\""));
+    }
+
+    return field.to_string();
+}
+
+fn tsv_field(field: &str) -> String {
+    return field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+}
+
+fn json_string(string: &str) -> String {
+    let mut json_string = String::from("\"");
+    string.chars().for_each(|c| match c {
+        '"' => json_string.push_str("\\\""),
+        '\\' => json_string.push_str("\\\\"),
+        '\n' => json_string.push_str("\\n"),
+        '\r' => json_string.push_str("\\r"),
+        '\t' => json_string.push_str("\\t"),
+        c if (c as u32) < 0x20 => json_string.push_str(&format!("\\u{:04x}", c as u32)),
+        c => json_string.push(c),
+    });
+    json_string.push('"');
+
+    return json_string;
+}
+
+fn json_value(typed_value: &TypedValue) -> String {
+    return match typed_value {
+        TypedValue::Str(inner) => json_string(inner),
+        // JSON has no infinities, nor NaN
+        TypedValue::Float(inner) if !inner.is_finite() => "null".to_string(),
+        not_str => plain_value(not_str),
+    };
+}
+
+pub fn export_row(
+    writer: &mut dyn Write,
+    format: &ExportFormat,
+    table: &str,
+    row: &[TypedValue],
+) -> Result<()> {
+    return match format {
+        ExportFormat::NTriples(prefixes) => {
+            let relation = TypedValue::Str(table.to_string());
+            let rdf_type = TypedValue::Str("rdf:type".to_string());
+            let (subject, predicate, object) = match row {
+                [subject] => (subject, &rdf_type, &relation),
+                [subject, object] => (subject, &relation, object),
+                [subject, predicate, object] => (subject, predicate, object),
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "{} has {} columns, while N-Triples only fits up to 3",
+                            table,
+                            row.len()
+                        ),
+                    ))
+                }
+            };
+
+            let (subject, predicate, object) = (
+                rdf_term(subject, prefixes),
+                rdf_term(predicate, prefixes),
+                rdf_term(object, prefixes),
+            );
+            if !subject.starts_with('<') && !subject.starts_with("_:") {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "{} is not an IRI, nor a blank node, hence no subject",
+                        subject
+                    ),
+                ));
+            }
+            if !predicate.starts_with('<') {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{} is not an IRI, hence no predicate", predicate),
+                ));
+            }
+
+            writeln!(writer, "{} {} {} .", subject, predicate, object)
+        }
+        ExportFormat::Csv => writeln!(
+            writer,
+            "{}",
+            std::iter::once(csv_field(table))
+                .chain(row.iter().map(|value| csv_field(&plain_value(value))))
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        ExportFormat::Tsv => writeln!(
+            writer,
+            "{}",
+            std::iter::once(tsv_field(table))
+                .chain(row.iter().map(|value| tsv_field(&plain_value(value))))
+                .collect::<Vec<_>>()
+                .join("\t")
+        ),
+        ExportFormat::JsonLines => writeln!(
+            writer,
+            "{{\"relation\":{},\"row\":[{}]}}",
+            json_string(table),
+            row.iter().map(json_value).collect::<Vec<_>>().join(",")
+        ),
+    };
+}
+
+pub fn export_rows<'a>(
+    writer: &mut dyn Write,
+    format: ExportFormat,
+    rows: impl Iterator<Item = (&'a str, Row)>,
+) -> Result<()> {
+    for (table, row) in rows {
+        export_row(writer, &format, table, &row)?;
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::misc::export::{export_row, ExportFormat};
+    use crate::models::datalog::TypedValue;
+    use crate::parsers::rdf::Prefixes;
+    use ordered_float::OrderedFloat;
+
+    fn export(format: ExportFormat, table: &str, row: &[TypedValue]) -> String {
+        let mut output = vec![];
+        export_row(&mut output, &format, table, row).unwrap();
+
+        return String::from_utf8(output).unwrap();
+    }
+
+    #[test]
+    fn test_export_row() {
+        let row = vec![
+            TypedValue::Str("say \"hi\", then\tleave".to_string()),
+            TypedValue::Int(-1),
+            TypedValue::Float(OrderedFloat(0.5)),
+            TypedValue::Bool(true),
+        ];
+
+        assert_eq!(
+            "said,\"say \"\"hi\"\", then\tleave\",-1,0.5,true\n",
+            export(ExportFormat::Csv, "said", &row)
+        );
+        assert_eq!(
+            "said\tsay \"hi\", then\\tleave\t-1\t0.5\ttrue\n",
+            export(ExportFormat::Tsv, "said", &row)
+        );
+        assert_eq!(
+            "{\"relation\":\"said\",\"row\":[\"say \\\"hi\\\", then\\tleave\",-1,0.5,true]}\n",
+            export(ExportFormat::JsonLines, "said", &row)
+        );
+    }
+
+    #[test]
+    fn test_export_n_triples() {
+        let mut prefixes = Prefixes::default();
+        prefixes.insert("ub", "http://swat.cse.lehigh.edu/onto/univ-bench.owl#");
+        let n_triples = ExportFormat::NTriples(prefixes);
+        let student = TypedValue::Str("http://example.org/alice".to_string());
+
+        assert_eq!(
+            "<http://example.org/alice> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://swat.cse.lehigh.edu/onto/univ-bench.owl#Student> .\n",
+            export(n_triples.clone(), "ub:Student", &[student.clone()])
+        );
+        assert_eq!(
+            "<http://example.org/alice> <http://swat.cse.lehigh.edu/onto/univ-bench.owl#age> \"30\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n",
+            export(n_triples.clone(), "ub:age", &[student.clone(), TypedValue::UInt(30)])
+        );
+        assert_eq!(
+            "<http://example.org/alice> <http://www.w3.org/2000/01/rdf-schema#label> \"Alice\"@en .\n",
+            export(
+                n_triples.clone(),
+                "T",
+                &[
+                    student.clone(),
+                    TypedValue::Str("rdfs:label".to_string()),
+                    TypedValue::Str("\"Alice\"@en".to_string())
+                ]
+            )
+        );
+        assert_eq!(
+            "<http://example.org/alice> <http://swat.cse.lehigh.edu/onto/univ-bench.owl#name> \"John \\\"Jack\\\" Smith\" .\n",
+            export(
+                n_triples.clone(),
+                "ub:name",
+                &[student.clone(), TypedValue::Str("John \"Jack\" Smith".to_string())]
+            )
+        );
+
+        let mut output = vec![];
+        assert!(export_row(&mut output, &n_triples, "T", &vec![student.clone(); 4]).is_err());
+        assert!(export_row(&mut output, &n_triples, "name", &[student.clone(), student]).is_err());
+        assert!(export_row(
+            &mut output,
+            &n_triples,
+            "ub:Student",
+            &[TypedValue::Str("John Smith".to_string())]
+        )
+        .is_err());
+    }
+}
@@ -74,3 +74,27 @@ pub fn idempotent_program_strong_intern(
 
     return interned_sugared_program;
 }
+
+// The position of every rule in the program, each matching a different one of its rules, or that of
+// the first rule that is not in it. Rules are compared once interned, since sugared rules that only
+// differ in their symbols or variables are equal.
+pub fn find_rules(program: &SugaredProgram, rules: &SugaredProgram) -> Result<Vec<usize>, usize> {
+    let mut interner = Interner::default();
+    let interned_program: Program = program
+        .iter()
+        .map(|rule| interner.intern_rule_weak(rule))
+        .collect();
+    let mut positions: Vec<usize> = vec![];
+
+    for (rule_idx, rule) in rules.iter().enumerate() {
+        let interned_rule = interner.intern_rule_weak(rule);
+        match (0..interned_program.len())
+            .find(|idx| interned_program[*idx] == interned_rule && !positions.contains(idx))
+        {
+            Some(idx) => positions.push(idx),
+            None => return Err(rule_idx),
+        }
+    }
+
+    return Ok(positions);
+}
@@ -1,31 +1,50 @@
 use std::collections::{HashMap, HashSet};
 
-use crate::models::datalog::SugaredRule;
+use crate::models::datalog::{SugaredProgram, SugaredRule};
 use petgraph::graphmap::DiGraphMap;
 use petgraph::prelude::GraphMap;
 use petgraph::{algo, Directed};
 
-type RuleGraph<'a> = GraphMap<&'a SugaredRule, bool, Directed>;
+// Rules are identified by their position in the program, since rules that only differ in their
+// symbols or variables are equal
+type RuleGraph = GraphMap<usize, bool, Directed>;
 
-pub fn generate_rule_dependency_graph<'a>(program: &Vec<SugaredRule>) -> RuleGraph {
+pub fn generate_rule_dependency_graph(program: &Vec<SugaredRule>) -> RuleGraph {
     let mut output = DiGraphMap::new();
-    let mut idb_relations = HashMap::new();
-    for rule in program {
-        idb_relations.insert(rule.clone().head.symbol, rule);
-        output.add_node(rule);
+    let mut idb_relations: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, rule) in program.iter().enumerate() {
+        idb_relations
+            .entry(rule.head.symbol.as_str())
+            .or_default()
+            .push(idx);
+        output.add_node(idx);
     }
-    for rule in program {
+    for (idx, rule) in program.iter().enumerate() {
         for body_atom in &rule.body {
-            if let Some(body_atom_rule) = idb_relations.get(&body_atom.symbol) {
-                output.add_edge(body_atom_rule, rule, body_atom.positive);
+            if let Some(body_atom_rules) = idb_relations.get(body_atom.symbol.as_str()) {
+                for body_atom_rule in body_atom_rules {
+                    output.add_edge(
+                        *body_atom_rule,
+                        idx,
+                        body_atom.positive && !rule.head.has_aggregates(),
+                    );
+                }
             }
         }
     }
     return output;
 }
 
-pub fn stratify<'a>(rule_graph: &'a RuleGraph) -> (bool, Vec<Vec<&'a SugaredRule>>) {
-    let sccs = algo::kosaraju_scc(&rule_graph);
+// Aggregating over a relation requires it to be complete, hence, just like negation, aggregates
+// can not be part of a recursive cycle.
+pub fn stratify<'a>(
+    program: &'a Vec<SugaredRule>,
+    rule_graph: &RuleGraph,
+) -> (bool, Vec<Vec<&'a SugaredRule>>) {
+    let sccs: Vec<Vec<&SugaredRule>> = algo::kosaraju_scc(&rule_graph)
+        .into_iter()
+        .map(|scc| scc.into_iter().map(|idx| &program[idx]).collect())
+        .collect();
     for scc in &sccs {
         let mut relations = HashSet::new();
         for rule in scc {
@@ -33,7 +52,9 @@ pub fn stratify<'a>(rule_graph: &'a RuleGraph) -> (bool, Vec<Vec<&'a SugaredRule
         }
         for rule in scc {
             for atom in &rule.body {
-                if relations.contains(&atom.symbol) && !atom.positive {
+                if relations.contains(&atom.symbol)
+                    && (!atom.positive || rule.head.has_aggregates())
+                {
                     return (false, sccs);
                 }
             }
@@ -42,41 +63,208 @@ pub fn stratify<'a>(rule_graph: &'a RuleGraph) -> (bool, Vec<Vec<&'a SugaredRule
     return (true, sccs);
 }
 
+// The strongly connected components of the rule dependency graph in topological order, such that
+// every rule comes after all of the rules that it depends on. Components that define the same
+// relation are merged into the last one of them, so that every relation is computed by exactly one.
 pub fn sort_program(program: &Vec<SugaredRule>) -> Vec<Vec<SugaredRule>> {
     let rule_graph = generate_rule_dependency_graph(&program);
-    let (_valid, stratification) = stratify(&rule_graph);
+    let (_valid, stratification) = stratify(program, &rule_graph);
 
-    return stratification
-        .into_iter()
-        .map(|sugared_program| {
-            sugared_program
-                .into_iter()
-                .map(|rule| rule.clone())
-                .collect()
-        })
+    let mut components: Vec<Vec<SugaredRule>> = vec![];
+    stratification.into_iter().rev().for_each(|scc| {
+        let relations: HashSet<String> = scc.iter().map(|rule| rule.head.symbol.clone()).collect();
+        let (shared, rest): (Vec<_>, Vec<_>) = components.drain(..).partition(|component| {
+            component
+                .iter()
+                .any(|rule| relations.contains(&rule.head.symbol))
+        });
+
+        let mut merged_component: Vec<SugaredRule> = shared.into_iter().flatten().collect();
+        merged_component.extend(scc.into_iter().map(|rule| rule.clone()));
+
+        components = rest;
+        components.push(merged_component);
+    });
+
+    return components;
+}
+
+// Splits the program into strata, such that every relation that is negated or aggregated over in a
+// stratum is fully computed by the preceding ones. Returns None if the program is not stratifiable.
+pub fn stratify_by_negation(program: &SugaredProgram) -> Option<Vec<SugaredProgram>> {
+    let rule_graph = generate_rule_dependency_graph(program);
+    let (valid, _sccs) = stratify(program, &rule_graph);
+    if !valid {
+        return None;
+    }
+
+    let mut relation_strata: HashMap<&str, usize> = program
+        .iter()
+        .map(|rule| (rule.head.symbol.as_str(), 0))
         .collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for rule in program {
+            let head_stratum = relation_strata[rule.head.symbol.as_str()];
+            let required_stratum = rule
+                .body
+                .iter()
+                .filter_map(|body_atom| {
+                    if let Some(body_stratum) = relation_strata.get(body_atom.symbol.as_str()) {
+                        if body_atom.positive && !rule.head.has_aggregates() {
+                            return Some(*body_stratum);
+                        }
+                        return Some(body_stratum + 1);
+                    }
+                    return None;
+                })
+                .max()
+                .unwrap_or(0);
+
+            if required_stratum > head_stratum {
+                relation_strata.insert(rule.head.symbol.as_str(), required_stratum);
+                changed = true;
+            }
+        }
+    }
+
+    let stratum_count = relation_strata
+        .values()
+        .max()
+        .map(|stratum| stratum + 1)
+        .unwrap_or(0);
+    let mut strata = vec![vec![]; stratum_count];
+    program.iter().for_each(|rule| {
+        strata[relation_strata[rule.head.symbol.as_str()]].push(rule.clone());
+    });
+
+    return Some(strata);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::misc::rule_graph::generate_rule_dependency_graph;
+    use crate::misc::rule_graph::{
+        generate_rule_dependency_graph, sort_program, stratify_by_negation,
+    };
     use crate::models::datalog::SugaredRule;
     use std::collections::HashSet;
 
     #[test]
     fn generate_rule_dependency_graph_test() {
-        let r_1 = SugaredRule::from("S() <- [r'_1(), !r()]");
-        let r_2 = SugaredRule::from("T() <- [r'_2(), !r()]");
-        let r_3 = SugaredRule::from("U() <- [r'_3(), !T()]");
-        let r_4 = SugaredRule::from("V() <- [r'_4(), !S(), !U()]");
+        let r_1 = SugaredRule::try_from("S() <- [s_base(), !r()]").unwrap();
+        let r_2 = SugaredRule::try_from("T() <- [t_base(), !r()]").unwrap();
+        let r_3 = SugaredRule::try_from("U() <- [u_base(), !T()]").unwrap();
+        let r_4 = SugaredRule::try_from("V() <- [v_base(), !S(), !U()]").unwrap();
 
         let not_recursive_program = vec![r_1.clone(), r_2.clone(), r_3.clone(), r_4.clone()];
 
         let graph = generate_rule_dependency_graph(&not_recursive_program);
-        let edges: HashSet<(&SugaredRule, &SugaredRule, &bool)> =
-            graph.all_edges().into_iter().collect();
+        let edges: HashSet<(&SugaredRule, &SugaredRule, &bool)> = graph
+            .all_edges()
+            .into_iter()
+            .map(|(from, to, positive)| {
+                (
+                    &not_recursive_program[from],
+                    &not_recursive_program[to],
+                    positive,
+                )
+            })
+            .collect();
         assert_eq!(edges.contains(&(&r_2, &r_3, &false)), true);
         assert_eq!(edges.contains(&(&r_3, &r_4, &false)), true);
         assert_eq!(edges.contains(&(&r_1, &r_4, &false)), true);
     }
+
+    #[test]
+    fn stratify_by_negation_test() {
+        let r_1 = SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap();
+        let r_2 = SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap();
+        let r_3 =
+            SugaredRule::try_from("unreachable(?x, ?y) <- [node(?x), node(?y), !reach(?x, ?y)]")
+                .unwrap();
+        let r_4 =
+            SugaredRule::try_from("isolated(?x) <- [node(?x), !unreachable(?x, ?x)]").unwrap();
+
+        let program = vec![r_4.clone(), r_3.clone(), r_2.clone(), r_1.clone()];
+
+        let expected_strata = vec![vec![r_2, r_1], vec![r_3], vec![r_4]];
+        assert_eq!(Some(expected_strata), stratify_by_negation(&program));
+
+        let not_stratifiable_program = vec![
+            SugaredRule::try_from("win(?x) <- [move(?x, ?y), !win(?y)]").unwrap(),
+            SugaredRule::try_from("lose(?x) <- [move(?x, ?y), !lose(?y)]").unwrap(),
+        ];
+
+        assert_eq!(None, stratify_by_negation(&not_stratifiable_program));
+    }
+
+    #[test]
+    fn stratify_aggregates_test() {
+        let r_1 = SugaredRule::try_from("deg(?x, count(?y)) <- [edge(?x, ?y)]").unwrap();
+        let r_2 = SugaredRule::try_from("hub(?x) <- [deg(?x, ?d), ?d > 2]").unwrap();
+        let r_3 = SugaredRule::try_from("busiest(max(?d)) <- [deg(?x, ?d)]").unwrap();
+
+        let program = vec![r_3.clone(), r_2.clone(), r_1.clone()];
+
+        let strata = stratify_by_negation(&program).unwrap();
+        assert_eq!(2, strata.len());
+        assert!(strata[0].contains(&r_1) && strata[0].contains(&r_2));
+        assert_eq!(vec![r_3], strata[1]);
+
+        let not_stratifiable_program = vec![
+            SugaredRule::try_from("size(?x, count(?y)) <- [member(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("member(?x, ?n) <- [size(?x, ?n)]").unwrap(),
+        ];
+
+        assert_eq!(None, stratify_by_negation(&not_stratifiable_program));
+    }
+
+    #[test]
+    fn sort_program_test() {
+        let r_1 = SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap();
+        let r_2 = SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap();
+        let r_3 =
+            SugaredRule::try_from("unreachable(?x, ?y) <- [node(?x), node(?y), !reach(?x, ?y)]")
+                .unwrap();
+        let r_4 =
+            SugaredRule::try_from("isolated(?x) <- [node(?x), !unreachable(?x, ?x)]").unwrap();
+        let r_5 = SugaredRule::try_from("even(?x) <- [succ(?y, ?x), odd(?y)]").unwrap();
+        let r_6 = SugaredRule::try_from("odd(?x) <- [succ(?y, ?x), even(?y)]").unwrap();
+
+        let program = vec![
+            r_4.clone(),
+            r_6.clone(),
+            r_3.clone(),
+            r_2.clone(),
+            r_5.clone(),
+            r_1.clone(),
+        ];
+
+        let components = sort_program(&program);
+        let position = |rule: &SugaredRule| {
+            components
+                .iter()
+                .position(|component| {
+                    component
+                        .iter()
+                        .any(|other| other.to_string() == rule.to_string())
+                })
+                .unwrap()
+        };
+
+        assert_eq!(4, components.len());
+        assert_eq!(position(&r_5), position(&r_6));
+        assert_eq!(position(&r_1), position(&r_2));
+        assert!(position(&r_2) < position(&r_3));
+        assert!(position(&r_3) < position(&r_4));
+
+        // Rules that only differ in their variables are still different rules
+        let same_shape_program = vec![
+            SugaredRule::try_from("hop(?x, ?z) <- [left(?x, ?y), right(?y, ?z)]").unwrap(),
+            SugaredRule::try_from("hop(?x, ?z) <- [left(?y, ?z), right(?x, ?y)]").unwrap(),
+        ];
+        assert_eq!(2, sort_program(&same_shape_program)[0].len());
+    }
 }
@@ -1,6 +1,7 @@
-use crate::models::datalog::{Atom, Rule, SugaredAtom, SugaredRule, Term, TypedValue};
+use crate::models::datalog::{Atom, Builtin, Rule, SugaredAtom, SugaredRule, Term, TypedValue};
 use crate::models::relational_algebra::Row;
-use lasso::Rodeo;
+use lasso::{Key, Rodeo, Spur};
+use std::num::NonZeroU32;
 
 #[derive(Clone)]
 pub struct Interner {
@@ -24,12 +25,12 @@ impl Interner {
             })
             .collect();
 
-        let relation_id = self.rodeo.get_or_intern(&sugared_atom.symbol).into_inner();
+        let relation_id = self.intern_symbol(&sugared_atom.symbol);
 
         return Atom {
             terms: new_terms,
             relation_id,
-            positive: true,
+            positive: sugared_atom.positive,
         };
     }
 
@@ -37,11 +38,20 @@ impl Interner {
     pub(crate) fn intern_atom_weak(&mut self, sugared_atom: &SugaredAtom) -> Atom {
         return Atom {
             terms: sugared_atom.terms.clone(),
-            relation_id: self.rodeo.get_or_intern(&sugared_atom.symbol).into_inner(),
-            positive: true,
+            relation_id: self.intern_symbol(&sugared_atom.symbol),
+            positive: sugared_atom.positive,
         };
     }
 
+    // Built-ins are never interned, as they have fixed relation ids
+    fn intern_symbol(&mut self, symbol: &str) -> NonZeroU32 {
+        if let Some(builtin) = Builtin::from_symbol(symbol) {
+            return builtin.relation_id();
+        }
+
+        return self.rodeo.get_or_intern(symbol).into_inner();
+    }
+
     pub(crate) fn intern_sugared_atom(&mut self, sugared_atom: &SugaredAtom) -> SugaredAtom {
         let new_terms = sugared_atom.terms.iter().map(|term| match term {
             Term::Constant(inner) => match inner {
@@ -56,7 +66,7 @@ impl Interner {
         return SugaredAtom {
             terms: new_terms.collect(),
             symbol: sugared_atom.symbol.clone(),
-            positive: true,
+            positive: sugared_atom.positive,
         };
     }
 
@@ -88,6 +98,41 @@ impl Interner {
             .collect();
     }
 
+    // Interns nothing new, hence it returns None if any constant was never interned.
+    pub(crate) fn try_intern_sugared_atom(
+        &self,
+        sugared_atom: &SugaredAtom,
+    ) -> Option<SugaredAtom> {
+        let new_terms = sugared_atom
+            .terms
+            .iter()
+            .map(|term| match term {
+                Term::Constant(TypedValue::Str(inner)) => self
+                    .rodeo
+                    .get(inner.as_str())
+                    .map(|spur| Term::Constant(TypedValue::InternedStr(spur.into_inner()))),
+                not_str => Some(not_str.clone()),
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        return Some(SugaredAtom {
+            terms: new_terms,
+            symbol: sugared_atom.symbol.clone(),
+            positive: sugared_atom.positive,
+        });
+    }
+
+    pub fn resolve_value(&self, typed_value: TypedValue) -> TypedValue {
+        return match typed_value {
+            TypedValue::InternedStr(inner) => TypedValue::Str(
+                self.rodeo
+                    .resolve(&Spur::try_from_usize(inner.get() as usize - 1).unwrap())
+                    .to_string(),
+            ),
+            not_interned_str => not_interned_str,
+        };
+    }
+
     pub(crate) fn intern_sugared_rule(&mut self, rule: &SugaredRule) -> SugaredRule {
         let mut new_rule: SugaredRule = Default::default();
         new_rule.head = self.intern_sugared_atom(&rule.head);
@@ -123,6 +168,37 @@ impl Interner {
 
         return new_rule;
     }
+
+    pub(crate) fn resolve_symbol(&self, relation_id: NonZeroU32) -> String {
+        if let Some(builtin) = Builtin::from_relation_id(relation_id) {
+            return builtin.symbol().to_string();
+        }
+
+        return self
+            .rodeo
+            .resolve(&Spur::try_from_usize(relation_id.get() as usize - 1).unwrap())
+            .to_string();
+    }
+
+    // The inverse of weak interning, hence the terms are left as they are
+    pub(crate) fn resolve_atom_weak(&self, atom: &Atom) -> SugaredAtom {
+        return SugaredAtom {
+            terms: atom.terms.clone(),
+            symbol: self.resolve_symbol(atom.relation_id),
+            positive: atom.positive,
+        };
+    }
+
+    pub(crate) fn resolve_rule_weak(&self, rule: &Rule) -> SugaredRule {
+        return SugaredRule {
+            head: self.resolve_atom_weak(&rule.head),
+            body: rule
+                .body
+                .iter()
+                .map(|body_atom| self.resolve_atom_weak(body_atom))
+                .collect(),
+        };
+    }
 }
 
 impl Default for Interner {
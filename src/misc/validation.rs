@@ -0,0 +1,407 @@
+// Checks that a program can be evaluated before any of it is, such that malformed rules are
+// reported instead of panicking halfway through a materialization.
+
+use crate::misc::rule_graph::stratify_by_negation;
+use crate::models::datalog::{Builtin, SugaredAtom, SugaredProgram, SugaredRule, Term, TypedValue};
+use crate::models::relational_algebra::fresh_variable_start;
+use crate::models::schema::ColumnType;
+use crate::reasoning::algorithms::counting::{CANDIDATE_PREFIX, DERIVATION_PREFIX};
+use crate::reasoning::algorithms::delete_rederive::{OVERDELETION_PREFIX, REDERIVATION_PREFIX};
+use crate::reasoning::algorithms::delta_rule_rewrite::DELTA_PREFIX;
+use crate::reasoning::algorithms::magic_sets::{ADORNMENT_PREFIX, MAGIC_PREFIX};
+use ahash::{HashMap, HashSet, HashSetExt};
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    // A head, or built-in, variable that no positive body atom binds
+    UnsafeVariable {
+        rule: String,
+        variable: u32,
+    },
+    // A variable of a negated atom that no positive body atom binds
+    NegatedOnlyVariable {
+        rule: String,
+        variable: u32,
+    },
+    ArityMismatch {
+        symbol: String,
+        expected: usize,
+        found: usize,
+    },
+    // Symbols starting with a prefix that maintenance uses for its own relations
+    ReservedPrefix {
+        rule: String,
+        symbol: String,
+    },
+    NotStratifiable,
+    Unsupported {
+        rule: String,
+        feature: &'static str,
+    },
+    // A row, or a constant of an atom, that does not fit the declared type of its column
+    TypeMismatch {
+        symbol: String,
+        column: String,
+        expected: ColumnType,
+        found: TypedValue,
+    },
+    ConflictingDeclaration {
+        symbol: String,
+    },
+    // More terms than there are fresh variables to rename them apart into relational algebra
+    TooManyTerms {
+        rule: String,
+    },
+    // A rule to unmaterialize that is not part of the materialized program
+    UnknownRule {
+        rule: String,
+    },
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::UnsafeVariable { rule, variable } => write!(
+                f,
+                "unsafe rule {}: ?{} does not occur in a positive body atom",
+                rule, variable
+            ),
+            ValidationError::NegatedOnlyVariable { rule, variable } => write!(
+                f,
+                "unsafe rule {}: ?{} only occurs in negated atoms",
+                rule, variable
+            ),
+            ValidationError::ArityMismatch {
+                symbol,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} is used with {} terms, but also with {}",
+                symbol, expected, found
+            ),
+            ValidationError::ReservedPrefix { rule, symbol } => {
+                write!(f, "rule {} uses the reserved symbol {}", rule, symbol)
+            }
+            ValidationError::NotStratifiable => write!(f, "the program is not stratifiable"),
+            ValidationError::Unsupported { rule, feature } => {
+                write!(f, "rule {} uses {}, which is not supported", rule, feature)
+            }
+            ValidationError::TypeMismatch {
+                symbol,
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "column {} of {} is declared as {}, but found {}",
+                column, symbol, expected, found
+            ),
+            ValidationError::ConflictingDeclaration { symbol } => {
+                write!(f, "{} is already declared differently", symbol)
+            }
+            ValidationError::TooManyTerms { rule } => {
+                write!(f, "rule {} has too many terms to be rewritten", rule)
+            }
+            ValidationError::UnknownRule { rule } => {
+                write!(f, "rule {} is not materialized", rule)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+fn variables(terms: &[Term]) -> impl Iterator<Item = u32> + '_ {
+    return terms.iter().filter_map(|term| match term {
+        Term::Variable(identifier) | Term::Aggregate(_, identifier) => Some(*identifier),
+        Term::Constant(_) => None,
+    });
+}
+
+fn is_bound(term: &Term, bound_variables: &HashSet<u32>) -> bool {
+    return match term {
+        Term::Variable(identifier) => bound_variables.contains(identifier),
+        _ => true,
+    };
+}
+
+fn check_arity(
+    atom: &SugaredAtom,
+    arities: &mut HashMap<String, usize>,
+) -> Result<(), ValidationError> {
+    let expected = match atom.builtin() {
+        Some(builtin) if builtin.is_arithmetic() => 3,
+        Some(_) => 2,
+        None => *arities
+            .entry(atom.symbol.clone())
+            .or_insert(atom.terms.len()),
+    };
+
+    if expected != atom.terms.len() {
+        return Err(ValidationError::ArityMismatch {
+            symbol: atom.symbol.clone(),
+            expected,
+            found: atom.terms.len(),
+        });
+    }
+
+    return Ok(());
+}
+
+// Built-ins are solved in the order they were written, after all positive atoms, such that they can
+// only rely on what those, or earlier built-ins, have bound.
+fn check_safety(rule: &SugaredRule) -> Result<(), ValidationError> {
+    let mut bound_variables: HashSet<u32> = HashSet::new();
+    rule.body
+        .iter()
+        .filter(|body_atom| body_atom.positive && !body_atom.is_builtin())
+        .for_each(|body_atom| bound_variables.extend(variables(&body_atom.terms)));
+
+    let unsafe_variable = |terms: &[Term], bound_variables: &HashSet<u32>| {
+        return variables(terms).find(|variable| !bound_variables.contains(variable));
+    };
+
+    for body_atom in rule.body.iter() {
+        let builtin = match body_atom.builtin() {
+            Some(builtin) => builtin,
+            None => continue,
+        };
+
+        let bound_term = match (builtin, body_atom.terms.as_slice()) {
+            (_, [left, right, Term::Variable(result)])
+                if builtin.is_arithmetic()
+                    && is_bound(left, &bound_variables)
+                    && is_bound(right, &bound_variables) =>
+            {
+                Some(*result)
+            }
+            (Builtin::Equal, [Term::Variable(unbound), other])
+            | (Builtin::Equal, [other, Term::Variable(unbound)])
+                if is_bound(other, &bound_variables) =>
+            {
+                Some(*unbound)
+            }
+            _ => None,
+        };
+
+        match bound_term {
+            Some(variable) if body_atom.positive => {
+                bound_variables.insert(variable);
+            }
+            _ => {
+                if let Some(variable) = unsafe_variable(&body_atom.terms, &bound_variables) {
+                    return Err(ValidationError::UnsafeVariable {
+                        rule: rule.to_string(),
+                        variable,
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some(variable) = unsafe_variable(&rule.head.terms, &bound_variables) {
+        return Err(ValidationError::UnsafeVariable {
+            rule: rule.to_string(),
+            variable,
+        });
+    }
+
+    for body_atom in rule
+        .body
+        .iter()
+        .filter(|body_atom| !body_atom.positive && !body_atom.is_builtin())
+    {
+        if let Some(variable) = unsafe_variable(&body_atom.terms, &bound_variables) {
+            return Err(ValidationError::NegatedOnlyVariable {
+                rule: rule.to_string(),
+                variable,
+            });
+        }
+    }
+
+    return Ok(());
+}
+
+// Returns the first error, going through the rules in order, and only then checks stratification
+pub fn validate_program(program: &SugaredProgram) -> Result<(), ValidationError> {
+    let mut arities: HashMap<String, usize> = Default::default();
+
+    for rule in program {
+        for atom in [&rule.head].into_iter().chain(rule.body.iter()) {
+            if !atom.is_builtin()
+                && [
+                    DELTA_PREFIX,
+                    OVERDELETION_PREFIX,
+                    REDERIVATION_PREFIX,
+                    DERIVATION_PREFIX,
+                    CANDIDATE_PREFIX,
+                    MAGIC_PREFIX,
+                    ADORNMENT_PREFIX,
+                ]
+                .iter()
+                .any(|prefix| atom.symbol.starts_with(prefix))
+            {
+                return Err(ValidationError::ReservedPrefix {
+                    rule: rule.to_string(),
+                    symbol: atom.symbol.clone(),
+                });
+            }
+            check_arity(atom, &mut arities)?;
+        }
+        check_safety(rule)?;
+        if fresh_variable_start(rule).is_none() {
+            return Err(ValidationError::TooManyTerms {
+                rule: rule.to_string(),
+            });
+        }
+    }
+
+    if stratify_by_negation(program).is_none() {
+        return Err(ValidationError::NotStratifiable);
+    }
+
+    return Ok(());
+}
+
+// Interned strings are only ever equal or not, hence a reasoner that interns can neither match
+// them, nor order them against a string constant. Ordering numbers is unaffected.
+pub fn validate_interned_builtins(program: &SugaredProgram) -> Result<(), ValidationError> {
+    for rule in program {
+        let orders_strings = rule.body.iter().any(|atom| match atom.builtin() {
+            Some(Builtin::StartsWith | Builtin::EndsWith) => true,
+            Some(
+                Builtin::LessThan
+                | Builtin::LessThanOrEqual
+                | Builtin::GreaterThan
+                | Builtin::GreaterThanOrEqual,
+            ) => atom
+                .terms
+                .iter()
+                .any(|term| matches!(term, Term::Constant(TypedValue::Str(_)))),
+            _ => false,
+        });
+
+        if orders_strings {
+            return Err(ValidationError::Unsupported {
+                rule: rule.to_string(),
+                feature: "ordering or matching interned strings",
+            });
+        }
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::misc::validation::{validate_interned_builtins, validate_program, ValidationError};
+    use crate::models::datalog::SugaredRule;
+
+    fn validate(rules: &[&str]) -> Result<(), ValidationError> {
+        let program = rules
+            .iter()
+            .map(|rule| SugaredRule::try_from(*rule).unwrap())
+            .collect();
+
+        return validate_program(&program);
+    }
+
+    #[test]
+    fn test_validate_program() {
+        assert_eq!(
+            Ok(()),
+            validate(&[
+                "reach(?x, ?y) <- [edge(?x, ?y)]",
+                "reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]",
+                "far(?x, ?z, ?d) <- [reach(?x, ?z), dist(?x, ?z, ?w), ?d = ?w * 2, ?d > 10]",
+                "lonely(?x) <- [node(?x), !reach(?x, ?x)]",
+                "deg(?x, count(?y)) <- [edge(?x, ?y)]",
+            ])
+        );
+
+        assert_eq!(
+            Err(ValidationError::UnsafeVariable {
+                rule: "reach(?0, ?1) <- [edge(?0, ?2)]".to_string(),
+                variable: 1
+            }),
+            validate(&["reach(?x, ?z) <- [edge(?x, ?y)]"])
+        );
+        assert_eq!(
+            Err(ValidationError::UnsafeVariable {
+                rule: "small(?0) <- [node(?0), ?1 < 10]".to_string(),
+                variable: 1
+            }),
+            validate(&["small(?x) <- [node(?x), ?y < 10]"])
+        );
+        assert_eq!(
+            Err(ValidationError::NegatedOnlyVariable {
+                rule: "source(?0) <- [node(?0), !edge(?1, ?0)]".to_string(),
+                variable: 1
+            }),
+            validate(&["source(?x) <- [node(?x), !edge(?y, ?x)]"])
+        );
+        assert_eq!(
+            Err(ValidationError::ArityMismatch {
+                symbol: "edge".to_string(),
+                expected: 2,
+                found: 3
+            }),
+            validate(&[
+                "reach(?x, ?y) <- [edge(?x, ?y)]",
+                "reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z, ?w)]",
+            ])
+        );
+        assert_eq!(
+            Err(ValidationError::ReservedPrefix {
+                rule: "reach(?0, ?1) <- [-edge(?0, ?1)]".to_string(),
+                symbol: "-edge".to_string()
+            }),
+            validate(&["reach(?x, ?y) <- [-edge(?x, ?y)]"])
+        );
+        assert_eq!(
+            Err(ValidationError::ReservedPrefix {
+                rule: "magic_reach(?0) <- [edge(?0, ?1)]".to_string(),
+                symbol: "magic_reach".to_string()
+            }),
+            validate(&["magic_reach(?x) <- [edge(?x, ?y)]"])
+        );
+        assert_eq!(
+            Err(ValidationError::NotStratifiable),
+            validate(&["win(?x) <- [move(?x, ?y), !win(?y)]"])
+        );
+    }
+
+    #[test]
+    fn test_validate_interned_builtins() {
+        let program = |rules: &[&str]| {
+            return rules
+                .iter()
+                .map(|rule| SugaredRule::try_from(*rule).unwrap())
+                .collect();
+        };
+
+        assert_eq!(
+            Ok(()),
+            validate_interned_builtins(&program(&[
+                "small(?x) <- [size(?x, ?s), ?s < 10]",
+                "other(?x, ?y) <- [node(?x), node(?y), ?x != alice]",
+            ]))
+        );
+        assert_eq!(
+            Err(ValidationError::Unsupported {
+                rule: "al(?0) <- [person(?0), starts_with(?0, al)]".to_string(),
+                feature: "ordering or matching interned strings"
+            }),
+            validate_interned_builtins(&program(&["al(?x) <- [person(?x), starts_with(?x, al)]"]))
+        );
+        assert_eq!(
+            Err(ValidationError::Unsupported {
+                rule: "early(?0) <- [person(?0), ?0 < m]".to_string(),
+                feature: "ordering or matching interned strings"
+            }),
+            validate_interned_builtins(&program(&["early(?x) <- [person(?x), ?x < m]"]))
+        );
+    }
+}
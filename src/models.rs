@@ -3,3 +3,4 @@ pub mod index;
 pub mod instance;
 pub mod reasoner;
 pub mod relational_algebra;
+pub mod schema;
@@ -1,14 +1,18 @@
+use crate::lexers::datalog::DatalogToken;
 use crate::misc::string_interning::Interner;
 use itertools::Itertools;
+use logos::Logos;
 use ordered_float::OrderedFloat;
+use std::cmp::Ordering;
 use std::fmt::{Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::num::NonZeroU32;
 
-use crate::parsers::datalog::{parse_sugared_atom, parse_sugared_rule};
+use crate::parsers::datalog::{parse_pattern, parse_sugared_atom, parse_sugared_rule, ParseError};
 
-// TypedValue are the allowed types in the datalog model. Not canonical.
-#[derive(Eq, PartialEq, Clone, Debug, Hash, PartialOrd, Ord)]
+// TypedValue are the allowed types in the datalog model. Not canonical, though the integer variants
+// are equal, hashed and ordered by value.
+#[derive(Clone, Debug)]
 pub enum TypedValue {
     Str(String),
     Bool(bool),
@@ -16,6 +20,90 @@ pub enum TypedValue {
     // Internal type, lives only inside the reasoner
     InternedStr(NonZeroU32),
     Float(OrderedFloat<f64>),
+    Int(i64),
+    UInt64(u64),
+}
+
+impl TypedValue {
+    // Integers are always kept in the narrowest variant that fits them, such that the same number
+    // is the same value, regardless of how it was written. Returns None if no variant fits it.
+    pub fn from_integer(integer: i128) -> Option<TypedValue> {
+        if let Ok(inner) = u32::try_from(integer) {
+            return Some(TypedValue::UInt(inner));
+        }
+        if let Ok(inner) = u64::try_from(integer) {
+            return Some(TypedValue::UInt64(inner));
+        }
+        return i64::try_from(integer).ok().map(TypedValue::Int);
+    }
+    pub fn as_integer(&self) -> Option<i128> {
+        return match self {
+            TypedValue::UInt(inner) => Some(*inner as i128),
+            TypedValue::Int(inner) => Some(*inner as i128),
+            TypedValue::UInt64(inner) => Some(*inner as i128),
+            _ => None,
+        };
+    }
+    // The position of the variant in the order, in which all integers share a position
+    fn rank(&self) -> u8 {
+        return match self {
+            TypedValue::Str(_) => 0,
+            TypedValue::Bool(_) => 1,
+            TypedValue::UInt(_) | TypedValue::Int(_) | TypedValue::UInt64(_) => 2,
+            TypedValue::InternedStr(_) => 3,
+            TypedValue::Float(_) => 4,
+        };
+    }
+}
+
+impl PartialEq for TypedValue {
+    fn eq(&self, other: &Self) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl Eq for TypedValue {}
+
+impl PartialOrd for TypedValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for TypedValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if let (Some(left_inner), Some(right_inner)) = (self.as_integer(), other.as_integer()) {
+            return left_inner.cmp(&right_inner);
+        }
+        return match (self, other) {
+            (TypedValue::Str(left_inner), TypedValue::Str(right_inner)) => {
+                left_inner.cmp(right_inner)
+            }
+            (TypedValue::Bool(left_inner), TypedValue::Bool(right_inner)) => {
+                left_inner.cmp(right_inner)
+            }
+            (TypedValue::InternedStr(left_inner), TypedValue::InternedStr(right_inner)) => {
+                left_inner.cmp(right_inner)
+            }
+            (TypedValue::Float(left_inner), TypedValue::Float(right_inner)) => {
+                left_inner.cmp(right_inner)
+            }
+            _ => self.rank().cmp(&other.rank()),
+        };
+    }
+}
+
+impl Hash for TypedValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            TypedValue::Str(inner) => inner.hash(state),
+            TypedValue::Bool(inner) => inner.hash(state),
+            TypedValue::InternedStr(inner) => inner.hash(state),
+            TypedValue::Float(inner) => inner.hash(state),
+            integer => integer.as_integer().hash(state),
+        }
+    }
 }
 
 impl TryInto<u32> for TypedValue {
@@ -30,6 +118,28 @@ impl TryInto<u32> for TypedValue {
     }
 }
 
+impl TryInto<i64> for TypedValue {
+    type Error = ();
+
+    fn try_into(self) -> Result<i64, Self::Error> {
+        match self.as_integer() {
+            Some(inner) => i64::try_from(inner).map_err(|_| ()),
+            None => Err(()),
+        }
+    }
+}
+
+impl TryInto<u64> for TypedValue {
+    type Error = ();
+
+    fn try_into(self) -> Result<u64, Self::Error> {
+        match self.as_integer() {
+            Some(inner) => u64::try_from(inner).map_err(|_| ()),
+            None => Err(()),
+        }
+    }
+}
+
 impl TryInto<String> for TypedValue {
     type Error = ();
 
@@ -70,6 +180,8 @@ impl Into<Box<dyn Ty>> for TypedValue {
             TypedValue::Bool(inner) => Box::new(inner),
             TypedValue::UInt(inner) => Box::new(inner),
             TypedValue::Float(inner) => Box::new(inner.into_inner()),
+            TypedValue::Int(inner) => Box::new(inner),
+            TypedValue::UInt64(inner) => Box::new(inner),
             _ => panic!("woopsie!"),
         };
     }
@@ -99,6 +211,24 @@ impl Ty for u32 {
     }
 }
 
+impl Ty for i32 {
+    fn to_typed_value(&self) -> TypedValue {
+        return TypedValue::from_integer(*self as i128).unwrap();
+    }
+}
+
+impl Ty for i64 {
+    fn to_typed_value(&self) -> TypedValue {
+        return TypedValue::from_integer(*self as i128).unwrap();
+    }
+}
+
+impl Ty for u64 {
+    fn to_typed_value(&self) -> TypedValue {
+        return TypedValue::from_integer(*self as i128).unwrap();
+    }
+}
+
 impl Ty for bool {
     fn to_typed_value(&self) -> TypedValue {
         return TypedValue::Bool(self.clone());
@@ -111,30 +241,111 @@ impl Ty for f64 {
     }
 }
 
+// Strings are written the way they would be lexed back: bare if they are a single symbol, in
+// angle brackets if they are an IRI, and quoted otherwise.
+fn write_str(f: &mut Formatter, inner: &str) -> std::fmt::Result {
+    let mut lexer = DatalogToken::lexer(inner);
+    if lexer.next() == Some(DatalogToken::Str(inner)) && lexer.next().is_none() {
+        return write!(f, "{}", inner);
+    }
+
+    let bracketed = format!("<{}>", inner);
+    let mut lexer = DatalogToken::lexer(&bracketed);
+    if inner.contains(':')
+        && lexer.next() == Some(DatalogToken::Iri(inner))
+        && lexer.next().is_none()
+    {
+        return write!(f, "{}", bracketed);
+    }
+
+    write!(f, "\"")?;
+    for char in inner.chars() {
+        match char {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            '\r' => write!(f, "\\r")?,
+            _ => write!(f, "{}", char)?,
+        }
+    }
+    return write!(f, "\"");
+}
+
 impl Display for TypedValue {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         match self {
-            TypedValue::Str(inner) => write!(f, "{}", inner),
+            TypedValue::Str(inner) => write_str(f, inner),
             TypedValue::Bool(inner) => write!(f, "{}", inner),
             TypedValue::UInt(inner) => write!(f, "{}", inner),
             TypedValue::Float(inner) => write!(f, "{}", inner),
+            TypedValue::Int(inner) => write!(f, "{}", inner),
+            TypedValue::UInt64(inner) => write!(f, "{}", inner),
             TypedValue::InternedStr(inner) => write!(f, "IStr{}", inner),
         }
     }
 }
 
-// A Term is either a Variable or a Constant
+// Aggregates summarize all bindings of a variable that share the rest of the head
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Hash, PartialOrd, Ord)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        return match symbol {
+            "count" => Some(Aggregate::Count),
+            "sum" => Some(Aggregate::Sum),
+            "min" => Some(Aggregate::Min),
+            "max" => Some(Aggregate::Max),
+            _ => None,
+        };
+    }
+    pub fn symbol(&self) -> &'static str {
+        return match self {
+            Aggregate::Count => "count",
+            Aggregate::Sum => "sum",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+        };
+    }
+    // Returns None if the values cannot be summed, or if there are none to pick from
+    pub fn apply(&self, values: &[TypedValue]) -> Option<TypedValue> {
+        return match self {
+            Aggregate::Count => Some(TypedValue::UInt(values.len() as u32)),
+            Aggregate::Sum => values.iter().try_fold(TypedValue::UInt(0), |sum, value| {
+                Builtin::Add.compute(&[sum, value.clone()])
+            }),
+            Aggregate::Min => values
+                .iter()
+                .min_by(|left, right| total_compare(left, right))
+                .cloned(),
+            Aggregate::Max => values
+                .iter()
+                .max_by(|left, right| total_compare(left, right))
+                .cloned(),
+        };
+    }
+}
+
+// A Term is either a Variable or a Constant, or, only in the head of a rule, an Aggregate over a
+// Variable
 #[derive(Eq, PartialEq, Clone, Debug, Hash, PartialOrd, Ord)]
 pub enum Term {
     Constant(TypedValue),
-    Variable(u8),
+    Variable(u32),
+    Aggregate(Aggregate, u32),
 }
 
 impl Into<TypedValue> for Term {
     fn into(self) -> TypedValue {
         match self {
             Term::Constant(constant) => constant,
-            Term::Variable(_name) => {
+            _ => {
                 panic!("cannot insert not-ground atom")
             }
         }
@@ -146,7 +357,190 @@ impl Display for Term {
         match self {
             Term::Constant(value) => write!(f, "{}", value),
             Term::Variable(value) => write!(f, "?{}", value),
+            Term::Aggregate(aggregate, value) => write!(f, "{}(?{})", aggregate.symbol(), value),
+        }
+    }
+}
+
+// Built-in predicates are atoms whose symbol is reserved, and that are computed rather than looked
+// up. Comparisons and string predicates take two terms, while arithmetic takes the two operands
+// followed by the result, such that ?z = ?x + 1 is +(?x, 1, ?z).
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Hash, PartialOrd, Ord)]
+pub enum Builtin {
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    Equal,
+    NotEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    StartsWith,
+    EndsWith,
+}
+
+const BUILTINS: [Builtin; 12] = [
+    Builtin::LessThan,
+    Builtin::LessThanOrEqual,
+    Builtin::GreaterThan,
+    Builtin::GreaterThanOrEqual,
+    Builtin::Equal,
+    Builtin::NotEqual,
+    Builtin::Add,
+    Builtin::Subtract,
+    Builtin::Multiply,
+    Builtin::Divide,
+    Builtin::StartsWith,
+    Builtin::EndsWith,
+];
+
+fn as_float(typed_value: &TypedValue) -> Option<f64> {
+    if let Some(integer) = typed_value.as_integer() {
+        return Some(integer as f64);
+    }
+    return match typed_value {
+        TypedValue::Float(inner) => Some(inner.into_inner()),
+        _ => None,
+    };
+}
+
+fn compare(left: &TypedValue, right: &TypedValue) -> Option<Ordering> {
+    if let (Some(left_inner), Some(right_inner)) = (left.as_integer(), right.as_integer()) {
+        return Some(left_inner.cmp(&right_inner));
+    }
+    return match (left, right) {
+        (TypedValue::Str(left_inner), TypedValue::Str(right_inner)) => {
+            Some(left_inner.cmp(right_inner))
+        }
+        (TypedValue::Bool(left_inner), TypedValue::Bool(right_inner)) => {
+            Some(left_inner.cmp(right_inner))
+        }
+        // Interned strings are only ever equal or not
+        (TypedValue::InternedStr(_), TypedValue::InternedStr(_)) => None,
+        _ => as_float(left)?.partial_cmp(&as_float(right)?),
+    };
+}
+
+// Numbers are ordered by value, and everything that is not comparable by the derived order
+fn total_compare(left: &TypedValue, right: &TypedValue) -> Ordering {
+    return compare(left, right).unwrap_or_else(|| left.cmp(right));
+}
+
+impl Builtin {
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        return BUILTINS
+            .iter()
+            .find(|builtin| builtin.symbol() == symbol)
+            .copied();
+    }
+    pub fn symbol(&self) -> &'static str {
+        return match self {
+            Builtin::LessThan => "<",
+            Builtin::LessThanOrEqual => "<=",
+            Builtin::GreaterThan => ">",
+            Builtin::GreaterThanOrEqual => ">=",
+            Builtin::Equal => "=",
+            Builtin::NotEqual => "!=",
+            Builtin::Add => "+",
+            Builtin::Subtract => "-",
+            Builtin::Multiply => "*",
+            Builtin::Divide => "/",
+            Builtin::StartsWith => "starts_with",
+            Builtin::EndsWith => "ends_with",
+        };
+    }
+    pub fn is_arithmetic(&self) -> bool {
+        return matches!(
+            self,
+            Builtin::Add | Builtin::Subtract | Builtin::Multiply | Builtin::Divide
+        );
+    }
+    pub fn is_infix(&self) -> bool {
+        return !matches!(self, Builtin::StartsWith | Builtin::EndsWith);
+    }
+    // Built-ins take the topmost relation ids, which the interner never reaches
+    pub fn relation_id(&self) -> NonZeroU32 {
+        return NonZeroU32::new(u32::MAX - *self as u32).unwrap();
+    }
+    pub fn from_relation_id(relation_id: NonZeroU32) -> Option<Self> {
+        return BUILTINS
+            .iter()
+            .find(|builtin| builtin.relation_id() == relation_id)
+            .copied();
+    }
+    // The result of an arithmetic built-in, or the value that an equality binds
+    pub fn compute(&self, arguments: &[TypedValue]) -> Option<TypedValue> {
+        return match (self, arguments) {
+            (Builtin::Equal, [value]) => Some(value.clone()),
+            (_, [left, right]) if left.as_integer().is_some() && right.as_integer().is_some() => {
+                let (left, right) = (left.as_integer()?, right.as_integer()?);
+                match self {
+                    Builtin::Add => left.checked_add(right),
+                    Builtin::Subtract => left.checked_sub(right),
+                    Builtin::Multiply => left.checked_mul(right),
+                    Builtin::Divide => left.checked_div(right),
+                    _ => None,
+                }
+                .and_then(TypedValue::from_integer)
+            }
+            (_, [left, right]) => {
+                let (left, right) = (as_float(left)?, as_float(right)?);
+                match self {
+                    Builtin::Add => Some(left + right),
+                    Builtin::Subtract => Some(left - right),
+                    Builtin::Multiply => Some(left * right),
+                    Builtin::Divide if right != 0.0 => Some(left / right),
+                    _ => None,
+                }
+                .map(|result| TypedValue::Float(OrderedFloat(result)))
+            }
+            _ => None,
+        };
+    }
+    pub fn holds(&self, arguments: &[TypedValue]) -> bool {
+        if self.is_arithmetic() {
+            return match arguments {
+                [left, right, result] => self
+                    .compute(&[left.clone(), right.clone()])
+                    .map_or(false, |computed| {
+                        Builtin::Equal.holds(&[computed, result.clone()])
+                    }),
+                _ => false,
+            };
         }
+
+        let (left, right) = match arguments {
+            [left, right] => (left, right),
+            _ => return false,
+        };
+        return match self {
+            Builtin::Equal => left == right || compare(left, right) == Some(Ordering::Equal),
+            Builtin::NotEqual => !Builtin::Equal.holds(arguments),
+            Builtin::LessThan => compare(left, right) == Some(Ordering::Less),
+            Builtin::LessThanOrEqual => {
+                matches!(compare(left, right), Some(Ordering::Less | Ordering::Equal))
+            }
+            Builtin::GreaterThan => compare(left, right) == Some(Ordering::Greater),
+            Builtin::GreaterThanOrEqual => {
+                matches!(
+                    compare(left, right),
+                    Some(Ordering::Greater | Ordering::Equal)
+                )
+            }
+            Builtin::StartsWith | Builtin::EndsWith => match (left, right) {
+                (TypedValue::Str(string), TypedValue::Str(affix)) => {
+                    if *self == Builtin::StartsWith {
+                        string.starts_with(affix.as_str())
+                    } else {
+                        string.ends_with(affix.as_str())
+                    }
+                }
+                _ => false,
+            },
+            _ => false,
+        };
     }
 }
 
@@ -161,7 +555,7 @@ pub struct SugaredAtom {
 }
 
 // An atom is equivalent to another, if and only if they both have the same arity and all of their
-// constants match in the same position
+// constants, and aggregates, match in the same position
 impl PartialEq for SugaredAtom {
     fn eq(&self, other: &Self) -> bool {
         if self.terms.len() != other.terms.len() {
@@ -175,9 +569,13 @@ impl PartialEq for SugaredAtom {
                         return false;
                     }
                 }
-                (Term::Variable(_), Term::Constant(_)) => return false,
-                (Term::Constant(_), Term::Variable(_)) => return false,
-                _ => (),
+                (Term::Aggregate(left_inner, _), Term::Aggregate(right_inner, _)) => {
+                    if left_inner != right_inner {
+                        return false;
+                    }
+                }
+                (Term::Variable(_), Term::Variable(_)) => (),
+                _ => return false,
             };
         }
 
@@ -187,14 +585,38 @@ impl PartialEq for SugaredAtom {
 
 impl Eq for SugaredAtom {}
 
-impl From<&str> for SugaredAtom {
-    fn from(str: &str) -> Self {
+impl TryFrom<&str> for SugaredAtom {
+    type Error = ParseError;
+
+    fn try_from(str: &str) -> Result<Self, Self::Error> {
         return parse_sugared_atom(str);
     }
 }
 
 impl Display for SugaredAtom {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let polarity = if self.positive { "" } else { "!" };
+        // Built-ins are written the same way that they are parsed
+        if let Some(builtin) = self.builtin() {
+            match self.terms.as_slice() {
+                [left, right, result] if builtin.is_arithmetic() => {
+                    return write!(
+                        f,
+                        "{}{} = {} {} {}",
+                        polarity,
+                        result,
+                        left,
+                        builtin.symbol(),
+                        right
+                    );
+                }
+                [left, right] if builtin.is_infix() && !builtin.is_arithmetic() => {
+                    return write!(f, "{}{} {} {}", polarity, left, builtin.symbol(), right);
+                }
+                _ => {}
+            }
+        }
+
         let terms: String = self
             .terms
             .clone()
@@ -204,22 +626,13 @@ impl Display for SugaredAtom {
             .join(", ");
         let atom_representation: String = format!("({})", terms);
 
-        write!(f, "{}{}", self.symbol, atom_representation)
+        write!(f, "{}{}{}", polarity, self.symbol, atom_representation)
     }
 }
 
 impl Debug for SugaredAtom {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let terms: String = self
-            .terms
-            .clone()
-            .into_iter()
-            .map(|term| term.to_string())
-            .collect::<Vec<String>>()
-            .join(", ");
-        let atom_representation: String = format!("({})", terms);
-
-        write!(f, "{}{}", self.symbol, atom_representation)
+        write!(f, "{}", self)
     }
 }
 
@@ -233,6 +646,21 @@ impl Default for SugaredAtom {
     }
 }
 
+impl SugaredAtom {
+    pub fn builtin(&self) -> Option<Builtin> {
+        return Builtin::from_symbol(&self.symbol);
+    }
+    pub fn is_builtin(&self) -> bool {
+        return self.builtin().is_some();
+    }
+    pub fn has_aggregates(&self) -> bool {
+        return self
+            .terms
+            .iter()
+            .any(|term| matches!(term, Term::Aggregate(..)));
+    }
+}
+
 impl Hash for SugaredAtom {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.symbol.hash(state);
@@ -261,8 +689,10 @@ impl PartialEq for SugaredRule {
 
 impl Eq for SugaredRule {}
 
-impl From<&str> for SugaredRule {
-    fn from(str: &str) -> Self {
+impl TryFrom<&str> for SugaredRule {
+    type Error = ParseError;
+
+    fn try_from(str: &str) -> Result<Self, Self::Error> {
         return parse_sugared_rule(str);
     }
 }
@@ -283,6 +713,43 @@ impl Default for SugaredRule {
     }
 }
 
+// A conjunction of atoms, such as [T(?x, rdf:type, ?c)], along with the names of its variables, by
+// identifier
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    pub atoms: Vec<SugaredAtom>,
+    pub variables: Vec<String>,
+}
+
+impl TryFrom<&str> for Pattern {
+    type Error = ParseError;
+
+    fn try_from(str: &str) -> Result<Self, Self::Error> {
+        return parse_pattern(str);
+    }
+}
+
+// Parsed atoms have lost the names of their variables, hence they are named as they are displayed
+impl From<Vec<SugaredAtom>> for Pattern {
+    fn from(atoms: Vec<SugaredAtom>) -> Self {
+        let variables = atoms
+            .iter()
+            .flat_map(|atom| atom.terms.iter())
+            .filter_map(|term| match term {
+                Term::Variable(identifier) => Some(*identifier),
+                Term::Constant(_) | Term::Aggregate(..) => None,
+            })
+            .max()
+            .map_or(vec![], |max_identifier| {
+                (0..=max_identifier)
+                    .map(|identifier| format!("?{}", identifier))
+                    .collect()
+            });
+
+        return Self { atoms, variables };
+    }
+}
+
 pub type Program = Vec<Rule>;
 
 // Used for computation.
@@ -303,8 +770,9 @@ impl Display for Atom {
             .collect::<Vec<String>>()
             .join(", ");
         let atom_representation: String = format!("({})", terms);
+        let polarity = if self.positive { "" } else { "!" };
 
-        write!(f, "{}{}", self.relation_id, atom_representation)
+        write!(f, "{}{}{}", polarity, self.relation_id, atom_representation)
     }
 }
 
@@ -330,10 +798,19 @@ impl Default for Atom {
 
 impl Atom {
     #[allow(dead_code)]
-    pub(crate) fn from_str_with_interner(str: &str, interner: &mut Interner) -> Self {
-        let sugared_atom = parse_sugared_atom(str);
+    pub(crate) fn from_str_with_interner(
+        str: &str,
+        interner: &mut Interner,
+    ) -> Result<Self, ParseError> {
+        let sugared_atom = parse_sugared_atom(str)?;
 
-        return interner.intern_atom_weak(&sugared_atom);
+        return Ok(interner.intern_atom_weak(&sugared_atom));
+    }
+    pub fn has_aggregates(&self) -> bool {
+        return self
+            .terms
+            .iter()
+            .any(|term| matches!(term, Term::Aggregate(..)));
     }
 }
 
@@ -361,21 +838,117 @@ impl Display for Rule {
 
 #[cfg(test)]
 mod tests {
-    use crate::models::datalog::{SugaredAtom, SugaredRule};
+    use crate::models::datalog::{Aggregate, Builtin, SugaredAtom, SugaredRule, Ty, TypedValue};
+    use ordered_float::OrderedFloat;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
 
     #[test]
     fn test_atom_eq() {
-        let left_atom = SugaredAtom::from("T_rdf:type(?x, ?y)");
-        let right_atom = SugaredAtom::from("T_rdf:type(?y, ?x)");
+        let left_atom = SugaredAtom::try_from("T_rdf:type(?x, ?y)").unwrap();
+        let right_atom = SugaredAtom::try_from("T_rdf:type(?y, ?x)").unwrap();
 
         assert_eq!(left_atom, right_atom)
     }
 
     #[test]
     fn test_rule_eq() {
-        let left_rule = SugaredRule::from("T_rdf:type(?x, ?y) <- [T(?x, rdf:type, ?y)]");
-        let right_rule = SugaredRule::from("T_rdf:type(?y, ?x) <- [T(?y, rdf:type, ?x)]");
+        let left_rule =
+            SugaredRule::try_from("T_rdf:type(?x, ?y) <- [T(?x, rdf:type, ?y)]").unwrap();
+        let right_rule =
+            SugaredRule::try_from("T_rdf:type(?y, ?x) <- [T(?y, rdf:type, ?x)]").unwrap();
 
         assert_eq!(left_rule, right_rule)
     }
+
+    #[test]
+    fn test_builtins() {
+        let one = TypedValue::UInt(1);
+        let two = TypedValue::UInt(2);
+        let half = TypedValue::Float(OrderedFloat(0.5));
+
+        assert!(Builtin::LessThan.holds(&[one.clone(), two.clone()]));
+        assert!(Builtin::GreaterThan.holds(&[one.clone(), half.clone()]));
+        assert!(Builtin::Equal.holds(&[one.clone(), TypedValue::Float(OrderedFloat(1.0))]));
+        assert!(Builtin::NotEqual.holds(&[one.clone(), TypedValue::Str("1".to_string())]));
+        assert!(Builtin::Add.holds(&[one.clone(), one.clone(), two.clone()]));
+        assert!(Builtin::StartsWith.holds(&[
+            TypedValue::Str("rdf:type".to_string()),
+            TypedValue::Str("rdf:".to_string())
+        ]));
+        assert!(!Builtin::LessThan.holds(&[
+            TypedValue::InternedStr(1.try_into().unwrap()),
+            TypedValue::InternedStr(2.try_into().unwrap())
+        ]));
+
+        assert_eq!(
+            Some(TypedValue::Float(OrderedFloat(1.5))),
+            Builtin::Add.compute(&[one.clone(), half])
+        );
+        assert_eq!(
+            Some(TypedValue::Int(-1)),
+            Builtin::Subtract.compute(&[one.clone(), two.clone()])
+        );
+        assert_eq!(None, Builtin::Divide.compute(&[two, TypedValue::UInt(0)]));
+
+        assert_eq!(
+            Some(Builtin::Multiply),
+            Builtin::from_relation_id(Builtin::Multiply.relation_id())
+        );
+    }
+
+    #[test]
+    fn test_integers() {
+        let minus_one = TypedValue::from_integer(-1).unwrap();
+        let one = TypedValue::from_integer(1).unwrap();
+        let big = TypedValue::from_integer(u32::MAX as i128 + 1).unwrap();
+
+        assert_eq!(TypedValue::Int(-1), minus_one);
+        assert_eq!(TypedValue::UInt(1), one);
+        assert_eq!(TypedValue::UInt64(4294967296), big);
+        assert_eq!(None, TypedValue::from_integer(u64::MAX as i128 + 1));
+        assert_eq!(one, 1i64.to_typed_value());
+        assert_eq!(big, 4294967296u64.to_typed_value());
+
+        assert!(Builtin::LessThan.holds(&[minus_one.clone(), one.clone()]));
+        assert!(Builtin::GreaterThan.holds(&[big.clone(), one.clone()]));
+        assert!(
+            Builtin::LessThan.holds(&[minus_one.clone(), TypedValue::Float(OrderedFloat(-0.5))])
+        );
+        assert!(Builtin::Add.holds(&[minus_one.clone(), one.clone(), TypedValue::UInt(0)]));
+        assert_eq!(
+            Some(TypedValue::UInt(u32::MAX)),
+            Builtin::Subtract.compute(&[big.clone(), one.clone()])
+        );
+        assert_eq!(
+            Some(TypedValue::Int(-4294967296)),
+            Builtin::Multiply.compute(&[minus_one.clone(), big.clone()])
+        );
+
+        let values = vec![big.clone(), one, minus_one.clone()];
+        assert_eq!(Some(minus_one), Aggregate::Min.apply(&values));
+        assert_eq!(Some(big), Aggregate::Max.apply(&values));
+        assert_eq!(
+            Some(TypedValue::UInt64(4294967296)),
+            Aggregate::Sum.apply(&values)
+        );
+
+        let hash = |value: &TypedValue| {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            return hasher.finish();
+        };
+        let variants = vec![
+            TypedValue::UInt(5),
+            TypedValue::Int(5),
+            TypedValue::UInt64(5),
+        ];
+        variants.iter().for_each(|variant| {
+            assert_eq!(TypedValue::UInt(5), *variant);
+            assert_eq!(hash(&TypedValue::UInt(5)), hash(variant));
+        });
+        assert!(TypedValue::Int(-1) < TypedValue::UInt(0));
+        assert!(TypedValue::UInt(7) < TypedValue::UInt64(4294967296));
+        assert_ne!(TypedValue::UInt(5), TypedValue::Float(OrderedFloat(5.0)));
+    }
 }
@@ -3,10 +3,12 @@ use indexmap::IndexSet;
 use lasso::{Key, Spur};
 use std::collections::HashMap;
 
+use crate::models::datalog::SugaredRule;
 use crate::models::index::IndexBacking;
 use crate::models::relational_algebra::{Container, Row};
 use crate::reasoning::algorithms::evaluation::{Empty, Set};
-use crate::reasoning::algorithms::relational_algebra::evaluate;
+use crate::reasoning::algorithms::provenance::DerivedRow;
+use crate::reasoning::algorithms::relational_algebra::{evaluate, evaluate_with_provenance};
 
 use super::relational_algebra::{RelationalExpression, SimpleRelationWithOneIndexBacking};
 
@@ -272,6 +274,14 @@ impl<T: IndexBacking + PartialEq> SimpleDatabaseWithIndex<T> {
     ) -> Option<SimpleRelationWithOneIndexBacking<T>> {
         return evaluate(expression, &self.storage, view_name);
     }
+    pub fn evaluate_with_provenance(
+        &self,
+        expression: &RelationalExpression,
+        view_name: &str,
+        rule: &SugaredRule,
+    ) -> Option<(SimpleRelationWithOneIndexBacking<T>, Vec<DerivedRow>)> {
+        return evaluate_with_provenance(expression, &self.storage, view_name, rule);
+    }
 }
 
 impl<T: IndexBacking + PartialEq> Default for SimpleDatabaseWithIndex<T> {
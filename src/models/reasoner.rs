@@ -1,51 +1,138 @@
-use crate::models::datalog::{SugaredProgram, SugaredRule, Ty, TypedValue};
+use crate::misc::export::ExportFormat;
+use crate::misc::validation::ValidationError;
+use crate::models::datalog::{Pattern, SugaredProgram, SugaredRule, Ty, TypedValue};
 use crate::models::relational_algebra::Row;
+use crate::models::schema::{RelationDeclaration, Schema};
+use crate::reasoning::algorithms::counting::MaintenanceStrategy;
+use crate::reasoning::algorithms::provenance::DerivationTree;
+use crate::reasoning::algorithms::subscriptions::ChangeFeed;
 use ahash::HashMap;
 use indexmap::IndexSet;
+use std::io::Write;
 
 pub type UntypedRow = Vec<Box<dyn Ty>>;
 
 // General API
 pub trait Dynamic {
-    // Inserts data
+    // Inserts data, panicking if it does not fit the declaration of its relation
     fn insert(&mut self, table: &str, row: UntypedRow);
+    // Inserts data, unless it does not fit the declaration of its relation
+    fn try_insert(&mut self, table: &str, row: UntypedRow) -> Result<(), ValidationError>;
     // Marks as deleted
     fn delete(&mut self, table: &str, row: &UntypedRow);
 }
 
 // For internal consumption only
 pub trait DynamicTyped {
-    // Inserts data
+    // Inserts data, panicking if it does not fit the declaration of its relation
     fn insert_typed(&mut self, table: &str, row: Box<[TypedValue]>);
+    // Inserts data, unless it does not fit the declaration of its relation
+    fn try_insert_typed(
+        &mut self,
+        table: &str,
+        row: Box<[TypedValue]>,
+    ) -> Result<(), ValidationError>;
     // Marks as deleted
     fn delete_typed(&mut self, table: &str, row: &Box<[TypedValue]>);
 }
 
+// Relations may be declared with typed columns, after which inserted rows and materialized programs
+// are checked against them
+pub trait Declarable {
+    // fails if the relation is already declared differently, or if the materialized program does
+    // not fit the declaration
+    fn declare(&mut self, declaration: RelationDeclaration) -> Result<(), ValidationError>;
+    fn schema(&self) -> &Schema;
+}
+
 pub trait RelationDropper {
     fn drop_relation(&mut self, table: &str);
 }
 
+// Facts that were inserted, or added by an update, rather than derived, which maintenance never
+// retracts, unless they are deleted, or retracted by an update, themselves
+pub trait ExplicitFacts {
+    fn is_explicit(&self, table: &str, row: &Row) -> bool;
+}
+
 pub type Diff<'a> = (bool, (&'a str, UntypedRow));
 
 pub trait Materializer {
-    // merges the given program with the already being materialized programs, and updates
-    fn materialize(&mut self, program: &SugaredProgram);
+    // merges the given program with the already being materialized programs, and updates, unless
+    // the merged program is not valid
+    fn materialize(&mut self, program: &SugaredProgram) -> Result<(), ValidationError>;
+    // removes the given rules from the materialized programs, and retracts their consequences,
+    // unless the remaining rules still derive them, or they were inserted explicitly, failing
+    // without removing anything if any of the rules is not being materialized
+    fn unmaterialize(&mut self, program: &SugaredProgram) -> Result<(), ValidationError>;
     // given the changes, incrementally maintain the materialization
     fn update(&mut self, changes: Vec<Diff>);
     // returns the amount of facts currently materialized(possibly extensional and intensional)
     fn triple_count(&self) -> usize;
-    // dump
-    fn dump(&self);
+}
+
+pub trait Exporter {
+    // writes the un-interned rows of every relation that passes the filter, failing on the first
+    // row that can not be written
+    fn export(
+        &self,
+        writer: &mut dyn Write,
+        relation_filter: &dyn Fn(&str) -> bool,
+        format: ExportFormat,
+    ) -> std::io::Result<()>;
+}
+
+// Materializations are maintained with delete-rederive unless told otherwise
+pub trait Maintainer {
+    // Switching to counting counts the derivations of what is already materialized
+    fn set_maintenance_strategy(&mut self, strategy: MaintenanceStrategy);
+    fn maintenance_strategy(&self) -> MaintenanceStrategy;
 }
 
 pub trait Queryable {
     fn contains_row(&self, table: &str, row: &UntypedRow) -> bool;
 }
 
+// Opt-in record of how every fact was derived, for debugging programs
+pub trait Explainer {
+    // Only what is derived while recording can be explained
+    fn record_provenance(&mut self, record: bool);
+    // The derivation of the fact down to facts that were not derived, or None if it does not hold
+    fn explain(&self, table: &str, row: &UntypedRow) -> Option<DerivationTree>;
+}
+
+// Change feeds of relations, receiving the rows that every materialization, unmaterialization and
+// update removed from, or inserted into, them
+pub trait Subscribable {
+    fn subscribe(&mut self, table: &str) -> ChangeFeed;
+}
+
+// The values of the variables of a pattern, by name, e.g. {?x: alice, ?c: Person}
+pub type Bindings = HashMap<String, TypedValue>;
+pub type QueryResult<'a> = Box<dyn Iterator<Item = Bindings> + 'a>;
+
+pub trait PatternQueryable {
+    // All bindings of the variables in the conjunction of atoms, e.g. [T(?x, rdf:type, ?c)]
+    fn query(&self, pattern: &Pattern) -> QueryResult<'_>;
+}
+
 pub type EvaluationResult = HashMap<String, IndexSet<Row, ahash::RandomState>>;
 
+// Evaluators return a ValidationError::NotStratifiable, rather than evaluating, programs that are not
+// stratifiable
 pub trait BottomUpEvaluator {
-    fn evaluate_program_bottom_up(&mut self, program: &SugaredProgram) -> EvaluationResult;
+    fn evaluate_program_bottom_up(
+        &mut self,
+        program: &SugaredProgram,
+    ) -> Result<EvaluationResult, ValidationError>;
+}
+
+// Only evaluates what follows from the Δ-prefixed relations, for incremental maintenance
+pub trait DeltaEvaluator {
+    fn evaluate_program_on_deltas(
+        &mut self,
+        program: &SugaredProgram,
+    ) -> Result<EvaluationResult, ValidationError>;
 }
 
 pub trait TopDownEvaluator {
@@ -53,5 +140,5 @@ pub trait TopDownEvaluator {
         &mut self,
         program: &SugaredProgram,
         query: &SugaredRule,
-    ) -> EvaluationResult;
+    ) -> Result<EvaluationResult, ValidationError>;
 }
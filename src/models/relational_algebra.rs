@@ -4,7 +4,7 @@ use std::collections::{BTreeSet, HashMap};
 use std::fmt::{Display, Formatter};
 use std::num::NonZeroU32;
 
-use super::datalog::{self, SugaredRule, TypedValue};
+use super::datalog::{self, Aggregate, Builtin, SugaredRule, TypedValue};
 use crate::data_structures;
 use crate::models::index::IndexBacking;
 use crate::models::instance::IndexedHashSetBacking;
@@ -22,11 +22,25 @@ pub trait Relation {
     fn select_equality(self, left_column_idx: usize, right_column_idx: usize) -> Self;
     fn product(self, other: &Self) -> Self;
     fn join(self, other: &Self, left_column_idx: usize, right_column_idx: usize) -> Self;
+    fn antijoin(self, other: &Self, probe: Vec<SelectionTypedValue>) -> Self;
+    fn select_builtin(
+        self,
+        builtin: Builtin,
+        positive: bool,
+        arguments: Vec<SelectionTypedValue>,
+    ) -> Self;
+    fn extend(self, builtin: Builtin, arguments: Vec<SelectionTypedValue>) -> Self;
     fn project(
         self,
         new_column_indexes_and_values: Vec<SelectionTypedValue>,
         new_symbol: String,
     ) -> Self;
+    fn aggregate(
+        self,
+        new_column_indexes_and_values: Vec<SelectionTypedValue>,
+        aggregates: Vec<(usize, Aggregate)>,
+        new_symbol: String,
+    ) -> Self;
     fn symbol(&self) -> String;
 }
 
@@ -71,6 +85,8 @@ pub enum SelectionTypedValue {
     Column(usize),
     InternedStr(NonZeroU32),
     Float(OrderedFloat<f64>),
+    Int(i64),
+    UInt64(u64),
 }
 
 impl From<TypedValue> for SelectionTypedValue {
@@ -81,6 +97,8 @@ impl From<TypedValue> for SelectionTypedValue {
             TypedValue::UInt(inner) => SelectionTypedValue::UInt(inner),
             TypedValue::Float(inner) => SelectionTypedValue::Float(inner),
             TypedValue::InternedStr(inner) => SelectionTypedValue::InternedStr(inner),
+            TypedValue::Int(inner) => SelectionTypedValue::Int(inner),
+            TypedValue::UInt64(inner) => SelectionTypedValue::UInt64(inner),
         };
     }
 }
@@ -101,6 +119,8 @@ impl TryInto<TypedValue> for SelectionTypedValue {
             SelectionTypedValue::UInt(inner) => Ok(TypedValue::UInt(inner)),
             SelectionTypedValue::Float(inner) => Ok(TypedValue::Float(inner)),
             SelectionTypedValue::InternedStr(inner) => Ok(TypedValue::InternedStr(inner)),
+            SelectionTypedValue::Int(inner) => Ok(TypedValue::Int(inner)),
+            SelectionTypedValue::UInt64(inner) => Ok(TypedValue::UInt64(inner)),
             SelectionTypedValue::Column(_inner) => Err(()),
         };
     }
@@ -112,6 +132,8 @@ impl Display for SelectionTypedValue {
             SelectionTypedValue::Str(inner) => write!(f, "{}", inner),
             SelectionTypedValue::Bool(inner) => write!(f, "{}", inner),
             SelectionTypedValue::UInt(inner) => write!(f, "{}u32", inner),
+            SelectionTypedValue::Int(inner) => write!(f, "{}i64", inner),
+            SelectionTypedValue::UInt64(inner) => write!(f, "{}u64", inner),
             SelectionTypedValue::Column(inner) => write!(f, "{}usize", inner),
             SelectionTypedValue::InternedStr(inner) => write!(f, "{}IStr", inner),
             SelectionTypedValue::Float(inner) => {
@@ -128,6 +150,13 @@ pub enum Term {
     Relation(SugaredAtom),
     Product,
     Join(usize, usize),
+    Antijoin(SugaredAtom, Vec<SelectionTypedValue>),
+    // A built-in that filters rows, and whether it holds or not
+    BuiltinSelection(Builtin, bool, Vec<SelectionTypedValue>),
+    // A built-in that binds a variable, whose value is appended to every row as a new column
+    Extension(Builtin, Vec<SelectionTypedValue>),
+    // A projection that groups by every column that is not aggregated, and the positions that are
+    Aggregate(Vec<SelectionTypedValue>, Vec<(usize, Aggregate)>),
 }
 
 impl Display for Term {
@@ -150,6 +179,52 @@ impl Display for Term {
             Term::Join(left_column_idx, right_column_idx) => {
                 write!(f, "{}_{}={}", "⋈", left_column_idx, right_column_idx)
             }
+            Term::Antijoin(atom, probe) => write!(
+                f,
+                "▷_{}[{}]",
+                atom.symbol,
+                probe
+                    .into_iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Term::BuiltinSelection(builtin, positive, arguments) => write!(
+                f,
+                "σ_{}{}[{}]",
+                if *positive { "" } else { "¬" },
+                builtin.symbol(),
+                arguments
+                    .into_iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Term::Extension(builtin, arguments) => write!(
+                f,
+                "ε_{}[{}]",
+                builtin.symbol(),
+                arguments
+                    .into_iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Term::Aggregate(column_indexes, aggregates) => write!(
+                f,
+                "γ_[{}]",
+                column_indexes
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, x)| {
+                        match aggregates.iter().find(|(position, _)| *position == idx) {
+                            Some((_, aggregate)) => format!("{}({})", aggregate.symbol(), x),
+                            None => x.to_string(),
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
         }
     }
 }
@@ -285,7 +360,12 @@ pub fn select_product_to_join(expr: &RelationalExpression) -> RelationalExpressi
 }
 
 fn rule_body_to_expression(rule: &SugaredRule) -> RelationalExpression {
-    let rule_body = rule.body.clone();
+    let rule_body: Vec<SugaredAtom> = rule
+        .body
+        .iter()
+        .filter(|body_atom| body_atom.positive && !body_atom.is_builtin())
+        .cloned()
+        .collect();
 
     let mut expression = RelationalExpression::new();
 
@@ -315,9 +395,26 @@ fn rule_body_to_expression(rule: &SugaredRule) -> RelationalExpression {
     return expression;
 }
 
+// Rewriting a rule renames each constant and repeated variable of its positive body atoms apart,
+// with fresh variables past the count of its terms. None if those would run out.
+pub fn fresh_variable_start(rule: &SugaredRule) -> Option<u32> {
+    let body_term_count: usize = rule
+        .body
+        .iter()
+        .filter(|body_atom| body_atom.positive && !body_atom.is_builtin())
+        .map(|body_atom| body_atom.terms.len())
+        .sum();
+    let start = u32::try_from(rule.head.terms.len() + body_term_count)
+        .ok()?
+        .checked_add(1)?;
+    start.checked_add(u32::try_from(body_term_count).ok()?)?;
+
+    return Some(start);
+}
+
 fn constant_to_selection<'a>(
     expr: &RelationalExpression,
-    next_id: &mut u8,
+    next_id: &mut u32,
 ) -> RelationalExpression {
     let mut expression = expr.clone();
     expression.arena.clone().into_iter().for_each(|node| {
@@ -344,6 +441,13 @@ fn constant_to_selection<'a>(
                             selection =
                                 Term::Selection(idx, SelectionTypedValue::InternedStr(usize_value))
                         }
+                        TypedValue::Int(int_value) => {
+                            selection = Term::Selection(idx, SelectionTypedValue::Int(int_value))
+                        }
+                        TypedValue::UInt64(uint64_value) => {
+                            selection =
+                                Term::Selection(idx, SelectionTypedValue::UInt64(uint64_value))
+                        }
                     }
 
                     let newvar = datalog::Term::Variable(*next_id);
@@ -353,14 +457,14 @@ fn constant_to_selection<'a>(
 
                     let selection_node_id = expression.allocate(&selection);
 
-                    if let Some(parent_addr) = node.parent {
+                    // The relation's current parent, which is the previous selection if the atom
+                    // has more than one constant
+                    if let Some(parent_addr) = expression.arena[node.idx].parent {
                         let parent = expression.arena[parent_addr].clone();
-                        if let Some(left_child_addr) = parent.left_child {
-                            if left_child_addr == node.idx {
-                                expression.arena[parent_addr].left_child = Some(selection_node_id)
-                            } else {
-                                expression.arena[parent_addr].right_child = Some(selection_node_id)
-                            }
+                        if parent.left_child == Some(node.idx) {
+                            expression.set_left_child(parent_addr, selection_node_id)
+                        } else {
+                            expression.set_right_child(parent_addr, selection_node_id)
                         }
                     }
 
@@ -378,7 +482,7 @@ fn constant_to_selection<'a>(
     return expression;
 }
 
-fn equality_to_selection(expr: &RelationalExpression, next_id: &mut u8) -> RelationalExpression {
+fn equality_to_selection(expr: &RelationalExpression, next_id: &mut u32) -> RelationalExpression {
     let mut expression = expr.clone();
     let relations = expression.arena.clone().into_iter().enumerate().fold(
         vec![],
@@ -427,23 +531,130 @@ fn equality_to_selection(expr: &RelationalExpression, next_id: &mut u8) -> Relat
     return expression;
 }
 
-fn project_head(rule: &SugaredRule) -> Term {
-    let rule_body_terms: Vec<datalog::Term> = rule
+fn positive_body_terms(rule: &SugaredRule) -> Vec<datalog::Term> {
+    return rule
         .body
-        .clone()
-        .into_iter()
+        .iter()
+        .filter(|body_atom| body_atom.positive && !body_atom.is_builtin())
         .flat_map(|body_atom| body_atom.terms.clone())
         .collect();
+}
+
+// Every built-in is applied on top of the body, in the order they were written, either as a
+// selection, or as an extension if it binds a variable, which then becomes a new column. Returns
+// the terms that every column of the body refers to.
+fn builtin_to_selection(
+    rule: &SugaredRule,
+    expr: &RelationalExpression,
+) -> (RelationalExpression, Vec<datalog::Term>) {
+    let mut expression = expr.clone();
+    let mut rule_body_terms = positive_body_terms(rule);
+
+    rule.body
+        .iter()
+        .filter(|body_atom| body_atom.is_builtin())
+        .for_each(|body_atom| {
+            let builtin = body_atom.builtin().unwrap();
+            let arguments: Vec<Option<SelectionTypedValue>> = body_atom
+                .terms
+                .iter()
+                .map(|term| {
+                    if let datalog::Term::Constant(constant) = term {
+                        return Some(SelectionTypedValue::from(constant.clone()));
+                    }
+                    return rule_body_terms
+                        .iter()
+                        .position(|body_term| body_term == term)
+                        .map(SelectionTypedValue::from);
+                })
+                .collect();
+
+            let bound_term = match (builtin, arguments.as_slice()) {
+                (_, [Some(left), Some(right), None]) if builtin.is_arithmetic() => {
+                    Some((2, vec![left.clone(), right.clone()]))
+                }
+                (Builtin::Equal, [Some(value), None]) => Some((1, vec![value.clone()])),
+                (Builtin::Equal, [None, Some(value)]) => Some((0, vec![value.clone()])),
+                _ => None,
+            };
+
+            let term = match bound_term {
+                Some((bound_term_idx, arguments)) if body_atom.positive => {
+                    rule_body_terms.push(body_atom.terms[bound_term_idx].clone());
+                    Term::Extension(builtin, arguments)
+                }
+                _ => Term::BuiltinSelection(
+                    builtin,
+                    body_atom.positive,
+                    arguments
+                        .into_iter()
+                        .map(|argument| argument.unwrap())
+                        .collect(),
+                ),
+            };
+
+            let builtin_idx = expression.allocate(&term);
+            expression.set_left_child(builtin_idx, expression.root.unwrap());
+            expression.set_root(builtin_idx);
+        });
+
+    return (expression, rule_body_terms);
+}
+
+// Every negated atom becomes an antijoin, probing the negated relation with the columns that its
+// variables were first bound at, or with its constants.
+fn negation_to_antijoin(
+    rule: &SugaredRule,
+    expr: &RelationalExpression,
+    rule_body_terms: &Vec<datalog::Term>,
+) -> RelationalExpression {
+    let mut expression = expr.clone();
+
+    rule.body
+        .iter()
+        .filter(|body_atom| !body_atom.positive && !body_atom.is_builtin())
+        .for_each(|body_atom| {
+            let probe = body_atom
+                .terms
+                .iter()
+                .map(|term| {
+                    if let datalog::Term::Constant(constant) = term {
+                        return SelectionTypedValue::from(constant.clone());
+                    }
+                    return SelectionTypedValue::from(
+                        rule_body_terms
+                            .iter()
+                            .position(|body_term| body_term == term)
+                            .unwrap(),
+                    );
+                })
+                .collect();
+
+            let antijoin_idx = expression.allocate(&Term::Antijoin(body_atom.clone(), probe));
+            expression.set_left_child(antijoin_idx, expression.root.unwrap());
+            expression.set_root(antijoin_idx);
+        });
+
+    return expression;
+}
 
+fn project_head(rule: &SugaredRule, rule_body_terms: &Vec<datalog::Term>) -> Term {
+    let mut aggregates = vec![];
     let projected_head_indexes: Vec<SelectionTypedValue> = rule
         .head
         .terms
         .clone()
         .into_iter()
-        .map(|head_term| {
-            if let datalog::Term::Constant(constant) = head_term {
-                return SelectionTypedValue::from(constant);
-            }
+        .enumerate()
+        .map(|(idx, head_term)| {
+            let head_term = match head_term {
+                datalog::Term::Constant(constant) => return SelectionTypedValue::from(constant),
+                datalog::Term::Aggregate(aggregate, identifier) => {
+                    aggregates.push((idx, aggregate));
+                    datalog::Term::Variable(identifier)
+                }
+                variable => variable,
+            };
             return SelectionTypedValue::from(
                 rule_body_terms
                     .clone()
@@ -454,29 +665,34 @@ fn project_head(rule: &SugaredRule) -> Term {
         })
         .collect();
 
+    if !aggregates.is_empty() {
+        return Term::Aggregate(projected_head_indexes, aggregates);
+    }
+
     return Term::Projection(projected_head_indexes.clone());
 }
 
 impl From<&SugaredRule> for RelationalExpression {
     fn from(rule: &SugaredRule) -> Self {
-        // This is necessary in order to create fresh relations.
-        let head_term_count = rule.head.terms.len();
-        let body_term_count: usize = rule
-            .body
-            .iter()
-            .map(|body_atom| body_atom.terms.len())
-            .sum();
-        // This could be a source of funny problems, but only if rules are hilariously long.
-        let mut expression_variable_start: u8 = (head_term_count + body_term_count + 1) as u8;
+        // This is necessary in order to create fresh relations. validate_program rejects rules
+        // with too many terms for it.
+        let mut expression_variable_start: u32 =
+            fresh_variable_start(rule).expect("the rule has too many terms to be rewritten");
         // Turning the body into products
         let products = rule_body_to_expression(&rule);
         // Morphing relations with constants to selection equalities
         let products_and_selections =
             constant_to_selection(&products, &mut expression_variable_start);
-        let mut expression =
+        let products_selections_and_equalities =
             equality_to_selection(&products_and_selections, &mut expression_variable_start);
+        // Applying built-ins
+        let (products_selections_and_builtins, rule_body_terms) =
+            builtin_to_selection(&rule, &products_selections_and_equalities);
+        // Filtering out everything that matches a negated atom
+        let mut expression =
+            negation_to_antijoin(&rule, &products_selections_and_builtins, &rule_body_terms);
         // Projecting the head
-        let projection_idx = expression.allocate(&project_head(&rule));
+        let projection_idx = expression.allocate(&project_head(&rule, &rule_body_terms));
         expression.set_left_child(projection_idx, expression.root.unwrap());
         expression.set_root(projection_idx);
         // Converting selections followed by products into joins
@@ -488,13 +704,14 @@ impl From<&SugaredRule> for RelationalExpression {
 #[cfg(test)]
 mod tests {
     use crate::models::datalog::SugaredRule;
-    use crate::models::relational_algebra::RelationalExpression;
+    use crate::models::relational_algebra::{fresh_variable_start, RelationalExpression};
 
     #[test]
     fn test_rule_to_expression() {
-        let rule = SugaredRule::from(
+        let rule = SugaredRule::try_from(
             "HardcoreToTheMega(?x, ?z) <- [T(?x, ?y), T(?y, ?z), U(?y, hardcore)]",
-        );
+        )
+        .unwrap();
 
         let expected_expression = "π_[0usize, 3usize](σ_1=4usize(⋈_1=0(T(?0, ?2), ⋈_0=0(T(?10, ?1), σ_1=hardcore(U(?12, ?9))))))";
 
@@ -505,7 +722,8 @@ mod tests {
     #[test]
     fn test_rule_to_expression_complex() {
         let rule =
-            SugaredRule::from("T(?y, rdf:type, ?x) <- [T(?a, rdfs:domain, ?x), T(?y, ?a, ?z)]");
+            SugaredRule::try_from("T(?y, rdf:type, ?x) <- [T(?a, rdfs:domain, ?x), T(?y, ?a, ?z)]")
+                .unwrap();
 
         let expected_expression =
             "π_[3usize, rdf:type, 2usize](⋈_0=1(σ_1=rdfs:domain(T(?2, ?10, ?1)), T(?0, ?11, ?3)))";
@@ -513,4 +731,56 @@ mod tests {
         let actual_expression = RelationalExpression::from(&rule).to_string();
         assert_eq!(expected_expression, actual_expression)
     }
+
+    #[test]
+    fn test_rule_with_constants_to_expression() {
+        let rule = SugaredRule::try_from(
+            "T(?x, ?p, ?z) <- [T(?p, rdf:type, owl:TransitiveProperty), T(?x, ?p, ?y), T(?y, ?p, ?z)]",
+        )
+        .unwrap();
+
+        let expected_expression = "π_[3usize, 0usize, 8usize](σ_5=6usize(σ_0=7usize(⋈_0=1(σ_1=rdf:type(σ_2=owl:TransitiveProperty(T(?1, ?13, ?14))), ⋈_1=1(T(?0, ?15, ?3), T(?18, ?17, ?2))))))";
+        assert_eq!(Some(13), fresh_variable_start(&rule));
+
+        let actual_expression = RelationalExpression::from(&rule).to_string();
+        assert_eq!(expected_expression, actual_expression)
+    }
+
+    #[test]
+    fn test_rule_with_negation_to_expression() {
+        let rule = SugaredRule::try_from(
+            "unreachable(?x, ?y) <- [node(?x), node(?y), !reach(?x, ?y), !blocked(?y, gate)]",
+        )
+        .unwrap();
+
+        let expected_expression = "π_[0usize, 1usize](▷_blocked[1usize, gate](▷_reach[0usize, 1usize](×(node(?0), node(?1)))))";
+
+        let actual_expression = RelationalExpression::from(&rule).to_string();
+        assert_eq!(expected_expression, actual_expression)
+    }
+
+    #[test]
+    fn test_rule_with_builtins_to_expression() {
+        let rule = SugaredRule::try_from(
+            "cheap(?x, ?c) <- [edge(?x, ?y, ?w), ?c = ?w * 2, ?c < 10, !?x = ?y]",
+        )
+        .unwrap();
+
+        let expected_expression =
+            "π_[0usize, 3usize](σ_¬=[0usize, 1usize](σ_<[3usize, 10u32](ε_*[2usize, 2u32](edge(?0, ?2, ?3)))))";
+
+        let actual_expression = RelationalExpression::from(&rule).to_string();
+        assert_eq!(expected_expression, actual_expression)
+    }
+
+    #[test]
+    fn test_rule_with_aggregates_to_expression() {
+        let rule = SugaredRule::try_from("heaviest(?x, max(?w), count(?y)) <- [edge(?x, ?y, ?w)]")
+            .unwrap();
+
+        let expected_expression = "γ_[0usize, max(2usize), count(1usize)](edge(?0, ?2, ?1))";
+
+        let actual_expression = RelationalExpression::from(&rule).to_string();
+        assert_eq!(expected_expression, actual_expression)
+    }
 }
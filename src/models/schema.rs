@@ -0,0 +1,261 @@
+// Relations are untyped unless they are declared, as in .decl edge(from: uint, to: uint), after which
+// every row and every atom of theirs must have the declared arity, and constants of the declared
+// types.
+
+use crate::misc::validation::ValidationError;
+use crate::models::datalog::{SugaredAtom, SugaredProgram, SugaredRule, Term, TypedValue};
+use crate::parsers::datalog::{parse_declaration, ParseError};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use std::fmt::{Display, Formatter};
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Hash, PartialOrd, Ord)]
+pub enum ColumnType {
+    Str,
+    Bool,
+    // Any integer that is not negative
+    UInt,
+    Int,
+    Float,
+}
+
+const COLUMN_TYPES: [ColumnType; 5] = [
+    ColumnType::Str,
+    ColumnType::Bool,
+    ColumnType::UInt,
+    ColumnType::Int,
+    ColumnType::Float,
+];
+
+impl ColumnType {
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        return COLUMN_TYPES
+            .iter()
+            .find(|column_type| column_type.symbol() == symbol)
+            .copied();
+    }
+    pub fn symbol(&self) -> &'static str {
+        return match self {
+            ColumnType::Str => "str",
+            ColumnType::Bool => "bool",
+            ColumnType::UInt => "uint",
+            ColumnType::Int => "int",
+            ColumnType::Float => "float",
+        };
+    }
+    // Integers are canonical, hence whether they fit is a matter of their value, not of their variant
+    pub fn admits(&self, typed_value: &TypedValue) -> bool {
+        return match (self, typed_value) {
+            (ColumnType::Str, TypedValue::Str(_) | TypedValue::InternedStr(_)) => true,
+            (ColumnType::Bool, TypedValue::Bool(_)) => true,
+            (ColumnType::UInt, TypedValue::UInt(_) | TypedValue::UInt64(_)) => true,
+            (ColumnType::Int, typed_value) => typed_value.as_integer().is_some(),
+            (ColumnType::Float, TypedValue::Float(_)) => true,
+            _ => false,
+        };
+    }
+}
+
+impl Display for ColumnType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Hash, PartialOrd, Ord)]
+pub struct ColumnDeclaration {
+    pub name: String,
+    pub column_type: ColumnType,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Hash, PartialOrd, Ord)]
+pub struct RelationDeclaration {
+    pub symbol: String,
+    pub columns: Vec<ColumnDeclaration>,
+}
+
+impl RelationDeclaration {
+    fn check_arity(&self, found: usize) -> Result<(), ValidationError> {
+        if self.columns.len() != found {
+            return Err(ValidationError::ArityMismatch {
+                symbol: self.symbol.clone(),
+                expected: self.columns.len(),
+                found,
+            });
+        }
+
+        return Ok(());
+    }
+    fn check_value(
+        &self,
+        column: &ColumnDeclaration,
+        typed_value: &TypedValue,
+    ) -> Result<(), ValidationError> {
+        if !column.column_type.admits(typed_value) {
+            return Err(ValidationError::TypeMismatch {
+                symbol: self.symbol.clone(),
+                column: column.name.clone(),
+                expected: column.column_type,
+                found: typed_value.clone(),
+            });
+        }
+
+        return Ok(());
+    }
+    pub fn check_row(&self, row: &[TypedValue]) -> Result<(), ValidationError> {
+        self.check_arity(row.len())?;
+
+        return self
+            .columns
+            .iter()
+            .zip(row.iter())
+            .try_for_each(|(column, typed_value)| self.check_value(column, typed_value));
+    }
+    // Variables may stand for anything, hence only constants are checked
+    pub fn check_atom(&self, atom: &SugaredAtom) -> Result<(), ValidationError> {
+        self.check_arity(atom.terms.len())?;
+
+        return self.columns.iter().zip(atom.terms.iter()).try_for_each(
+            |(column, term)| match term {
+                Term::Constant(typed_value) => self.check_value(column, typed_value),
+                Term::Variable(_) | Term::Aggregate(..) => Ok(()),
+            },
+        );
+    }
+}
+
+impl TryFrom<&str> for RelationDeclaration {
+    type Error = ParseError;
+
+    fn try_from(str: &str) -> Result<Self, Self::Error> {
+        return parse_declaration(str);
+    }
+}
+
+impl Display for RelationDeclaration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            ".decl {}({})",
+            self.symbol,
+            self.columns
+                .iter()
+                .map(|column| format!("{}: {}", column.name, column.column_type))
+                .join(", ")
+        )
+    }
+}
+
+// All declarations of a reasoner, in the order they were made
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct Schema {
+    pub declarations: IndexMap<String, RelationDeclaration, ahash::RandomState>,
+}
+
+impl Schema {
+    // Declaring a relation again is fine, as long as it is declared the same way
+    pub fn declare(&mut self, declaration: RelationDeclaration) -> Result<(), ValidationError> {
+        if let Some(existing_declaration) = self.declarations.get(&declaration.symbol) {
+            if *existing_declaration != declaration {
+                return Err(ValidationError::ConflictingDeclaration {
+                    symbol: declaration.symbol,
+                });
+            }
+            return Ok(());
+        }
+
+        self.declarations
+            .insert(declaration.symbol.clone(), declaration);
+
+        return Ok(());
+    }
+    pub fn get(&self, symbol: &str) -> Option<&RelationDeclaration> {
+        return self.declarations.get(symbol);
+    }
+    pub fn check_row(&self, symbol: &str, row: &[TypedValue]) -> Result<(), ValidationError> {
+        return match self.get(symbol) {
+            Some(declaration) => declaration.check_row(row),
+            None => Ok(()),
+        };
+    }
+    pub fn check_atom(&self, atom: &SugaredAtom) -> Result<(), ValidationError> {
+        return match self.get(&atom.symbol) {
+            Some(declaration) => declaration.check_atom(atom),
+            None => Ok(()),
+        };
+    }
+    pub fn check_rule(&self, rule: &SugaredRule) -> Result<(), ValidationError> {
+        return [&rule.head]
+            .into_iter()
+            .chain(rule.body.iter())
+            .try_for_each(|atom| self.check_atom(atom));
+    }
+    pub fn check_program(&self, program: &SugaredProgram) -> Result<(), ValidationError> {
+        return program.iter().try_for_each(|rule| self.check_rule(rule));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::misc::validation::ValidationError;
+    use crate::models::datalog::{SugaredRule, TypedValue};
+    use crate::models::schema::{ColumnType, RelationDeclaration, Schema};
+
+    #[test]
+    fn test_schema() {
+        let mut schema = Schema::default();
+        let edge = RelationDeclaration::try_from(".decl edge(from: uint, to: int)").unwrap();
+        schema.declare(edge.clone()).unwrap();
+        schema.declare(edge).unwrap();
+
+        assert_eq!(
+            Err(ValidationError::ConflictingDeclaration {
+                symbol: "edge".to_string()
+            }),
+            schema.declare(RelationDeclaration::try_from(".decl edge(from: str)").unwrap())
+        );
+
+        assert_eq!(
+            Ok(()),
+            schema.check_row(
+                "edge",
+                &[TypedValue::UInt64(5000000000), TypedValue::Int(-1)]
+            )
+        );
+        assert_eq!(
+            Err(ValidationError::TypeMismatch {
+                symbol: "edge".to_string(),
+                column: "from".to_string(),
+                expected: ColumnType::UInt,
+                found: TypedValue::Int(-1),
+            }),
+            schema.check_row("edge", &[TypedValue::Int(-1), TypedValue::UInt(1)])
+        );
+        assert_eq!(
+            Err(ValidationError::ArityMismatch {
+                symbol: "edge".to_string(),
+                expected: 2,
+                found: 1,
+            }),
+            schema.check_row("edge", &[TypedValue::UInt(1)])
+        );
+        assert_eq!(Ok(()), schema.check_row("node", &[TypedValue::UInt(1)]));
+
+        let program = vec![
+            SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("edge(?x, 1) <- [node(?x)]").unwrap(),
+        ];
+        assert_eq!(Ok(()), schema.check_program(&program));
+
+        let program = vec![SugaredRule::try_from("from_a(?y) <- [edge(a, ?y)]").unwrap()];
+        assert_eq!(
+            Err(ValidationError::TypeMismatch {
+                symbol: "edge".to_string(),
+                column: "from".to_string(),
+                expected: ColumnType::UInt,
+                found: TypedValue::Str("a".to_string()),
+            }),
+            schema.check_program(&program)
+        );
+    }
+}
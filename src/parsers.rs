@@ -1 +1,3 @@
 pub mod datalog;
+pub mod loader;
+pub mod rdf;
@@ -1,114 +1,672 @@
-use logos::{Lexer, Logos};
+use logos::{Logos, SpannedIter};
+use std::cmp::{max, min};
 use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
 use std::iter::Peekable;
+use std::ops::Range;
 
 use crate::lexers::datalog::DatalogToken;
-use crate::models::datalog::{SugaredAtom, SugaredRule, Term, TypedValue};
+use crate::misc::validation::ValidationError;
+use crate::models::datalog::{
+    Aggregate, Builtin, Pattern, SugaredAtom, SugaredProgram, SugaredRule, Term, TypedValue,
+};
+use crate::models::schema::{ColumnDeclaration, ColumnType, RelationDeclaration, Schema};
 
-fn parse_lexed_sugared_atom<'a>(
-    lexer: &mut Peekable<Lexer<'a, DatalogToken<'a>>>,
-    interner: &mut BTreeMap<&'a str, u8>,
-) -> SugaredAtom {
-    let mut terms: Vec<Term> = vec![];
-    while let Some(token) = lexer.next() {
-        match token {
-            DatalogToken::RParen => break,
-            DatalogToken::Str(current_token_value) => terms.push(Term::Constant(TypedValue::Str(
-                current_token_value.to_string(),
-            ))),
-            DatalogToken::UIntConst(current_token_value) => {
-                terms.push(Term::Constant(TypedValue::UInt(current_token_value)))
-            }
-            DatalogToken::BoolConst(current_token_value) => {
-                terms.push(Term::Constant(TypedValue::Bool(current_token_value)))
+type SpannedLexer<'a> = Peekable<SpannedIter<'a, DatalogToken<'a>>>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    // Byte range of the offending token, which is empty at the end of the input
+    pub span: Range<usize>,
+    pub expected: String,
+    pub found: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(source: &str, span: Range<usize>, expected: &str) -> Self {
+        let found = if span.is_empty() {
+            "end of input".to_string()
+        } else {
+            format!("'{}'", &source[span.clone()])
+        };
+
+        return Self {
+            span,
+            expected: expected.to_string(),
+            found,
+        };
+    }
+    // The line of the source in which the error happened, with the offending token underlined
+    pub fn render(&self, source: &str) -> String {
+        let line_start = source[..self.span.start]
+            .rfind('\n')
+            .map_or(0, |idx| idx + 1);
+        let line_end = source[self.span.start..]
+            .find('\n')
+            .map_or(source.len(), |idx| self.span.start + idx);
+        let line_number = source[..line_start].matches('\n').count() + 1;
+        let column = self.span.start - line_start;
+        let underline_length = max(1, min(self.span.end, line_end) - self.span.start);
+
+        return format!(
+            "error at {}:{}: {}\n{}\n{}{}",
+            line_number,
+            column + 1,
+            self,
+            &source[line_start..line_end],
+            " ".repeat(column),
+            "^".repeat(underline_length)
+        );
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expected {}, found {} at {}..{}",
+            self.expected, self.found, self.span.start, self.span.end
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn next_token<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+    expected: &'static str,
+) -> Result<(DatalogToken<'a>, Range<usize>), ParseError> {
+    return match lexer.next() {
+        Some((DatalogToken::Error, span)) => Err(ParseError::new(source, span, expected)),
+        Some(token) => Ok(token),
+        None => Err(ParseError::new(
+            source,
+            source.len()..source.len(),
+            expected,
+        )),
+    };
+}
+
+fn expect_end_of_input<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+) -> Result<(), ParseError> {
+    if let Some((_token, span)) = lexer.next() {
+        return Err(ParseError::new(source, span, "end of input"));
+    }
+
+    return Ok(());
+}
+
+// Variables are numbered in order of appearance, which fails, rather than wraps around, once a
+// rule has more of them than identifiers
+// A "-" followed by a number is a negative constant, since the lexer only reads the digits, such that
+// ?z = ?x -1 remains a subtraction
+fn term_from_token<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+    (token, span): (DatalogToken<'a>, Range<usize>),
+    interner: &mut BTreeMap<&'a str, u32>,
+    expected: &'static str,
+) -> Result<Term, ParseError> {
+    return match token {
+        DatalogToken::Str("-") if matches!(lexer.peek(), Some((DatalogToken::UIntConst(_), _))) => {
+            match lexer.next() {
+                Some((DatalogToken::UIntConst(magnitude), magnitude_span)) => {
+                    TypedValue::from_integer(-(magnitude as i128))
+                        .map(Term::Constant)
+                        .ok_or_else(|| {
+                            ParseError::new(
+                                source,
+                                span.start..magnitude_span.end,
+                                "an integer of at most 64 bits",
+                            )
+                        })
+                }
+                _ => unreachable!(),
             }
-            DatalogToken::Variable(current_token_value) => {
-                let mut current_idx = interner.len() as u8;
-                if let Some(idx) = interner.get(current_token_value) {
-                    current_idx = *idx
-                } else {
-                    interner.insert(current_token_value, current_idx);
+        }
+        DatalogToken::Str("-")
+            if matches!(lexer.peek(), Some((DatalogToken::FloatConst(_), _))) =>
+        {
+            match lexer.next() {
+                Some((DatalogToken::FloatConst(magnitude), _)) => {
+                    Ok(Term::Constant(TypedValue::Float(-magnitude)))
                 }
-                terms.push(Term::Variable(current_idx))
+                _ => unreachable!(),
             }
-            _ => continue,
         }
+        DatalogToken::Str(current_token_value) => Ok(Term::Constant(TypedValue::Str(
+            current_token_value.to_string(),
+        ))),
+        DatalogToken::QuotedStr(current_token_value) => {
+            Ok(Term::Constant(TypedValue::Str(current_token_value)))
+        }
+        DatalogToken::Iri(current_token_value) => Ok(Term::Constant(TypedValue::Str(
+            current_token_value.to_string(),
+        ))),
+        DatalogToken::UIntConst(current_token_value) => Ok(Term::Constant(
+            TypedValue::from_integer(current_token_value as i128).unwrap(),
+        )),
+        DatalogToken::BoolConst(current_token_value) => {
+            Ok(Term::Constant(TypedValue::Bool(current_token_value)))
+        }
+        DatalogToken::FloatConst(current_token_value) => {
+            Ok(Term::Constant(TypedValue::Float(current_token_value)))
+        }
+        DatalogToken::Variable(current_token_value) => {
+            if let Some(idx) = interner.get(current_token_value) {
+                return Ok(Term::Variable(*idx));
+            }
+            let current_idx = u32::try_from(interner.len()).map_err(|_| {
+                ParseError::new(source, span, "at most 2^32 distinct variables in a rule")
+            })?;
+            interner.insert(current_token_value, current_idx);
+
+            Ok(Term::Variable(current_idx))
+        }
+        _ => Err(ParseError::new(source, span, expected)),
+    };
+}
+
+fn parse_lexed_term<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+    interner: &mut BTreeMap<&'a str, u32>,
+) -> Result<Term, ParseError> {
+    let token = next_token(lexer, source, "a term")?;
+
+    return term_from_token(lexer, source, token, interner, "a term");
+}
+
+// Aggregates, such as count(?y), are only allowed where the caller says so, which is in heads
+fn parse_lexed_terms<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+    interner: &mut BTreeMap<&'a str, u32>,
+    aggregates: bool,
+) -> Result<Vec<Term>, ParseError> {
+    match next_token(lexer, source, "'('")? {
+        (DatalogToken::LParen, _) => {}
+        (_, span) => return Err(ParseError::new(source, span, "'('")),
     }
-    return SugaredAtom {
-        terms,
-        symbol: "".to_string(),
-        positive: true,
+
+    let mut terms: Vec<Term> = vec![];
+    loop {
+        let expected = if terms.is_empty() {
+            "a term or ')'"
+        } else {
+            "a term"
+        };
+        let term = match next_token(lexer, source, expected)? {
+            (DatalogToken::RParen, _) if terms.is_empty() => break,
+            (DatalogToken::Str(symbol), _)
+                if aggregates && matches!(lexer.peek(), Some((DatalogToken::LParen, _))) =>
+            {
+                parse_lexed_aggregate(lexer, source, interner, symbol)?
+            }
+            token => term_from_token(lexer, source, token, interner, expected)?,
+        };
+        terms.push(term);
+
+        match next_token(lexer, source, "',' or ')'")? {
+            (DatalogToken::Comma, _) => continue,
+            (DatalogToken::RParen, _) => break,
+            (_, span) => return Err(ParseError::new(source, span, "',' or ')'")),
+        }
+    }
+
+    return Ok(terms);
+}
+
+fn parse_lexed_aggregate<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+    interner: &mut BTreeMap<&'a str, u32>,
+    symbol: &'a str,
+) -> Result<Term, ParseError> {
+    let aggregate = match Aggregate::from_symbol(symbol) {
+        Some(aggregate) => aggregate,
+        None => {
+            let span = lexer.peek().unwrap().1.clone();
+            return Err(ParseError::new(source, span, "',' or ')'"));
+        }
+    };
+    lexer.next();
+
+    let term = match next_token(lexer, source, "a variable")? {
+        (DatalogToken::Variable(variable), span) => {
+            match term_from_token(
+                lexer,
+                source,
+                (DatalogToken::Variable(variable), span),
+                interner,
+                "a variable",
+            )? {
+                Term::Variable(identifier) => Term::Aggregate(aggregate, identifier),
+                _ => unreachable!(),
+            }
+        }
+        (_, span) => return Err(ParseError::new(source, span, "a variable")),
+    };
+
+    return match next_token(lexer, source, "')'")? {
+        (DatalogToken::RParen, _) => Ok(term),
+        (_, span) => Err(ParseError::new(source, span, "')'")),
     };
 }
 
-pub fn parse_sugared_atom(sugared_atom: &str) -> SugaredAtom {
-    let mut lexer = DatalogToken::lexer(sugared_atom).peekable();
-    let mut sugared_atom = SugaredAtom {
-        terms: vec![],
-        symbol: "".to_string(),
-        positive: true,
+fn parse_lexed_sugared_atom<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+    interner: &mut BTreeMap<&'a str, u32>,
+) -> Result<SugaredAtom, ParseError> {
+    let mut positive = true;
+    let mut token = next_token(lexer, source, "an atom")?;
+    if token.0 == DatalogToken::Negation {
+        positive = false;
+        token = next_token(lexer, source, "a predicate symbol")?;
+    }
+    let symbol = match token {
+        (DatalogToken::Str(symbol), _) => symbol.to_string(),
+        (_, span) => return Err(ParseError::new(source, span, "a predicate symbol")),
     };
-    let mut interner: BTreeMap<&str, u8> = BTreeMap::new();
+    let terms = parse_lexed_terms(lexer, source, interner, true)?;
+
+    return Ok(SugaredAtom {
+        terms,
+        symbol,
+        positive,
+    });
+}
+
+// Body atoms can also be built-ins, which are written infix, as in ?x < ?y or ?z = ?x + 1. A symbol
+// can only be told apart from a constant by whether it is followed by a '('.
+fn parse_lexed_body_atom<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+    interner: &mut BTreeMap<&'a str, u32>,
+) -> Result<SugaredAtom, ParseError> {
+    let mut positive = true;
+    let mut token = next_token(lexer, source, "an atom")?;
+    if token.0 == DatalogToken::Negation {
+        positive = false;
+        token = next_token(lexer, source, "an atom")?;
+    }
+    let expected = if let DatalogToken::Str(symbol) = token.0 {
+        if let Some((DatalogToken::LParen, _)) = lexer.peek() {
+            let terms = parse_lexed_terms(lexer, source, interner, false)?;
 
-    while let Some(token) = lexer.next() {
-        match token {
-            DatalogToken::Negation => sugared_atom.positive = false,
-            DatalogToken::Str(predicate_symbol) => {
-                let parsed_sugared_atom = parse_lexed_sugared_atom(&mut lexer, &mut interner);
-                sugared_atom.symbol = predicate_symbol.to_string();
-                sugared_atom.terms = parsed_sugared_atom.terms;
+            return Ok(SugaredAtom {
+                terms,
+                symbol: symbol.to_string(),
+                positive,
+            });
+        }
+        "'('"
+    } else {
+        "a comparison"
+    };
+
+    let left = term_from_token(lexer, source, token, interner, "an atom")?;
+    let builtin = match next_token(lexer, source, expected)? {
+        (DatalogToken::LessThan, _) => Builtin::LessThan,
+        (DatalogToken::LessThanOrEqual, _) => Builtin::LessThanOrEqual,
+        (DatalogToken::GreaterThan, _) => Builtin::GreaterThan,
+        (DatalogToken::GreaterThanOrEqual, _) => Builtin::GreaterThanOrEqual,
+        (DatalogToken::Equal, _) => Builtin::Equal,
+        (DatalogToken::NotEqual, _) => Builtin::NotEqual,
+        (_, span) => return Err(ParseError::new(source, span, expected)),
+    };
+    let right = parse_lexed_term(lexer, source, interner)?;
+
+    if builtin == Builtin::Equal {
+        let arithmetic = match lexer.peek() {
+            Some((DatalogToken::Str(operator), _)) => {
+                Builtin::from_symbol(operator).filter(|builtin| builtin.is_arithmetic())
             }
-            _ => {}
-        }
-    }
-
-    return sugared_atom;
-}
-
-pub fn parse_sugared_rule(rule: &str) -> SugaredRule {
-    let mut lexer = DatalogToken::lexer(rule).peekable();
-    let mut head = SugaredAtom {
-        terms: vec![],
-        symbol: "".to_string(),
-        positive: false,
-    };
-    let mut body: Vec<SugaredAtom> = vec![];
-    let mut look_behind: DatalogToken = DatalogToken::Error;
-    let mut look_ahead: DatalogToken = DatalogToken::Error;
-
-    let mut interner = Default::default();
-    while let Some(token) = lexer.next() {
-        if let Some(peek) = lexer.peek() {
-            look_ahead = peek.clone()
-        }
-        match token {
-            DatalogToken::Str(symbol) => {
-                if look_ahead == DatalogToken::LParen {
-                    let mut parsed_sugared_atom =
-                        parse_lexed_sugared_atom(&mut lexer, &mut interner);
-                    parsed_sugared_atom.symbol = symbol.parse().unwrap();
-                    if look_behind == DatalogToken::HeadDirection
-                        || look_behind == DatalogToken::Error
-                    {
-                        head = parsed_sugared_atom;
-                        continue;
-                    }
-                    if look_behind == DatalogToken::Negation {
-                        parsed_sugared_atom.positive = false;
-                    }
-                    body.push(parsed_sugared_atom)
+            Some((DatalogToken::Times, _)) => Some(Builtin::Multiply),
+            Some((DatalogToken::Slash, _)) => Some(Builtin::Divide),
+            _ => None,
+        };
+
+        if let Some(arithmetic) = arithmetic {
+            lexer.next();
+            let operand = parse_lexed_term(lexer, source, interner)?;
+
+            return Ok(SugaredAtom {
+                terms: vec![right, operand, left],
+                symbol: arithmetic.symbol().to_string(),
+                positive,
+            });
+        }
+    }
+
+    return Ok(SugaredAtom {
+        terms: vec![left, right],
+        symbol: builtin.symbol().to_string(),
+        positive,
+    });
+}
+
+fn parse_lexed_body<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+    interner: &mut BTreeMap<&'a str, u32>,
+) -> Result<Vec<SugaredAtom>, ParseError> {
+    match next_token(lexer, source, "'['")? {
+        (DatalogToken::LBracket, _) => {}
+        (_, span) => return Err(ParseError::new(source, span, "'['")),
+    }
+
+    let mut body = vec![];
+    if let Some((DatalogToken::RBracket, _)) = lexer.peek() {
+        lexer.next();
+        return Ok(body);
+    }
+    loop {
+        body.push(parse_lexed_body_atom(lexer, source, interner)?);
+
+        match next_token(lexer, source, "',' or ']'")? {
+            (DatalogToken::Comma, _) => continue,
+            (DatalogToken::RBracket, _) => return Ok(body),
+            (_, span) => return Err(ParseError::new(source, span, "',' or ']'")),
+        }
+    }
+}
+
+fn parse_lexed_head<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+    interner: &mut BTreeMap<&'a str, u32>,
+) -> Result<SugaredAtom, ParseError> {
+    match lexer.peek() {
+        Some((DatalogToken::Negation, span)) => {
+            return Err(ParseError::new(source, span.clone(), "a predicate symbol"));
+        }
+        Some((DatalogToken::Str(symbol), span)) if Builtin::from_symbol(symbol).is_some() => {
+            return Err(ParseError::new(source, span.clone(), "a predicate symbol"));
+        }
+        _ => {}
+    }
+
+    return parse_lexed_sugared_atom(lexer, source, interner);
+}
+
+fn parse_head_direction<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+    head_direction: &'static str,
+    expected: &'static str,
+) -> Result<(), ParseError> {
+    return match next_token(lexer, source, expected)? {
+        (DatalogToken::HeadDirection, span) if &source[span.clone()] == head_direction => Ok(()),
+        (_, span) => Err(ParseError::new(source, span, expected)),
+    };
+}
+
+// Returns where the terminated statement ends
+fn parse_terminator<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+) -> Result<usize, ParseError> {
+    return match next_token(lexer, source, "'.'")? {
+        (DatalogToken::Dot, span) => Ok(span.end),
+        (_, span) => Err(ParseError::new(source, span, "'.'")),
+    };
+}
+
+pub fn parse_sugared_atom(sugared_atom: &str) -> Result<SugaredAtom, ParseError> {
+    let mut lexer = DatalogToken::lexer(sugared_atom).spanned().peekable();
+    let mut interner: BTreeMap<&str, u32> = BTreeMap::new();
+
+    let parsed_sugared_atom = parse_lexed_body_atom(&mut lexer, sugared_atom, &mut interner)?;
+    expect_end_of_input(&mut lexer, sugared_atom)?;
+
+    return Ok(parsed_sugared_atom);
+}
+
+// Patterns are written as a body, or as a single atom, and keep the names of their variables
+pub fn parse_pattern(pattern: &str) -> Result<Pattern, ParseError> {
+    let mut lexer = DatalogToken::lexer(pattern).spanned().peekable();
+    let mut interner: BTreeMap<&str, u32> = BTreeMap::new();
+
+    let atoms = if let Some((DatalogToken::LBracket, _)) = lexer.peek() {
+        parse_lexed_body(&mut lexer, pattern, &mut interner)?
+    } else {
+        vec![parse_lexed_body_atom(&mut lexer, pattern, &mut interner)?]
+    };
+    expect_end_of_input(&mut lexer, pattern)?;
+
+    let mut variables: Vec<(u32, String)> = interner
+        .into_iter()
+        .map(|(name, idx)| (idx, name.to_string()))
+        .collect();
+    variables.sort();
+
+    return Ok(Pattern {
+        atoms,
+        variables: variables.into_iter().map(|(_idx, name)| name).collect(),
+    });
+}
+
+// Rules are either written as head <- [body] or [body] -> head
+pub fn parse_sugared_rule(rule: &str) -> Result<SugaredRule, ParseError> {
+    let mut lexer = DatalogToken::lexer(rule).spanned().peekable();
+    let mut interner: BTreeMap<&str, u32> = BTreeMap::new();
+
+    let (head, body) = if let Some((DatalogToken::LBracket, _)) = lexer.peek() {
+        let body = parse_lexed_body(&mut lexer, rule, &mut interner)?;
+        parse_head_direction(&mut lexer, rule, "->", "'->'")?;
+        let head = parse_lexed_head(&mut lexer, rule, &mut interner)?;
+
+        (head, body)
+    } else {
+        let head = parse_lexed_head(&mut lexer, rule, &mut interner)?;
+        parse_head_direction(&mut lexer, rule, "<-", "'<-'")?;
+        let body = parse_lexed_body(&mut lexer, rule, &mut interner)?;
+
+        (head, body)
+    };
+    expect_end_of_input(&mut lexer, rule)?;
+
+    return Ok(SugaredRule { head, body });
+}
+
+// Columns are written as name: type, where the ':' is lexed as part of the name, since symbols may
+// contain it.
+fn parse_lexed_column<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+) -> Result<ColumnDeclaration, ParseError> {
+    let (name, mut column_type) = match next_token(lexer, source, "a column name")? {
+        (DatalogToken::Str(name), span) => match name.split_once(':') {
+            Some((name, column_type)) if !name.is_empty() => (name, (column_type, span)),
+            None => match next_token(lexer, source, "':'")? {
+                (DatalogToken::Str(column_type), span) if column_type.starts_with(':') => {
+                    (name, (&column_type[1..], span))
                 }
+                (_, span) => return Err(ParseError::new(source, span, "':'")),
+            },
+            _ => return Err(ParseError::new(source, span, "a column name")),
+        },
+        (_, span) => return Err(ParseError::new(source, span, "a column name")),
+    };
+    if column_type.0.is_empty() {
+        column_type = match next_token(lexer, source, "a column type")? {
+            (DatalogToken::Str(column_type), span) => (column_type, span),
+            (_, span) => return Err(ParseError::new(source, span, "a column type")),
+        };
+    }
+
+    return match ColumnType::from_symbol(column_type.0) {
+        Some(inner) => Ok(ColumnDeclaration {
+            name: name.to_string(),
+            column_type: inner,
+        }),
+        None => Err(ParseError::new(source, column_type.1, "a column type")),
+    };
+}
+
+fn parse_lexed_declaration<'a>(
+    lexer: &mut SpannedLexer<'a>,
+    source: &'a str,
+) -> Result<RelationDeclaration, ParseError> {
+    match next_token(lexer, source, "'.decl'")? {
+        (DatalogToken::Dot, _) => {}
+        (_, span) => return Err(ParseError::new(source, span, "'.decl'")),
+    }
+    match next_token(lexer, source, "'.decl'")? {
+        (DatalogToken::Str("decl"), _) => {}
+        (_, span) => return Err(ParseError::new(source, span, "'.decl'")),
+    }
+    let symbol = match next_token(lexer, source, "a predicate symbol")? {
+        (DatalogToken::Str(symbol), _) if Builtin::from_symbol(symbol).is_none() => symbol,
+        (_, span) => return Err(ParseError::new(source, span, "a predicate symbol")),
+    };
+    match next_token(lexer, source, "'('")? {
+        (DatalogToken::LParen, _) => {}
+        (_, span) => return Err(ParseError::new(source, span, "'('")),
+    }
+
+    let mut columns = vec![];
+    if let Some((DatalogToken::RParen, _)) = lexer.peek() {
+        lexer.next();
+    } else {
+        loop {
+            columns.push(parse_lexed_column(lexer, source)?);
+
+            match next_token(lexer, source, "',' or ')'")? {
+                (DatalogToken::Comma, _) => continue,
+                (DatalogToken::RParen, _) => break,
+                (_, span) => return Err(ParseError::new(source, span, "',' or ')'")),
             }
-            _ => {}
         }
-        look_behind = token
     }
-    return SugaredRule { head, body };
+
+    return Ok(RelationDeclaration {
+        symbol: symbol.to_string(),
+        columns,
+    });
+}
+
+pub fn parse_declaration(declaration: &str) -> Result<RelationDeclaration, ParseError> {
+    let mut lexer = DatalogToken::lexer(declaration).spanned().peekable();
+
+    let parsed_declaration = parse_lexed_declaration(&mut lexer, declaration)?;
+    expect_end_of_input(&mut lexer, declaration)?;
+
+    return Ok(parsed_declaration);
+}
+
+// Schema violations are reported on the whole statement, since terms do not keep their spans
+fn schema_error(source: &str, span: Range<usize>, error: ValidationError) -> ParseError {
+    let expected = match error {
+        ValidationError::TypeMismatch {
+            symbol,
+            column,
+            expected,
+            ..
+        } => format!("a {} in column {} of {}", expected, column, symbol),
+        ValidationError::ArityMismatch {
+            symbol, expected, ..
+        } => format!("{} terms in {}, as declared", expected, symbol),
+        ValidationError::ConflictingDeclaration { symbol } => {
+            format!("a single declaration of {}", symbol)
+        }
+        error => error.to_string(),
+    };
+
+    return ParseError::new(source, span, &expected);
+}
+
+// A program document is a sequence of declarations, rules and ground facts, each rule and fact
+// ended by a '.', and possibly spanning several lines. Declarations, such as
+// .decl edge(from: uint, to: uint), apply to the whole document, regardless of where they are.
+// Everything after a '%' or '//' until the end of the line is a comment.
+pub fn parse_program(
+    document: &str,
+) -> Result<(SugaredProgram, Vec<SugaredAtom>, Schema), ParseError> {
+    let mut lexer = DatalogToken::lexer(document).spanned().peekable();
+    let mut program = vec![];
+    let mut facts = vec![];
+    let mut schema = Schema::default();
+    let mut rule_spans = vec![];
+    let mut fact_spans = vec![];
+
+    while let Some((token, span)) = lexer.peek() {
+        let statement_start = span.start;
+        let mut interner: BTreeMap<&str, u32> = BTreeMap::new();
+
+        if *token == DatalogToken::Dot {
+            let declaration = parse_lexed_declaration(&mut lexer, document)?;
+            let statement_end = lexer
+                .peek()
+                .map_or(document.len(), |(_token, span)| span.start);
+            schema
+                .declare(declaration)
+                .map_err(|error| schema_error(document, statement_start..statement_end, error))?;
+            continue;
+        }
+
+        if *token == DatalogToken::LBracket {
+            let body = parse_lexed_body(&mut lexer, document, &mut interner)?;
+            parse_head_direction(&mut lexer, document, "->", "'->'")?;
+            let head = parse_lexed_head(&mut lexer, document, &mut interner)?;
+            let statement_end = parse_terminator(&mut lexer, document)?;
+
+            program.push(SugaredRule { head, body });
+            rule_spans.push(statement_start..statement_end);
+            continue;
+        }
+
+        let head = parse_lexed_head(&mut lexer, document, &mut interner)?;
+        if let Some((DatalogToken::Dot, span)) = lexer.peek() {
+            if !interner.is_empty() {
+                return Err(ParseError::new(
+                    document,
+                    statement_start..span.start,
+                    "a ground fact",
+                ));
+            }
+            let statement_end = span.end;
+            lexer.next();
+
+            facts.push(head);
+            fact_spans.push(statement_start..statement_end);
+            continue;
+        }
+        parse_head_direction(&mut lexer, document, "<-", "'<-' or '.'")?;
+        let body = parse_lexed_body(&mut lexer, document, &mut interner)?;
+        let statement_end = parse_terminator(&mut lexer, document)?;
+
+        program.push(SugaredRule { head, body });
+        rule_spans.push(statement_start..statement_end);
+    }
+
+    for (rule, span) in program.iter().zip(rule_spans) {
+        schema
+            .check_rule(rule)
+            .map_err(|error| schema_error(document, span, error))?;
+    }
+    for (fact, span) in facts.iter().zip(fact_spans) {
+        schema
+            .check_atom(fact)
+            .map_err(|error| schema_error(document, span, error))?;
+    }
+
+    return Ok((program, facts, schema));
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::models::datalog::{SugaredAtom, SugaredRule, Term, TypedValue};
+    use crate::models::datalog::{Aggregate, Pattern, SugaredAtom, SugaredRule, Term, TypedValue};
+    use crate::parsers::datalog::{
+        parse_declaration, parse_pattern, parse_program, parse_sugared_atom, parse_sugared_rule,
+        ParseError,
+    };
+    use ordered_float::OrderedFloat;
 
     #[test]
     fn test_parse_sugared_atom() {
@@ -116,9 +674,9 @@ mod tests {
         let some_sugared_atom_2 = "!Y(?a, yeah, false)";
         let some_sugared_atom_3 = "Z(?a, 4, 5)";
 
-        let parsed_sugared_atom_1 = SugaredAtom::from(some_sugared_atom_1);
-        let parsed_sugared_atom_2 = SugaredAtom::from(some_sugared_atom_2);
-        let parsed_sugared_atom_3 = SugaredAtom::from(some_sugared_atom_3);
+        let parsed_sugared_atom_1 = SugaredAtom::try_from(some_sugared_atom_1).unwrap();
+        let parsed_sugared_atom_2 = SugaredAtom::try_from(some_sugared_atom_2).unwrap();
+        let parsed_sugared_atom_3 = SugaredAtom::try_from(some_sugared_atom_3).unwrap();
 
         let expected_parsed_sugared_atom_1 = SugaredAtom {
             terms: vec![
@@ -188,9 +746,297 @@ mod tests {
                 },
             ],
         };
-        let some_parsed_rule = SugaredRule::from(some_rule);
-        let some_parsed_reversed_rule = SugaredRule::from(some_reversed_rule);
+        let some_parsed_rule = SugaredRule::try_from(some_rule).unwrap();
+        let some_parsed_reversed_rule = SugaredRule::try_from(some_reversed_rule).unwrap();
         assert_eq!(expected_parsing, some_parsed_rule);
         assert_eq!(expected_parsing, some_parsed_reversed_rule)
     }
+
+    #[test]
+    fn test_parse_pattern() {
+        let pattern = parse_pattern("[T(?x, type, ?c), !retired(?x), ?c != Robot]").unwrap();
+
+        assert_eq!(
+            vec![
+                "T(?0, type, ?1)".to_string(),
+                "!retired(?0)".to_string(),
+                "?1 != Robot".to_string()
+            ],
+            pattern
+                .atoms
+                .iter()
+                .map(|atom| atom.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec!["?x".to_string(), "?c".to_string()], pattern.variables);
+
+        let pattern = parse_pattern("T(?x, type, Person)").unwrap();
+        assert_eq!(1, pattern.atoms.len());
+        assert_eq!(vec!["?x".to_string()], pattern.variables);
+
+        let pattern = Pattern::from(vec![parse_sugared_atom("T(?x, ?y, ?x)").unwrap()]);
+        assert_eq!(vec!["?0".to_string(), "?1".to_string()], pattern.variables);
+
+        assert!(parse_pattern("[T(?x, type, Person)] -> q(?x)").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Err(ParseError {
+                span: 8..10,
+                expected: "',' or ')'".to_string(),
+                found: "'?y'".to_string()
+            }),
+            parse_sugared_atom("edge(?x ?y)")
+        );
+
+        let missing_bracket = "reach(?x, ?y) <- [edge(?x, ?y)";
+        assert_eq!(
+            Err(ParseError {
+                span: 30..30,
+                expected: "',' or ']'".to_string(),
+                found: "end of input".to_string()
+            }),
+            parse_sugared_rule(missing_bracket)
+        );
+
+        let missing_parenthesis = "reach(?x, ?y) <- [edge ?x, ?y)]";
+        let error = parse_sugared_rule(missing_parenthesis).unwrap_err();
+        assert_eq!(
+            "error at 1:24: expected '(', found '?x' at 23..25\n\
+             reach(?x, ?y) <- [edge ?x, ?y)]\n                       ^^",
+            error.render(missing_parenthesis)
+        );
+
+        assert!(parse_sugared_rule("!reach(?x, ?y) <- [edge(?x, ?y)]").is_err());
+        assert!(parse_sugared_rule("[edge(?x, ?y)] <- reach(?x, ?y)").is_err());
+        assert!(parse_sugared_rule("reach(?x, ?y) <- [edge(?x, ?y)] ]").is_err());
+    }
+
+    #[test]
+    fn test_parse_program() {
+        let document = "
+            % Reachability
+            reach(?x, ?y) <- [edge(?x, ?y)].
+            reach(?x, ?z) <- [
+                reach(?x, ?y), // Left recursive
+                edge(?y, ?z)
+            ].
+            [node(?x), !reach(?x, ?x)] -> acyclic(?x).
+
+            edge(a, b). edge(b, 2).
+        ";
+
+        let (program, facts, _schema) = parse_program(document).unwrap();
+
+        assert_eq!(
+            vec![
+                "reach(?0, ?1) <- [edge(?0, ?1)]",
+                "reach(?0, ?1) <- [reach(?0, ?2), edge(?2, ?1)]",
+                "acyclic(?0) <- [node(?0), !reach(?0, ?0)]",
+            ],
+            program
+                .iter()
+                .map(|rule| rule.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["edge(a, b)", "edge(b, 2)"],
+            facts
+                .iter()
+                .map(|fact| fact.to_string())
+                .collect::<Vec<_>>()
+        );
+
+        let error = parse_program("edge(a, b).\nreach(?x, ?y) <- [edge(?x, ?y)]").unwrap_err();
+        assert_eq!("'.'", error.expected);
+        assert_eq!("end of input", error.found);
+
+        let error = parse_program("edge(?x, b).").unwrap_err();
+        assert_eq!("a ground fact", error.expected);
+        assert_eq!("'edge(?x, b)'", error.found);
+    }
+
+    #[test]
+    fn test_parse_declarations() {
+        let document = "
+            .decl edge(from: uint, to:uint)
+            .decl name(id : uint, name: str)
+            edge(1, 2).
+            reach(?x, ?y) <- [edge(?x, ?y)].
+        ";
+
+        let (_program, facts, schema) = parse_program(document).unwrap();
+
+        assert_eq!(1, facts.len());
+        assert_eq!(
+            vec![
+                ".decl edge(from: uint, to: uint)",
+                ".decl name(id: uint, name: str)"
+            ],
+            schema
+                .declarations
+                .values()
+                .map(|declaration| declaration.to_string())
+                .collect::<Vec<_>>()
+        );
+
+        let error = parse_program(".decl edge(from: uint, to: uint)\nedge(a, 2).").unwrap_err();
+        assert_eq!("a uint in column from of edge", error.expected);
+        assert_eq!("'edge(a, 2).'", error.found);
+
+        let error = parse_program("reach(?x) <- [edge(?x)].\n.decl edge(from: uint, to: uint)")
+            .unwrap_err();
+        assert_eq!("2 terms in edge, as declared", error.expected);
+        assert_eq!("'reach(?x) <- [edge(?x)].'", error.found);
+
+        let error = parse_program(".decl edge(from: uint)\n.decl edge(to: uint)").unwrap_err();
+        assert_eq!("a single declaration of edge", error.expected);
+
+        let error = parse_declaration(".decl edge(from: node)").unwrap_err();
+        assert_eq!("a column type", error.expected);
+        assert_eq!("'node'", error.found);
+    }
+
+    #[test]
+    fn test_parse_builtins() {
+        let rule = parse_sugared_rule(
+            "dist(?x, ?z, ?d) <- [dist(?x, ?y, ?v), edge(?y, ?z, ?w), ?d = ?v + ?w, ?d < 10, \
+             !?y = ?z, starts_with(?x, a)]",
+        )
+        .unwrap();
+        let expected_rule = "dist(?0, ?1, ?2) <- [dist(?0, ?3, ?4), edge(?3, ?1, ?5), \
+                             ?2 = ?4 + ?5, ?2 < 10, !?3 = ?1, starts_with(?0, a)]";
+
+        assert_eq!(expected_rule, rule.to_string());
+        assert_eq!(
+            expected_rule,
+            parse_sugared_rule(expected_rule).unwrap().to_string()
+        );
+        assert_eq!("+", rule.body[2].symbol);
+        assert_eq!(
+            vec![Term::Variable(4), Term::Variable(5), Term::Variable(2)],
+            rule.body[2].terms
+        );
+        assert!(!rule.body[4].positive);
+        assert_eq!(
+            "?0 >= 1.5",
+            parse_sugared_atom("?x >= 1.5").unwrap().to_string()
+        );
+
+        let error = parse_sugared_rule("q(?x) <- [a(?x), ?x]").unwrap_err();
+        assert_eq!("a comparison", error.expected);
+        assert_eq!("']'", error.found);
+
+        let error = parse_sugared_rule("q(?x) <- [a(?x), ?x < ]").unwrap_err();
+        assert_eq!("a term", error.expected);
+
+        assert!(parse_sugared_rule("starts_with(?x, a) <- [a(?x)]").is_err());
+    }
+
+    #[test]
+    fn test_parse_aggregates() {
+        let rule = parse_sugared_rule("deg(?x, count(?y), max(?w)) <- [edge(?x, ?y, ?w)]").unwrap();
+
+        assert_eq!(
+            "deg(?0, count(?1), max(?2)) <- [edge(?0, ?1, ?2)]",
+            rule.to_string()
+        );
+        assert_eq!(Term::Aggregate(Aggregate::Count, 1), rule.head.terms[1]);
+        assert!(rule.head.has_aggregates());
+
+        let error = parse_sugared_rule("deg(?x, median(?y)) <- [edge(?x, ?y)]").unwrap_err();
+        assert_eq!("',' or ')'", error.expected);
+
+        let error = parse_sugared_rule("deg(?x, count(a)) <- [edge(?x, ?y)]").unwrap_err();
+        assert_eq!("a variable", error.expected);
+
+        assert!(parse_sugared_rule("deg(?x) <- [edge(?x, count(?y))]").is_err());
+    }
+
+    #[test]
+    fn test_parse_literals() {
+        let rule = parse_sugared_rule(
+            r#"name(?x, "John \"Jr\"\tSmith") <- [T(?x, <http://xmlns.com/foaf/0.1/name>, "John Smith"), T(?x, rdf:type, ub:Course10), !T(?x, age, "10")]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Term::Constant(TypedValue::Str("John \"Jr\"\tSmith".to_string())),
+            rule.head.terms[1]
+        );
+        assert_eq!(
+            Term::Constant(TypedValue::Str(
+                "http://xmlns.com/foaf/0.1/name".to_string()
+            )),
+            rule.body[0].terms[1]
+        );
+        assert_eq!(
+            Term::Constant(TypedValue::Str("10".to_string())),
+            rule.body[2].terms[2]
+        );
+
+        let expected_rule = r#"name(?0, "John \"Jr\"\tSmith") <- [T(?0, <http://xmlns.com/foaf/0.1/name>, "John Smith"), T(?0, rdf:type, ub:Course10), !T(?0, age, "10")]"#;
+        assert_eq!(expected_rule, rule.to_string());
+        assert_eq!(rule, parse_sugared_rule(&rule.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_parse_integers() {
+        let atom = parse_sugared_atom("T(-5, 5, 4294967296)").unwrap();
+
+        assert_eq!(
+            vec![
+                Term::Constant(TypedValue::Int(-5)),
+                Term::Constant(TypedValue::UInt(5)),
+                Term::Constant(TypedValue::UInt64(4294967296)),
+            ],
+            atom.terms
+        );
+        assert_eq!("T(-5, 5, 4294967296)", atom.to_string());
+        assert!(parse_sugared_atom("T(18446744073709551616)").is_err());
+        assert!(parse_sugared_atom("T(-9223372036854775809)").is_err());
+    }
+
+    #[test]
+    fn test_parse_subtraction() {
+        let expected_terms = vec![
+            Term::Variable(1),
+            Term::Constant(TypedValue::UInt(1)),
+            Term::Variable(0),
+        ];
+
+        for body in ["?z = ?x - 1", "?z = ?x -1", "?z = ?x-1"] {
+            let rule = parse_sugared_rule(&format!("q(?z) <- [p(?x), {}]", body)).unwrap();
+
+            assert_eq!("-", rule.body[1].symbol);
+            assert_eq!(expected_terms, rule.body[1].terms);
+        }
+
+        let rule = parse_sugared_rule("q(?z) <- [p(?x), ?z = ?x -1.5]").unwrap();
+        assert_eq!("-", rule.body[1].symbol);
+        assert_eq!(
+            Term::Constant(TypedValue::Float(OrderedFloat(1.5))),
+            rule.body[1].terms[1]
+        );
+
+        let rule = parse_sugared_rule("q(?z) <- [p(?x), ?z = ?x - -1]").unwrap();
+        assert_eq!("-", rule.body[1].symbol);
+        assert_eq!(Term::Constant(TypedValue::Int(-1)), rule.body[1].terms[1]);
+    }
+
+    #[test]
+    fn test_parse_many_variables() {
+        let variables: Vec<String> = (0..300).map(|idx| format!("?v{}", idx)).collect();
+        let rule = parse_sugared_rule(&format!(
+            "ends(?v0, ?v299) <- [wide({})]",
+            variables.join(", ")
+        ))
+        .unwrap();
+
+        assert_eq!(vec![Term::Variable(0), Term::Variable(1)], rule.head.terms);
+        assert_eq!(Term::Variable(299), rule.body[0].terms[298]);
+        assert_eq!(Term::Variable(1), rule.body[0].terms[299]);
+    }
 }
@@ -0,0 +1,758 @@
+// Fact loaders read data files a line at a time, turning every line into atoms, which are then
+// either collected, or streamed into a materializer in batches of insertions.
+
+use crate::lexers::datalog::DatalogToken;
+use crate::models::datalog::{SugaredAtom, Term, TypedValue};
+use crate::models::reasoner::{Diff, Materializer, UntypedRow};
+use crate::parsers::datalog::ParseError;
+use crate::parsers::rdf::{
+    parse_n_quad, parse_n_triple, Prefixes, RdfTerm, Triple, TurtleDocument, OWL,
+};
+use flate2::read::MultiGzDecoder;
+use logos::Logos;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(std::io::Error),
+    // The line, counting from 1, and what was wrong with it
+    Parse {
+        line_number: usize,
+        line: String,
+        error: ParseError,
+    },
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "{}", e),
+            LoadError::Parse {
+                line_number,
+                line,
+                error,
+            } => write!(f, "line {}: {}\n{}", line_number, error, line),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<std::io::Error> for LoadError {
+    fn from(e: std::io::Error) -> Self {
+        return LoadError::Io(e);
+    }
+}
+
+pub trait FactParser {
+    // A line may hold no atom at all, such as a blank line or a comment, or several
+    fn parse_line(&mut self, line: &str) -> Result<Vec<SugaredAtom>, ParseError>;
+    // After the last line, for parsers whose statements may span several lines
+    fn finish(&mut self) -> Result<Vec<SugaredAtom>, ParseError> {
+        return Ok(vec![]);
+    }
+}
+
+fn fact(symbol: &str, values: Vec<TypedValue>) -> SugaredAtom {
+    return SugaredAtom {
+        terms: values.into_iter().map(Term::Constant).collect(),
+        symbol: symbol.to_string(),
+        positive: true,
+    };
+}
+
+fn triple_fact(prefixes: &Prefixes, (subject, predicate, object): Triple) -> SugaredAtom {
+    return fact(
+        "T",
+        vec![
+            prefixes.to_typed_value(subject),
+            prefixes.to_typed_value(predicate),
+            prefixes.to_typed_value(object),
+        ],
+    );
+}
+
+// Every triple is a T(subject, predicate, object) atom, as in data/rdfs.dl
+#[derive(Default)]
+pub struct NTriplesParser {
+    prefixes: Prefixes,
+}
+
+impl NTriplesParser {
+    pub fn new(prefixes: Prefixes) -> Self {
+        return Self { prefixes };
+    }
+}
+
+impl FactParser for NTriplesParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<SugaredAtom>, ParseError> {
+        return Ok(parse_n_triple(line)?
+            .into_iter()
+            .map(|triple| triple_fact(&self.prefixes, triple))
+            .collect());
+    }
+}
+
+// Statements of the default graph are T(subject, predicate, object) atoms, and those of a named
+// graph Q(subject, predicate, object, graph) atoms
+#[derive(Default)]
+pub struct NQuadsParser {
+    prefixes: Prefixes,
+}
+
+impl NQuadsParser {
+    pub fn new(prefixes: Prefixes) -> Self {
+        return Self { prefixes };
+    }
+}
+
+impl FactParser for NQuadsParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<SugaredAtom>, ParseError> {
+        return Ok(parse_n_quad(line)?
+            .into_iter()
+            .map(|(subject, predicate, object, graph)| match graph {
+                Some(graph) => fact(
+                    "Q",
+                    vec![
+                        self.prefixes.to_typed_value(subject),
+                        self.prefixes.to_typed_value(predicate),
+                        self.prefixes.to_typed_value(object),
+                        self.prefixes.to_typed_value(graph),
+                    ],
+                ),
+                None => triple_fact(&self.prefixes, (subject, predicate, object)),
+            })
+            .collect());
+    }
+}
+
+// Every triple is a T(subject, predicate, object) atom. Lines are buffered until they make up whole
+// statements. The document's own prefixes only expand its prefixed names, while the given ones
+// abbreviate IRIs.
+#[derive(Default)]
+pub struct TurtleParser {
+    prefixes: Prefixes,
+    document: TurtleDocument,
+    // The lines of the statement that is yet to be completed
+    buffer: String,
+}
+
+impl TurtleParser {
+    pub fn new(prefixes: Prefixes) -> Self {
+        return Self {
+            prefixes,
+            ..Default::default()
+        };
+    }
+}
+
+impl FactParser for TurtleParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<SugaredAtom>, ParseError> {
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+        let (triples, end) = self.document.parse_statements(&self.buffer)?;
+        self.buffer.drain(..end);
+
+        return Ok(triples
+            .into_iter()
+            .map(|triple| triple_fact(&self.prefixes, triple))
+            .collect());
+    }
+    fn finish(&mut self) -> Result<Vec<SugaredAtom>, ParseError> {
+        if self.buffer.is_empty() {
+            return Ok(vec![]);
+        }
+
+        return Err(ParseError::new(
+            &self.buffer,
+            self.buffer.len()..self.buffer.len(),
+            "the rest of the statement",
+        ));
+    }
+}
+
+const UNIV_BENCH: &str = "http://www.lehigh.edu/~zhp2/2004/0401/univ-bench.owl#";
+
+// LUBM's univ-bench vocabulary, where every rdf:type triple is a unary atom of its class, and every
+// triple of a univ-bench property a binary atom of the property. Other triples are skipped.
+pub struct LubmParser;
+
+impl FactParser for LubmParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<SugaredAtom>, ParseError> {
+        let (subject, predicate, object) = match parse_n_triple(line)? {
+            Some(triple) => triple,
+            None => return Ok(vec![]),
+        };
+
+        return Ok(match (predicate, object) {
+            (RdfTerm::Iri(predicate), RdfTerm::Iri(class))
+                if predicate == "http://www.w3.org/1999/02/22-rdf-syntax-ns#type" =>
+            {
+                let symbol = match class.strip_prefix(UNIV_BENCH) {
+                    Some(local_name) => local_name.to_string(),
+                    None => OWL
+                        .get(class.as_str())
+                        .map_or(class.clone(), |alias| alias.to_string()),
+                };
+
+                vec![fact(&symbol, vec![subject.into()])]
+            }
+            (RdfTerm::Iri(predicate), object) => match predicate.strip_prefix(UNIV_BENCH) {
+                Some(property) => vec![fact(property, vec![subject.into(), object.into()])],
+                None => vec![],
+            },
+            _ => vec![],
+        });
+    }
+}
+
+// Whitespace separated constants, followed by the relation that they belong to
+pub struct SpaceSeparatedParser;
+
+impl FactParser for SpaceSeparatedParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<SugaredAtom>, ParseError> {
+        let raw_terms: Vec<&str> = line.split_whitespace().collect();
+
+        return Ok(match raw_terms.split_last() {
+            Some((symbol, raw_terms)) => vec![fact(
+                symbol,
+                raw_terms
+                    .iter()
+                    .map(|raw_term| TypedValue::Str(raw_term.to_string()))
+                    .collect(),
+            )],
+            None => vec![],
+        });
+    }
+}
+
+// The atoms of a relation, out of the named columns of a delimited file, in order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RelationMapping {
+    pub relation: String,
+    pub columns: Vec<String>,
+}
+
+// Comma or tab separated values, whose first line is a header that names the columns. Every row is
+// an atom of every mapping. Fields are read the way constants are in programs, such that numbers
+// and booleans are not strings, except for quoted CSV fields, which always are.
+pub struct DelimitedParser {
+    delimiter: char,
+    mappings: Vec<RelationMapping>,
+    // The amount of columns, and the indexes of the columns of every mapping, once the header was
+    // read
+    header: Option<(usize, Vec<Vec<usize>>)>,
+}
+
+impl DelimitedParser {
+    pub fn csv(mappings: Vec<RelationMapping>) -> Self {
+        return Self {
+            delimiter: ',',
+            mappings,
+            header: None,
+        };
+    }
+    pub fn tsv(mappings: Vec<RelationMapping>) -> Self {
+        return Self {
+            delimiter: '\t',
+            mappings,
+            header: None,
+        };
+    }
+    // Every field, and whether it was quoted
+    fn split(&self, line: &str) -> Result<Vec<(String, bool)>, ParseError> {
+        if self.delimiter == '\t' {
+            return line
+                .split('\t')
+                .map(|field| Ok((unescape_tsv_field(field), false)))
+                .collect();
+        }
+
+        let mut fields = vec![];
+        let mut chars = line.char_indices().peekable();
+        loop {
+            let mut field = String::new();
+            let quoted = matches!(chars.peek(), Some((_, '"')));
+            if quoted {
+                let (start, _) = chars.next().unwrap();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) if matches!(chars.peek(), Some((_, '"'))) => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some((_, '"')) => break,
+                        Some((_, c)) => field.push(c),
+                        None => {
+                            return Err(ParseError::new(line, start..start + 1, "a closing '\"'"))
+                        }
+                    }
+                }
+            }
+            let mut last_field = true;
+            loop {
+                match chars.next() {
+                    Some((_, ',')) => {
+                        last_field = false;
+                        break;
+                    }
+                    None => break,
+                    Some((position, c)) if quoted => {
+                        return Err(ParseError::new(
+                            line,
+                            position..position + c.len_utf8(),
+                            "',' or end of line",
+                        ))
+                    }
+                    Some((_, c)) => field.push(c),
+                }
+            }
+            fields.push((field, quoted));
+
+            if last_field {
+                return Ok(fields);
+            }
+        }
+    }
+}
+
+fn unescape_tsv_field(field: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other)
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+
+    return unescaped;
+}
+
+fn field_to_typed_value(field: &str) -> TypedValue {
+    // The lexer leaves the sign of a number to the parser, hence it is split off here
+    let (negative, unsigned) = match field.strip_prefix('-') {
+        Some(unsigned) if unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '.') => {
+            (true, unsigned)
+        }
+        _ => (false, field),
+    };
+    let mut lexer = DatalogToken::lexer(unsigned);
+    let typed_value = match lexer.next() {
+        Some(DatalogToken::UIntConst(inner)) if negative => {
+            TypedValue::from_integer(-(inner as i128))
+        }
+        Some(DatalogToken::UIntConst(inner)) => TypedValue::from_integer(inner as i128),
+        Some(DatalogToken::FloatConst(inner)) if negative => Some(TypedValue::Float(-inner)),
+        Some(DatalogToken::BoolConst(inner)) => Some(TypedValue::Bool(inner)),
+        Some(DatalogToken::FloatConst(inner)) => Some(TypedValue::Float(inner)),
+        _ => None,
+    };
+    // Only if the whole field is the constant
+    if lexer.span() != (0..unsigned.len()) {
+        return TypedValue::Str(field.to_string());
+    }
+
+    return typed_value.unwrap_or_else(|| TypedValue::Str(field.to_string()));
+}
+
+impl FactParser for DelimitedParser {
+    fn parse_line(&mut self, line: &str) -> Result<Vec<SugaredAtom>, ParseError> {
+        if line.trim().is_empty() {
+            return Ok(vec![]);
+        }
+        let fields = self.split(line)?;
+
+        let (column_count, column_indexes) = match &self.header {
+            Some(header) => header,
+            None => {
+                let names: Vec<&str> = fields.iter().map(|(name, _)| name.trim()).collect();
+                let column_indexes = self
+                    .mappings
+                    .iter()
+                    .map(|mapping| {
+                        mapping
+                            .columns
+                            .iter()
+                            .map(|column| {
+                                names.iter().position(|name| name == column).ok_or_else(|| {
+                                    ParseError::new(
+                                        line,
+                                        0..line.len(),
+                                        &format!("a column named {}", column),
+                                    )
+                                })
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.header = Some((names.len(), column_indexes));
+
+                return Ok(vec![]);
+            }
+        };
+        if fields.len() != *column_count {
+            return Err(ParseError::new(
+                line,
+                line.len()..line.len(),
+                &format!("{} fields, as in the header", column_count),
+            ));
+        }
+
+        return Ok(self
+            .mappings
+            .iter()
+            .zip(column_indexes)
+            .map(|(mapping, indexes)| {
+                fact(
+                    &mapping.relation,
+                    indexes
+                        .iter()
+                        .map(|index| match &fields[*index] {
+                            (field, true) => TypedValue::Str(field.clone()),
+                            (field, false) => field_to_typed_value(field),
+                        })
+                        .collect(),
+                )
+            })
+            .collect());
+    }
+}
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// Opens the file for reading, decompressing it on the fly if it is zstd or gzip compressed, which is
+// told apart by its first bytes rather than by its extension
+pub fn open_data_file(path: impl AsRef<Path>) -> Result<Box<dyn BufRead>, LoadError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let magic = reader.fill_buf()?;
+
+    if magic.starts_with(&ZSTD_MAGIC) {
+        return Ok(Box::new(BufReader::new(zstd::Decoder::with_buffer(
+            reader,
+        )?)));
+    }
+    if magic.starts_with(&GZIP_MAGIC) {
+        return Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))));
+    }
+
+    return Ok(Box::new(reader));
+}
+
+// Facts have constants only
+pub fn atom_to_diff(sign: bool, atom: &SugaredAtom) -> Diff<'_> {
+    let row: UntypedRow = atom
+        .terms
+        .iter()
+        .map(|term| match term {
+            Term::Constant(inner) => inner.clone().into(),
+            _ => unreachable!(),
+        })
+        .collect();
+
+    return (sign, (atom.symbol.as_str(), row));
+}
+
+// Hands every atom over, in order, stopping at the first line that can not be read
+fn for_each_fact(
+    reader: impl BufRead,
+    parser: &mut dyn FactParser,
+    mut f: impl FnMut(SugaredAtom),
+) -> Result<usize, LoadError> {
+    let mut fact_count = 0;
+    let mut line_count = 0;
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let atoms = parser.parse_line(&line).map_err(|error| LoadError::Parse {
+            line_number: line_index + 1,
+            line: line.clone(),
+            error,
+        })?;
+
+        fact_count += atoms.len();
+        atoms.into_iter().for_each(&mut f);
+        line_count = line_index + 1;
+    }
+    let atoms = parser.finish().map_err(|error| LoadError::Parse {
+        line_number: line_count,
+        line: String::new(),
+        error,
+    })?;
+    fact_count += atoms.len();
+    atoms.into_iter().for_each(&mut f);
+
+    return Ok(fact_count);
+}
+
+pub fn read_facts(
+    reader: impl BufRead,
+    parser: &mut dyn FactParser,
+) -> Result<Vec<SugaredAtom>, LoadError> {
+    let mut facts = vec![];
+    for_each_fact(reader, parser, |atom| facts.push(atom))?;
+
+    return Ok(facts);
+}
+
+// Inserts the facts with an update for every batch_size of them, or a single one if it is 0, and
+// returns how many there were. The batches before a line that can not be read stay inserted.
+pub fn load_facts(
+    reader: impl BufRead,
+    parser: &mut dyn FactParser,
+    materializer: &mut dyn Materializer,
+    batch_size: usize,
+) -> Result<usize, LoadError> {
+    let mut batch: Vec<SugaredAtom> = vec![];
+    let mut flush = |batch: &mut Vec<SugaredAtom>| {
+        materializer.update(batch.iter().map(|atom| atom_to_diff(true, atom)).collect());
+        batch.clear();
+    };
+
+    let fact_count = for_each_fact(reader, parser, |atom| {
+        batch.push(atom);
+        if batch.len() == batch_size {
+            flush(&mut batch)
+        }
+    })?;
+    if !batch.is_empty() {
+        flush(&mut batch)
+    }
+
+    return Ok(fact_count);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::datalog::{SugaredAtom, SugaredRule, Term, TypedValue};
+    use crate::models::reasoner::{Materializer, Queryable};
+    use crate::parsers::loader::{
+        load_facts, open_data_file, read_facts, DelimitedParser, FactParser, LoadError, LubmParser,
+        NQuadsParser, NTriplesParser, RelationMapping, SpaceSeparatedParser, TurtleParser,
+    };
+    use crate::parsers::rdf::Prefixes;
+    use crate::reasoning::reasoners::chibi::ChibiDatalog;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use ordered_float::OrderedFloat;
+    use std::io::Write;
+
+    fn parse(parser: &mut dyn FactParser, line: &str) -> Vec<String> {
+        return parser
+            .parse_line(line)
+            .unwrap()
+            .iter()
+            .map(|atom| atom.to_string())
+            .collect();
+    }
+
+    #[test]
+    fn test_fact_parsers() {
+        assert_eq!(
+            vec![SugaredAtom::try_from(
+                r#"T(<http://a.org/x>, rdf:type, "\"label with spaces\"@en")"#
+            )
+            .unwrap()
+            .to_string()],
+            parse(
+                &mut NTriplesParser::default(),
+                r#"<http://a.org/x> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> "label with spaces"@en ."#
+            )
+        );
+        assert_eq!(
+            vec!["Student(<http://a.org/x>)".to_string()],
+            parse(
+                &mut LubmParser,
+                "<http://a.org/x> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://www.lehigh.edu/~zhp2/2004/0401/univ-bench.owl#Student> ."
+            )
+        );
+        assert!(parse(
+            &mut LubmParser,
+            "<http://a.org/x> <http://a.org/knows> <http://a.org/y> ."
+        )
+        .is_empty());
+        assert_eq!(
+            vec!["edge(a, b)".to_string()],
+            parse(&mut SpaceSeparatedParser, "a\tb  edge")
+        );
+
+        let mut prefixes = Prefixes::default();
+        prefixes.insert(
+            "ub",
+            "http://www.lehigh.edu/~zhp2/2004/0401/univ-bench.owl#",
+        );
+        let mut n_quads_parser = NQuadsParser::new(prefixes);
+        assert_eq!(
+            vec!["Q(<http://a.org/x>, rdf:type, ub:Student, <http://a.org/g>)".to_string()],
+            parse(
+                &mut n_quads_parser,
+                "<http://a.org/x> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://www.lehigh.edu/~zhp2/2004/0401/univ-bench.owl#Student> <http://a.org/g> ."
+            )
+        );
+        assert_eq!(
+            vec!["T(<http://a.org/x>, rdf:type, ub:Student)".to_string()],
+            parse(
+                &mut n_quads_parser,
+                "<http://a.org/x> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://www.lehigh.edu/~zhp2/2004/0401/univ-bench.owl#Student> ."
+            )
+        );
+
+        let mut csv_parser = DelimitedParser::csv(vec![
+            RelationMapping {
+                relation: "edge".to_string(),
+                columns: vec!["source".to_string(), "target".to_string()],
+            },
+            RelationMapping {
+                relation: "weight".to_string(),
+                columns: vec!["source".to_string(), "weight".to_string()],
+            },
+        ]);
+        assert!(parse(&mut csv_parser, "weight,source,target").is_empty());
+
+        let atoms = csv_parser.parse_line(r#"-1.5,"a, ""b""",42"#).unwrap();
+        assert_eq!(
+            vec![
+                Term::Constant(TypedValue::Str("a, \"b\"".to_string())),
+                Term::Constant(TypedValue::Float(OrderedFloat(-1.5))),
+            ],
+            atoms[1].terms
+        );
+        assert!(csv_parser.parse_line("1,a").is_err());
+        assert!(csv_parser.parse_line(r#"1,"a,2"#).is_err());
+
+        let mut tsv_parser = DelimitedParser::tsv(vec![RelationMapping {
+            relation: "said".to_string(),
+            columns: vec!["text".to_string()],
+        }]);
+        assert!(parse(&mut tsv_parser, "text").is_empty());
+        assert_eq!(
+            vec![r#"said("hi\tthere")"#.to_string()],
+            parse(&mut tsv_parser, "hi\\tthere")
+        );
+        assert!(tsv_parser.parse_line("").unwrap().is_empty());
+
+        let mut missing_column_parser = DelimitedParser::csv(vec![RelationMapping {
+            relation: "edge".to_string(),
+            columns: vec!["source".to_string()],
+        }]);
+        assert!(missing_column_parser.parse_line("target").is_err());
+    }
+
+    #[test]
+    fn test_load_facts() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, true, true);
+        reasoner
+            .materialize(&vec![SugaredRule::try_from(
+                "reach(?x, ?z) <- [edge(?x, ?y), edge(?y, ?z)]",
+            )
+            .unwrap()])
+            .unwrap();
+
+        let data = "a b edge\nb c edge\n\nc d edge\n";
+        let fact_count =
+            load_facts(data.as_bytes(), &mut SpaceSeparatedParser, &mut reasoner, 2).unwrap();
+
+        assert_eq!(3, fact_count);
+        assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("c")]));
+        assert!(reasoner.contains_row("reach", &vec![Box::new("b"), Box::new("d")]));
+
+        let error = read_facts(
+            "<http://a.org/x> <http://a.org/y> <http://a.org/z> .\n<broken".as_bytes(),
+            &mut NTriplesParser::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, LoadError::Parse { line_number: 2, .. }));
+
+        let mut prefixes = Prefixes::default();
+        prefixes.insert("ex", "http://a.org/");
+        let turtle = "@prefix ex: <http://a.org/> .\nex:a ex:edge ex:b ;\n  ex:edge ex:c .\n";
+        let facts = read_facts(turtle.as_bytes(), &mut TurtleParser::new(prefixes)).unwrap();
+        assert_eq!(
+            vec!["T(ex:a, ex:edge, ex:b)", "T(ex:a, ex:edge, ex:c)"],
+            facts
+                .iter()
+                .map(|atom| atom.to_string())
+                .collect::<Vec<_>>()
+        );
+
+        let error = read_facts(
+            "@prefix ex: <http://a.org/> .\nex:a ex:edge\n".as_bytes(),
+            &mut TurtleParser::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, LoadError::Parse { line_number: 2, .. }));
+    }
+
+    #[test]
+    fn test_load_literals() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, true, true);
+        reasoner
+            .materialize(&vec![SugaredRule::try_from(
+                r#"labelled(?x) <- [T(?x, rdfs:label, "label with spaces")]"#,
+            )
+            .unwrap()])
+            .unwrap();
+
+        let data = concat!(
+            "<http://a.org/x> <http://www.w3.org/2000/01/rdf-schema#label> \"label with spaces\" .\n",
+            "<http://a.org/y> <http://www.w3.org/2000/01/rdf-schema#label> \"label with spaces\"@en .\n",
+        );
+        load_facts(
+            data.as_bytes(),
+            &mut NTriplesParser::default(),
+            &mut reasoner,
+            1,
+        )
+        .unwrap();
+
+        assert!(reasoner.contains_row("labelled", &vec![Box::new("http://a.org/x")]));
+        assert!(!reasoner.contains_row("labelled", &vec![Box::new("http://a.org/y")]));
+    }
+
+    #[test]
+    fn test_open_data_file() {
+        let data = "a b edge\nb c edge\n";
+        let mut gzip_encoder = GzEncoder::new(vec![], Compression::default());
+        gzip_encoder.write_all(data.as_bytes()).unwrap();
+        let encodings = vec![
+            ("plain", data.as_bytes().to_vec()),
+            ("zst", zstd::encode_all(data.as_bytes(), 0).unwrap()),
+            ("gz", gzip_encoder.finish().unwrap()),
+        ];
+
+        encodings.into_iter().for_each(|(extension, contents)| {
+            let path = std::env::temp_dir().join(format!(
+                "shapiro_test_open_data_file_{}.{}",
+                std::process::id(),
+                extension
+            ));
+            std::fs::write(&path, contents).unwrap();
+
+            let facts = read_facts(open_data_file(&path).unwrap(), &mut SpaceSeparatedParser);
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(
+                vec!["edge(a, b)", "edge(b, c)"],
+                facts
+                    .unwrap()
+                    .iter()
+                    .map(|atom| atom.to_string())
+                    .collect::<Vec<_>>()
+            );
+        });
+    }
+}
@@ -0,0 +1,1033 @@
+// N-Triples and N-Quads, read a statement at a time, and Turtle. IRIs are kept without their angle
+// brackets, and abbreviated if they are one of the rdf, rdfs or owl IRIs that the rule sets use, or
+// are in one of the namespaces of the given Prefixes. Blank nodes keep their "_:" prefix, such that
+// they can not be mistaken for IRIs. Numeric and boolean literals become numbers and booleans, and
+// plain literals, of type xsd:string, their unquoted lexical form, as string constants of rules are,
+// while literals with a language tag, or another datatype, are kept in their canonical N-Triples
+// form, quotes, language tag and datatype included, such that they can be written back as they were.
+
+use crate::models::datalog::TypedValue;
+use crate::parsers::datalog::ParseError;
+use ahash::HashMap;
+use ordered_float::OrderedFloat;
+use phf::phf_map;
+
+pub static OWL: phf::Map<&'static str, &'static str> = phf_map! {
+    "http://www.w3.org/1999/02/22-rdf-syntax-ns#type" => "rdf:type",
+    "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest" => "rdf:rest",
+    "http://www.w3.org/1999/02/22-rdf-syntax-ns#first" => "rdf:first",
+    "http://www.w3.org/1999/02/22-rdf-syntax-ns#nil" => "rdf:nil",
+    "http://www.w3.org/1999/02/22-rdf-syntax-ns#Property" => "rdf:Property",
+    "http://www.w3.org/2000/01/rdf-schema#subClassOf" => "rdfs:subClassOf",
+    "http://www.w3.org/2000/01/rdf-schema#subPropertyOf" => "rdfs:subPropertyOf",
+    "http://www.w3.org/2000/01/rdf-schema#domain" => "rdfs:domain",
+    "http://www.w3.org/2000/01/rdf-schema#range" => "rdfs:range",
+    "http://www.w3.org/2000/01/rdf-schema#comment" => "rdfs:comment",
+    "http://www.w3.org/2000/01/rdf-schema#label" => "rdfs:label",
+    "http://www.w3.org/2000/01/rdf-schema#Literal" => "rdfs:Literal",
+    "http://www.w3.org/2002/07/owl#TransitiveProperty" => "owl:TransitiveProperty",
+    "http://www.w3.org/2002/07/owl#inverseOf" => "owl:inverseOf",
+    "http://www.w3.org/2002/07/owl#Thing" => "owl:Thing",
+    "http://www.w3.org/2002/07/owl#maxQualifiedCardinality" => "owl:maxQualifiedCardinality",
+    "http://www.w3.org/2002/07/owl#maxCardinality" => "owl:maxCardinality",
+    "http://www.w3.org/2002/07/owl#someValuesFrom" => "owl:someValuesFrom",
+    "http://www.w3.org/2002/07/owl#equivalentClass" => "owl:equivalentClass",
+    "http://www.w3.org/2002/07/owl#intersectionOf" => "owl:intersectionOf",
+    "http://www.w3.org/2002/07/owl#members" => "owl:members",
+    "http://www.w3.org/2002/07/owl#equivalentProperty" => "owl:equivalentProperty",
+    "http://www.w3.org/2002/07/owl#onProperty" => "owl:onProperty",
+    "http://www.w3.org/2002/07/owl#propertyChainAxiom" => "owl:propertyChainAxiom",
+    "http://www.w3.org/2002/07/owl#disjointWith" => "owl:disjointWith",
+    "http://www.w3.org/2002/07/owl#propertyDisjointWith" => "owl:propertyDisjointWith",
+    "http://www.w3.org/2002/07/owl#unionOf" => "owl:unionOf",
+    "http://www.w3.org/2002/07/owl#hasKey" => "owl:hasKey",
+    "http://www.w3.org/2002/07/owl#allValuesFrom" => "owl:allValuesFrom",
+    "http://www.w3.org/2002/07/owl#complementOf" => "owl:complementOf",
+    "http://www.w3.org/2002/07/owl#onClass" => "owl:onClass",
+    "http://www.w3.org/2002/07/owl#distinctMembers" => "owl:distinctMembers",
+    "http://www.w3.org/2002/07/owl#FunctionalProperty" => "owl:FunctionalProperty",
+    "http://www.w3.org/2002/07/owl#NamedIndividual" => "owl:NamedIndividual",
+    "http://www.w3.org/2002/07/owl#ObjectProperty" => "owl:ObjectProperty",
+    "http://www.w3.org/2002/07/owl#Class" => "owl:Class",
+    "http://www.w3.org/2002/07/owl#AllDisjointClasses" => "owl:AllDisjointClasses",
+    "http://www.w3.org/2002/07/owl#Restriction" => "owl:Restriction",
+    "http://www.w3.org/2002/07/owl#DatatypeProperty" => "owl:DatatypeProperty",
+    "http://www.w3.org/2002/07/owl#Ontology" => "owl:Ontology",
+    "http://www.w3.org/2002/07/owl#AsymmetricProperty" => "owl:AsymmetricProperty",
+    "http://www.w3.org/2002/07/owl#SymmetricProperty" => "owl:SymmetricProperty",
+    "http://www.w3.org/2002/07/owl#IrreflexiveProperty" => "owl:IrreflexiveProperty",
+    "http://www.w3.org/2002/07/owl#AllDifferent" => "owl:AllDifferent",
+    "http://www.w3.org/2002/07/owl#InverseFunctionalProperty" => "owl:InverseFunctionalProperty",
+    "http://www.w3.org/2002/07/owl#sameAs" => "owl:sameAs",
+    "http://www.w3.org/2002/07/owl#hasValue" => "owl:hasValue",
+    "http://www.w3.org/2002/07/owl#Nothing" => "owl:Nothing",
+    "http://www.w3.org/2002/07/owl#oneOf" => "owl:oneOf",
+};
+
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+const XSD_INTEGERS: [&str; 13] = [
+    "integer",
+    "int",
+    "long",
+    "short",
+    "byte",
+    "nonNegativeInteger",
+    "positiveInteger",
+    "nonPositiveInteger",
+    "negativeInteger",
+    "unsignedLong",
+    "unsignedInt",
+    "unsignedShort",
+    "unsignedByte",
+];
+
+const XSD_FLOATS: [&str; 3] = ["double", "float", "decimal"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RdfTerm {
+    Iri(String),
+    BlankNode(String),
+    Literal {
+        lexical_form: String,
+        language: Option<String>,
+        datatype: Option<String>,
+    },
+}
+
+pub type Triple = (RdfTerm, RdfTerm, RdfTerm);
+
+// A triple, and the IRI or blank node of its graph, if it is not in the default graph
+pub type Quad = (RdfTerm, RdfTerm, RdfTerm, Option<RdfTerm>);
+
+fn canonical_literal(
+    lexical_form: &str,
+    language: &Option<String>,
+    datatype: &Option<String>,
+) -> String {
+    let mut literal = String::from("\"");
+    lexical_form.chars().for_each(|c| match c {
+        '"' => literal.push_str("\\\""),
+        '\\' => literal.push_str("\\\\"),
+        '\n' => literal.push_str("\\n"),
+        '\r' => literal.push_str("\\r"),
+        c => literal.push(c),
+    });
+    literal.push('"');
+    if let Some(language) = language {
+        literal.push('@');
+        literal.push_str(language);
+    } else if let Some(datatype) = datatype {
+        literal.push_str(&format!("^^<{}>", datatype));
+    }
+
+    return literal;
+}
+
+fn typed_literal(lexical_form: &str, datatype: &str) -> Option<TypedValue> {
+    let xsd_type = datatype.strip_prefix(XSD)?;
+    let lexical_form = lexical_form.trim();
+
+    if XSD_INTEGERS.contains(&xsd_type) {
+        return TypedValue::from_integer(lexical_form.parse::<i128>().ok()?);
+    }
+    if XSD_FLOATS.contains(&xsd_type) {
+        return Some(TypedValue::Float(OrderedFloat(lexical_form.parse().ok()?)));
+    }
+    if xsd_type == "boolean" {
+        return match lexical_form {
+            "true" | "1" => Some(TypedValue::Bool(true)),
+            "false" | "0" => Some(TypedValue::Bool(false)),
+            _ => None,
+        };
+    }
+
+    return None;
+}
+
+// The prefixes that IRIs are abbreviated with, besides the OWL aliases, such that data lines up with
+// the constants of the rules, as in ub:Student
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Prefixes {
+    // Every prefix, and its namespace
+    namespaces: Vec<(String, String)>,
+}
+
+impl Prefixes {
+    pub fn insert(&mut self, prefix: &str, namespace: &str) {
+        self.namespaces
+            .retain(|(other_prefix, _)| other_prefix != prefix);
+        self.namespaces
+            .push((prefix.to_string(), namespace.to_string()));
+    }
+    // An OWL alias goes first, then the longest namespace that the IRI is in
+    pub fn abbreviate(&self, iri: &str) -> String {
+        if let Some(alias) = OWL.get(iri) {
+            return alias.to_string();
+        }
+
+        return self
+            .namespaces
+            .iter()
+            .filter(|(_, namespace)| iri.starts_with(namespace.as_str()))
+            .max_by_key(|(_, namespace)| namespace.len())
+            .map_or(iri.to_string(), |(prefix, namespace)| {
+                format!("{}:{}", prefix, &iri[namespace.len()..])
+            });
+    }
+    // The IRI that a prefixed name abbreviates, if its prefix is known
+    pub fn expand(&self, name: &str) -> Option<String> {
+        let (prefix, local_name) = name.split_once(':')?;
+
+        return self
+            .namespaces
+            .iter()
+            .find(|(other_prefix, _)| other_prefix == prefix)
+            .map(|(_, namespace)| format!("{}{}", namespace, local_name));
+    }
+    pub fn to_typed_value(&self, rdf_term: RdfTerm) -> TypedValue {
+        return match rdf_term {
+            RdfTerm::Iri(iri) => TypedValue::Str(self.abbreviate(&iri)),
+            RdfTerm::BlankNode(label) => TypedValue::Str(format!("_:{}", label)),
+            RdfTerm::Literal {
+                lexical_form,
+                language,
+                datatype,
+            } => {
+                let plain = language.is_none()
+                    && datatype
+                        .as_ref()
+                        .map_or(true, |datatype| *datatype == format!("{}string", XSD));
+                if plain {
+                    return TypedValue::Str(lexical_form);
+                }
+
+                datatype
+                    .as_ref()
+                    .and_then(|datatype| typed_literal(&lexical_form, datatype))
+                    .unwrap_or_else(|| {
+                        TypedValue::Str(canonical_literal(&lexical_form, &language, &datatype))
+                    })
+            }
+        };
+    }
+}
+
+impl From<RdfTerm> for TypedValue {
+    fn from(rdf_term: RdfTerm) -> Self {
+        return Prefixes::default().to_typed_value(rdf_term);
+    }
+}
+
+// Reads terms off a source, with spans relative to its start
+pub(crate) struct RdfCursor<'a> {
+    pub source: &'a str,
+    pub position: usize,
+}
+
+impl<'a> RdfCursor<'a> {
+    pub fn new(source: &'a str) -> Self {
+        return Self {
+            source,
+            position: 0,
+        };
+    }
+    pub fn peek(&self) -> Option<char> {
+        return self.source[self.position..].chars().next();
+    }
+    pub fn is_at_end(&self) -> bool {
+        return self.position == self.source.len();
+    }
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+
+        return Some(c);
+    }
+    // The offending character, or the end of the source
+    pub fn error(&self, expected: &str) -> ParseError {
+        let end = self.position + self.peek().map_or(0, |c| c.len_utf8());
+
+        return ParseError::new(self.source, self.position..end, expected);
+    }
+    pub fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\r' | '\n') = self.peek() {
+            self.bump();
+        }
+    }
+    // Comments run until the end of the line
+    pub fn skip_whitespace_and_comments(&mut self) {
+        self.skip_whitespace();
+        while self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+            self.skip_whitespace();
+        }
+    }
+    pub fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("'{}'", expected)));
+        }
+        self.bump();
+
+        return Ok(());
+    }
+    fn hex_escape(&mut self, digits: usize) -> Result<char, ParseError> {
+        let start = self.position;
+        for _ in 0..digits {
+            match self.peek() {
+                Some(c) if c.is_ascii_hexdigit() => {
+                    self.bump();
+                }
+                _ => return Err(self.error("a hexadecimal digit")),
+            }
+        }
+
+        return u32::from_str_radix(&self.source[start..self.position], 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| ParseError::new(self.source, start..self.position, "a unicode scalar"));
+    }
+    // After the backslash. Only \u and \U are allowed in IRIs
+    fn escape(&mut self, in_iri: bool) -> Result<char, ParseError> {
+        let escaped = match self.peek() {
+            Some('u') => {
+                self.bump();
+                return self.hex_escape(4);
+            }
+            Some('U') => {
+                self.bump();
+                return self.hex_escape(8);
+            }
+            Some('t') if !in_iri => '\t',
+            Some('b') if !in_iri => '\u{8}',
+            Some('n') if !in_iri => '\n',
+            Some('r') if !in_iri => '\r',
+            Some('f') if !in_iri => '\u{c}',
+            Some(c @ ('"' | '\'' | '\\')) if !in_iri => c,
+            _ => return Err(self.error("an escape sequence")),
+        };
+        self.bump();
+
+        return Ok(escaped);
+    }
+    pub fn iri(&mut self) -> Result<String, ParseError> {
+        self.expect('<')?;
+        let mut iri = String::new();
+        loop {
+            match self.peek() {
+                Some('>') => {
+                    self.bump();
+                    return Ok(iri);
+                }
+                Some('\\') => {
+                    self.bump();
+                    iri.push(self.escape(true)?);
+                }
+                Some(c) if c > ' ' && !"<\"{}|^`".contains(c) => {
+                    self.bump();
+                    iri.push(c);
+                }
+                _ => return Err(self.error("an IRI character or '>'")),
+            }
+        }
+    }
+    // A label ends at whitespace, or at a final '.', which ends the statement instead
+    pub fn blank_node(&mut self) -> Result<String, ParseError> {
+        self.expect('_')?;
+        self.expect(':')?;
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            if !(c.is_alphanumeric() || "_-.\u{b7}".contains(c)) {
+                break;
+            }
+            self.bump();
+        }
+        while self.source[start..self.position].ends_with('.') {
+            self.position -= 1;
+        }
+        if self.position == start {
+            return Err(self.error("a blank node label"));
+        }
+
+        return Ok(self.source[start..self.position].to_string());
+    }
+    fn language_tag(&mut self) -> Result<String, ParseError> {
+        self.expect('@')?;
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '-') {
+                break;
+            }
+            self.bump();
+        }
+        let language = &self.source[start..self.position];
+        if language.is_empty() || language.starts_with('-') || language.ends_with('-') {
+            return Err(ParseError::new(
+                self.source,
+                start..self.position,
+                "a language tag",
+            ));
+        }
+
+        return Ok(language.to_string());
+    }
+    pub fn literal(&mut self) -> Result<RdfTerm, ParseError> {
+        self.expect('"')?;
+        let mut lexical_form = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.bump();
+                    break;
+                }
+                Some('\\') => {
+                    self.bump();
+                    lexical_form.push(self.escape(false)?);
+                }
+                Some(c) if c != '\n' && c != '\r' => {
+                    self.bump();
+                    lexical_form.push(c);
+                }
+                _ => return Err(self.error("a string character or '\"'")),
+            }
+        }
+
+        let (language, datatype) = match self.peek() {
+            Some('@') => (Some(self.language_tag()?), None),
+            Some('^') => {
+                self.expect('^')?;
+                self.expect('^')?;
+                (None, Some(self.iri()?))
+            }
+            _ => (None, None),
+        };
+
+        return Ok(RdfTerm::Literal {
+            lexical_form,
+            language,
+            datatype,
+        });
+    }
+    pub fn subject(&mut self) -> Result<RdfTerm, ParseError> {
+        return match self.peek() {
+            Some('<') => Ok(RdfTerm::Iri(self.iri()?)),
+            Some('_') => Ok(RdfTerm::BlankNode(self.blank_node()?)),
+            _ => Err(self.error("an IRI or a blank node")),
+        };
+    }
+    pub fn object(&mut self) -> Result<RdfTerm, ParseError> {
+        return match self.peek() {
+            Some('"') => self.literal(),
+            Some('<' | '_') => self.subject(),
+            _ => Err(self.error("an IRI, a blank node or a literal")),
+        };
+    }
+}
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+
+fn is_name_char(c: char) -> bool {
+    return c.is_alphanumeric() || "_-.\u{b7}".contains(c);
+}
+
+// What Turtle statements share: the prefix and base declarations, and the blank nodes. Every blank
+// node is relabelled, with the labelled ones keeping theirs throughout the document, such that the
+// ones that brackets and collections stand for can not clash with them.
+#[derive(Default)]
+pub struct TurtleDocument {
+    namespaces: HashMap<String, String>,
+    base: String,
+    blank_nodes: HashMap<String, RdfTerm>,
+    blank_node_count: usize,
+}
+
+impl TurtleDocument {
+    // The triples of the complete statements at the start of the source, and where the incomplete
+    // statement that follows them starts, which is the end of the source if there is none, such
+    // that a document can be read a line at a time
+    pub fn parse_statements(&mut self, source: &str) -> Result<(Vec<Triple>, usize), ParseError> {
+        let mut cursor = RdfCursor::new(source);
+        let mut triples = vec![];
+        loop {
+            cursor.skip_whitespace_and_comments();
+            let start = cursor.position;
+            if cursor.is_at_end() {
+                return Ok((triples, start));
+            }
+
+            let mut statement_triples = vec![];
+            match self.statement(&mut cursor, &mut statement_triples) {
+                Ok(()) => triples.extend(statement_triples),
+                Err(error) if error.span.start == source.len() => return Ok((triples, start)),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+    fn fresh_blank_node(&mut self) -> RdfTerm {
+        self.blank_node_count += 1;
+
+        return RdfTerm::BlankNode(format!("b{}", self.blank_node_count));
+    }
+    fn labelled_blank_node(&mut self, label: String) -> RdfTerm {
+        if let Some(blank_node) = self.blank_nodes.get(&label) {
+            return blank_node.clone();
+        }
+        let blank_node = self.fresh_blank_node();
+        self.blank_nodes.insert(label, blank_node.clone());
+
+        return blank_node;
+    }
+    // Either kind of directive, whose keyword is followed by whitespace
+    fn keyword(cursor: &mut RdfCursor, keyword: &str) -> bool {
+        let rest = &cursor.source[cursor.position..];
+        let matches = rest
+            .get(..keyword.len())
+            .map_or(false, |word| word.eq_ignore_ascii_case(keyword))
+            && rest[keyword.len()..].starts_with(|c: char| c.is_whitespace());
+        if matches {
+            cursor.position += keyword.len();
+        }
+
+        return matches;
+    }
+    fn statement(
+        &mut self,
+        cursor: &mut RdfCursor,
+        triples: &mut Vec<Triple>,
+    ) -> Result<(), ParseError> {
+        if Self::keyword(cursor, "@prefix") {
+            self.prefix_declaration(cursor)?;
+            cursor.skip_whitespace_and_comments();
+            return cursor.expect('.');
+        }
+        if Self::keyword(cursor, "@base") {
+            self.base_declaration(cursor)?;
+            cursor.skip_whitespace_and_comments();
+            return cursor.expect('.');
+        }
+        // SPARQL's, which are not ended by a '.'
+        if Self::keyword(cursor, "prefix") {
+            return self.prefix_declaration(cursor);
+        }
+        if Self::keyword(cursor, "base") {
+            return self.base_declaration(cursor);
+        }
+
+        let subject = match cursor.peek() {
+            Some('[') => {
+                let subject = self.blank_node_property_list(cursor, triples)?;
+                cursor.skip_whitespace_and_comments();
+                if cursor.peek() == Some('.') {
+                    cursor.bump();
+                    return Ok(());
+                }
+
+                subject
+            }
+            Some('(') => self.collection(cursor, triples)?,
+            _ => self.resource(cursor)?,
+        };
+        cursor.skip_whitespace_and_comments();
+        self.predicate_object_list(cursor, &subject, triples)?;
+        cursor.skip_whitespace_and_comments();
+
+        return cursor.expect('.');
+    }
+    fn prefix_declaration(&mut self, cursor: &mut RdfCursor) -> Result<(), ParseError> {
+        cursor.skip_whitespace_and_comments();
+        let start = cursor.position;
+        while let Some(c) = cursor.peek() {
+            if !is_name_char(c) {
+                break;
+            }
+            cursor.bump();
+        }
+        let prefix = cursor.source[start..cursor.position].to_string();
+        cursor.expect(':')?;
+        cursor.skip_whitespace_and_comments();
+        let namespace = self.iri(cursor)?;
+        self.namespaces.insert(prefix, namespace);
+
+        return Ok(());
+    }
+    fn base_declaration(&mut self, cursor: &mut RdfCursor) -> Result<(), ParseError> {
+        cursor.skip_whitespace_and_comments();
+        self.base = self.iri(cursor)?;
+
+        return Ok(());
+    }
+    // Relative IRIs, those without a scheme, are appended to the base
+    fn iri(&self, cursor: &mut RdfCursor) -> Result<String, ParseError> {
+        let iri = cursor.iri()?;
+        let has_scheme = iri.split_once(':').map_or(false, |(scheme, _)| {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        });
+        if has_scheme {
+            return Ok(iri);
+        }
+
+        return Ok(format!("{}{}", self.base, iri));
+    }
+    fn prefixed_name(&self, cursor: &mut RdfCursor) -> Result<String, ParseError> {
+        let start = cursor.position;
+        while let Some(c) = cursor.peek() {
+            if !is_name_char(c) {
+                break;
+            }
+            cursor.bump();
+        }
+        let prefix_end = cursor.position;
+        if cursor.peek() != Some(':') {
+            cursor.position = start;
+            return Err(cursor.error("an IRI, a prefixed name or a blank node"));
+        }
+        cursor.bump();
+        let namespace = self
+            .namespaces
+            .get(&cursor.source[start..prefix_end])
+            .ok_or_else(|| {
+                ParseError::new(cursor.source, start..prefix_end, "a declared prefix")
+            })?;
+
+        let mut local_name = String::new();
+        loop {
+            match cursor.peek() {
+                Some('\\') => {
+                    cursor.bump();
+                    match cursor.peek() {
+                        Some(c) if "_~.-!$&'()*+,;=/?#@%".contains(c) => {
+                            cursor.bump();
+                            local_name.push(c);
+                        }
+                        _ => return Err(cursor.error("an escaped local name character")),
+                    }
+                }
+                Some('%') => {
+                    cursor.bump();
+                    let hex_start = cursor.position;
+                    cursor.hex_escape(2)?;
+                    local_name.push('%');
+                    local_name.push_str(&cursor.source[hex_start..cursor.position]);
+                }
+                Some(c) if is_name_char(c) || c == ':' => {
+                    cursor.bump();
+                    local_name.push(c);
+                }
+                _ => break,
+            }
+        }
+        // As with blank node labels, a final '.' ends the statement instead
+        while cursor.source[..cursor.position].ends_with('.')
+            && !cursor.source[..cursor.position - 1].ends_with('\\')
+        {
+            cursor.position -= 1;
+            local_name.pop();
+        }
+
+        return Ok(format!("{}{}", namespace, local_name));
+    }
+    // An IRI, a prefixed name or a blank node
+    fn resource(&mut self, cursor: &mut RdfCursor) -> Result<RdfTerm, ParseError> {
+        return match cursor.peek() {
+            Some('<') => Ok(RdfTerm::Iri(self.iri(cursor)?)),
+            Some('_') if cursor.source[cursor.position..].starts_with("_:") => {
+                let label = cursor.blank_node()?;
+                Ok(self.labelled_blank_node(label))
+            }
+            _ => Ok(RdfTerm::Iri(self.prefixed_name(cursor)?)),
+        };
+    }
+    // Whether the keyword is a whole word, rather than the start of a prefixed name
+    fn is_word(cursor: &RdfCursor, word: &str) -> bool {
+        let rest = &cursor.source[cursor.position..];
+
+        return rest.starts_with(word)
+            && !rest[word.len()..].starts_with(|c: char| is_name_char(c) || c == ':');
+    }
+    fn predicate_object_list(
+        &mut self,
+        cursor: &mut RdfCursor,
+        subject: &RdfTerm,
+        triples: &mut Vec<Triple>,
+    ) -> Result<(), ParseError> {
+        loop {
+            let predicate = if Self::is_word(cursor, "a") {
+                cursor.bump();
+                RdfTerm::Iri(format!("{}type", RDF))
+            } else if cursor.peek() == Some('<') {
+                RdfTerm::Iri(self.iri(cursor)?)
+            } else {
+                RdfTerm::Iri(self.prefixed_name(cursor)?)
+            };
+            cursor.skip_whitespace_and_comments();
+            loop {
+                let object = self.object(cursor, triples)?;
+                triples.push((subject.clone(), predicate.clone(), object));
+                cursor.skip_whitespace_and_comments();
+                if cursor.peek() != Some(',') {
+                    break;
+                }
+                cursor.bump();
+                cursor.skip_whitespace_and_comments();
+            }
+
+            if cursor.peek() != Some(';') {
+                return Ok(());
+            }
+            while cursor.peek() == Some(';') {
+                cursor.bump();
+                cursor.skip_whitespace_and_comments();
+            }
+            if matches!(cursor.peek(), Some('.' | ']')) {
+                return Ok(());
+            }
+        }
+    }
+    fn object(
+        &mut self,
+        cursor: &mut RdfCursor,
+        triples: &mut Vec<Triple>,
+    ) -> Result<RdfTerm, ParseError> {
+        if Self::is_word(cursor, "true") || Self::is_word(cursor, "false") {
+            let start = cursor.position;
+            while matches!(cursor.peek(), Some(c) if c.is_ascii_alphabetic()) {
+                cursor.bump();
+            }
+
+            return Ok(RdfTerm::Literal {
+                lexical_form: cursor.source[start..cursor.position].to_string(),
+                language: None,
+                datatype: Some(format!("{}boolean", XSD)),
+            });
+        }
+
+        return match cursor.peek() {
+            Some('[') => self.blank_node_property_list(cursor, triples),
+            Some('(') => self.collection(cursor, triples),
+            Some('"' | '\'') => self.literal(cursor),
+            Some(c) if c.is_ascii_digit() || "+-.".contains(c) => Self::numeric_literal(cursor),
+            _ => self.resource(cursor),
+        };
+    }
+    fn blank_node_property_list(
+        &mut self,
+        cursor: &mut RdfCursor,
+        triples: &mut Vec<Triple>,
+    ) -> Result<RdfTerm, ParseError> {
+        cursor.expect('[')?;
+        cursor.skip_whitespace_and_comments();
+        let blank_node = self.fresh_blank_node();
+        if cursor.peek() != Some(']') {
+            self.predicate_object_list(cursor, &blank_node, triples)?;
+            cursor.skip_whitespace_and_comments();
+        }
+        cursor.expect(']')?;
+
+        return Ok(blank_node);
+    }
+    // A list of rdf:first and rdf:rest statements, ending with rdf:nil
+    fn collection(
+        &mut self,
+        cursor: &mut RdfCursor,
+        triples: &mut Vec<Triple>,
+    ) -> Result<RdfTerm, ParseError> {
+        cursor.expect('(')?;
+        cursor.skip_whitespace_and_comments();
+        let mut items = vec![];
+        while cursor.peek() != Some(')') {
+            items.push(self.object(cursor, triples)?);
+            cursor.skip_whitespace_and_comments();
+        }
+        cursor.bump();
+
+        let nodes: Vec<RdfTerm> = items.iter().map(|_| self.fresh_blank_node()).collect();
+        let nil = RdfTerm::Iri(format!("{}nil", RDF));
+        nodes
+            .iter()
+            .zip(items)
+            .enumerate()
+            .for_each(|(index, (node, item))| {
+                let rest = nodes.get(index + 1).unwrap_or(&nil).clone();
+                triples.push((node.clone(), RdfTerm::Iri(format!("{}first", RDF)), item));
+                triples.push((node.clone(), RdfTerm::Iri(format!("{}rest", RDF)), rest));
+            });
+
+        return Ok(nodes.first().unwrap_or(&nil).clone());
+    }
+    // How many were skipped
+    fn digits(cursor: &mut RdfCursor) -> usize {
+        let start = cursor.position;
+        while matches!(cursor.peek(), Some(c) if c.is_ascii_digit()) {
+            cursor.bump();
+        }
+
+        return cursor.position - start;
+    }
+    fn numeric_literal(cursor: &mut RdfCursor) -> Result<RdfTerm, ParseError> {
+        let start = cursor.position;
+        if let Some('+' | '-') = cursor.peek() {
+            cursor.bump();
+        }
+        let mut digit_count = Self::digits(cursor);
+        let mut datatype = "integer";
+        // Otherwise the '.' ends the statement
+        if cursor.peek() == Some('.')
+            && cursor.source[cursor.position + 1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            cursor.bump();
+            digit_count += Self::digits(cursor);
+            datatype = "decimal";
+        }
+        if digit_count == 0 {
+            return Err(cursor.error("a number"));
+        }
+        if let Some('e' | 'E') = cursor.peek() {
+            cursor.bump();
+            if let Some('+' | '-') = cursor.peek() {
+                cursor.bump();
+            }
+            if Self::digits(cursor) == 0 {
+                return Err(cursor.error("an exponent"));
+            }
+            datatype = "double";
+        }
+
+        return Ok(RdfTerm::Literal {
+            lexical_form: cursor.source[start..cursor.position].to_string(),
+            language: None,
+            datatype: Some(format!("{}{}", XSD, datatype)),
+        });
+    }
+    // Single or double quoted, and tripled for strings that span several lines
+    fn literal(&mut self, cursor: &mut RdfCursor) -> Result<RdfTerm, ParseError> {
+        let quote = cursor.bump().unwrap();
+        let long_quote: String = [quote; 3].iter().collect();
+        let long = cursor.source[cursor.position..].starts_with(&long_quote[1..]);
+        if long {
+            cursor.position += 2;
+        }
+
+        let mut lexical_form = String::new();
+        loop {
+            match cursor.peek() {
+                Some('\\') => {
+                    cursor.bump();
+                    lexical_form.push(cursor.escape(false)?);
+                }
+                Some(c) if c == quote && !long => {
+                    cursor.bump();
+                    break;
+                }
+                // The last three quotes of a run close the string
+                Some(c) if c == quote => {
+                    let quote_count = cursor.source[cursor.position..]
+                        .chars()
+                        .take_while(|c| *c == quote)
+                        .count();
+                    cursor.position += quote_count;
+                    if quote_count >= 3 {
+                        (3..quote_count).for_each(|_| lexical_form.push(quote));
+                        break;
+                    }
+                    (0..quote_count).for_each(|_| lexical_form.push(quote));
+                }
+                Some('\n' | '\r') if !long => return Err(cursor.error("a string character")),
+                Some(c) => {
+                    cursor.bump();
+                    lexical_form.push(c);
+                }
+                None => return Err(cursor.error(&format!("a closing {}", quote))),
+            }
+        }
+
+        let (language, datatype) = match cursor.peek() {
+            Some('@') => (Some(cursor.language_tag()?), None),
+            Some('^') => {
+                cursor.expect('^')?;
+                cursor.expect('^')?;
+                let datatype = if cursor.peek() == Some('<') {
+                    self.iri(cursor)?
+                } else {
+                    self.prefixed_name(cursor)?
+                };
+                (None, Some(datatype))
+            }
+            _ => (None, None),
+        };
+
+        return Ok(RdfTerm::Literal {
+            lexical_form,
+            language,
+            datatype,
+        });
+    }
+}
+
+fn parse_n_statement(line: &str, in_graph: bool) -> Result<Option<Quad>, ParseError> {
+    let mut cursor = RdfCursor::new(line);
+    cursor.skip_whitespace_and_comments();
+    if cursor.is_at_end() {
+        return Ok(None);
+    }
+
+    let subject = cursor.subject()?;
+    cursor.skip_whitespace();
+    let predicate = RdfTerm::Iri(cursor.iri()?);
+    cursor.skip_whitespace();
+    let object = cursor.object()?;
+    cursor.skip_whitespace();
+    let mut graph = None;
+    if in_graph && matches!(cursor.peek(), Some('<' | '_')) {
+        graph = Some(cursor.subject()?);
+        cursor.skip_whitespace();
+    }
+    cursor.expect('.')?;
+    cursor.skip_whitespace_and_comments();
+    if !cursor.is_at_end() {
+        return Err(cursor.error("end of line"));
+    }
+
+    return Ok(Some((subject, predicate, object, graph)));
+}
+
+// None if the line is blank, or a comment
+pub fn parse_n_triple(line: &str) -> Result<Option<Triple>, ParseError> {
+    return Ok(parse_n_statement(line, false)?
+        .map(|(subject, predicate, object, _)| (subject, predicate, object)));
+}
+
+// The graph is None for statements of the default graph
+pub fn parse_n_quad(line: &str) -> Result<Option<Quad>, ParseError> {
+    return parse_n_statement(line, true);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::datalog::TypedValue;
+    use crate::parsers::rdf::{parse_n_quad, parse_n_triple, Prefixes, RdfTerm, TurtleDocument};
+
+    #[test]
+    fn test_parse_n_triple() {
+        assert_eq!(Ok(None), parse_n_triple("  # a comment"));
+
+        let (subject, predicate, object) = parse_n_triple(
+            r#"_:alice <http://xmlns.com/foaf/0.1/name> "Alice \"Al\" Smithé"@en-GB . # name"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(RdfTerm::BlankNode("alice".to_string()), subject);
+        assert_eq!(
+            RdfTerm::Iri("http://xmlns.com/foaf/0.1/name".to_string()),
+            predicate
+        );
+        assert_eq!(
+            RdfTerm::Literal {
+                lexical_form: "Alice \"Al\" Smithé".to_string(),
+                language: Some("en-GB".to_string()),
+                datatype: None,
+            },
+            object
+        );
+        assert_eq!(
+            TypedValue::Str(r#""Alice \"Al\" Smithé"@en-GB"#.to_string()),
+            TypedValue::from(object)
+        );
+
+        let (_, predicate, object) = parse_n_triple(
+            "<http://a.org/b> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> \"42\"^^<http://www.w3.org/2001/XMLSchema#integer>.",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            TypedValue::Str("rdf:type".to_string()),
+            TypedValue::from(predicate)
+        );
+        assert_eq!(TypedValue::UInt(42), TypedValue::from(object));
+
+        let error = parse_n_triple("<http://a.org/b> <http://a.org/c> with spaces .").unwrap_err();
+        assert_eq!(34..35, error.span);
+        assert!(parse_n_triple("<http://a.org/b> <http://a.org/c> \"unterminated .").is_err());
+        assert!(parse_n_triple("<http://a.org/b> <http://a.org/c> _:d . extra").is_err());
+    }
+
+    #[test]
+    fn test_parse_n_quad() {
+        let (_, _, _, graph) =
+            parse_n_quad("<http://a.org/b> <http://a.org/c> \"d\" <http://a.org/g> .")
+                .unwrap()
+                .unwrap();
+        assert_eq!(Some(RdfTerm::Iri("http://a.org/g".to_string())), graph);
+
+        let (_, _, _, graph) = parse_n_quad("<http://a.org/b> <http://a.org/c> _:d .")
+            .unwrap()
+            .unwrap();
+        assert_eq!(None, graph);
+        assert!(parse_n_triple("<http://a.org/b> <http://a.org/c> _:d _:g .").is_err());
+    }
+
+    #[test]
+    fn test_parse_turtle() {
+        let mut prefixes = Prefixes::default();
+        prefixes.insert("ex", "http://a.org/");
+        let mut document = TurtleDocument::default();
+        let source = r#"
+            @prefix ex: <http://a.org/> . # a comment
+            PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+            @base <http://b.org/> .
+            ex:alice a ex:Person ;
+                ex:knows ex:bob, _:carol, [ ex:name 'Dan' ] ;
+                ex:age 42 ; ex:height 1.8 ; ex:awake true ;
+                ex:quote """She said "hi"
+            twice""" ;
+                ex:likes ( <tea> "cake"^^xsd:string ) ;
+                .
+            _:carol ex:knows ex:alice.
+            ex:bob ex:"#;
+
+        let (triples, end) = document.parse_statements(source).unwrap();
+        assert_eq!(source.rfind("ex:bob").unwrap(), end);
+
+        let statements: Vec<String> = triples
+            .into_iter()
+            .map(|(subject, predicate, object)| {
+                format!(
+                    "{} {} {}",
+                    prefixes.to_typed_value(subject),
+                    prefixes.to_typed_value(predicate),
+                    prefixes.to_typed_value(object)
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                r#"ex:alice rdf:type ex:Person"#,
+                r#"ex:alice ex:knows ex:bob"#,
+                r#"ex:alice ex:knows _:b1"#,
+                r#"_:b2 ex:name Dan"#,
+                r#"ex:alice ex:knows _:b2"#,
+                r#"ex:alice ex:age 42"#,
+                r#"ex:alice ex:height 1.8"#,
+                r#"ex:alice ex:awake true"#,
+                r#"ex:alice ex:quote "She said \"hi\"\n            twice""#,
+                r#"_:b3 rdf:first <http://b.org/tea>"#,
+                r#"_:b3 rdf:rest _:b4"#,
+                r#"_:b4 rdf:first cake"#,
+                r#"_:b4 rdf:rest rdf:nil"#,
+                r#"ex:alice ex:likes _:b3"#,
+                r#"_:b1 ex:knows ex:alice"#,
+            ],
+            statements
+        );
+
+        let error = document
+            .parse_statements("ex:a ex:b ex:c ex:d .")
+            .unwrap_err();
+        assert_eq!(15..16, error.span);
+        assert!(document.parse_statements("nope:a ex:b ex:c .").is_err());
+    }
+}
@@ -1,2 +1,3 @@
 pub mod algorithms;
 pub mod reasoners;
+pub mod rule_sets;
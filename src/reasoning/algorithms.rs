@@ -1,6 +1,11 @@
 pub mod constant_specialization;
+pub mod counting;
 pub mod delete_rederive;
 pub mod delta_rule_rewrite;
 pub mod evaluation;
+pub mod magic_sets;
+pub mod provenance;
 pub mod relational_algebra;
 pub mod rewriting;
+pub mod subscriptions;
+pub mod tabling;
@@ -10,7 +10,7 @@ fn get_constants(sugared_atom: &SugaredAtom) -> Vec<(usize, Term)> {
         .iter()
         .enumerate()
         .filter_map(|(position, term)| match term {
-            Term::Variable(_) => None,
+            Term::Variable(_) | Term::Aggregate(..) => None,
             constant => Some((position, constant.clone())),
         })
         .collect()
@@ -20,7 +20,10 @@ fn specialize_atom(sugared_atom: &SugaredAtom) -> Option<(SugaredAtom, SugaredRu
     let constants = get_constants(sugared_atom);
     let mut specialized_sugared_atom = sugared_atom.clone();
 
-    if !constants.is_empty() && constants.len() != sugared_atom.terms.len() {
+    if !sugared_atom.is_builtin()
+        && !constants.is_empty()
+        && constants.len() != sugared_atom.terms.len()
+    {
         let mut new_symbol = sugared_atom.symbol.to_string();
 
         constants.into_iter().for_each(|(position, constant)| {
@@ -79,11 +82,11 @@ mod tests {
 
     #[test]
     fn test_specialize_atom() {
-        let input_atom = SugaredAtom::from("T(?y, rdf:type, ?x)");
+        let input_atom = SugaredAtom::try_from("T(?y, rdf:type, ?x)").unwrap();
 
-        let expected_specialized_atom = SugaredAtom::from("T_rdf:type(?y, ?x)");
+        let expected_specialized_atom = SugaredAtom::try_from("T_rdf:type(?y, ?x)").unwrap();
         let expected_selection_rule =
-            SugaredRule::from("T_rdf:type(?y, ?x) <- [T(?y, rdf:type, ?x)]");
+            SugaredRule::try_from("T_rdf:type(?y, ?x) <- [T(?y, rdf:type, ?x)]").unwrap();
 
         let (actual_specialized_atom, actual_selection_rule) =
             specialize_atom(&input_atom).unwrap();
@@ -95,27 +98,27 @@ mod tests {
     #[test]
     fn test_specialize_to_constants() {
         let input_program = vec![
-            SugaredRule::from("T(?y, rdf:type, ?x) <- [T(?a, rdfs:domain, ?x), T(?y, ?a, ?z)]"),
-            SugaredRule::from("T(?z, rdf:type, ?x) <- [T(?a, rdfs:range, ?x), T(?y, ?a, ?z)]"),
-            SugaredRule::from("T(?x, rdfs:subPropertyOf, ?z) <- [T(?x, rdfs:subPropertyOf, ?y), T(?y, rdfs:subPropertyOf, ?z)]"),
-            SugaredRule::from("T(?x, rdfs:subClassOf, ?z) <- [T(?x, rdfs:subClassOf, ?y), T(?y, rdfs:subClassOf, ?z)]"),
-            SugaredRule::from("T(?z, rdf:type, ?y) <- [T(?x, rdfs:subClassOf, ?y), T(?z, rdf:type, ?x)]"),
-            SugaredRule::from("T(?x, ?b, ?y) <- [T(?a, rdfs:subPropertyOf, ?b), T(?x, ?a, ?y)]"),
+            SugaredRule::try_from("T(?y, rdf:type, ?x) <- [T(?a, rdfs:domain, ?x), T(?y, ?a, ?z)]").unwrap(),
+            SugaredRule::try_from("T(?z, rdf:type, ?x) <- [T(?a, rdfs:range, ?x), T(?y, ?a, ?z)]").unwrap(),
+            SugaredRule::try_from("T(?x, rdfs:subPropertyOf, ?z) <- [T(?x, rdfs:subPropertyOf, ?y), T(?y, rdfs:subPropertyOf, ?z)]").unwrap(),
+            SugaredRule::try_from("T(?x, rdfs:subClassOf, ?z) <- [T(?x, rdfs:subClassOf, ?y), T(?y, rdfs:subClassOf, ?z)]").unwrap(),
+            SugaredRule::try_from("T(?z, rdf:type, ?y) <- [T(?x, rdfs:subClassOf, ?y), T(?z, rdf:type, ?x)]").unwrap(),
+            SugaredRule::try_from("T(?x, ?b, ?y) <- [T(?a, rdfs:subPropertyOf, ?b), T(?x, ?a, ?y)]").unwrap(),
         ];
 
         let expected_output_program: HashSet<_> = vec![
-            SugaredRule::from("T_rdfs:domain(?a, ?x) <- [T(?a, rdfs:domain, ?x)]"),
-            SugaredRule::from("T_rdfs:range(?a, ?x) <- [T(?a, rdfs:range, ?x)]"),
-            SugaredRule::from("T_rdf:type(?y, ?x) <- [T(?y, rdf:type, ?x)]"),
-            SugaredRule::from("T_rdfs:subPropertyOf(?x, ?z) <- [T(?x, rdfs:subPropertyOf, ?z)]"),
-            SugaredRule::from("T_rdfs:subClassOf(?x, ?z) <- [T(?x, rdfs:subClassOf, ?z)]"),
-
-            SugaredRule::from("T_rdf:type(?y, ?x) <- [T_rdfs:domain(?a, ?x), T(?y, ?a, ?z)]"),
-            SugaredRule::from("T_rdf:type(?z, ?x) <- [T_rdfs:range(?a, ?x), T(?y, ?a, ?z)]"),
-            SugaredRule::from("T_rdfs:subPropertyOf(?x, ?z) <- [T_rdfs:subPropertyOf(?x, ?y), T_rdfs:subPropertyOf(?y, ?z)]"),
-            SugaredRule::from("T_rdfs:subClassOf(?x, ?z) <- [T_rdfs:subClassOf(?x, ?y), T_rdfs:subClassOf(?y, ?z)]"),
-            SugaredRule::from("T_rdf:type(?z, ?y) <- [T_rdfs:subClassOf(?x, ?y), T_rdf:type(?z, ?x)]"),
-            SugaredRule::from("T(?x, ?b, ?y) <- [T_rdfs:subPropertyOf(?a, ?b), T(?x, ?a, ?y)]"),
+            SugaredRule::try_from("T_rdfs:domain(?a, ?x) <- [T(?a, rdfs:domain, ?x)]").unwrap(),
+            SugaredRule::try_from("T_rdfs:range(?a, ?x) <- [T(?a, rdfs:range, ?x)]").unwrap(),
+            SugaredRule::try_from("T_rdf:type(?y, ?x) <- [T(?y, rdf:type, ?x)]").unwrap(),
+            SugaredRule::try_from("T_rdfs:subPropertyOf(?x, ?z) <- [T(?x, rdfs:subPropertyOf, ?z)]").unwrap(),
+            SugaredRule::try_from("T_rdfs:subClassOf(?x, ?z) <- [T(?x, rdfs:subClassOf, ?z)]").unwrap(),
+
+            SugaredRule::try_from("T_rdf:type(?y, ?x) <- [T_rdfs:domain(?a, ?x), T(?y, ?a, ?z)]").unwrap(),
+            SugaredRule::try_from("T_rdf:type(?z, ?x) <- [T_rdfs:range(?a, ?x), T(?y, ?a, ?z)]").unwrap(),
+            SugaredRule::try_from("T_rdfs:subPropertyOf(?x, ?z) <- [T_rdfs:subPropertyOf(?x, ?y), T_rdfs:subPropertyOf(?y, ?z)]").unwrap(),
+            SugaredRule::try_from("T_rdfs:subClassOf(?x, ?z) <- [T_rdfs:subClassOf(?x, ?y), T_rdfs:subClassOf(?y, ?z)]").unwrap(),
+            SugaredRule::try_from("T_rdf:type(?z, ?y) <- [T_rdfs:subClassOf(?x, ?y), T_rdf:type(?z, ?x)]").unwrap(),
+            SugaredRule::try_from("T(?x, ?b, ?y) <- [T_rdfs:subPropertyOf(?a, ?b), T(?x, ?a, ?y)]").unwrap(),
         ].into_iter().collect();
 
         let actual_output_program = specialize_to_constants(&input_program)
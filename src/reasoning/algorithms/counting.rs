@@ -0,0 +1,857 @@
+// Counting-based maintenance, as an alternative to delete-rederive. Every fact of a nonrecursive
+// stratum keeps how many derivations it has, and is only deleted once none are left, hence only the
+// derivations that a change takes part in are ever looked at. Facts of recursive strata may
+// support each other, hence instead of being counted, the facts that lost a derivation are checked
+// for a proof that does not rely on them, with magic sets, and only those without one are deleted
+// and propagated further, one step at a time(Backward/Forward).
+//
+// Unlike delete-rederive, nothing is ever overdeleted, hence the "-" and "Δ" prefixed relations hold
+// exactly what got deleted and inserted.
+
+use crate::misc::helpers::terms_to_row;
+use crate::misc::rule_graph::sort_program;
+use crate::misc::validation::ValidationError;
+use crate::models::datalog::{SugaredAtom, SugaredProgram, SugaredRule, Term};
+use crate::models::reasoner::{
+    BottomUpEvaluator, DeltaEvaluator, DynamicTyped, ExplicitFacts, RelationDropper,
+};
+use crate::models::relational_algebra::Row;
+use crate::reasoning::algorithms::delete_rederive::{
+    evaluate_before_changes, make_stratum_overdeletion_program, make_stratum_retraction_program,
+    make_unblocked_derivation_program, TypedDiff, OVERDELETION_PREFIX,
+};
+use crate::reasoning::algorithms::delta_rule_rewrite::DELTA_PREFIX;
+use crate::reasoning::algorithms::magic_sets::{make_magic_program, MAGIC_PREFIX};
+use ahash::{HashMap, HashSet, HashSetExt};
+
+pub const DERIVATION_PREFIX: &'static str = "#";
+pub const CANDIDATE_PREFIX: &'static str = "~";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MaintenanceStrategy {
+    #[default]
+    DeleteRederive,
+    // Derivation counting for nonrecursive strata, and Backward/Forward for recursive ones
+    Counting,
+}
+
+// How many derivations every fact of a counted relation has
+pub type DerivationCounts = HashMap<String, HashMap<Row, usize>>;
+
+pub trait CountingMaintainer:
+    DynamicTyped + BottomUpEvaluator + DeltaEvaluator + RelationDropper + ExplicitFacts
+{
+    fn contains_typed(&self, table: &str, row: &Row) -> bool;
+    fn derivation_counts(&mut self) -> &mut DerivationCounts;
+}
+
+fn is_recursive(stratum: &Vec<SugaredRule>) -> bool {
+    return stratum.iter().any(|rule| {
+        rule.body.iter().any(|body_atom| {
+            stratum
+                .iter()
+                .any(|other_rule| other_rule.head.symbol == body_atom.symbol)
+        })
+    });
+}
+
+fn is_counted(stratum: &Vec<SugaredRule>) -> bool {
+    return !is_recursive(stratum) && !stratum.iter().any(|rule| rule.head.has_aggregates());
+}
+
+fn prefixed(prefix: &str, atom: &SugaredAtom) -> SugaredAtom {
+    let mut prefixed_atom = atom.clone();
+    prefixed_atom.symbol = format!("{}{}", prefix, atom.symbol);
+
+    return prefixed_atom;
+}
+
+// One fact per derivation of the rule, as its head followed by every variable that its body binds
+fn derivation_head(rule: &SugaredRule, symbol: String) -> SugaredAtom {
+    let mut terms = rule.head.terms.clone();
+    let head_len = terms.len();
+    rule.body
+        .iter()
+        .filter(|body_atom| body_atom.positive && !body_atom.is_builtin())
+        .flat_map(|body_atom| body_atom.terms.iter())
+        .for_each(|term| {
+            if let Term::Variable(_) = term {
+                if !terms[head_len..].contains(term) {
+                    terms.push(term.clone());
+                }
+            }
+        });
+
+    return SugaredAtom {
+        terms,
+        symbol,
+        positive: true,
+    };
+}
+
+pub fn make_derivation_program(stratum: &Vec<SugaredRule>) -> SugaredProgram {
+    return stratum
+        .iter()
+        .enumerate()
+        .map(|(idx, rule)| SugaredRule {
+            head: derivation_head(rule, format!("{}{}", DERIVATION_PREFIX, idx)),
+            body: rule.body.clone(),
+        })
+        .collect();
+}
+
+// Derivations that were lost and gained by the changes, such that every derivation is found exactly
+// once. The lost ones are to be evaluated with `evaluate_before_changes`, on what held before the
+// changes, and the gained ones on what holds after. Every body atom that might have changed is
+// replaced by its changes in a rule of its own, with positive atoms looking at what got deleted(lost)
+// or inserted(gained), and negated ones the other way around, while the changed atoms before it only
+// match what did not change, and those after it whatever holds.
+pub fn make_count_change_program(
+    stratum: &Vec<SugaredRule>,
+    deleted: &HashSet<String>,
+    inserted: &HashSet<String>,
+) -> (SugaredProgram, SugaredProgram) {
+    let mut lost_program = vec![];
+    let mut gained_program = vec![];
+
+    stratum.iter().enumerate().for_each(|(rule_idx, rule)| {
+        let changed_atoms: Vec<usize> = rule
+            .body
+            .iter()
+            .enumerate()
+            .filter(|(_idx, body_atom)| {
+                !body_atom.is_builtin()
+                    && (deleted.contains(&body_atom.symbol) || inserted.contains(&body_atom.symbol))
+            })
+            .map(|(idx, _body_atom)| idx)
+            .collect();
+
+        [true, false].into_iter().for_each(|lost| {
+            let change_prefix = if lost {
+                OVERDELETION_PREFIX
+            } else {
+                DELTA_PREFIX
+            };
+            let new_head = derivation_head(
+                rule,
+                format!("{}{}{}", DERIVATION_PREFIX, change_prefix, rule_idx),
+            );
+
+            changed_atoms.iter().for_each(|changed_idx| {
+                let mut new_rule = SugaredRule {
+                    head: new_head.clone(),
+                    body: vec![],
+                };
+                let mut unchanged_body = vec![];
+
+                rule.body.iter().enumerate().for_each(|(idx, body_atom)| {
+                    if idx == *changed_idx {
+                        let prefix = if body_atom.positive == lost {
+                            OVERDELETION_PREFIX
+                        } else {
+                            DELTA_PREFIX
+                        };
+                        let mut changed_atom = prefixed(prefix, body_atom);
+                        changed_atom.positive = true;
+                        new_rule.body.push(changed_atom);
+                        return;
+                    }
+
+                    unchanged_body.push(body_atom.clone());
+                    if idx > *changed_idx || !changed_atoms.contains(&idx) {
+                        return;
+                    }
+                    // What did not change is what was not deleted before the changes, and not
+                    // inserted after them, or the other way around for negated atoms
+                    let changes = if lost == body_atom.positive {
+                        (deleted, OVERDELETION_PREFIX)
+                    } else {
+                        (inserted, DELTA_PREFIX)
+                    };
+                    if changes.0.contains(&body_atom.symbol) {
+                        let mut unchanged_atom = prefixed(changes.1, body_atom);
+                        unchanged_atom.positive = false;
+                        unchanged_body.push(unchanged_atom);
+                    }
+                });
+
+                new_rule.body.extend(unchanged_body);
+                if lost {
+                    lost_program.push(new_rule);
+                } else {
+                    gained_program.push(new_rule);
+                }
+            });
+        });
+    });
+
+    return (lost_program, gained_program);
+}
+
+// Copies every overdeletion relation of the stratum into its candidate relation
+fn make_retracted_program(stratum: &Vec<SugaredRule>) -> SugaredProgram {
+    return make_stratum_retraction_program(stratum)
+        .into_iter()
+        .map(|mut rule| {
+            rule.body[0].symbol = rule.head.symbol.clone();
+            rule.head.symbol = format!(
+                "{}{}",
+                CANDIDATE_PREFIX,
+                rule.head.symbol.strip_prefix(OVERDELETION_PREFIX).unwrap()
+            );
+            rule
+        })
+        .collect();
+}
+
+// Facts of the stratum that are one step away from the changes, and that might have lost their
+// derivation
+fn make_candidate_program(
+    stratum: &Vec<SugaredRule>,
+    deleted: &HashSet<String>,
+    inserted: &HashSet<String>,
+) -> SugaredProgram {
+    return make_stratum_overdeletion_program(stratum, deleted, inserted)
+        .into_iter()
+        .map(|mut rule| {
+            rule.head.symbol = format!(
+                "{}{}",
+                CANDIDATE_PREFIX,
+                rule.head.symbol.strip_prefix(OVERDELETION_PREFIX).unwrap()
+            );
+            rule
+        })
+        .collect();
+}
+
+// The facts that the candidate program derives, from what held before the deletions
+fn candidates<T: CountingMaintainer>(
+    instance: &mut T,
+    program: &SugaredProgram,
+    deleted: &HashSet<String>,
+) -> Result<Vec<(String, Row)>, ValidationError> {
+    if program.is_empty() {
+        return Ok(vec![]);
+    }
+
+    return Ok(
+        evaluate_before_changes(instance, program, deleted, &HashSet::new())?
+            .into_iter()
+            .flat_map(|(candidate_sym, row_set)| {
+                let sym = candidate_sym
+                    .strip_prefix(CANDIDATE_PREFIX)
+                    .unwrap()
+                    .to_string();
+                row_set.into_iter().map(move |row| (sym.clone(), row))
+            })
+            .collect(),
+    );
+}
+
+// Which of the facts can still be derived, answering them all at once with magic sets
+fn provable<T: CountingMaintainer>(
+    instance: &mut T,
+    stratum: &Vec<SugaredRule>,
+    facts: &Vec<(String, Row)>,
+) -> Result<HashSet<(String, Row)>, ValidationError> {
+    if facts.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    let mut magic_program: SugaredProgram = vec![];
+    let mut seeds: Vec<SugaredAtom> = vec![];
+    let mut adorned_queries: HashMap<String, String> = Default::default();
+
+    facts.iter().for_each(|(sym, row)| {
+        let query = SugaredAtom {
+            terms: row
+                .iter()
+                .map(|value| Term::Constant(value.clone()))
+                .collect(),
+            symbol: sym.clone(),
+            positive: true,
+        };
+        // Every column is bound, hence every fact of a relation has the same magic program
+        if !adorned_queries.contains_key(sym) {
+            let (program, program_seeds, adorned_query) = make_magic_program(stratum, &query);
+            program.into_iter().for_each(|rule| {
+                if !magic_program
+                    .iter()
+                    .any(|magic_rule| magic_rule.to_string() == rule.to_string())
+                {
+                    magic_program.push(rule);
+                }
+            });
+            seeds.extend(program_seeds);
+            adorned_queries.insert(sym.clone(), adorned_query.symbol);
+        }
+        if !row.is_empty() {
+            let mut seed = query;
+            seed.symbol = format!("{}{}", MAGIC_PREFIX, adorned_queries[sym]);
+            seeds.push(seed);
+        }
+    });
+
+    let seed_symbols: HashSet<String> = seeds.iter().map(|seed| seed.symbol.clone()).collect();
+    seeds.into_iter().for_each(|seed| {
+        instance.insert_typed(&seed.symbol, terms_to_row(seed.terms));
+    });
+    let answers = instance.evaluate_program_bottom_up(&magic_program)?;
+    seed_symbols
+        .iter()
+        .for_each(|seed_symbol| instance.drop_relation(seed_symbol));
+
+    return Ok(facts
+        .iter()
+        .filter(|(sym, row)| {
+            answers
+                .get(&adorned_queries[sym])
+                .map_or(false, |row_set| row_set.contains(row))
+        })
+        .cloned()
+        .collect());
+}
+
+// Inserts the row, unless it is already there, keeping track of what changed
+fn insert_exact<T: CountingMaintainer>(
+    instance: &mut T,
+    inserted: &mut HashSet<String>,
+    sym: &str,
+    row: Row,
+) {
+    let del_sym = format!("{}{}", OVERDELETION_PREFIX, sym);
+    if instance.contains_typed(&del_sym, &row) {
+        instance.delete_typed(&del_sym, &row);
+        instance.insert_typed(sym, row);
+    } else if !instance.contains_typed(sym, &row) {
+        instance.insert_typed(&format!("{}{}", DELTA_PREFIX, sym), row.clone());
+        instance.insert_typed(sym, row);
+        inserted.insert(sym.to_string());
+    }
+}
+
+// Deletes the row, if it is there, keeping track of what changed
+fn delete_exact<T: CountingMaintainer>(
+    instance: &mut T,
+    deleted: &mut HashSet<String>,
+    sym: &str,
+    row: &Row,
+) {
+    let delta_sym = format!("{}{}", DELTA_PREFIX, sym);
+    if instance.contains_typed(&delta_sym, row) {
+        instance.delete_typed(&delta_sym, row);
+        instance.delete_typed(sym, row);
+    } else if instance.contains_typed(sym, row) {
+        instance.delete_typed(sym, row);
+        instance.insert_typed(&format!("{}{}", OVERDELETION_PREFIX, sym), row.clone());
+        deleted.insert(sym.to_string());
+    }
+}
+
+fn count_derivations<T: CountingMaintainer>(
+    instance: &mut T,
+    stratum: &Vec<SugaredRule>,
+) -> Result<DerivationCounts, ValidationError> {
+    let mut counts: DerivationCounts = Default::default();
+    stratum.iter().for_each(|rule| {
+        counts.entry(rule.head.symbol.clone()).or_default();
+    });
+
+    instance
+        .evaluate_program_bottom_up(&make_derivation_program(stratum))?
+        .into_iter()
+        .for_each(|(derivation_sym, row_set)| {
+            let rule_idx: usize = derivation_sym
+                .strip_prefix(DERIVATION_PREFIX)
+                .unwrap()
+                .parse()
+                .unwrap();
+            let head = &stratum[rule_idx].head;
+            let relation_counts = counts.get_mut(&head.symbol).unwrap();
+            row_set.iter().for_each(|row| {
+                *relation_counts
+                    .entry(row[..head.terms.len()].into())
+                    .or_default() += 1;
+            });
+        });
+
+    return Ok(counts);
+}
+
+// Counts the derivations of every nonrecursive stratum of the program from scratch
+pub fn initialize_derivation_counts<T: CountingMaintainer>(
+    instance: &mut T,
+    program: &SugaredProgram,
+) -> Result<(), ValidationError> {
+    let mut counts: DerivationCounts = Default::default();
+    for stratum in sort_program(program)
+        .iter()
+        .filter(|stratum| is_counted(stratum))
+    {
+        counts.extend(count_derivations(instance, stratum)?);
+    }
+
+    *instance.derivation_counts() = counts;
+
+    return Ok(());
+}
+
+fn maintain_counts<T: CountingMaintainer>(
+    instance: &mut T,
+    stratum: &Vec<SugaredRule>,
+    fresh: bool,
+    deleted: &mut HashSet<String>,
+    inserted: &mut HashSet<String>,
+) -> Result<(), ValidationError> {
+    let missing_counts = stratum
+        .iter()
+        .any(|rule| !instance.derivation_counts().contains_key(&rule.head.symbol));
+    if fresh || missing_counts {
+        let counts = count_derivations(instance, stratum)?;
+        counts.iter().for_each(|(sym, relation_counts)| {
+            relation_counts
+                .keys()
+                .for_each(|row| insert_exact(instance, inserted, sym, row.clone()));
+        });
+        instance.derivation_counts().extend(counts);
+        return Ok(());
+    }
+
+    let mut count_changes: HashMap<(String, Row), isize> = Default::default();
+    let (lost_program, gained_program) = make_count_change_program(stratum, deleted, inserted);
+    if !lost_program.is_empty() {
+        let lost = evaluate_before_changes(instance, &lost_program, deleted, inserted)?;
+        let gained = instance.evaluate_program_bottom_up(&gained_program)?;
+        lost.into_iter()
+            .chain(gained)
+            .for_each(|(derivation_sym, row_set)| {
+                let change = derivation_sym.strip_prefix(DERIVATION_PREFIX).unwrap();
+                let (change, rule_idx) = match change.strip_prefix(OVERDELETION_PREFIX) {
+                    Some(rule_idx) => (-1, rule_idx),
+                    None => (1, change.strip_prefix(DELTA_PREFIX).unwrap()),
+                };
+                let head = &stratum[rule_idx.parse::<usize>().unwrap()].head;
+                row_set.iter().for_each(|row| {
+                    *count_changes
+                        .entry((head.symbol.clone(), row[..head.terms.len()].into()))
+                        .or_default() += change;
+                });
+            });
+    }
+
+    count_changes.into_iter().for_each(|((sym, row), change)| {
+        let relation_counts = instance.derivation_counts().get_mut(&sym).unwrap();
+        let count = relation_counts.get(&row).copied().unwrap_or(0);
+        let new_count = count.checked_add_signed(change);
+        debug_assert!(
+            new_count.is_some(),
+            "a fact lost more derivations than it had"
+        );
+        let new_count = new_count.unwrap_or(0);
+        if new_count == 0 {
+            relation_counts.remove(&row);
+        } else {
+            relation_counts.insert(row.clone(), new_count);
+        }
+
+        if count == 0 && new_count > 0 {
+            insert_exact(instance, inserted, &sym, row);
+        // What has just been added, or was inserted explicitly, is never deleted
+        } else if count > 0
+            && new_count == 0
+            && !instance.contains_typed(&format!("{}{}", DELTA_PREFIX, sym), &row)
+            && !instance.is_explicit(&sym, &row)
+        {
+            delete_exact(instance, deleted, &sym, &row);
+        }
+    });
+
+    // Retracting a fact that still has derivations does not delete it
+    let retracted = candidates(instance, &make_retracted_program(stratum), &HashSet::new())?;
+    retracted.into_iter().for_each(|(sym, row)| {
+        if instance.derivation_counts()[&sym].contains_key(&row) {
+            insert_exact(instance, inserted, &sym, row);
+        }
+    });
+
+    return Ok(());
+}
+
+fn backward_forward<T: CountingMaintainer>(
+    instance: &mut T,
+    stratum: &Vec<SugaredRule>,
+    fresh: bool,
+    deleted: &mut HashSet<String>,
+    inserted: &mut HashSet<String>,
+) -> Result<(), ValidationError> {
+    let seeded = stratum.iter().any(|rule| {
+        rule.body.iter().any(|body_atom| {
+            if body_atom.positive {
+                return deleted.contains(&body_atom.symbol);
+            }
+            return inserted.contains(&body_atom.symbol);
+        })
+    }) || stratum
+        .iter()
+        .any(|rule| deleted.contains(&rule.head.symbol));
+    // Backward/Forward deletion. Each round only looks at what the previous one deleted, hence the
+    // overdeletion relations of the stratum only hold that, until they are restored at the end.
+    if seeded {
+        let retracted = candidates(instance, &make_retracted_program(stratum), &HashSet::new())?;
+        let mut stratum_deletions = retracted.clone();
+        let mut last_round = retracted.clone();
+        let mut candidate_program = make_candidate_program(stratum, deleted, inserted);
+        loop {
+            let round_candidates: Vec<(String, Row)> =
+                candidates(instance, &candidate_program, deleted)?
+                    .into_iter()
+                    .filter(|(sym, row)| {
+                        instance.contains_typed(sym, row)
+                            && !instance.contains_typed(&format!("{}{}", DELTA_PREFIX, sym), row)
+                            && !instance.is_explicit(sym, row)
+                    })
+                    .collect();
+            let provable_candidates = provable(instance, stratum, &round_candidates)?;
+            let unprovable_candidates: Vec<(String, Row)> = round_candidates
+                .into_iter()
+                .filter(|candidate| !provable_candidates.contains(candidate))
+                .collect();
+
+            last_round.iter().for_each(|(sym, row)| {
+                instance.delete_typed(&format!("{}{}", OVERDELETION_PREFIX, sym), row);
+            });
+            if unprovable_candidates.is_empty() {
+                break;
+            }
+            unprovable_candidates
+                .iter()
+                .for_each(|(sym, row)| delete_exact(instance, deleted, sym, row));
+            stratum_deletions.extend(unprovable_candidates.iter().cloned());
+            last_round = unprovable_candidates;
+            candidate_program = make_candidate_program(stratum, &HashSet::new(), &HashSet::new());
+        }
+        stratum_deletions.into_iter().for_each(|(sym, row)| {
+            instance.insert_typed(&format!("{}{}", OVERDELETION_PREFIX, sym), row);
+        });
+        // Retracting a fact that can still be derived does not delete it
+        provable(instance, stratum, &retracted)?
+            .into_iter()
+            .for_each(|(sym, row)| insert_exact(instance, inserted, &sym, row));
+    }
+    // Derivations that are no longer blocked by a negated atom
+    let unblocked_derivation_program = make_unblocked_derivation_program(stratum, deleted);
+    if unblocked_derivation_program.len() > 0 {
+        instance
+            .evaluate_program_bottom_up(&unblocked_derivation_program)?
+            .into_iter()
+            .for_each(|(sym, row_set)| {
+                row_set
+                    .into_iter()
+                    .for_each(|row| insert_exact(instance, inserted, &sym, row))
+            });
+    }
+    // Insertion, from scratch if the stratum has new rules
+    let insertions = if fresh {
+        instance.evaluate_program_bottom_up(stratum)?
+    } else if stratum.iter().any(|rule| {
+        rule.body
+            .iter()
+            .any(|body_atom| body_atom.positive && inserted.contains(&body_atom.symbol))
+    }) {
+        instance.evaluate_program_on_deltas(stratum)?
+    } else {
+        Default::default()
+    };
+    insertions.into_iter().for_each(|(sym, row_set)| {
+        row_set
+            .into_iter()
+            .for_each(|row| insert_exact(instance, inserted, &sym, row))
+    });
+
+    return Ok(());
+}
+
+// The counting counterpart of `stratified_delete_rederive`, with the same arguments. Nonrecursive
+// strata are maintained by counting derivations, recursive ones with Backward/Forward, and those
+// with aggregates are recomputed.
+pub fn stratified_counting<'a, T: CountingMaintainer>(
+    instance: &mut T,
+    program: &SugaredProgram,
+    fresh_program: &SugaredProgram,
+    additions: Vec<TypedDiff<'a>>,
+    retractions: Vec<TypedDiff<'a>>,
+) -> Result<(), ValidationError> {
+    let strata = sort_program(program);
+    let mut deleted: HashSet<String> = HashSet::new();
+    let mut inserted: HashSet<String> = HashSet::new();
+
+    retractions.iter().for_each(|(sym, retraction)| {
+        delete_exact(instance, &mut deleted, sym, retraction);
+    });
+    additions.iter().for_each(|(sym, addition)| {
+        insert_exact(instance, &mut inserted, sym, addition.clone());
+    });
+
+    for stratum in strata.iter() {
+        let fresh = stratum.iter().any(|rule| {
+            fresh_program
+                .iter()
+                .any(|fresh_rule| fresh_rule.to_string() == rule.to_string())
+        });
+        let changed = stratum.iter().any(|rule| {
+            rule.body.iter().any(|body_atom| {
+                deleted.contains(&body_atom.symbol) || inserted.contains(&body_atom.symbol)
+            }) || deleted.contains(&rule.head.symbol)
+        });
+
+        if stratum.iter().any(|rule| rule.head.has_aggregates()) {
+            if changed || fresh {
+                let retractions = instance
+                    .evaluate_program_bottom_up(&make_stratum_retraction_program(stratum))?;
+                retractions.into_iter().for_each(|(del_sym, row_set)| {
+                    let sym = del_sym.strip_prefix(OVERDELETION_PREFIX).unwrap();
+                    row_set.iter().for_each(|retraction| {
+                        if !instance.is_explicit(sym, retraction) {
+                            delete_exact(instance, &mut deleted, sym, retraction)
+                        }
+                    });
+                });
+                let insertions = instance.evaluate_program_bottom_up(stratum)?;
+                insertions.into_iter().for_each(|(sym, row_set)| {
+                    row_set
+                        .into_iter()
+                        .for_each(|row| insert_exact(instance, &mut inserted, &sym, row))
+                });
+            }
+            continue;
+        }
+        if is_recursive(stratum) {
+            backward_forward(instance, stratum, fresh, &mut deleted, &mut inserted)?;
+        } else if changed || fresh {
+            maintain_counts(instance, stratum, fresh, &mut deleted, &mut inserted)?;
+        }
+    }
+
+    deleted.iter().for_each(|sym| {
+        instance.drop_relation(&format!("{}{}", OVERDELETION_PREFIX, sym));
+    });
+    inserted.iter().for_each(|sym| {
+        instance.drop_relation(&format!("{}{}", DELTA_PREFIX, sym));
+    });
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::datalog::{SugaredRule, TypedValue};
+    use crate::models::index::VecIndex;
+    use crate::models::reasoner::{Diff, Dynamic, Maintainer, Materializer, Queryable, UntypedRow};
+    use crate::models::relational_algebra::Row;
+    use crate::reasoning::algorithms::counting::{CountingMaintainer, MaintenanceStrategy};
+    use crate::reasoning::reasoners::chibi::ChibiDatalog;
+    use crate::reasoning::reasoners::relational::RelationalDatalog;
+
+    const NODES: [&str; 5] = ["a", "b", "c", "d", "e"];
+
+    fn edge(source: &'static str, destination: &'static str) -> (&'static str, UntypedRow) {
+        return ("edge", vec![Box::new(source), Box::new(destination)]);
+    }
+
+    fn program() -> Vec<SugaredRule> {
+        return vec![
+            SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reach(?x, ?z) <- [edge(?x, ?y), reach(?y, ?z)]").unwrap(),
+            SugaredRule::try_from("hop(?x, ?z) <- [edge(?x, ?y), edge(?y, ?z)]").unwrap(),
+            SugaredRule::try_from("unreached(?x, ?y) <- [node(?x), node(?y), !reach(?x, ?y)]")
+                .unwrap(),
+        ];
+    }
+
+    fn updates() -> Vec<Vec<Diff<'static>>> {
+        return vec![
+            // A cycle, and two ways to hop from a to c
+            vec![
+                (true, edge("a", "b")),
+                (true, edge("b", "c")),
+                (true, edge("a", "d")),
+                (true, edge("d", "c")),
+                (true, edge("c", "a")),
+            ],
+            vec![(false, edge("a", "b"))],
+            vec![(false, edge("c", "a")), (true, edge("c", "e"))],
+            // Retracting a fact that can still be derived does not delete it
+            vec![(false, ("reach", vec![Box::new("a"), Box::new("d")]))],
+            vec![(false, edge("a", "d")), (true, edge("a", "b"))],
+            vec![(false, edge("d", "c")), (false, edge("b", "c"))],
+        ];
+    }
+
+    fn relation_rows(reasoner: &impl Queryable) -> Vec<(&'static str, &'static str, &'static str)> {
+        let mut rows = vec![];
+        ["edge", "reach", "hop", "unreached"]
+            .into_iter()
+            .for_each(|relation| {
+                NODES.into_iter().for_each(|source| {
+                    NODES.into_iter().for_each(|destination| {
+                        if reasoner
+                            .contains_row(relation, &vec![Box::new(source), Box::new(destination)])
+                        {
+                            rows.push((relation, source, destination));
+                        }
+                    })
+                })
+            });
+
+        return rows;
+    }
+
+    fn assert_same_as_delete_rederive<T>(mut counting: T, mut delete_rederive: T)
+    where
+        T: Dynamic + Materializer + Maintainer + Queryable,
+    {
+        [&mut counting, &mut delete_rederive]
+            .into_iter()
+            .for_each(|reasoner| {
+                NODES
+                    .into_iter()
+                    .for_each(|node| reasoner.insert("node", vec![Box::new(node)]));
+                reasoner.materialize(&program()).unwrap();
+            });
+        counting.set_maintenance_strategy(MaintenanceStrategy::Counting);
+
+        updates()
+            .into_iter()
+            .zip(updates())
+            .for_each(|(update, same_update)| {
+                counting.update(update);
+                delete_rederive.update(same_update);
+
+                assert_eq!(relation_rows(&delete_rederive), relation_rows(&counting));
+            });
+    }
+
+    #[test]
+    fn test_counting() {
+        assert_same_as_delete_rederive(
+            ChibiDatalog::new(false, true, true),
+            ChibiDatalog::new(false, true, true),
+        );
+        assert_same_as_delete_rederive(
+            RelationalDatalog::<VecIndex>::new(false, true),
+            RelationalDatalog::<VecIndex>::new(false, true),
+        );
+
+        let mut reasoner = ChibiDatalog::new(false, true, true);
+        reasoner.set_maintenance_strategy(MaintenanceStrategy::Counting);
+        reasoner.materialize(&program()).unwrap();
+        reasoner.update(updates().remove(0));
+
+        let hop_a_c: UntypedRow = vec![Box::new("a"), Box::new("c")];
+        reasoner.update(vec![(false, edge("a", "b"))]);
+        assert!(reasoner.contains_row("hop", &hop_a_c));
+        reasoner.update(vec![(false, edge("a", "d"))]);
+        assert!(!reasoner.contains_row("hop", &hop_a_c));
+    }
+
+    #[test]
+    fn test_counting_nonrecursive_derivation_counts() {
+        let program =
+            vec![SugaredRule::try_from("hop(?x, ?z) <- [edge(?x, ?y), edge(?y, ?z)]").unwrap()];
+        let updates = || -> Vec<Vec<Diff<'static>>> {
+            return vec![
+                vec![
+                    (true, edge("a", "b")),
+                    (true, edge("b", "c")),
+                    (true, edge("a", "d")),
+                    (true, edge("d", "c")),
+                ],
+                vec![(false, edge("a", "b"))],
+                vec![(false, ("hop", vec![Box::new("a"), Box::new("c")]))],
+                vec![(true, edge("a", "e")), (true, edge("e", "c"))],
+                vec![(false, edge("d", "c")), (false, edge("e", "c"))],
+            ];
+        };
+        // How many derivations hop(a, c) has after every update
+        let hop_a_c_counts = [Some(2), Some(1), Some(1), Some(2), None];
+        let hop_a_c: Row = Box::new([
+            TypedValue::Str("a".to_string()),
+            TypedValue::Str("c".to_string()),
+        ]);
+
+        let mut counting = ChibiDatalog::new(false, false, true);
+        let mut delete_rederive = ChibiDatalog::new(false, false, true);
+        counting.set_maintenance_strategy(MaintenanceStrategy::Counting);
+        [&mut counting, &mut delete_rederive]
+            .into_iter()
+            .for_each(|reasoner| reasoner.materialize(&program).unwrap());
+
+        updates()
+            .into_iter()
+            .zip(updates())
+            .zip(hop_a_c_counts)
+            .for_each(|((update, same_update), hop_a_c_count)| {
+                counting.update(update);
+                delete_rederive.update(same_update);
+
+                assert_eq!(relation_rows(&delete_rederive), relation_rows(&counting));
+                assert_eq!(
+                    hop_a_c_count,
+                    counting.derivation_counts()["hop"].get(&hop_a_c).copied()
+                );
+            });
+    }
+
+    #[test]
+    fn test_counting_recursive_deletion() {
+        let program = vec![
+            SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reach(?x, ?z) <- [edge(?x, ?y), reach(?y, ?z)]").unwrap(),
+        ];
+        let updates = || -> Vec<Vec<Diff<'static>>> {
+            return vec![
+                // A chain into a cycle through b, c and d
+                vec![
+                    (true, edge("a", "b")),
+                    (true, edge("b", "c")),
+                    (true, edge("c", "d")),
+                    (true, edge("d", "b")),
+                ],
+                // Facts of the cycle only support each other once it is broken
+                vec![(false, edge("b", "c"))],
+                vec![(true, edge("a", "c"))],
+                vec![(false, edge("c", "d")), (false, edge("d", "b"))],
+            ];
+        };
+
+        let mut counting = ChibiDatalog::new(false, false, true);
+        let mut delete_rederive = ChibiDatalog::new(false, false, true);
+        counting.set_maintenance_strategy(MaintenanceStrategy::Counting);
+        [&mut counting, &mut delete_rederive]
+            .into_iter()
+            .for_each(|reasoner| reasoner.materialize(&program).unwrap());
+
+        updates().into_iter().zip(updates()).enumerate().for_each(
+            |(round, (update, same_update))| {
+                counting.update(update);
+                delete_rederive.update(same_update);
+
+                assert_eq!(relation_rows(&delete_rederive), relation_rows(&counting));
+                if round == 1 {
+                    assert!(!counting.contains_row("reach", &vec![Box::new("b"), Box::new("b")]));
+                    assert!(!counting.contains_row("reach", &vec![Box::new("a"), Box::new("d")]));
+                    assert!(counting.contains_row("reach", &vec![Box::new("c"), Box::new("b")]));
+                }
+            },
+        );
+        assert_eq!(
+            vec![
+                ("edge", "a", "b"),
+                ("edge", "a", "c"),
+                ("reach", "a", "b"),
+                ("reach", "a", "c")
+            ],
+            relation_rows(&counting)
+        );
+    }
+}
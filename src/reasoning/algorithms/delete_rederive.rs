@@ -1,10 +1,16 @@
-use crate::models::datalog::SugaredRule;
-use crate::models::reasoner::{BottomUpEvaluator, Dynamic, DynamicTyped, RelationDropper};
+use crate::misc::rule_graph::sort_program;
+use crate::misc::validation::ValidationError;
+use crate::models::datalog::{SugaredAtom, SugaredProgram, SugaredRule, Term};
+use crate::models::reasoner::{
+    BottomUpEvaluator, DeltaEvaluator, DynamicTyped, EvaluationResult, ExplicitFacts,
+    RelationDropper,
+};
 use crate::models::relational_algebra::Row;
+use crate::reasoning::algorithms::delta_rule_rewrite::DELTA_PREFIX;
 use ahash::{HashSet, HashSetExt};
 
-const OVERDELETION_PREFIX: &'static str = "-";
-const REDERIVATION_PREFIX: &'static str = "+";
+pub const OVERDELETION_PREFIX: &'static str = "-";
+pub const REDERIVATION_PREFIX: &'static str = "+";
 
 pub fn make_overdeletion_program(program: &Vec<SugaredRule>) -> Vec<SugaredRule> {
     let mut overdeletion_program = vec![];
@@ -14,6 +20,9 @@ pub fn make_overdeletion_program(program: &Vec<SugaredRule>) -> Vec<SugaredRule>
         let mut new_head = rule.head.clone();
         new_head.symbol = new_symbol;
         rule.body.iter().enumerate().for_each(|(idx, body_atom)| {
+            if body_atom.is_builtin() {
+                return;
+            }
             let mut new_rule = rule.clone();
             new_rule.head = new_head.clone();
             new_rule.body = new_rule.body;
@@ -45,48 +54,375 @@ pub fn make_alternative_derivation_program(program: &Vec<SugaredRule>) -> Vec<Su
     alternative_derivation_program
 }
 
+// Overdeletion program for a single stratum, to be evaluated with `evaluate_before_changes`. Every
+// body atom that might have changed is replaced by its changes in a rule of its own, with positive
+// atoms looking at what got deleted, and negated ones at what got inserted, while the other positive
+// atoms match whatever held before. Negated atoms that are not replaced are dropped, which can only
+// ever overdelete more.
+pub fn make_stratum_overdeletion_program(
+    stratum: &Vec<SugaredRule>,
+    deleted: &HashSet<String>,
+    inserted: &HashSet<String>,
+) -> Vec<SugaredRule> {
+    let stratum_relations: HashSet<&str> = stratum
+        .iter()
+        .map(|rule| rule.head.symbol.as_str())
+        .collect();
+    let mut overdeletion_program = vec![];
+
+    stratum.iter().for_each(|rule| {
+        let mut new_head = rule.head.clone();
+        new_head.symbol = format!("{}{}", OVERDELETION_PREFIX, rule.head.symbol);
+
+        rule.body
+            .iter()
+            .enumerate()
+            .filter(|(_idx, body_atom)| {
+                if body_atom.positive {
+                    return deleted.contains(&body_atom.symbol)
+                        || stratum_relations.contains(body_atom.symbol.as_str());
+                }
+                return inserted.contains(&body_atom.symbol);
+            })
+            .for_each(|(changed_idx, changed_atom)| {
+                let mut changes = changed_atom.clone();
+                if changed_atom.positive {
+                    changes.symbol = format!("{}{}", OVERDELETION_PREFIX, changed_atom.symbol);
+                } else {
+                    changes.symbol = format!("{}{}", DELTA_PREFIX, changed_atom.symbol);
+                    changes.positive = true;
+                }
+
+                let mut new_rule = SugaredRule {
+                    head: new_head.clone(),
+                    body: vec![changes],
+                };
+                new_rule.body.extend(
+                    rule.body
+                        .iter()
+                        .enumerate()
+                        .filter(|(idx, body_atom)| *idx != changed_idx && body_atom.positive)
+                        .map(|(_idx, body_atom)| body_atom.clone()),
+                );
+                overdeletion_program.push(new_rule);
+            });
+    });
+
+    overdeletion_program
+}
+
+// Evaluates the program as if the relations that it reads had not lost their deleted facts, nor,
+// unless they are left out of `inserted`, gained their inserted ones. Facts are only put back, and
+// taken out, for the duration of the evaluation, which relies on the deleted facts not being there.
+pub fn evaluate_before_changes<T: DynamicTyped + BottomUpEvaluator>(
+    instance: &mut T,
+    program: &SugaredProgram,
+    deleted: &HashSet<String>,
+    inserted: &HashSet<String>,
+) -> Result<EvaluationResult, ValidationError> {
+    let relations: HashSet<(&str, usize)> = program
+        .iter()
+        .flat_map(|rule| rule.body.iter())
+        .map(|body_atom| (body_atom.symbol.as_str(), body_atom.terms.len()))
+        .collect();
+    // The changes are copied under the rederivation prefix, since evaluating only yields what is not
+    // there yet, and not into the relations themselves, whose deltas would then be mixed up with
+    // those of the evaluation
+    let mut change_program: SugaredProgram = vec![];
+    relations.into_iter().for_each(|(sym, arity)| {
+        let atom = |symbol: String| SugaredAtom {
+            terms: (0..arity).map(|idx| Term::Variable(idx as u32)).collect(),
+            symbol,
+            positive: true,
+        };
+        [(deleted, OVERDELETION_PREFIX), (inserted, DELTA_PREFIX)]
+            .into_iter()
+            .filter(|(changed, _prefix)| changed.contains(sym))
+            .for_each(|(_changed, prefix)| {
+                let change_sym = format!("{}{}", prefix, sym);
+                change_program.push(SugaredRule {
+                    head: atom(format!("{}{}", REDERIVATION_PREFIX, change_sym)),
+                    body: vec![atom(change_sym)],
+                });
+            });
+    });
+    if change_program.is_empty() {
+        return instance.evaluate_program_bottom_up(program);
+    }
+
+    let changes = instance.evaluate_program_bottom_up(&change_program)?;
+    // Moves the changed facts back to where they were before the changes, or forth again
+    let shift = |instance: &mut T, back: bool| {
+        changes.iter().for_each(|(sym, row_set)| {
+            let change_sym = sym.strip_prefix(REDERIVATION_PREFIX).unwrap();
+            let (sym, deletion) = match change_sym.strip_prefix(OVERDELETION_PREFIX) {
+                Some(sym) => (sym, true),
+                None => (change_sym.strip_prefix(DELTA_PREFIX).unwrap(), false),
+            };
+            row_set.iter().for_each(|row| {
+                if deletion == back {
+                    instance.insert_typed(sym, row.clone());
+                } else {
+                    instance.delete_typed(sym, row);
+                }
+            });
+        });
+    };
+    shift(instance, true);
+    let evaluation = instance.evaluate_program_bottom_up(program);
+    shift(instance, false);
+
+    return evaluation;
+}
+
+// Rules whose derivations might have been unblocked by deleting facts that they negate
+pub fn make_unblocked_derivation_program(
+    stratum: &Vec<SugaredRule>,
+    deleted: &HashSet<String>,
+) -> Vec<SugaredRule> {
+    let mut unblocked_derivation_program = vec![];
+
+    stratum.iter().for_each(|rule| {
+        rule.body
+            .iter()
+            .filter(|body_atom| !body_atom.positive && deleted.contains(&body_atom.symbol))
+            .for_each(|body_atom| {
+                let mut del_atom = body_atom.clone();
+                del_atom.symbol = format!("{}{}", OVERDELETION_PREFIX, body_atom.symbol);
+                del_atom.positive = true;
+
+                let mut new_rule = rule.clone();
+                new_rule.body.insert(0, del_atom);
+                unblocked_derivation_program.push(new_rule);
+            })
+    });
+
+    unblocked_derivation_program
+}
+
+// Copies every relation defined by the stratum into its overdeletion relation
+pub fn make_stratum_retraction_program(stratum: &Vec<SugaredRule>) -> Vec<SugaredRule> {
+    let mut retraction_program: Vec<SugaredRule> = vec![];
+
+    stratum.iter().for_each(|rule| {
+        let mut body_atom = rule.head.clone();
+        body_atom.terms = (0..rule.head.terms.len())
+            .map(|idx| Term::Variable(idx as u32))
+            .collect();
+        let mut new_head = body_atom.clone();
+        new_head.symbol = format!("{}{}", OVERDELETION_PREFIX, rule.head.symbol);
+
+        let retraction_rule = SugaredRule {
+            head: new_head,
+            body: vec![body_atom],
+        };
+        if !retraction_program.contains(&retraction_rule) {
+            retraction_program.push(retraction_rule);
+        }
+    });
+
+    retraction_program
+}
+
+// Rules whose heads are their overdeletion relations, such that evaluating them yields everything
+// that they currently derive
+pub fn make_consequence_retraction_program(program: &SugaredProgram) -> SugaredProgram {
+    return program
+        .iter()
+        .map(|rule| {
+            let mut retraction_rule = rule.clone();
+            retraction_rule.head.symbol = format!("{}{}", OVERDELETION_PREFIX, rule.head.symbol);
+            retraction_rule
+        })
+        .collect();
+}
+
 pub type TypedDiff<'a> = (&'a str, Row);
 
+fn insert_change<T: DynamicTyped>(
+    instance: &mut T,
+    inserted: &mut HashSet<String>,
+    sym: &str,
+    row: Row,
+) {
+    instance.delete_typed(&format!("{}{}", OVERDELETION_PREFIX, sym), &row);
+    instance.insert_typed(&format!("{}{}", DELTA_PREFIX, sym), row.clone());
+    instance.insert_typed(sym, row);
+    inserted.insert(sym.to_string());
+}
+
 pub fn delete_rederive<'a, T>(
     instance: &mut T,
     program: &'a Vec<SugaredRule>,
     deletions: Vec<TypedDiff<'a>>,
-) where
-    T: DynamicTyped + Dynamic + BottomUpEvaluator + RelationDropper,
+) -> Result<(), ValidationError>
+where
+    T: DynamicTyped + BottomUpEvaluator + DeltaEvaluator + RelationDropper + ExplicitFacts,
+{
+    return stratified_delete_rederive(instance, program, &vec![], vec![], deletions);
+}
+
+// Maintains the materialization of a stratifiable program one component of `sort_program` at a
+// time, such that every component sees the net changes, deletions as "-" and insertions as "Δ"
+// prefixed relations, of all components before it, and those that are not touched by any change
+// are skipped. The consequences of freshly added rules are computed from scratch at their own
+// component.
+pub fn stratified_delete_rederive<'a, T>(
+    instance: &mut T,
+    program: &SugaredProgram,
+    fresh_program: &SugaredProgram,
+    additions: Vec<TypedDiff<'a>>,
+    retractions: Vec<TypedDiff<'a>>,
+) -> Result<(), ValidationError>
+where
+    T: DynamicTyped + BottomUpEvaluator + DeltaEvaluator + RelationDropper + ExplicitFacts,
 {
-    let mut relations_to_be_dropped: HashSet<String> = HashSet::new();
-    deletions.iter().for_each(|(sym, deletion)| {
-        let del_sym = format!("{}{}", OVERDELETION_PREFIX, sym);
-        instance.insert_typed(&del_sym, deletion.clone());
-        instance.delete_typed(sym, &deletion);
-        relations_to_be_dropped.insert(del_sym);
+    let strata = sort_program(program);
+    let mut deleted: HashSet<String> = HashSet::new();
+    let mut inserted: HashSet<String> = HashSet::new();
+
+    retractions.iter().for_each(|(sym, retraction)| {
+        instance.delete_typed(sym, retraction);
+        instance.insert_typed(
+            &format!("{}{}", OVERDELETION_PREFIX, sym),
+            retraction.clone(),
+        );
+        deleted.insert(sym.to_string());
     });
-    // Overdeletion and Rederivation programs
-    let overdeletion_program = make_overdeletion_program(program);
-    let rederivation_program = make_alternative_derivation_program(program);
-    // Stage 1 - intensional overdeletion
-    let overdeletions = instance.evaluate_program_bottom_up(&overdeletion_program);
-    overdeletions.into_iter().for_each(|(del_sym, row_set)| {
-        let sym = del_sym.strip_prefix(OVERDELETION_PREFIX).unwrap();
-        row_set.into_iter().for_each(|overdeletion| {
-            instance.delete_typed(sym, &overdeletion);
-            instance.insert_typed(&del_sym, overdeletion);
-        });
-        relations_to_be_dropped.insert(del_sym);
+    additions.iter().for_each(|(sym, addition)| {
+        insert_change(instance, &mut inserted, sym, addition.clone());
     });
 
-    //Stage 2 - intensional rederivation
-    let rederivations = instance.evaluate_program_bottom_up(&rederivation_program);
-    rederivations.into_iter().for_each(|(alt_sym, row_set)| {
-        let sym = alt_sym.strip_prefix(REDERIVATION_PREFIX).unwrap();
-        row_set.into_iter().for_each(|row| {
-            instance.insert_typed(&sym, row);
-        })
-    });
+    for stratum in strata.iter() {
+        let fresh = stratum.iter().any(|rule| {
+            fresh_program
+                .iter()
+                .any(|fresh_rule| fresh_rule.to_string() == rule.to_string())
+        });
+        // Aggregates can not be maintained fact by fact, hence their stratum is recomputed
+        if stratum.iter().any(|rule| rule.head.has_aggregates()) {
+            let changed = stratum.iter().any(|rule| {
+                rule.body.iter().any(|body_atom| {
+                    deleted.contains(&body_atom.symbol) || inserted.contains(&body_atom.symbol)
+                })
+            });
+            if changed || fresh {
+                let retractions = instance
+                    .evaluate_program_bottom_up(&make_stratum_retraction_program(stratum))?;
+                retractions.into_iter().for_each(|(del_sym, row_set)| {
+                    let sym = del_sym.strip_prefix(OVERDELETION_PREFIX).unwrap();
+                    row_set.into_iter().for_each(|retraction| {
+                        if !instance.is_explicit(sym, &retraction) {
+                            instance.delete_typed(sym, &retraction);
+                            instance.insert_typed(&del_sym, retraction);
+                        }
+                    });
+                    deleted.insert(sym.to_string());
+                });
+                let insertions = instance.evaluate_program_bottom_up(stratum)?;
+                insertions.into_iter().for_each(|(sym, row_set)| {
+                    row_set.into_iter().for_each(|row| {
+                        insert_change(instance, &mut inserted, &sym, row);
+                    })
+                });
+            }
+            continue;
+        }
+        // Retracted facts of the stratum itself are rederived if they still hold
+        let seeded = stratum.iter().any(|rule| {
+            deleted.contains(&rule.head.symbol)
+                || rule.body.iter().any(|body_atom| {
+                    if body_atom.positive {
+                        return deleted.contains(&body_atom.symbol);
+                    }
+                    return inserted.contains(&body_atom.symbol);
+                })
+        });
+        // Stage 1 - intensional overdeletion
+        if seeded {
+            let overdeletion_program =
+                make_stratum_overdeletion_program(stratum, &deleted, &inserted);
+            // Insertions are not taken out, which can only ever overdelete more
+            let overdeletions = evaluate_before_changes(
+                instance,
+                &overdeletion_program,
+                &deleted,
+                &HashSet::new(),
+            )?;
+            // Explicit facts hold regardless of their derivations, hence are never overdeleted
+            overdeletions.into_iter().for_each(|(del_sym, row_set)| {
+                let sym = del_sym.strip_prefix(OVERDELETION_PREFIX).unwrap();
+                row_set.into_iter().for_each(|overdeletion| {
+                    if !instance.is_explicit(sym, &overdeletion) {
+                        instance.delete_typed(sym, &overdeletion);
+                        instance.insert_typed(&del_sym, overdeletion);
+                    }
+                });
+                deleted.insert(sym.to_string());
+            });
+            // What has just been added is never overdeleted
+            additions
+                .iter()
+                .filter(|(sym, _addition)| stratum.iter().any(|rule| rule.head.symbol == *sym))
+                .for_each(|(sym, addition)| {
+                    insert_change(instance, &mut inserted, sym, addition.clone());
+                });
+            // Stage 2 - intensional rederivation
+            let rederivation_program = make_alternative_derivation_program(
+                &stratum
+                    .iter()
+                    .filter(|rule| deleted.contains(&rule.head.symbol))
+                    .cloned()
+                    .collect(),
+            );
+            let rederivations = instance.evaluate_program_bottom_up(&rederivation_program)?;
+            rederivations.into_iter().for_each(|(alt_sym, row_set)| {
+                let sym = alt_sym.strip_prefix(REDERIVATION_PREFIX).unwrap();
+                row_set.into_iter().for_each(|row| {
+                    insert_change(instance, &mut inserted, sym, row);
+                })
+            });
+        }
+        // Stage 3 - derivations that are no longer blocked by a negated atom
+        let unblocked_derivation_program = make_unblocked_derivation_program(stratum, &deleted);
+        if unblocked_derivation_program.len() > 0 {
+            let unblocked_derivations =
+                instance.evaluate_program_bottom_up(&unblocked_derivation_program)?;
+            unblocked_derivations
+                .into_iter()
+                .for_each(|(sym, row_set)| {
+                    row_set.into_iter().for_each(|row| {
+                        insert_change(instance, &mut inserted, &sym, row);
+                    })
+                });
+        }
+        // Stage 4 - insertion, from scratch if the stratum has new rules
+        let insertions = if fresh {
+            instance.evaluate_program_bottom_up(stratum)?
+        } else if stratum.iter().any(|rule| {
+            rule.body
+                .iter()
+                .any(|body_atom| body_atom.positive && inserted.contains(&body_atom.symbol))
+        }) {
+            instance.evaluate_program_on_deltas(stratum)?
+        } else {
+            Default::default()
+        };
+        insertions.into_iter().for_each(|(sym, row_set)| {
+            row_set.into_iter().for_each(|row| {
+                insert_change(instance, &mut inserted, &sym, row);
+            })
+        });
+    }
 
-    relations_to_be_dropped.into_iter().for_each(|del_sym| {
-        instance.drop_relation(&del_sym);
+    deleted.into_iter().for_each(|sym| {
+        instance.drop_relation(&format!("{}{}", OVERDELETION_PREFIX, sym));
     });
+    inserted.into_iter().for_each(|sym| {
+        instance.drop_relation(&format!("{}{}", DELTA_PREFIX, sym));
+    });
+
+    return Ok(());
 }
 
 #[cfg(test)]
@@ -99,58 +435,88 @@ mod tests {
     use crate::models::relational_algebra::Row;
     use crate::reasoning::algorithms::delete_rederive::{
         delete_rederive, make_alternative_derivation_program, make_overdeletion_program,
-        OVERDELETION_PREFIX, REDERIVATION_PREFIX,
+        make_stratum_overdeletion_program, OVERDELETION_PREFIX, REDERIVATION_PREFIX,
     };
     use crate::reasoning::reasoners::chibi::ChibiDatalog;
     use crate::reasoning::reasoners::relational::RelationalDatalog;
+    use ahash::{HashSet, HashSetExt};
     use indexmap::IndexSet;
     use rand::distributions::uniform::SampleBorrow;
 
     #[test]
     fn test_make_overdeletion_program() {
         let program = vec![
-            SugaredRule::from("reach(?x, ?y) <- [edge(?x, ?y)]"),
-            SugaredRule::from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]"),
+            SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap(),
         ];
 
         let actual_overdeletion_program = make_overdeletion_program(&program);
 
         let exp_overdeletion_program = vec![
-            SugaredRule::from(&*format!(
+            SugaredRule::try_from(&*format!(
                 "{}reach(?x, ?y) <- [{}edge(?x, ?y)]",
                 OVERDELETION_PREFIX, OVERDELETION_PREFIX
-            )),
-            SugaredRule::from(&*format!(
+            ))
+            .unwrap(),
+            SugaredRule::try_from(&*format!(
                 "{}reach(?x, ?z) <- [{}reach(?x, ?y), edge(?y, ?z)]",
                 OVERDELETION_PREFIX, OVERDELETION_PREFIX
-            )),
-            SugaredRule::from(&*format!(
+            ))
+            .unwrap(),
+            SugaredRule::try_from(&*format!(
                 "{}reach(?x, ?z) <- [reach(?x, ?y), {}edge(?y, ?z)]",
                 OVERDELETION_PREFIX, OVERDELETION_PREFIX
-            )),
+            ))
+            .unwrap(),
         ];
 
         assert_eq!(exp_overdeletion_program, actual_overdeletion_program)
     }
 
+    #[test]
+    fn test_make_stratum_overdeletion_program_wide_body() {
+        let body: Vec<String> = (0..70)
+            .map(|idx| format!("edge(?v{}, ?v{})", idx, idx + 1))
+            .collect();
+        let stratum =
+            vec![
+                SugaredRule::try_from(&*format!("path(?v0, ?v70) <- [{}]", body.join(", ")))
+                    .unwrap(),
+            ];
+        let mut deleted = HashSet::new();
+        deleted.insert("edge".to_string());
+
+        let overdeletion_program =
+            make_stratum_overdeletion_program(&stratum, &deleted, &HashSet::new());
+
+        assert_eq!(70, overdeletion_program.len());
+        overdeletion_program.iter().for_each(|rule| {
+            assert_eq!(format!("{}path", OVERDELETION_PREFIX), rule.head.symbol);
+            assert_eq!(format!("{}edge", OVERDELETION_PREFIX), rule.body[0].symbol);
+            assert_eq!(70, rule.body.len());
+        });
+    }
+
     #[test]
     fn test_make_alternative_derivation_program() {
         let program = vec![
-            SugaredRule::from("reach(?x, ?y) <- [edge(?x, ?y)]"),
-            SugaredRule::from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]"),
+            SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap(),
         ];
 
         let actual_alt_program = make_alternative_derivation_program(&program);
         let exp_alt_program = vec![
-            SugaredRule::from(&*format!(
+            SugaredRule::try_from(&*format!(
                 "{}reach(?x, ?y) <- [{}reach(?x, ?y), edge(?x, ?y)]",
                 REDERIVATION_PREFIX, OVERDELETION_PREFIX
-            )),
-            SugaredRule::from(&*format!(
+            ))
+            .unwrap(),
+            SugaredRule::try_from(&*format!(
                 //
                 "{}reach(?x, ?z) <- [{}reach(?x, ?z), reach(?x, ?y), edge(?y, ?z)]",
                 REDERIVATION_PREFIX, OVERDELETION_PREFIX
-            )),
+            ))
+            .unwrap(),
         ];
 
         assert_eq!(exp_alt_program, actual_alt_program)
@@ -179,11 +545,11 @@ mod tests {
         });
 
         let program = vec![
-            SugaredRule::from("reach(?x, ?y) <- [edge(?x, ?y)]"),
-            SugaredRule::from("reach(?x, ?z) <- [edge(?x, ?y), reach(?y, ?z)]"),
+            SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reach(?x, ?z) <- [edge(?x, ?y), reach(?y, ?z)]").unwrap(),
         ];
 
-        chibi.materialize(&program);
+        chibi.materialize(&program).unwrap();
 
         // Overdeletions
         let overdeletion_program = make_overdeletion_program(&program);
@@ -191,7 +557,9 @@ mod tests {
         chibi.delete("edge", &vec![Box::new("e"), Box::new("f")]);
         chibi.insert("-edge", vec![Box::new("e"), Box::new("f")]);
 
-        let actual_overdeletions = chibi.evaluate_program_bottom_up(&overdeletion_program);
+        let actual_overdeletions = chibi
+            .evaluate_program_bottom_up(&overdeletion_program)
+            .unwrap();
         let expected_overdeletions = vec![
             vec![
                 TypedValue::Str("a".to_string()),
@@ -257,7 +625,9 @@ mod tests {
             });
 
         let rederivation_program = make_alternative_derivation_program(&program);
-        let actual_rederivations = chibi.evaluate_program_bottom_up(&rederivation_program);
+        let actual_rederivations = chibi
+            .evaluate_program_bottom_up(&rederivation_program)
+            .unwrap();
         let expected_rederivations = vec![
             vec![
                 TypedValue::Str("a".to_string()),
@@ -317,11 +687,11 @@ mod tests {
         });
 
         let program = vec![
-            SugaredRule::from("reach(?x, ?y) <- [edge(?x, ?y)]"),
-            SugaredRule::from("reach(?x, ?z) <- [edge(?x, ?y), reach(?y, ?z)]"),
+            SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reach(?x, ?z) <- [edge(?x, ?y), reach(?y, ?z)]").unwrap(),
         ];
 
-        chibi.materialize(&program);
+        chibi.materialize(&program).unwrap();
 
         let expected_deletion_1: UntypedRow = vec![Box::new("e"), Box::new("f")];
         let expected_deletion_2: UntypedRow = vec![Box::new("e"), Box::new("h")];
@@ -350,7 +720,8 @@ mod tests {
                 "edge",
                 Box::new(["e".to_typed_value(), "f".to_typed_value()]),
             )],
-        );
+        )
+        .unwrap();
 
         //println!("post delete rederive");
 
@@ -369,4 +740,18 @@ mod tests {
         assert!(!chibi.contains_row("reach", &expected_deletion_3));
         assert!(!chibi.contains_row("reach", &expected_deletion_4));
     }
+
+    #[test]
+    fn test_delete_rederive_still_derived() {
+        let mut chibi: ChibiDatalog = Default::default();
+        chibi.insert("edge", vec![Box::new("a"), Box::new("b")]);
+
+        let program = vec![SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap()];
+        chibi.materialize(&program).unwrap();
+
+        // Retracting a fact that the program still derives rederives it
+        chibi.update(vec![(false, ("reach", vec![Box::new("a"), Box::new("b")]))]);
+
+        assert!(chibi.contains_row("reach", &vec![Box::new("a"), Box::new("b")]));
+    }
 }
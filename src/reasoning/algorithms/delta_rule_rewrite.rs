@@ -1,4 +1,4 @@
-use crate::models::datalog::SugaredRule;
+use crate::models::datalog::{SugaredRule, Term};
 use ahash::HashSet;
 
 pub const DELTA_PREFIX: &'static str = "Δ";
@@ -15,7 +15,7 @@ pub fn make_sne_programs(program: &Vec<SugaredRule>) -> (Vec<SugaredRule>, Vec<S
         if !rule
             .body
             .iter()
-            .any(|body_atom| idb_relations.contains(&body_atom.symbol))
+            .any(|body_atom| body_atom.positive && idb_relations.contains(&body_atom.symbol))
         {
             nonrecursive_program.push(rule.clone());
         }
@@ -26,7 +26,7 @@ pub fn make_sne_programs(program: &Vec<SugaredRule>) -> (Vec<SugaredRule>, Vec<S
 
     program.iter().for_each(|rule| {
         rule.body.iter().enumerate().for_each(|(idx, body_atom)| {
-            if idb_relations.contains(&body_atom.symbol) {
+            if body_atom.positive && idb_relations.contains(&body_atom.symbol) {
                 let mut new_rule = rule.clone();
                 new_rule.body = new_rule.body;
                 new_rule.body[idx].symbol = format!("{}{}", DELTA_PREFIX, body_atom.symbol);
@@ -53,9 +53,14 @@ pub fn make_update_sne_programs(program: &Vec<SugaredRule>) -> (Vec<SugaredRule>
         if !rule
             .body
             .iter()
-            .any(|body_atom| idb_relations.contains(&body_atom.symbol))
+            .any(|body_atom| body_atom.positive && idb_relations.contains(&body_atom.symbol))
         {
+            // Negated atoms are never deltaified, they only ever refer to lower strata, and neither
+            // are built-ins, as they have no facts
             rule.body.iter().enumerate().for_each(|(idx, body_atom)| {
+                if !body_atom.positive || body_atom.is_builtin() {
+                    return;
+                }
                 let mut new_rule = rule.clone();
                 new_rule.body[idx].symbol = format!("{}{}", DELTA_PREFIX, body_atom.symbol);
 
@@ -71,7 +76,7 @@ pub fn make_update_sne_programs(program: &Vec<SugaredRule>) -> (Vec<SugaredRule>
     let mut delta_program = vec![];
     program.iter().for_each(|rule| {
         rule.body.iter().enumerate().for_each(|(idb_body_atom_idx, body_atom)| {
-            if idb_relations.contains(&body_atom.symbol) {
+            if body_atom.positive && idb_relations.contains(&body_atom.symbol) {
                 let mut new_rule = rule.clone();
                 new_rule.body = new_rule.body;
                 new_rule.body[idb_body_atom_idx].symbol = format!("{}{}", DELTA_PREFIX, body_atom.symbol);
@@ -85,6 +90,9 @@ pub fn make_update_sne_programs(program: &Vec<SugaredRule>) -> (Vec<SugaredRule>
                     .iter()
                     .enumerate()
                     .filter(|(_position, new_rule_body_atom)| {
+                        if !new_rule_body_atom.positive || new_rule_body_atom.is_builtin() {
+                            return false;
+                        }
                         let mut new_rule_body_atom_sym = new_rule_body_atom.symbol.as_str();
 
                         if let Some(undeltaified) = new_rule_body_atom.symbol.strip_prefix(DELTA_PREFIX) {
@@ -127,7 +135,26 @@ pub fn deltaify_idb(program: &Vec<SugaredRule>) -> Vec<SugaredRule> {
 
     return idb_relations
         .into_iter()
-        .map(|rule_head| {
+        .map(|mut rule_head| {
+            // Aggregates can not be matched against, hence they become fresh variables
+            let mut fresh_variable = rule_head
+                .terms
+                .iter()
+                .filter_map(|term| match term {
+                    Term::Variable(identifier) | Term::Aggregate(_, identifier) => {
+                        Some(*identifier)
+                    }
+                    Term::Constant(_) => None,
+                })
+                .max()
+                .unwrap_or(0);
+            rule_head.terms.iter_mut().for_each(|term| {
+                if let Term::Aggregate(..) = term {
+                    fresh_variable += 1;
+                    *term = Term::Variable(fresh_variable);
+                }
+            });
+
             let mut delta_rule = SugaredRule::default();
             let mut delta_rule_head = rule_head.clone();
             delta_rule_head.symbol = format!("{}{}", DELTA_PREFIX, rule_head.symbol);
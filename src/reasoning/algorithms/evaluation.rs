@@ -57,3 +57,36 @@ impl<T: Database + Set + Empty> IncrementalEvaluation<T> {
         }
     }
 }
+
+pub struct StratifiedEvaluation<T: Database + Set + Empty + Clone> {
+    pub strata: Vec<IncrementalEvaluation<T>>,
+    pub output: T,
+}
+
+impl<T: Database + Set + Empty + Clone> StratifiedEvaluation<T> {
+    pub(crate) fn new(strata: Vec<IncrementalEvaluation<T>>) -> Self {
+        return Self {
+            strata,
+            output: Default::default(),
+        };
+    }
+    // Every stratum is evaluated up to its fixpoint before the next one starts, with its
+    // consequences, and their deltas, being visible to all the following strata.
+    pub fn semi_naive(&mut self, fact_store: &T) {
+        let mut db: Option<T> = None;
+        let stratum_count = self.strata.len();
+
+        for (idx, stratum) in self.strata.iter_mut().enumerate() {
+            stratum.semi_naive(db.as_ref().unwrap_or(fact_store));
+            let consequences = std::mem::take(&mut stratum.output);
+
+            if idx + 1 < stratum_count {
+                let delta = stratum
+                    .immediate_consequence_operator
+                    .deltaify_idb(&consequences);
+                db.get_or_insert_with(|| fact_store.clone()).merge(delta);
+            }
+            self.output.merge(consequences);
+        }
+    }
+}
@@ -0,0 +1,522 @@
+// Magic sets rewrite the program such that bottom-up evaluation only derives what is relevant to a
+// query. Every intensional relation is adorned with which of its arguments are bound(b) or
+// free(f) when it is reached from the query, passing bindings from left to right in rule bodies,
+// and its rules are guarded by a "magic" relation holding all bindings that it can be called with.
+
+use crate::misc::helpers::terms_to_row;
+use crate::misc::validation::ValidationError;
+use crate::models::datalog::{Builtin, SugaredAtom, SugaredProgram, SugaredRule, Term};
+use crate::models::reasoner::{BottomUpEvaluator, DynamicTyped, RelationDropper};
+use crate::models::relational_algebra::Row;
+use ahash::{HashMap, HashSet, HashSetExt};
+use indexmap::IndexSet;
+
+pub const MAGIC_PREFIX: &'static str = "magic_";
+// Adorned relations are renamed apart, such that they never clash with a relation of the program
+pub const ADORNMENT_PREFIX: &'static str = "@";
+
+pub fn adornment(atom: &SugaredAtom, bound_variables: &HashSet<u32>) -> String {
+    return atom
+        .terms
+        .iter()
+        .map(|term| match term {
+            Term::Constant(_) => 'b',
+            Term::Variable(identifier) => {
+                if bound_variables.contains(identifier) {
+                    'b'
+                } else {
+                    'f'
+                }
+            }
+            Term::Aggregate(..) => 'f',
+        })
+        .collect();
+}
+
+pub fn adorn(atom: &SugaredAtom, adornment: &str) -> SugaredAtom {
+    let mut adorned_atom = atom.clone();
+    adorned_atom.symbol = format!("{}{}_{}", ADORNMENT_PREFIX, atom.symbol, adornment);
+
+    return adorned_atom;
+}
+
+// Returns None if nothing is bound, since then there is nothing to guard with
+fn magic_atom(adorned_atom: &SugaredAtom, adornment: &str) -> Option<SugaredAtom> {
+    if !adornment.contains('b') {
+        return None;
+    }
+
+    let mut magic_atom = adorned_atom.clone();
+    magic_atom.symbol = format!("{}{}", MAGIC_PREFIX, adorned_atom.symbol);
+    magic_atom.positive = true;
+    magic_atom.terms = adorned_atom
+        .terms
+        .iter()
+        .zip(adornment.chars())
+        .filter(|(_term, binding)| *binding == 'b')
+        .map(|(term, _binding)| term.clone())
+        .collect();
+
+    return Some(magic_atom);
+}
+
+// Aggregates are computed over every binding of their group, hence they are never bound
+fn free_aggregates(adornment: String, aggregate_columns: Option<&HashSet<usize>>) -> String {
+    return adornment
+        .chars()
+        .enumerate()
+        .map(|(idx, binding)| match aggregate_columns {
+            Some(columns) if columns.contains(&idx) => 'f',
+            _ => binding,
+        })
+        .collect();
+}
+
+// The variable that an assignment built-in binds, once all of its other arguments are bound
+fn assigned_variable(atom: &SugaredAtom, bound_variables: &HashSet<u32>) -> Option<u32> {
+    if !atom.positive {
+        return None;
+    }
+    let is_bound = |term: &Term| match term {
+        Term::Constant(_) => true,
+        Term::Variable(identifier) => bound_variables.contains(identifier),
+        Term::Aggregate(..) => false,
+    };
+
+    return match (atom.builtin()?, atom.terms.as_slice()) {
+        (builtin, [left, right, Term::Variable(result)])
+            if builtin.is_arithmetic() && is_bound(left) && is_bound(right) =>
+        {
+            Some(*result)
+        }
+        (Builtin::Equal, [value, Term::Variable(unbound)])
+        | (Builtin::Equal, [Term::Variable(unbound), value])
+            if is_bound(value) =>
+        {
+            Some(*unbound)
+        }
+        _ => None,
+    };
+}
+
+fn variables(atom: &SugaredAtom) -> impl Iterator<Item = u32> + '_ {
+    return atom.terms.iter().filter_map(|term| match term {
+        Term::Variable(identifier) => Some(*identifier),
+        Term::Constant(_) | Term::Aggregate(..) => None,
+    });
+}
+
+// Returns the magic program, its seed facts, and the adorned query, whose matches are the answers.
+// Relations that are negated are not rewritten, and are instead computed in full.
+pub fn make_magic_program(
+    program: &SugaredProgram,
+    query: &SugaredAtom,
+) -> (SugaredProgram, Vec<SugaredAtom>, SugaredAtom) {
+    let idb_relations: HashSet<&str> = program
+        .iter()
+        .map(|rule| rule.head.symbol.as_str())
+        .collect();
+
+    if !idb_relations.contains(query.symbol.as_str()) {
+        return (vec![], vec![], query.clone());
+    }
+
+    let mut aggregate_columns: HashMap<&str, HashSet<usize>> = Default::default();
+    program.iter().for_each(|rule| {
+        rule.head
+            .terms
+            .iter()
+            .enumerate()
+            .filter(|(_idx, term)| matches!(term, Term::Aggregate(..)))
+            .for_each(|(idx, _term)| {
+                aggregate_columns
+                    .entry(rule.head.symbol.as_str())
+                    .or_default()
+                    .insert(idx);
+            });
+    });
+
+    let mut magic_program = vec![];
+    let mut seeds = vec![];
+
+    let query_adornment = free_aggregates(
+        adornment(query, &HashSet::new()),
+        aggregate_columns.get(query.symbol.as_str()),
+    );
+    let adorned_query = adorn(query, &query_adornment);
+    if let Some(seed) = magic_atom(&adorned_query, &query_adornment) {
+        seeds.push(seed);
+    }
+
+    let mut adorned_relations: HashSet<(String, String)> = HashSet::new();
+    let mut pending_adorned_relations = vec![(query.symbol.clone(), query_adornment)];
+    let mut negated_relations: HashSet<String> = HashSet::new();
+
+    while let Some((symbol, head_adornment)) = pending_adorned_relations.pop() {
+        if !adorned_relations.insert((symbol.clone(), head_adornment.clone())) {
+            continue;
+        }
+
+        program
+            .iter()
+            .filter(|rule| rule.head.symbol == symbol)
+            .for_each(|rule| {
+                let adorned_head = adorn(&rule.head, &head_adornment);
+                let head_magic_atom = magic_atom(&adorned_head, &head_adornment);
+
+                let mut bound_variables: HashSet<u32> = HashSet::new();
+                rule.head
+                    .terms
+                    .iter()
+                    .zip(head_adornment.chars())
+                    .for_each(|(term, binding)| {
+                        if let (Term::Variable(identifier), 'b') = (term, binding) {
+                            bound_variables.insert(*identifier);
+                        }
+                    });
+
+                let mut adorned_body: Vec<SugaredAtom> = head_magic_atom.into_iter().collect();
+                // Negated atoms and built-ins, other than assignments of bound arguments, only
+                // filter, hence they bind nothing, and go last
+                let mut filters = vec![];
+
+                rule.body.iter().for_each(|body_atom| {
+                    if let (false, Some(variable)) = (
+                        adorned_body.is_empty(),
+                        assigned_variable(body_atom, &bound_variables),
+                    ) {
+                        bound_variables.insert(variable);
+                        adorned_body.push(body_atom.clone());
+                        return;
+                    }
+                    if body_atom.is_builtin() {
+                        filters.push(body_atom.clone());
+                        return;
+                    }
+                    if !body_atom.positive {
+                        if idb_relations.contains(body_atom.symbol.as_str()) {
+                            negated_relations.insert(body_atom.symbol.clone());
+                        }
+                        filters.push(body_atom.clone());
+                        return;
+                    }
+
+                    if !idb_relations.contains(body_atom.symbol.as_str()) {
+                        bound_variables.extend(variables(body_atom));
+                        adorned_body.push(body_atom.clone());
+                        return;
+                    }
+
+                    let body_atom_adornment = free_aggregates(
+                        adornment(body_atom, &bound_variables),
+                        aggregate_columns.get(body_atom.symbol.as_str()),
+                    );
+                    let adorned_body_atom = adorn(body_atom, &body_atom_adornment);
+
+                    if let Some(body_magic_atom) =
+                        magic_atom(&adorned_body_atom, &body_atom_adornment)
+                    {
+                        if adorned_body.is_empty() {
+                            seeds.push(body_magic_atom);
+                        // Recursive calls with the same bindings would only yield tautologies
+                        } else if adorned_body.len() > 1
+                            || adorned_body[0].to_string() != body_magic_atom.to_string()
+                        {
+                            magic_program.push(SugaredRule {
+                                head: body_magic_atom,
+                                body: adorned_body.clone(),
+                            });
+                        }
+                    }
+
+                    pending_adorned_relations.push((body_atom.symbol.clone(), body_atom_adornment));
+                    bound_variables.extend(variables(body_atom));
+                    adorned_body.push(adorned_body_atom);
+                });
+
+                adorned_body.extend(filters);
+                magic_program.push(SugaredRule {
+                    head: adorned_head,
+                    body: adorned_body,
+                });
+            });
+    }
+
+    let mut pending_negated_relations: Vec<String> = negated_relations.iter().cloned().collect();
+    while let Some(symbol) = pending_negated_relations.pop() {
+        program
+            .iter()
+            .filter(|rule| rule.head.symbol == symbol)
+            .for_each(|rule| {
+                rule.body
+                    .iter()
+                    .filter(|body_atom| idb_relations.contains(body_atom.symbol.as_str()))
+                    .for_each(|body_atom| {
+                        if negated_relations.insert(body_atom.symbol.clone()) {
+                            pending_negated_relations.push(body_atom.symbol.clone());
+                        }
+                    });
+                magic_program.push(rule.clone());
+            });
+    }
+
+    return (magic_program, seeds, adorned_query);
+}
+
+// Answers the query with any bottom-up evaluator, only deriving what is relevant to it. The seeds
+// are only inserted for the duration of the evaluation.
+pub fn evaluate_query_bottom_up<T: BottomUpEvaluator + DynamicTyped + RelationDropper>(
+    evaluator: &mut T,
+    program: &SugaredProgram,
+    query: &SugaredAtom,
+) -> Result<IndexSet<Row, ahash::RandomState>, ValidationError> {
+    let (mut magic_program, seeds, adorned_query) = make_magic_program(program, query);
+    // Adorned queries may leave constants and repeated variables unchecked, and queries of
+    // extensional relations are not rewritten at all, hence the answers are matched once more
+    let mut answer = adorned_query.clone();
+    answer.symbol = format!("{}query", ADORNMENT_PREFIX);
+    magic_program.push(SugaredRule {
+        head: answer.clone(),
+        body: vec![adorned_query],
+    });
+
+    let seed_symbols: HashSet<String> = seeds.iter().map(|seed| seed.symbol.clone()).collect();
+    seeds.into_iter().for_each(|seed| {
+        evaluator.insert_typed(&seed.symbol, terms_to_row(seed.terms));
+    });
+    let answers = evaluator.evaluate_program_bottom_up(&magic_program);
+    seed_symbols
+        .iter()
+        .for_each(|seed_symbol| evaluator.drop_relation(seed_symbol));
+
+    return Ok(answers?.remove(&answer.symbol).unwrap_or_default());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::misc::helpers::terms_to_row;
+    use crate::models::datalog::{SugaredAtom, SugaredRule, TypedValue};
+    use crate::models::reasoner::{BottomUpEvaluator, Dynamic, DynamicTyped};
+    use crate::reasoning::algorithms::magic_sets::{
+        evaluate_query_bottom_up, make_magic_program, MAGIC_PREFIX,
+    };
+    use crate::reasoning::reasoners::chibi::ChibiDatalog;
+
+    #[test]
+    fn test_make_magic_program() {
+        let program = vec![
+            SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap(),
+        ];
+        let query = SugaredAtom::try_from("reach(a, ?y)").unwrap();
+
+        let (magic_program, seeds, adorned_query) = make_magic_program(&program, &query);
+
+        // Adorned relations can not be parsed, hence the program is compared as text
+        let expected_magic_program = vec![
+            "@reach_bf(?0, ?1) <- [magic_@reach_bf(?0), edge(?0, ?1)]",
+            "@reach_bf(?0, ?1) <- [magic_@reach_bf(?0), @reach_bf(?0, ?2), edge(?2, ?1)]",
+        ];
+
+        assert_eq!(
+            expected_magic_program,
+            magic_program
+                .iter()
+                .map(|rule| rule.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("magic_@reach_bf(a)", seeds[0].to_string());
+        assert_eq!("@reach_bf(a, ?0)", adorned_query.to_string());
+    }
+
+    #[test]
+    fn test_magic_program_evaluation() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, false, true);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+        reasoner.insert("edge", vec![Box::new("b"), Box::new("c")]);
+        reasoner.insert("edge", vec![Box::new("d"), Box::new("e")]);
+        reasoner.insert("edge", vec![Box::new("e"), Box::new("f")]);
+
+        let program = vec![
+            SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap(),
+        ];
+        let (magic_program, seeds, adorned_query) =
+            make_magic_program(&program, &SugaredAtom::try_from("reach(a, ?y)").unwrap());
+
+        seeds.into_iter().for_each(|seed| {
+            reasoner.insert_typed(&seed.symbol, terms_to_row(seed.terms));
+        });
+
+        let answers = reasoner
+            .evaluate_program_bottom_up(&magic_program)
+            .unwrap()
+            .get(&adorned_query.symbol)
+            .unwrap()
+            .clone();
+
+        let a = TypedValue::Str("a".to_string());
+        assert_eq!(2, answers.len());
+        assert!(
+            answers.contains(&vec![a.clone(), TypedValue::Str("b".to_string())].into_boxed_slice())
+        );
+        assert!(answers.contains(&vec![a, TypedValue::Str("c".to_string())].into_boxed_slice()));
+
+        let answers = evaluate_query_bottom_up(
+            &mut reasoner,
+            &program,
+            &SugaredAtom::try_from("reach(?x, c)").unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(2, answers.len());
+        assert!(answers.contains(
+            &vec![
+                TypedValue::Str("b".to_string()),
+                TypedValue::Str("c".to_string())
+            ]
+            .into_boxed_slice()
+        ));
+
+        let answers = evaluate_query_bottom_up(
+            &mut reasoner,
+            &program,
+            &SugaredAtom::try_from("edge(d, ?y)").unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(1, answers.len());
+    }
+
+    #[test]
+    fn test_magic_program_builtins() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, false, true);
+        vec![(1, 2), (2, 0), (0, 3), (4, 5)].into_iter().for_each(
+            |(source, destination): (u32, u32)| {
+                reasoner.insert("edge", vec![Box::new(source), Box::new(destination)])
+            },
+        );
+
+        let program = vec![
+            SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap(),
+            SugaredRule::try_from("ahead(?x, ?y) <- [?x < ?y, reach(?x, ?y)]").unwrap(),
+        ];
+        let (magic_program, seeds, adorned_query) =
+            make_magic_program(&program, &SugaredAtom::try_from("ahead(1, ?y)").unwrap());
+
+        // The built-in binds nothing, hence reach is only called with its first argument bound, and
+        // no magic rule is filtered by it
+        assert!(magic_program
+            .iter()
+            .all(|rule| rule.head.symbol != "magic_@reach_bb"));
+        assert!(magic_program
+            .iter()
+            .filter(|rule| rule.head.symbol.starts_with(MAGIC_PREFIX))
+            .all(|rule| !rule.body.iter().any(|body_atom| body_atom.is_builtin())));
+
+        seeds.into_iter().for_each(|seed| {
+            reasoner.insert_typed(&seed.symbol, terms_to_row(seed.terms));
+        });
+
+        let answers = reasoner
+            .evaluate_program_bottom_up(&magic_program)
+            .unwrap()
+            .get(&adorned_query.symbol)
+            .unwrap()
+            .clone();
+
+        assert_eq!(2, answers.len());
+        assert!(
+            answers.contains(&vec![TypedValue::UInt(1), TypedValue::UInt(2)].into_boxed_slice())
+        );
+        assert!(
+            answers.contains(&vec![TypedValue::UInt(1), TypedValue::UInt(3)].into_boxed_slice())
+        );
+    }
+
+    #[test]
+    fn test_magic_program_assignments() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, false, true);
+        reasoner.insert("start", vec![Box::new(0u32)]);
+        vec![(1, 2), (2, 3), (5, 6)]
+            .into_iter()
+            .for_each(|(source, destination): (u32, u32)| {
+                reasoner.insert("edge", vec![Box::new(source), Box::new(destination)])
+            });
+
+        let program = vec![
+            SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap(),
+            SugaredRule::try_from("shifted(?x, ?z) <- [start(?x), ?y = ?x + 1, reach(?y, ?z)]")
+                .unwrap(),
+        ];
+        let query = SugaredAtom::try_from("shifted(?x, ?z)").unwrap();
+        let (magic_program, _seeds, _adorned_query) = make_magic_program(&program, &query);
+
+        // The assignment binds ?y, hence reach is only called from what start reaches
+        assert!(magic_program
+            .iter()
+            .any(|rule| rule.head.symbol == "magic_@reach_bf"
+                && rule.body.iter().any(|body_atom| body_atom.is_builtin())));
+        assert!(magic_program
+            .iter()
+            .all(|rule| rule.head.symbol != "@reach_ff"));
+
+        let answers = evaluate_query_bottom_up(&mut reasoner, &program, &query).unwrap();
+
+        assert_eq!(2, answers.len());
+        assert!(
+            answers.contains(&vec![TypedValue::UInt(0), TypedValue::UInt(2)].into_boxed_slice())
+        );
+        assert!(
+            answers.contains(&vec![TypedValue::UInt(0), TypedValue::UInt(3)].into_boxed_slice())
+        );
+    }
+
+    #[test]
+    fn test_magic_program_aggregates() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, false, true);
+        vec![("a", "b"), ("a", "c"), ("d", "e")]
+            .into_iter()
+            .for_each(|(source, destination)| {
+                reasoner.insert("edge", vec![Box::new(source), Box::new(destination)])
+            });
+        reasoner.insert("node", vec![Box::new("a")]);
+        reasoner.insert("node", vec![Box::new("d")]);
+
+        let program = vec![
+            SugaredRule::try_from("deg(?x, count(?y)) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("hub(?x) <- [node(?x), deg(?x, 2)]").unwrap(),
+        ];
+
+        // Counts are taken over every edge of a node, hence they are never bound
+        let query = SugaredAtom::try_from("deg(a, 2)").unwrap();
+        let (_magic_program, seeds, adorned_query) = make_magic_program(&program, &query);
+        assert_eq!("@deg_bf", adorned_query.symbol);
+        assert_eq!("magic_@deg_bf(a)", seeds[0].to_string());
+
+        let query = SugaredAtom::try_from("hub(?x)").unwrap();
+        let (magic_program, _seeds, _adorned_query) = make_magic_program(&program, &query);
+        assert!(magic_program
+            .iter()
+            .any(|rule| rule.head.symbol == "magic_@deg_bf"));
+        assert!(magic_program
+            .iter()
+            .all(|rule| rule.head.symbol != "@deg_bb"));
+
+        let answers = evaluate_query_bottom_up(&mut reasoner, &program, &query).unwrap();
+
+        assert_eq!(1, answers.len());
+        assert!(answers.contains(&vec![TypedValue::Str("a".to_string())].into_boxed_slice()));
+
+        let answers = evaluate_query_bottom_up(
+            &mut reasoner,
+            &program,
+            &SugaredAtom::try_from("deg(a, 3)").unwrap(),
+        )
+        .unwrap();
+
+        assert!(answers.is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+// Provenance keeps, for every derived fact, the rule and the body facts of the first derivation that
+// produced it, such that the fact can be explained all the way down to facts that were not derived.
+// Derivations are recorded while evaluating the programs of incremental maintenance, hence Δ and +
+// prefixed relations are the relations that they were renamed from, overdeletions are not
+// derivations at all, and the overdeleted atoms that guard rederivations are not premises.
+
+use crate::models::datalog::{SugaredAtom, SugaredRule, Term, TypedValue};
+use crate::models::relational_algebra::Row;
+use crate::reasoning::algorithms::delete_rederive::{OVERDELETION_PREFIX, REDERIVATION_PREFIX};
+use crate::reasoning::algorithms::delta_rule_rewrite::DELTA_PREFIX;
+use ahash::{HashMap, HashSet, HashSetExt};
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+
+pub type Fact = (String, Row);
+
+// A derived row, and the facts that it was derived from
+pub type DerivedRow = (Row, Vec<Fact>);
+
+// Derivations found by the immediate consequence operators, before they are recorded
+pub type DerivationLog<T> = Arc<Mutex<Vec<T>>>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Derivation {
+    pub rule: SugaredRule,
+    pub premises: Vec<Fact>,
+}
+
+fn is_overdeletion(symbol: &str) -> bool {
+    return symbol.starts_with(OVERDELETION_PREFIX);
+}
+
+fn original_symbol(symbol: &str) -> &str {
+    return symbol
+        .strip_prefix(DELTA_PREFIX)
+        .or_else(|| symbol.strip_prefix(REDERIVATION_PREFIX))
+        .unwrap_or(symbol);
+}
+
+fn original_atom(atom: &SugaredAtom) -> SugaredAtom {
+    let mut original_atom = atom.clone();
+    original_atom.symbol = original_symbol(&atom.symbol).to_string();
+
+    return original_atom;
+}
+
+fn resolve_atom(atom: &SugaredAtom, resolve: &dyn Fn(TypedValue) -> TypedValue) -> SugaredAtom {
+    let mut resolved_atom = atom.clone();
+    resolved_atom.terms = atom
+        .terms
+        .iter()
+        .map(|term| match term {
+            Term::Constant(inner) => Term::Constant(resolve(inner.clone())),
+            not_constant => not_constant.clone(),
+        })
+        .collect();
+
+    return resolved_atom;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Provenance {
+    derivations: HashMap<Fact, Derivation>,
+}
+
+impl Provenance {
+    // Later derivations of a fact that already has one are ignored
+    pub fn record(&mut self, rule: &SugaredRule, row: Row, premises: Vec<Fact>) {
+        if is_overdeletion(&rule.head.symbol) {
+            return;
+        }
+
+        let fact = (original_symbol(&rule.head.symbol).to_string(), row);
+        if self.derivations.contains_key(&fact) {
+            return;
+        }
+
+        let derivation = Derivation {
+            rule: SugaredRule {
+                head: original_atom(&rule.head),
+                body: rule
+                    .body
+                    .iter()
+                    .filter(|body_atom| !is_overdeletion(&body_atom.symbol))
+                    .map(original_atom)
+                    .collect(),
+            },
+            premises: premises
+                .into_iter()
+                .filter(|(symbol, _row)| !is_overdeletion(symbol))
+                .map(|(symbol, row)| (original_symbol(&symbol).to_string(), row))
+                .collect(),
+        };
+        self.derivations.insert(fact, derivation);
+    }
+    pub fn forget(&mut self, symbol: &str, row: &Row) {
+        self.derivations.remove(&(symbol.to_string(), row.clone()));
+    }
+    pub fn forget_relation(&mut self, symbol: &str) {
+        self.derivations
+            .retain(|(derived_symbol, _row), _derivation| derived_symbol != symbol);
+    }
+    pub fn get(&self, symbol: &str, row: &Row) -> Option<&Derivation> {
+        return self.derivations.get(&(symbol.to_string(), row.clone()));
+    }
+    // Unfolds the derivation of the fact, with all values resolved by `resolve`
+    pub fn explain(
+        &self,
+        symbol: &str,
+        row: &Row,
+        resolve: &dyn Fn(TypedValue) -> TypedValue,
+    ) -> DerivationTree {
+        return self.unfold(
+            &(symbol.to_string(), row.clone()),
+            resolve,
+            &mut HashSet::new(),
+        );
+    }
+    // A fact that is already being explained further up is not unfolded again
+    fn unfold(
+        &self,
+        fact: &Fact,
+        resolve: &dyn Fn(TypedValue) -> TypedValue,
+        path: &mut HashSet<Fact>,
+    ) -> DerivationTree {
+        let derivation = if path.contains(fact) {
+            None
+        } else {
+            self.derivations.get(fact)
+        };
+
+        path.insert(fact.clone());
+        let tree = DerivationTree {
+            fact: SugaredAtom {
+                terms: fact
+                    .1
+                    .iter()
+                    .map(|typed_value| Term::Constant(resolve(typed_value.clone())))
+                    .collect(),
+                symbol: fact.0.clone(),
+                positive: true,
+            },
+            rule: derivation.map(|derivation| SugaredRule {
+                head: resolve_atom(&derivation.rule.head, resolve),
+                body: derivation
+                    .rule
+                    .body
+                    .iter()
+                    .map(|body_atom| resolve_atom(body_atom, resolve))
+                    .collect(),
+            }),
+            premises: derivation.map_or(vec![], |derivation| {
+                derivation
+                    .premises
+                    .iter()
+                    .map(|premise| self.unfold(premise, resolve, path))
+                    .collect()
+            }),
+        };
+        path.remove(fact);
+
+        return tree;
+    }
+}
+
+// A fact, and if it was derived, the rule that derived it and the trees of the facts that it was
+// derived from
+#[derive(Clone, Debug)]
+pub struct DerivationTree {
+    pub fact: SugaredAtom,
+    pub rule: Option<SugaredRule>,
+    pub premises: Vec<DerivationTree>,
+}
+
+impl DerivationTree {
+    fn write_indented(&self, f: &mut Formatter<'_>, depth: usize) -> std::fmt::Result {
+        write!(f, "{}{}", "  ".repeat(depth), self.fact)?;
+        if let Some(rule) = &self.rule {
+            write!(f, " by {}", rule)?;
+        }
+        writeln!(f)?;
+
+        return self
+            .premises
+            .iter()
+            .try_for_each(|premise| premise.write_indented(f, depth + 1));
+    }
+}
+
+impl Display for DerivationTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::datalog::{SugaredRule, TypedValue};
+    use crate::reasoning::algorithms::provenance::Provenance;
+
+    fn row(values: &[&str]) -> Box<[TypedValue]> {
+        return values
+            .iter()
+            .map(|value| TypedValue::Str(value.to_string()))
+            .collect();
+    }
+
+    #[test]
+    fn test_provenance() {
+        let mut provenance = Provenance::default();
+
+        let mut base_rule =
+            SugaredRule::try_from("+reach(?x, ?y) <- [-reach(?x, ?y), edge(?x, ?y)]").unwrap();
+        base_rule.body[1].symbol = "Δedge".to_string();
+        provenance.record(
+            &base_rule,
+            row(&["a", "b"]),
+            vec![
+                ("-reach".to_string(), row(&["a", "b"])),
+                ("Δedge".to_string(), row(&["a", "b"])),
+            ],
+        );
+        let mut recursive_rule =
+            SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap();
+        recursive_rule.head.symbol = "Δreach".to_string();
+        provenance.record(
+            &recursive_rule,
+            row(&["a", "c"]),
+            vec![
+                ("reach".to_string(), row(&["a", "b"])),
+                ("edge".to_string(), row(&["b", "c"])),
+            ],
+        );
+        // Neither an overdeletion nor a later derivation are recorded
+        let overdeletion_rule = SugaredRule::try_from("-reach(?x, ?y) <- [-edge(?x, ?y)]").unwrap();
+        provenance.record(
+            &overdeletion_rule,
+            row(&["a", "b"]),
+            vec![("-edge".to_string(), row(&["a", "b"]))],
+        );
+        provenance.record(
+            &recursive_rule,
+            row(&["a", "c"]),
+            vec![
+                ("reach".to_string(), row(&["a", "d"])),
+                ("edge".to_string(), row(&["d", "c"])),
+            ],
+        );
+
+        let derivation = provenance.get("reach", &row(&["a", "b"])).unwrap();
+        assert_eq!(
+            "reach(?0, ?1) <- [edge(?0, ?1)]",
+            derivation.rule.to_string()
+        );
+        assert_eq!(
+            vec![("edge".to_string(), row(&["a", "b"]))],
+            derivation.premises
+        );
+        assert!(provenance.get("-reach", &row(&["a", "b"])).is_none());
+
+        let expected_explanation =
+            "reach(a, c) by reach(?0, ?1) <- [reach(?0, ?2), edge(?2, ?1)]\n  \
+            reach(a, b) by reach(?0, ?1) <- [edge(?0, ?1)]\n    \
+            edge(a, b)\n  \
+            edge(b, c)\n";
+        assert_eq!(
+            expected_explanation,
+            provenance
+                .explain("reach", &row(&["a", "c"]), &|typed_value| typed_value)
+                .to_string()
+        );
+
+        provenance.forget("reach", &row(&["a", "b"]));
+        assert_eq!(
+            "reach(a, b)\n",
+            provenance
+                .explain("reach", &row(&["a", "b"]), &|typed_value| typed_value)
+                .to_string()
+        );
+    }
+}
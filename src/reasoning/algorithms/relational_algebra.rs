@@ -1,9 +1,13 @@
+use crate::models::datalog::{Aggregate, Builtin, SugaredRule, TypedValue};
 use crate::models::index::IndexBacking;
 use crate::models::instance::StorageWithIndex;
 use crate::models::relational_algebra::{
-    Container, Relation, RelationalExpression, SelectionTypedValue,
+    Container, Relation, RelationalExpression, Row, SelectionTypedValue,
     SimpleRelationWithOneIndexBacking, Term,
 };
+use crate::reasoning::algorithms::provenance::{DerivedRow, Fact};
+use ahash::HashMap;
+use itertools::Itertools;
 
 impl<T: IndexBacking> Relation for SimpleRelationWithOneIndexBacking<T> {
     fn select_value(self, column_idx: usize, value: SelectionTypedValue) -> Self {
@@ -61,6 +65,55 @@ impl<T: IndexBacking> Relation for SimpleRelationWithOneIndexBacking<T> {
         return relation;
     }
 
+    fn antijoin(self, other: &Self, probe: Vec<SelectionTypedValue>) -> Self {
+        let mut relation = SimpleRelationWithOneIndexBacking::new(self.symbol());
+
+        self.ward
+            .into_iter()
+            .filter(|row| {
+                let probed_row: Row = probe
+                    .iter()
+                    .map(|column_idx| match column_idx {
+                        SelectionTypedValue::Column(idx) => row[*idx].clone(),
+                        _ => column_idx.clone().try_into().unwrap(),
+                    })
+                    .collect();
+
+                !other.ward.contains(&probed_row)
+            })
+            .for_each(|row| relation.insert_row(row));
+
+        return relation;
+    }
+
+    fn select_builtin(
+        self,
+        builtin: Builtin,
+        positive: bool,
+        arguments: Vec<SelectionTypedValue>,
+    ) -> Self {
+        let mut relation = SimpleRelationWithOneIndexBacking::new(self.symbol());
+
+        self.ward
+            .into_iter()
+            .filter(|row| builtin.holds(&select_arguments(row, &arguments)) == positive)
+            .for_each(|row| relation.insert_row(row));
+
+        return relation;
+    }
+
+    fn extend(self, builtin: Builtin, arguments: Vec<SelectionTypedValue>) -> Self {
+        let mut relation = SimpleRelationWithOneIndexBacking::new(self.symbol());
+
+        self.ward.into_iter().for_each(|row| {
+            if let Some(value) = builtin.compute(&select_arguments(&row, &arguments)) {
+                relation.insert_row(row.iter().cloned().chain([value]).collect())
+            }
+        });
+
+        return relation;
+    }
+
     fn project(
         self,
         new_column_indexes_and_values: Vec<SelectionTypedValue>,
@@ -83,11 +136,65 @@ impl<T: IndexBacking> Relation for SimpleRelationWithOneIndexBacking<T> {
         return new_relation;
     }
 
+    fn aggregate(
+        self,
+        new_column_indexes_and_values: Vec<SelectionTypedValue>,
+        aggregates: Vec<(usize, Aggregate)>,
+        new_symbol: String,
+    ) -> Self {
+        let mut new_relation = SimpleRelationWithOneIndexBacking::new(new_symbol.to_string());
+
+        let mut groups: HashMap<Row, Vec<Row>> = Default::default();
+        self.ward.into_iter().for_each(|row| {
+            let row: Row = new_column_indexes_and_values
+                .iter()
+                .map(|column_idx| match column_idx {
+                    SelectionTypedValue::Column(idx) => row[*idx].clone(),
+                    _ => column_idx.clone().try_into().unwrap(),
+                })
+                .collect();
+            let key = row
+                .iter()
+                .enumerate()
+                .filter(|(idx, _value)| !aggregates.iter().any(|(position, _)| position == idx))
+                .map(|(_idx, value)| value.clone())
+                .collect();
+
+            groups.entry(key).or_default().push(row)
+        });
+
+        groups.into_values().for_each(|group| {
+            let mut row = group[0].clone();
+            for (position, aggregate) in &aggregates {
+                let values: Vec<TypedValue> =
+                    group.iter().map(|row| row[*position].clone()).collect();
+                match aggregate.apply(&values) {
+                    Some(value) => row[*position] = value,
+                    None => return,
+                }
+            }
+
+            new_relation.insert_row(row)
+        });
+
+        return new_relation;
+    }
+
     fn symbol(&self) -> String {
         return self.symbol.clone();
     }
 }
 
+fn select_arguments(row: &Row, arguments: &Vec<SelectionTypedValue>) -> Vec<TypedValue> {
+    return arguments
+        .iter()
+        .map(|argument| match argument {
+            SelectionTypedValue::Column(idx) => row[*idx].clone(),
+            _ => argument.clone().try_into().unwrap(),
+        })
+        .collect();
+}
+
 pub fn build_index<T: IndexBacking>(
     relation: &mut SimpleRelationWithOneIndexBacking<T>,
     column_idx: usize,
@@ -185,6 +292,46 @@ where
                             }
                         };
                     }
+                    Term::Antijoin(atom, probe) => {
+                        let evaluation = evaluate(&left_subtree, database, new_symbol);
+                        return if let Some(relation) = evaluation {
+                            if let Some(negated_relation) = database.get(&atom.symbol) {
+                                Some(relation.antijoin(negated_relation, probe))
+                            } else {
+                                Some(relation)
+                            }
+                        } else {
+                            None
+                        };
+                    }
+                    Term::BuiltinSelection(builtin, positive, arguments) => {
+                        let evaluation = evaluate(&left_subtree, database, new_symbol);
+                        return if let Some(relation) = evaluation {
+                            Some(relation.select_builtin(builtin, positive, arguments))
+                        } else {
+                            None
+                        };
+                    }
+                    Term::Extension(builtin, arguments) => {
+                        let evaluation = evaluate(&left_subtree, database, new_symbol);
+                        return if let Some(relation) = evaluation {
+                            Some(relation.extend(builtin, arguments))
+                        } else {
+                            None
+                        };
+                    }
+                    Term::Aggregate(column_idxs, aggregates) => {
+                        let evaluation = evaluate(&left_subtree, database, new_symbol);
+                        return if let Some(relation) = evaluation {
+                            Some(relation.aggregate(
+                                column_idxs,
+                                aggregates,
+                                new_symbol.to_string(),
+                            ))
+                        } else {
+                            None
+                        };
+                    }
                     Term::Projection(column_idxs) => {
                         let evaluation = evaluate(&left_subtree, database, new_symbol);
                         return if let Some(relation) = evaluation {
@@ -203,6 +350,90 @@ where
     return None;
 }
 
+// Evaluates the expression of the rule as `evaluate` does, but also yields every derivation, as the
+// projected row and its premises. Before projection, every row is the rows of the positive body
+// atoms of the rule one after the other, followed by the columns of extensions. An aggregate follows
+// from the premises of its whole group.
+pub fn evaluate_with_provenance<T: IndexBacking>(
+    expr: &RelationalExpression,
+    database: &StorageWithIndex<T>,
+    new_symbol: &str,
+    rule: &SugaredRule,
+) -> Option<(SimpleRelationWithOneIndexBacking<T>, Vec<DerivedRow>)> {
+    let root_node = expr.arena[expr.root?].clone();
+    let body = evaluate(&expr.branch_at(root_node.left_child?), database, new_symbol)?;
+
+    let premises = |row: &Row| -> Vec<Fact> {
+        let mut offset = 0;
+        return rule
+            .body
+            .iter()
+            .filter(|body_atom| body_atom.positive && !body_atom.is_builtin())
+            .map(|body_atom| {
+                let premise = row[offset..offset + body_atom.terms.len()].into();
+                offset += body_atom.terms.len();
+
+                (body_atom.symbol.clone(), premise)
+            })
+            .collect();
+    };
+
+    return match root_node.value {
+        Term::Projection(column_idxs) => {
+            let derivations = body
+                .ward
+                .iter()
+                .map(|row| {
+                    (
+                        select_arguments(row, &column_idxs).into_boxed_slice(),
+                        premises(row),
+                    )
+                })
+                .collect();
+
+            Some((
+                body.project(column_idxs, new_symbol.to_string()),
+                derivations,
+            ))
+        }
+        Term::Aggregate(column_idxs, aggregates) => {
+            let relation = body.clone().aggregate(
+                column_idxs.clone(),
+                aggregates.clone(),
+                new_symbol.to_string(),
+            );
+            let in_group = |row: &Row, aggregated_row: &Row| {
+                select_arguments(row, &column_idxs)
+                    .iter()
+                    .zip(aggregated_row.iter())
+                    .enumerate()
+                    .all(|(idx, (value, aggregated_value))| {
+                        aggregates.iter().any(|(position, _)| *position == idx)
+                            || value == aggregated_value
+                    })
+            };
+            let derivations = relation
+                .ward
+                .iter()
+                .map(|aggregated_row| {
+                    let group_premises = body
+                        .ward
+                        .iter()
+                        .filter(|row| in_group(row, aggregated_row))
+                        .flat_map(|row| premises(row))
+                        .unique()
+                        .collect();
+
+                    (aggregated_row.clone(), group_premises)
+                })
+                .collect();
+
+            Some((relation, derivations))
+        }
+        _ => None,
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use crate::models::datalog::{SugaredRule, Ty};
@@ -376,7 +607,7 @@ mod tests {
         let rule =
             "mysticalAncestor(?x, ?z) <- [child(?x, ?y), child(?y, ?z), subClassOf(?y, demiGod)]";
 
-        let expression = RelationalExpression::from(&SugaredRule::from(rule));
+        let expression = RelationalExpression::from(&SugaredRule::try_from(rule).unwrap());
 
         let mut interner = Interner::default();
         let child_id = interner.rodeo.get_or_intern("child");
@@ -1,9 +1,12 @@
 use crate::data_structures::substitutions::Substitutions;
 use crate::misc::helpers::terms_to_row;
 use crate::misc::joins::nested_loop_join;
-use crate::models::datalog::{Atom, Rule, Term};
+use crate::misc::string_interning::Interner;
+use crate::models::datalog::{Atom, Builtin, Pattern, Rule, Term, TypedValue};
 use crate::models::instance::{HashSetDatabase, IndexedHashSetBacking};
-use ahash::HashMap;
+use crate::models::reasoner::QueryResult;
+use crate::models::relational_algebra::Row;
+use ahash::{HashMap, HashSet};
 use itertools::Itertools;
 use std::num::NonZeroU32;
 
@@ -66,6 +69,207 @@ pub fn is_ground(atom: &Atom) -> bool {
     return true;
 }
 
+pub fn row_to_atom(goal: &Atom, row: &Row) -> Atom {
+    return Atom {
+        terms: row
+            .iter()
+            .map(|typed_value| Term::Constant(typed_value.clone()))
+            .collect(),
+        relation_id: goal.relation_id,
+        positive: true,
+    };
+}
+
+// Relational goals go first, since they are the only ones that bind variables from facts. Then
+// come built-ins, in the order they were written, such that arithmetic can build upon previous
+// results, and negated goals go last, so that they are only checked against ground substitutions.
+pub fn goal_order(goal: &Atom) -> u8 {
+    if Builtin::from_relation_id(goal.relation_id).is_some() {
+        return 1;
+    }
+    if goal.positive {
+        return 0;
+    }
+    return 2;
+}
+
+// Built-ins are solved rather than matched. Arithmetic binds its result, and so does an equality
+// with a single unbound side, while everything else has to be ground in order to hold. Interned
+// strings are resolved through the interner, if there is one, such that they can be ordered.
+pub fn solve_builtin(
+    builtin: Builtin,
+    goal: &Atom,
+    subs: &Substitutions,
+    interner: Option<&Interner>,
+) -> Option<Substitutions> {
+    let rewrite_attempt = attempt_to_rewrite(subs, goal);
+    let values: Vec<Option<TypedValue>> = rewrite_attempt
+        .terms
+        .iter()
+        .map(|term| match term {
+            Term::Constant(inner) => Some(inner.clone()),
+            Term::Variable(_) | Term::Aggregate(..) => None,
+        })
+        .collect();
+
+    let binding = match (builtin, values.as_slice(), rewrite_attempt.terms.as_slice()) {
+        (_, [Some(left), Some(right), None], [_, _, Term::Variable(result)])
+            if builtin.is_arithmetic() =>
+        {
+            Some((*result, builtin.compute(&[left.clone(), right.clone()])))
+        }
+        (Builtin::Equal, [Some(value), None], [_, Term::Variable(unbound)])
+        | (Builtin::Equal, [None, Some(value)], [Term::Variable(unbound), _]) => {
+            Some((*unbound, Some(value.clone())))
+        }
+        _ => None,
+    };
+
+    if let Some((variable, value)) = binding {
+        // Negating a binding is meaningless, hence it never holds
+        if !goal.positive {
+            return None;
+        }
+
+        let mut extended_subs = subs.clone();
+        extended_subs.insert((variable, value?));
+        return Some(extended_subs);
+    }
+
+    if let Some(arguments) = values.into_iter().collect::<Option<Vec<_>>>() {
+        let arguments: Vec<_> = match interner {
+            Some(interner) => arguments
+                .into_iter()
+                .map(|argument| interner.resolve_value(argument))
+                .collect(),
+            None => arguments,
+        };
+        if builtin.holds(&arguments) == goal.positive {
+            return Some(subs.clone());
+        }
+    }
+
+    return None;
+}
+
+// Every substitution that satisfies all of the goals, each of them being matched against the
+// given relation, or solved if it is a built-in.
+pub fn answer_conjunctive_query(
+    goals: &Vec<(Atom, Option<&IndexedHashSetBacking>)>,
+    interner: Option<&Interner>,
+) -> Vec<Substitutions> {
+    let mut subs_product = vec![Substitutions::default()];
+
+    let mut ordered_goals: Vec<_> = goals.iter().collect();
+    ordered_goals.sort_by_key(|(goal, _relation)| goal_order(goal));
+
+    for (goal, relation) in ordered_goals {
+        let mut new_subs_product = vec![];
+
+        for subs in subs_product {
+            let rewrite_attempt = attempt_to_rewrite(&subs, goal);
+
+            let relation = match relation {
+                Some(relation) => relation,
+                None => {
+                    if let Some(builtin) = Builtin::from_relation_id(goal.relation_id) {
+                        new_subs_product.extend(solve_builtin(builtin, goal, &subs, interner));
+                    }
+                    continue;
+                }
+            };
+
+            if !goal.positive {
+                if !relation
+                    .iter()
+                    .any(|row| unify(&rewrite_attempt, &row_to_atom(goal, row)).is_some())
+                {
+                    new_subs_product.push(subs);
+                }
+                continue;
+            }
+
+            relation.iter().for_each(|row| {
+                if let Some(new_subs) = unify(&rewrite_attempt, &row_to_atom(goal, row)) {
+                    let mut extended_subs = subs.clone();
+                    extended_subs.extend(new_subs);
+                    new_subs_product.push(extended_subs);
+                }
+            });
+        }
+
+        subs_product = new_subs_product;
+    }
+
+    return subs_product;
+}
+
+// Answers a conjunctive query of sugared atoms, looking each relation up by its symbol. Constants
+// are interned beforehand if the reasoner interns, and the answers are resolved back, by the names of
+// their variables.
+pub fn answer_pattern_query<'a>(
+    interner: &'a Interner,
+    intern: bool,
+    pattern: &Pattern,
+    relation: impl Fn(&str) -> Option<(NonZeroU32, &'a IndexedHashSetBacking)>,
+) -> QueryResult<'a> {
+    let mut goals = vec![];
+    for sugared_atom in &pattern.atoms {
+        if let Some(builtin) = sugared_atom.builtin() {
+            let interned_sugared_atom = if intern {
+                interner.try_intern_sugared_atom(sugared_atom)
+            } else {
+                None
+            };
+            let goal = Atom {
+                terms: interned_sugared_atom
+                    .map_or(sugared_atom.terms.clone(), |interned| interned.terms),
+                relation_id: builtin.relation_id(),
+                positive: sugared_atom.positive,
+            };
+            goals.push((goal, None));
+            continue;
+        }
+
+        let interned_sugared_atom = if intern {
+            interner.try_intern_sugared_atom(sugared_atom)
+        } else {
+            Some(sugared_atom.clone())
+        };
+
+        if let (Some((relation_id, relation)), Some(interned_sugared_atom)) =
+            (relation(&sugared_atom.symbol), interned_sugared_atom)
+        {
+            let goal = Atom {
+                terms: interned_sugared_atom.terms,
+                relation_id,
+                positive: sugared_atom.positive,
+            };
+            goals.push((goal, Some(relation)));
+        // Nothing can match it, hence only a negated atom still holds
+        } else if sugared_atom.positive {
+            return Box::new(std::iter::empty());
+        }
+    }
+
+    let variables = pattern.variables.clone();
+    return Box::new(
+        answer_conjunctive_query(&goals, intern.then_some(interner))
+            .into_iter()
+            .map(move |subs| {
+                subs.inner
+                    .into_iter()
+                    .map(|(variable, value)| {
+                        (
+                            variables[variable as usize].clone(),
+                            interner.resolve_value(value),
+                        )
+                    })
+                    .collect()
+            }),
+    );
+}
+
 // pub fn proven(knowledge_base: &Vec<(u32, &IndexedHashSetBacking)>, subs: &Substitutions, current_goal_position: usize, goals: &Vec<Atom>, head: &Atom) -> bool {
 //     if current_goal_position == goals.len() {
 //         let fresh_atom = attempt_to_rewrite(&subs, &head);
@@ -111,16 +315,91 @@ pub fn is_ground(atom: &Atom) -> bool {
 //     false
 // }
 
-pub fn evaluate_rule(
+// Groups the distinct substitutions by the head they rewrite to, and then computes every aggregate
+// over the values that its variable takes within each group.
+pub fn aggregate_head(head: &Atom, subs_product: Vec<Substitutions>) -> Vec<Row> {
+    let distinct_subs_product: HashSet<Substitutions> = subs_product
+        .into_iter()
+        .map(|mut subs| {
+            subs.inner.sort();
+            subs
+        })
+        .collect();
+
+    let mut groups: HashMap<Atom, Vec<Substitutions>> = Default::default();
+    distinct_subs_product.into_iter().for_each(|subs| {
+        let fresh_atom = attempt_to_rewrite(&subs, head);
+        if fresh_atom
+            .terms
+            .iter()
+            .all(|term| !matches!(term, Term::Variable(_)))
+        {
+            groups.entry(fresh_atom).or_default().push(subs);
+        }
+    });
+
+    return groups
+        .into_iter()
+        .filter_map(|(fresh_atom, group)| {
+            fresh_atom
+                .terms
+                .iter()
+                .map(|term| match term {
+                    Term::Constant(inner) => Some(inner.clone()),
+                    Term::Aggregate(aggregate, identifier) => {
+                        let values: Option<Vec<TypedValue>> = group
+                            .iter()
+                            .map(|subs| subs.get(*identifier).cloned())
+                            .collect();
+
+                        aggregate.apply(&values?)
+                    }
+                    Term::Variable(_) => None,
+                })
+                .collect::<Option<Row>>()
+        })
+        .collect();
+}
+
+// A negated goal holds if no fact in the knowledge base unifies with its rewriting.
+pub fn negated_goal_holds(
     knowledge_base: &HashSetDatabase,
-    rule: &Rule,
-    index: bool,
-) -> Option<IndexedHashSetBacking> {
-    let mut out: IndexedHashSetBacking = Default::default();
+    goal: &Atom,
+    subs: &Substitutions,
+) -> bool {
+    let rewrite_attempt = attempt_to_rewrite(subs, goal);
+
+    if let Some(relation) = knowledge_base.storage.get(&goal.relation_id.get()) {
+        if is_ground(&rewrite_attempt) {
+            return !relation.contains(&terms_to_row(rewrite_attempt.terms));
+        }
 
-    let head = rule.head.clone();
+        return !relation.iter().any(|ground_fact| {
+            let proposed_atom = Atom {
+                terms: ground_fact
+                    .iter()
+                    .map(|typed_value| Term::Constant(typed_value.clone()))
+                    .collect(),
+                relation_id: goal.relation_id,
+                positive: true,
+            };
+
+            unify(&rewrite_attempt, &proposed_atom).is_some()
+        });
+    }
 
-    let goals: Vec<(usize, &Atom)> = rule.body.iter().enumerate().collect();
+    return true;
+}
+
+// Every substitution that satisfies the whole body of the rule
+fn solve_rule_body(
+    knowledge_base: &HashSetDatabase,
+    rule: &Rule,
+    index: bool,
+) -> Vec<Substitutions> {
+    let mut ordered_body: Vec<&Atom> = rule.body.iter().collect();
+    ordered_body.sort_by_key(|atom| goal_order(atom));
+    let goals: Vec<(usize, &Atom)> = ordered_body.into_iter().enumerate().collect();
 
     let mut subs_product = vec![(0usize, Substitutions::default())];
     if index {
@@ -132,6 +411,28 @@ pub fn evaluate_rule(
                 .filter(|(round, _)| *round == current_atom_id)
                 .collect();
 
+            if let Some(builtin) = Builtin::from_relation_id(goals[current_atom_id].1.relation_id) {
+                subs_product = subs_product
+                    .into_iter()
+                    .filter_map(|(round, subs)| {
+                        solve_builtin(builtin, goals[current_atom_id].1, &subs, None)
+                            .map(|new_subs| (round + 1, new_subs))
+                    })
+                    .collect();
+                continue;
+            }
+
+            if !goals[current_atom_id].1.positive {
+                subs_product = subs_product
+                    .into_iter()
+                    .filter(|(_round, subs)| {
+                        negated_goal_holds(knowledge_base, goals[current_atom_id].1, subs)
+                    })
+                    .map(|(round, subs)| (round + 1, subs))
+                    .collect();
+                continue;
+            }
+
             nested_loop_join(
                 &vec![goals[current_atom_id]],
                 &subs_product,
@@ -264,6 +565,28 @@ pub fn evaluate_rule(
                 .filter(|(round, _)| *round == current_atom_id)
                 .collect();
 
+            if let Some(builtin) = Builtin::from_relation_id(goals[current_atom_id].1.relation_id) {
+                subs_product = subs_product
+                    .into_iter()
+                    .filter_map(|(round, subs)| {
+                        solve_builtin(builtin, goals[current_atom_id].1, &subs, None)
+                            .map(|new_subs| (round + 1, new_subs))
+                    })
+                    .collect();
+                continue;
+            }
+
+            if !goals[current_atom_id].1.positive {
+                subs_product = subs_product
+                    .into_iter()
+                    .filter(|(_round, subs)| {
+                        negated_goal_holds(knowledge_base, goals[current_atom_id].1, subs)
+                    })
+                    .map(|(round, subs)| (round + 1, subs))
+                    .collect();
+                continue;
+            }
+
             nested_loop_join(
                 &vec![goals[current_atom_id]],
                 &subs_product,
@@ -305,15 +628,33 @@ pub fn evaluate_rule(
         }
     }
 
-    subs_product
+    return subs_product
         .into_iter()
         .filter(|(local_atom_id, _)| *local_atom_id == goals.len())
-        .for_each(|(_local_atom_id, subs)| {
+        .map(|(_local_atom_id, subs)| subs)
+        .collect();
+}
+
+pub fn evaluate_rule(
+    knowledge_base: &HashSetDatabase,
+    rule: &Rule,
+    index: bool,
+) -> Option<IndexedHashSetBacking> {
+    let mut out: IndexedHashSetBacking = Default::default();
+
+    let head = rule.head.clone();
+    let complete_subs_product = solve_rule_body(knowledge_base, rule, index);
+
+    if head.has_aggregates() {
+        out.extend(aggregate_head(&head, complete_subs_product));
+    } else {
+        complete_subs_product.into_iter().for_each(|subs| {
             let fresh_atom = attempt_to_rewrite(&subs, &head);
             if is_ground(&fresh_atom) {
                 out.insert(terms_to_row(fresh_atom.terms));
             }
         });
+    }
 
     if out.is_empty() {
         return None;
@@ -322,6 +663,66 @@ pub fn evaluate_rule(
     return Some(out);
 }
 
+// The ground positive atoms of the body that a substitution satisfied
+fn premises(rule: &Rule, subs: &Substitutions) -> Vec<Atom> {
+    return rule
+        .body
+        .iter()
+        .filter(|body_atom| {
+            body_atom.positive && Builtin::from_relation_id(body_atom.relation_id).is_none()
+        })
+        .map(|body_atom| attempt_to_rewrite(subs, body_atom))
+        .collect();
+}
+
+// Evaluates the rule as `evaluate_rule` does, but yields every derivation, as the derived row and
+// its premises. An aggregate follows from the premises of its whole group.
+pub fn evaluate_rule_with_provenance(
+    knowledge_base: &HashSetDatabase,
+    rule: &Rule,
+    index: bool,
+) -> Vec<(Row, Vec<Atom>)> {
+    let head = rule.head.clone();
+    let complete_subs_product = solve_rule_body(knowledge_base, rule, index);
+
+    if head.has_aggregates() {
+        return aggregate_head(&head, complete_subs_product.clone())
+            .into_iter()
+            .map(|row| {
+                let group_premises = complete_subs_product
+                    .iter()
+                    .filter(|subs| {
+                        attempt_to_rewrite(subs, &head)
+                            .terms
+                            .iter()
+                            .zip(row.iter())
+                            .all(|(term, value)| match term {
+                                Term::Constant(inner) => inner == value,
+                                _ => true,
+                            })
+                    })
+                    .flat_map(|subs| premises(rule, subs))
+                    .unique()
+                    .collect();
+
+                (row, group_premises)
+            })
+            .collect();
+    }
+
+    return complete_subs_product
+        .iter()
+        .filter_map(|subs| {
+            let fresh_atom = attempt_to_rewrite(subs, &head);
+            if !is_ground(&fresh_atom) {
+                return None;
+            }
+
+            return Some((terms_to_row(fresh_atom.terms), premises(rule, subs)));
+        })
+        .collect();
+}
+
 #[cfg(test)]
 mod tests {
     use crate::misc::helpers::terms_to_row;
@@ -340,8 +741,11 @@ mod tests {
         let mut relational = RelationalDatalog::<VecIndex>::new(false, false);
 
         let program = vec![
-            SugaredRule::from("+reach(?x, ?y) <- [-reach(?x, ?y), edge(?x, ?y)]"),
-            SugaredRule::from("+reach(?x, ?z) <- [-reach(?x, ?z), edge(?x, ?y), reach(?y, ?z)]"),
+            SugaredRule::try_from("+reach(?x, ?y) <- [-reach(?x, ?y), edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from(
+                "+reach(?x, ?z) <- [-reach(?x, ?z), edge(?x, ?y), reach(?y, ?z)]",
+            )
+            .unwrap(),
         ];
 
         vec![
@@ -411,15 +815,18 @@ mod tests {
         let mut interner: Interner = Default::default();
 
         let sugared_rule =
-            SugaredRule::from("ancestor(?X, ?Z) <- [ancestor(?X, ?Y), ancestor(?Y, ?Z)]");
+            SugaredRule::try_from("ancestor(?X, ?Z) <- [ancestor(?X, ?Y), ancestor(?Y, ?Z)]")
+                .unwrap();
         let rule = interner.intern_rule(&sugared_rule);
 
         let mut fact_store: HashSetDatabase = Default::default();
 
-        let fact_0 = Atom::from_str_with_interner("ancestor(adam, jumala)", &mut interner);
-        let fact_1 = Atom::from_str_with_interner("ancestor(vanasarvik, jumala)", &mut interner);
-        let fact_2 = Atom::from_str_with_interner("ancestor(eve, adam)", &mut interner);
-        let fact_3 = Atom::from_str_with_interner("ancestor(jumala, cthulu)", &mut interner);
+        let fact_0 = Atom::from_str_with_interner("ancestor(adam, jumala)", &mut interner).unwrap();
+        let fact_1 =
+            Atom::from_str_with_interner("ancestor(vanasarvik, jumala)", &mut interner).unwrap();
+        let fact_2 = Atom::from_str_with_interner("ancestor(eve, adam)", &mut interner).unwrap();
+        let fact_3 =
+            Atom::from_str_with_interner("ancestor(jumala, cthulu)", &mut interner).unwrap();
 
         vec![fact_0, fact_1, fact_2, fact_3]
             .into_iter()
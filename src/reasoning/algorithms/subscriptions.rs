@@ -0,0 +1,133 @@
+// Subscriptions are change feeds of relations. While reasoners change their materialization, they
+// record every row that they actually insert into, or delete from, a subscribed relation, and
+// afterwards send each subscriber the net changes, the rows that were removed followed by the rows
+// that were inserted.
+
+use crate::models::relational_algebra::Row;
+use ahash::HashMap;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+// Whether the row was inserted, or removed
+pub type RowDiff = (bool, Row);
+
+pub type ChangeFeed = Receiver<RowDiff>;
+
+// The net changes of every subscribed relation, with the rows as they are stored
+pub type Changes = HashMap<String, HashMap<Row, bool>>;
+
+#[derive(Default)]
+pub struct Subscriptions {
+    senders: HashMap<String, Vec<Sender<RowDiff>>>,
+    changes: Option<Changes>,
+}
+
+impl Subscriptions {
+    pub fn subscribe(&mut self, table: &str) -> ChangeFeed {
+        let (sender, receiver) = unbounded();
+        self.senders
+            .entry(table.to_string())
+            .or_default()
+            .push(sender);
+
+        return receiver;
+    }
+    // Starts recording changes afresh
+    pub fn record_changes(&mut self) {
+        self.changes = Some(Default::default());
+    }
+    // Whether changes to the relation are being recorded
+    pub fn is_recording(&self, table: &str) -> bool {
+        return self.changes.is_some() && self.senders.contains_key(table);
+    }
+    // Records that the row was inserted into, or removed from, the relation, which cancels out its
+    // opposite change
+    pub fn record(&mut self, table: &str, row: Row, inserted: bool) {
+        if !self.is_recording(table) {
+            return;
+        }
+
+        let relation_changes = self
+            .changes
+            .as_mut()
+            .unwrap()
+            .entry(table.to_string())
+            .or_default();
+        if relation_changes.get(&row) == Some(&!inserted) {
+            relation_changes.remove(&row);
+        } else {
+            relation_changes.insert(row, inserted);
+        }
+    }
+    // Sends the recorded changes, with their rows un-interned by `resolve`, and stops recording.
+    // Subscribers whose feed was dropped are forgotten
+    pub fn publish(&mut self, resolve: impl Fn(&Row) -> Row) {
+        let changes = self.changes.take().unwrap_or_default();
+
+        self.senders.iter_mut().for_each(|(table, senders)| {
+            let row_diffs: Vec<RowDiff> = changes.get(table).map_or(vec![], |relation_changes| {
+                let removed = relation_changes
+                    .iter()
+                    .filter(|(_row, inserted)| !**inserted);
+                let inserted = relation_changes
+                    .iter()
+                    .filter(|(_row, inserted)| **inserted);
+
+                removed
+                    .chain(inserted)
+                    .map(|(row, inserted)| (*inserted, resolve(row)))
+                    .collect()
+            });
+
+            senders.retain(|sender| {
+                row_diffs
+                    .iter()
+                    .all(|row_diff| sender.send(row_diff.clone()).is_ok())
+            });
+        });
+        self.senders.retain(|_table, senders| !senders.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::datalog::TypedValue;
+    use crate::models::relational_algebra::Row;
+    use crate::reasoning::algorithms::subscriptions::{RowDiff, Subscriptions};
+
+    fn row(value: u32) -> Row {
+        return vec![TypedValue::UInt(value)].into_boxed_slice();
+    }
+
+    #[test]
+    fn test_publish() {
+        let mut subscriptions: Subscriptions = Default::default();
+        let feed = subscriptions.subscribe("edge");
+        let dropped_feed = subscriptions.subscribe("edge");
+        drop(dropped_feed);
+
+        subscriptions.record("edge", row(0), true);
+        assert!(!subscriptions.is_recording("edge"));
+
+        subscriptions.record_changes();
+        assert!(subscriptions.is_recording("edge"));
+        assert!(!subscriptions.is_recording("node"));
+        subscriptions.record("edge", row(1), false);
+        subscriptions.record("edge", row(2), false);
+        subscriptions.record("edge", row(2), true);
+        subscriptions.record("edge", row(3), true);
+        subscriptions.record("node", row(4), true);
+        subscriptions.publish(|row| row.clone());
+
+        let expected_row_diffs: Vec<RowDiff> = vec![(false, row(1)), (true, row(3))];
+        assert_eq!(expected_row_diffs, feed.try_iter().collect::<Vec<_>>());
+        assert_eq!(1, subscriptions.senders.get("edge").unwrap().len());
+        assert!(!subscriptions.is_recording("edge"));
+
+        subscriptions.record_changes();
+        subscriptions.record("edge", row(3), false);
+        subscriptions.record("edge", row(3), true);
+        subscriptions.publish(|row| row.clone());
+        assert_eq!(0, feed.try_iter().count());
+        assert!(subscriptions.senders.contains_key("edge"));
+    }
+}
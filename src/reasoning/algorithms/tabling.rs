@@ -0,0 +1,384 @@
+// Tabled top-down resolution. Every subgoal, up to its bound columns, gets an answer table, and
+// reaching a subgoal that already has one only consumes its answers, instead of resolving it all over
+// again. This is what makes left-recursive rules terminate. Resolution is driven by a worklist of
+// rule bodies, each resolved up to some goal, and every new answer of a table is handed once to the
+// bodies that are waiting on that table, such that nothing is derived twice, and the depth of the
+// recursion does not grow with the depth of the proofs.
+//
+// Negated subgoals, and the bodies of aggregates, belong to lower strata, hence they wait until the
+// tables that they depend on are complete, which is once the worklist is empty and none of those
+// tables depends on anything that is still waiting. All tables are shared, hence every subgoal is
+// only ever resolved once.
+
+use crate::data_structures::substitutions::Substitutions;
+use crate::misc::helpers::terms_to_row;
+use crate::models::datalog::{Atom, Builtin, Program, Term, TypedValue};
+use crate::models::instance::{HashSetDatabase, IndexedHashSetBacking};
+use crate::models::relational_algebra::Row;
+use crate::reasoning::algorithms::rewriting::{
+    aggregate_head, attempt_to_rewrite, goal_order, is_ground, row_to_atom, solve_builtin, unify,
+};
+use ahash::{HashMap, HashSet};
+use indexmap::IndexMap;
+use std::num::NonZeroU32;
+
+// A subgoal, up to variable renaming
+pub type CallPattern = (u32, Vec<Option<TypedValue>>);
+
+pub fn call_pattern(goal: &Atom) -> CallPattern {
+    let bound_columns = goal
+        .terms
+        .iter()
+        .map(|term| match term {
+            Term::Constant(inner) => Some(inner.clone()),
+            Term::Variable(_) | Term::Aggregate(..) => None,
+        })
+        .collect();
+
+    return (goal.relation_id.get(), bound_columns);
+}
+
+// The most general subgoal with the given call pattern
+fn call_pattern_to_atom((relation_id, bound_columns): &CallPattern) -> Atom {
+    return Atom {
+        terms: bound_columns
+            .iter()
+            .enumerate()
+            .map(|(idx, bound_column)| match bound_column {
+                Some(inner) => Term::Constant(inner.clone()),
+                None => Term::Variable(idx as u32),
+            })
+            .collect(),
+        relation_id: NonZeroU32::new(*relation_id).unwrap(),
+        positive: true,
+    };
+}
+
+// A rule, called by the table `owner`, whose body is solved up to, but not including, `position`
+#[derive(Clone)]
+struct Continuation {
+    owner: usize,
+    rule: usize,
+    position: usize,
+    subs: Substitutions,
+}
+
+#[derive(Default)]
+struct Table {
+    answers: IndexedHashSetBacking,
+    // Continuations whose next goal is this table's subgoal
+    consumers: Vec<Continuation>,
+    // Tables that the rules of this one called
+    dependencies: HashSet<usize>,
+    // Whether the table has all of its answers
+    complete: bool,
+}
+
+// The body substitutions of an aggregate rule, which are only aggregated once every table that the
+// rule called is complete
+#[derive(Default)]
+struct Aggregation {
+    subs_product: Vec<Substitutions>,
+    dependencies: HashSet<usize>,
+}
+
+// Every row of a relation, by the values of some of its columns
+type FactIndex<'a> = HashMap<Vec<TypedValue>, Vec<&'a Row>>;
+
+pub struct TabledResolution<'a> {
+    program: &'a Program,
+    // The body of every rule of the program, in the order in which it is solved
+    bodies: Vec<Vec<&'a Atom>>,
+    knowledge_base: &'a HashSetDatabase,
+    indexes: HashMap<(u32, Vec<usize>), FactIndex<'a>>,
+    tables: IndexMap<CallPattern, Table, ahash::RandomState>,
+    worklist: Vec<Continuation>,
+    // Continuations at a negated goal, waiting for the completion of its table
+    negations: Vec<(usize, Continuation)>,
+    // The aggregate rules of every table, by table and rule
+    aggregations: IndexMap<(usize, usize), Aggregation, ahash::RandomState>,
+}
+
+impl<'a> TabledResolution<'a> {
+    pub fn new(program: &'a Program, knowledge_base: &'a HashSetDatabase) -> Self {
+        let bodies = program
+            .iter()
+            .map(|rule| {
+                let mut body: Vec<&Atom> = rule.body.iter().collect();
+                body.sort_by_key(|goal| goal_order(goal));
+                body
+            })
+            .collect();
+
+        return Self {
+            program,
+            bodies,
+            knowledge_base,
+            indexes: Default::default(),
+            tables: Default::default(),
+            worklist: vec![],
+            negations: vec![],
+            aggregations: Default::default(),
+        };
+    }
+    // Resolves the goal until all tables are complete, and returns its answers
+    pub fn query(&mut self, goal: &Atom) -> IndexedHashSetBacking {
+        let goal_table = self.table(goal);
+
+        loop {
+            while let Some(continuation) = self.worklist.pop() {
+                self.resume(continuation);
+            }
+            if !self.release_completed() {
+                break;
+            }
+        }
+
+        return self.tables[goal_table].answers.clone();
+    }
+    // The table of the goal's call pattern, which is seeded with the matching facts and the rules of
+    // the goal's relation if it is new
+    fn table(&mut self, goal: &Atom) -> usize {
+        let call_pattern = call_pattern(goal);
+        if let Some(table) = self.tables.get_index_of(&call_pattern) {
+            return table;
+        }
+
+        let (table, _) = self
+            .tables
+            .insert_full(call_pattern.clone(), Default::default());
+        self.facts(&call_pattern)
+            .into_iter()
+            .for_each(|row| self.add_answer(table, row));
+
+        let call = call_pattern_to_atom(&call_pattern);
+        let program = self.program;
+        // Without rules, the facts are all of the answers
+        self.tables[table].complete = true;
+        program
+            .iter()
+            .enumerate()
+            .filter(|(_rule_idx, rule)| rule.head.relation_id == call.relation_id)
+            .for_each(|(rule_idx, rule)| {
+                self.tables[table].complete = false;
+                if let Some(head_subs) = unify(&rule.head, &call) {
+                    if rule.head.has_aggregates() {
+                        self.aggregations
+                            .insert((table, rule_idx), Default::default());
+                    }
+                    self.worklist.push(Continuation {
+                        owner: table,
+                        rule: rule_idx,
+                        position: 0,
+                        subs: head_subs,
+                    });
+                }
+            });
+
+        return table;
+    }
+    // The facts that match the call pattern, looked up in an index on its bound columns
+    fn facts(&mut self, (relation_id, bound_columns): &CallPattern) -> Vec<Row> {
+        let knowledge_base = self.knowledge_base;
+        let relation = match knowledge_base.storage.get(relation_id) {
+            Some(relation) => relation,
+            None => return vec![],
+        };
+        let (columns, key): (Vec<usize>, Vec<TypedValue>) = bound_columns
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, bound_column)| bound_column.clone().map(|inner| (idx, inner)))
+            .unzip();
+        if columns.is_empty() {
+            return relation.iter().cloned().collect();
+        }
+
+        let index = self
+            .indexes
+            .entry((*relation_id, columns.clone()))
+            .or_insert_with(|| {
+                let mut index: FactIndex = Default::default();
+                relation.iter().for_each(|row| {
+                    let projected_row = columns.iter().map(|idx| row[*idx].clone()).collect();
+                    index.entry(projected_row).or_default().push(row);
+                });
+                index
+            });
+
+        return index.get(&key).map_or(vec![], |rows| {
+            rows.iter().map(|row| (*row).clone()).collect()
+        });
+    }
+    // Adds the answer to the table, and hands it to every continuation waiting on it, if it is new
+    fn add_answer(&mut self, table: usize, answer: Row) {
+        if !self.tables[table].answers.insert(answer.clone()) {
+            return;
+        }
+
+        for idx in 0..self.tables[table].consumers.len() {
+            let consumer = self.tables[table].consumers[idx].clone();
+            self.consume(consumer, &answer);
+        }
+    }
+    // Continues past the consumer's goal, if the answer matches it
+    fn consume(&mut self, consumer: Continuation, answer: &Row) {
+        let goal = self.bodies[consumer.rule][consumer.position];
+        let rewrite_attempt = attempt_to_rewrite(&consumer.subs, goal);
+
+        if let Some(new_subs) = unify(&rewrite_attempt, &row_to_atom(goal, answer)) {
+            let mut subs = consumer.subs;
+            subs.extend(new_subs);
+            self.worklist.push(Continuation {
+                position: consumer.position + 1,
+                subs,
+                ..consumer
+            });
+        }
+    }
+    fn resume(&mut self, continuation: Continuation) {
+        let body = &self.bodies[continuation.rule];
+        if continuation.position == body.len() {
+            return self.conclude(continuation);
+        }
+        let goal = body[continuation.position];
+
+        if let Some(builtin) = Builtin::from_relation_id(goal.relation_id) {
+            if let Some(subs) = solve_builtin(builtin, goal, &continuation.subs, None) {
+                self.worklist.push(Continuation {
+                    position: continuation.position + 1,
+                    subs,
+                    ..continuation
+                });
+            }
+            return;
+        }
+
+        let table = self.table(&attempt_to_rewrite(&continuation.subs, goal));
+        self.tables[continuation.owner].dependencies.insert(table);
+        if let Some(aggregation) = self
+            .aggregations
+            .get_mut(&(continuation.owner, continuation.rule))
+        {
+            aggregation.dependencies.insert(table);
+        }
+
+        if !goal.positive {
+            if self.tables[table].complete {
+                self.resolve_negation(table, continuation);
+            } else {
+                self.negations.push((table, continuation));
+            }
+            return;
+        }
+
+        let answers: Vec<Row> = self.tables[table].answers.iter().cloned().collect();
+        answers
+            .iter()
+            .for_each(|answer| self.consume(continuation.clone(), answer));
+        self.tables[table].consumers.push(continuation);
+    }
+    // The body of the continuation's rule holds
+    fn conclude(&mut self, continuation: Continuation) {
+        let head = &self.program[continuation.rule].head;
+
+        if head.has_aggregates() {
+            self.aggregations
+                .get_mut(&(continuation.owner, continuation.rule))
+                .unwrap()
+                .subs_product
+                .push(continuation.subs);
+            return;
+        }
+
+        let answer = attempt_to_rewrite(&continuation.subs, head);
+        if is_ground(&answer) {
+            self.add_answer(continuation.owner, terms_to_row(answer.terms));
+        }
+    }
+    // Whether the table is complete, which, once the worklist is empty, is when none of the tables
+    // that it depends on are still waiting on anything
+    fn is_complete(&mut self, table: usize, waiting: &HashSet<usize>) -> bool {
+        let mut visited: HashSet<usize> = Default::default();
+        let mut frontier = vec![table];
+        while let Some(current) = frontier.pop() {
+            if self.tables[current].complete || !visited.insert(current) {
+                continue;
+            }
+            if waiting.contains(&current) {
+                return false;
+            }
+            frontier.extend(self.tables[current].dependencies.iter().copied());
+        }
+
+        visited
+            .into_iter()
+            .for_each(|current| self.tables[current].complete = true);
+        return true;
+    }
+    // Continues past the negated goal, unless its table has an answer that matches it
+    fn resolve_negation(&mut self, table: usize, continuation: Continuation) {
+        let goal = self.bodies[continuation.rule][continuation.position];
+        let rewrite_attempt = attempt_to_rewrite(&continuation.subs, goal);
+        let holds = self.tables[table]
+            .answers
+            .iter()
+            .any(|answer| unify(&rewrite_attempt, &row_to_atom(goal, answer)).is_some());
+
+        if !holds {
+            self.worklist.push(Continuation {
+                position: continuation.position + 1,
+                ..continuation
+            });
+        }
+    }
+    // Once the worklist is empty, continues past the negated goals whose tables are complete, or
+    // otherwise aggregates the rules whose every table is complete. Returns whether anything was
+    // released.
+    fn release_completed(&mut self) -> bool {
+        let waiting: HashSet<usize> = self
+            .negations
+            .iter()
+            .map(|(_table, continuation)| continuation.owner)
+            .chain(self.aggregations.keys().map(|(owner, _rule)| *owner))
+            .collect();
+
+        let mut released = false;
+        std::mem::take(&mut self.negations)
+            .into_iter()
+            .for_each(|(table, continuation)| {
+                if self.is_complete(table, &waiting) {
+                    self.resolve_negation(table, continuation);
+                    released = true;
+                } else {
+                    self.negations.push((table, continuation));
+                }
+            });
+        if released {
+            return true;
+        }
+
+        let program = self.program;
+        std::mem::take(&mut self.aggregations).into_iter().for_each(
+            |((owner, rule), aggregation)| {
+                let complete = aggregation
+                    .dependencies
+                    .iter()
+                    .all(|dependency| self.is_complete(*dependency, &waiting));
+                if !complete {
+                    self.aggregations.insert((owner, rule), aggregation);
+                    return;
+                }
+
+                let call = call_pattern_to_atom(self.tables.get_index(owner).unwrap().0);
+                aggregate_head(&program[rule].head, aggregation.subs_product)
+                    .into_iter()
+                    // Aggregates are not bound by unification, hence they are checked here
+                    .filter(|answer| unify(&call, &row_to_atom(&call, answer)).is_some())
+                    .for_each(|answer| self.add_answer(owner, answer));
+                released = true;
+            },
+        );
+
+        return released;
+    }
+}
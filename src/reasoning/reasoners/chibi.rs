@@ -1,90 +1,84 @@
+use crate::misc::export::{export_rows, ExportFormat};
 use crate::misc::helpers::{
-    idempotent_intern, idempotent_program_strong_intern, idempotent_program_weak_intern, ty_to_row,
+    find_rules, idempotent_intern, idempotent_program_strong_intern,
+    idempotent_program_weak_intern, terms_to_row, ty_to_row,
 };
+use crate::misc::rule_graph::{sort_program, stratify_by_negation};
 use crate::misc::string_interning::Interner;
-use crate::models::datalog::{Program, SugaredProgram, SugaredRule};
-use crate::models::instance::{Database, HashSetDatabase};
+use crate::misc::validation::{validate_interned_builtins, validate_program, ValidationError};
+use crate::models::datalog::{Atom, Pattern, Program, Rule, SugaredProgram, SugaredRule};
+use crate::models::instance::{Database, HashSetDatabase, IndexedHashSetBacking};
 use crate::models::reasoner::{
-    BottomUpEvaluator, Diff, Dynamic, DynamicTyped, EvaluationResult, Materializer, Queryable,
-    RelationDropper, UntypedRow,
+    BottomUpEvaluator, Declarable, DeltaEvaluator, Diff, Dynamic, DynamicTyped, EvaluationResult,
+    Explainer, ExplicitFacts, Exporter, Maintainer, Materializer, PatternQueryable, QueryResult,
+    Queryable, RelationDropper, Subscribable, TopDownEvaluator, UntypedRow,
 };
 use crate::models::relational_algebra::Row;
-use crate::reasoning::algorithms::delete_rederive::delete_rederive;
+use crate::models::schema::{RelationDeclaration, Schema};
+use crate::reasoning::algorithms::counting::{
+    initialize_derivation_counts, stratified_counting, CountingMaintainer, DerivationCounts,
+    MaintenanceStrategy,
+};
+use crate::reasoning::algorithms::delete_rederive::{
+    make_consequence_retraction_program, stratified_delete_rederive, OVERDELETION_PREFIX,
+};
 use crate::reasoning::algorithms::delta_rule_rewrite::{
-    deltaify_idb, make_sne_programs, make_update_sne_programs, DELTA_PREFIX,
+    deltaify_idb, make_sne_programs, make_update_sne_programs,
 };
 use crate::reasoning::algorithms::evaluation::{
-    ImmediateConsequenceOperator, IncrementalEvaluation,
+    ImmediateConsequenceOperator, IncrementalEvaluation, StratifiedEvaluation,
 };
-use crate::reasoning::algorithms::rewriting::evaluate_rule;
+use crate::reasoning::algorithms::provenance::{DerivationLog, DerivationTree, Provenance};
+use crate::reasoning::algorithms::rewriting::{
+    answer_pattern_query, evaluate_rule, evaluate_rule_with_provenance,
+};
+use crate::reasoning::algorithms::subscriptions::{ChangeFeed, Subscriptions};
+use crate::reasoning::algorithms::tabling::TabledResolution;
 use colored::Colorize;
 use lasso::{Key, Spur};
-use phf::phf_map;
 use rayon::prelude::*;
-use std::fs::OpenOptions;
-use std::io::{BufWriter, Write};
+use std::io::Write;
 use std::time::Instant;
 
-static OWL_INV: phf::Map<&'static str, &'static str> = phf_map! {
-    "rdf:type" => "<http://www.w3.org/1999/02/22-rdf-syntax-ns#type>",
-    "rdf:rest" => "<http://www.w3.org/1999/02/22-rdf-syntax-ns#rest>",
-    "rdf:first" => "<http://www.w3.org/1999/02/22-rdf-syntax-ns#first>",
-    "rdf:nil" => "<http://www.w3.org/1999/02/22-rdf-syntax-ns#nil>",
-    "rdf:Property" => "<http://www.w3.org/1999/02/22-rdf-syntax-ns#Property>",
-    "rdfs:subClassOf" => "<http://www.w3.org/2000/01/rdf-schema#subClassOf>",
-    "rdfs:subPropertyOf" => "<http://www.w3.org/2000/01/rdf-schema#subPropertyOf>",
-    "rdfs:domain" => "<http://www.w3.org/2000/01/rdf-schema#domain>",
-    "rdfs:range" => "<http://www.w3.org/2000/01/rdf-schema#range>",
-    "rdfs:comment" => "<http://www.w3.org/2000/01/rdf-schema#comment>",
-    "rdfs:label" => "<http://www.w3.org/2000/01/rdf-schema#label>",
-    "rdfs:Literal" => "<http://www.w3.org/2000/01/rdf-schema#Literal>",
-    "owl:TransitiveProperty" => "<http://www.w3.org/2002/07/owl#TransitiveProperty>",
-    "owl:inverseOf" => "<http://www.w3.org/2002/07/owl#inverseOf>",
-    "owl:Thing" => "<http://www.w3.org/2002/07/owl#Thing>",
-    "owl:maxQualifiedCardinality" => "<http://www.w3.org/2002/07/owl#maxQualifiedCardinality>",
-    "owl:someValuesFrom" => "<http://www.w3.org/2002/07/owl#someValuesFrom>",
-    "owl:equivalentClass" => "<http://www.w3.org/2002/07/owl#equivalentClass>",
-    "owl:intersectionOf" => "<http://www.w3.org/2002/07/owl#intersectionOf>",
-    "owl:members" => "<http://www.w3.org/2002/07/owl#members>",
-    "owl:equivalentProperty" => "<http://www.w3.org/2002/07/owl#equivalentProperty>",
-    "owl:onProperty" => "<http://www.w3.org/2002/07/owl#onProperty>",
-    "owl:propertyChainAxiom" => "<http://www.w3.org/2002/07/owl#propertyChainAxiom>",
-    "owl:disjointWith" => "<http://www.w3.org/2002/07/owl#disjointWith>",
-    "owl:propertyDisjointWith" => "<http://www.w3.org/2002/07/owl#propertyDisjointWith>",
-    "owl:unionOf" => "<http://www.w3.org/2002/07/owl#unionOf>",
-    "owl:hasKey" => "<http://www.w3.org/2002/07/owl#hasKey>",
-    "owl:allValuesFrom" => "<http://www.w3.org/2002/07/owl#allValuesFrom>",
-    "owl:complementOf" => "<http://www.w3.org/2002/07/owl#complementOf>",
-    "owl:onClass" => "<http://www.w3.org/2002/07/owl#onClass>",
-    "owl:distinctMembers" => "<http://www.w3.org/2002/07/owl#distinctMembers>",
-    "owl:FunctionalProperty" => "<http://www.w3.org/2002/07/owl#FunctionalProperty>",
-    "owl:NamedIndividual" => "<http://www.w3.org/2002/07/owl#NamedIndividual>",
-    "owl:ObjectProperty" => "<http://www.w3.org/2002/07/owl#ObjectProperty>",
-    "owl:Class" => "<http://www.w3.org/2002/07/owl#Class>",
-    "owl:AllDisjointClasses" => "<http://www.w3.org/2002/07/owl#AllDisjointClasses>",
-    "owl:Restriction" => "<http://www.w3.org/2002/07/owl#Restriction>",
-    "owl:DatatypeProperty" => "<http://www.w3.org/2002/07/owl#DatatypeProperty>",
-    "owl:Ontology" => "<http://www.w3.org/2002/07/owl#Ontology>",
-    "owl:AsymmetricProperty" => "<http://www.w3.org/2002/07/owl#AsymmetricProperty>",
-    "owl:SymmetricProperty" => "<http://www.w3.org/2002/07/owl#SymmetricProperty>",
-    "owl:IrreflexiveProperty" => "<http://www.w3.org/2002/07/owl#IrreflexiveProperty>",
-    "owl:AllDifferent" => "<http://www.w3.org/2002/07/owl#AllDifferent>",
-    "owl:InverseFunctionalProperty" => "<http://www.w3.org/2002/07/owl#InverseFunctionalProperty>",
-    "owl:sameAs" => "<http://www.w3.org/2002/07/owl#sameAs>",
-    "owl:hasValue" => "<http://www.w3.org/2002/07/owl#hasValue>",
-    "owl:Nothing" => "<http://www.w3.org/2002/07/owl#Nothing>",
-    "owl:oneOf" => "<http://www.w3.org/2002/07/owl#oneOf>",
-};
+pub type RewritingLog = DerivationLog<(Rule, Row, Vec<Atom>)>;
+
+// Evaluates the rule, logging every derivation of it if there is a log
+fn evaluate_and_log_rule(
+    instance: &HashSetDatabase,
+    rule: &Rule,
+    index: bool,
+    log: &Option<RewritingLog>,
+) -> Option<IndexedHashSetBacking> {
+    let log = match log {
+        Some(log) => log,
+        None => return evaluate_rule(instance, rule, index),
+    };
+
+    let derivations = evaluate_rule_with_provenance(instance, rule, index);
+    let out: IndexedHashSetBacking = derivations.iter().map(|(row, _)| row.clone()).collect();
+    log.lock().unwrap().extend(
+        derivations
+            .into_iter()
+            .map(|(row, premises)| (rule.clone(), row, premises)),
+    );
+
+    if out.is_empty() {
+        return None;
+    }
+
+    return Some(out);
+}
 
 pub fn evaluate_rules_sequentially(
     program: &Program,
     instance: &HashSetDatabase,
     index: bool,
+    log: &Option<RewritingLog>,
 ) -> HashSetDatabase {
     let mut out: HashSetDatabase = Default::default();
 
     program.iter().for_each(|rule| {
-        if let Some(eval) = evaluate_rule(&instance, &rule, index) {
+        if let Some(eval) = evaluate_and_log_rule(&instance, &rule, index, log) {
             eval.into_iter()
                 .for_each(|row| out.insert_at(rule.head.relation_id.get(), row))
         }
@@ -97,13 +91,14 @@ pub fn evaluate_rules_in_parallel(
     program: &Program,
     instance: &HashSetDatabase,
     index: bool,
+    log: &Option<RewritingLog>,
 ) -> HashSetDatabase {
     let mut out: HashSetDatabase = Default::default();
 
     program
         .par_iter()
         .filter_map(|rule| {
-            if let Some(eval) = evaluate_rule(instance, &rule, index) {
+            if let Some(eval) = evaluate_and_log_rule(instance, &rule, index, log) {
                 return Some((rule.head.relation_id.get(), eval));
             }
 
@@ -124,6 +119,7 @@ pub struct Rewriting {
     pub recursive_program: Program,
     pub deltaifying_program: Program,
     pub index: bool,
+    pub log: Option<RewritingLog>,
 }
 
 impl Rewriting {
@@ -132,12 +128,14 @@ impl Rewriting {
         recursive_program: &Program,
         deltaifying_program: &Program,
         index: bool,
+        log: &Option<RewritingLog>,
     ) -> Self {
         return Rewriting {
             nonrecursive_program: nonrecursive_program.clone(),
             recursive_program: recursive_program.clone(),
             deltaifying_program: deltaifying_program.clone(),
             index,
+            log: log.clone(),
         };
     }
 }
@@ -148,11 +146,21 @@ impl ImmediateConsequenceOperator<HashSetDatabase> for Rewriting {
     }
 
     fn nonrecursive_program(&self, fact_store: &HashSetDatabase) -> HashSetDatabase {
-        return evaluate_rules_sequentially(&self.nonrecursive_program, fact_store, self.index);
+        return evaluate_rules_sequentially(
+            &self.nonrecursive_program,
+            fact_store,
+            self.index,
+            &self.log,
+        );
     }
 
     fn recursive_program(&self, fact_store: &HashSetDatabase) -> HashSetDatabase {
-        return evaluate_rules_sequentially(&self.recursive_program, fact_store, self.index);
+        return evaluate_rules_sequentially(
+            &self.recursive_program,
+            fact_store,
+            self.index,
+            &self.log,
+        );
     }
 }
 
@@ -161,6 +169,7 @@ pub struct ParallelRewriting {
     pub recursive_program: Program,
     pub deltaifying_program: Program,
     pub index: bool,
+    pub log: Option<RewritingLog>,
 }
 
 impl ParallelRewriting {
@@ -169,12 +178,14 @@ impl ParallelRewriting {
         recursive_program: &Program,
         deltaifying_program: &Program,
         index: bool,
+        log: &Option<RewritingLog>,
     ) -> Self {
         return ParallelRewriting {
             nonrecursive_program: nonrecursive_program.clone(),
             recursive_program: recursive_program.clone(),
             deltaifying_program: deltaifying_program.clone(),
             index,
+            log: log.clone(),
         };
     }
 }
@@ -200,23 +211,38 @@ impl ImmediateConsequenceOperator<HashSetDatabase> for ParallelRewriting {
     }
 
     fn nonrecursive_program(&self, fact_store: &HashSetDatabase) -> HashSetDatabase {
-        return evaluate_rules_in_parallel(&self.nonrecursive_program, fact_store, self.index);
+        return evaluate_rules_in_parallel(
+            &self.nonrecursive_program,
+            fact_store,
+            self.index,
+            &self.log,
+        );
     }
 
     fn recursive_program(&self, fact_store: &HashSetDatabase) -> HashSetDatabase {
-        return evaluate_rules_in_parallel(&self.recursive_program, fact_store, self.index);
+        return evaluate_rules_in_parallel(
+            &self.recursive_program,
+            fact_store,
+            self.index,
+            &self.log,
+        );
     }
 }
 
 pub struct ChibiDatalog {
     pub fact_store: HashSetDatabase,
     pub(crate) interner: Interner,
+    explicit_facts: HashSetDatabase,
     parallel: bool,
     intern: bool,
     index: bool,
     program: Program,
     sugared_program: SugaredProgram,
-    dred: bool,
+    schema: Schema,
+    provenance: Option<Provenance>,
+    maintenance_strategy: MaintenanceStrategy,
+    derivation_counts: DerivationCounts,
+    subscriptions: Subscriptions,
 }
 
 impl Default for ChibiDatalog {
@@ -224,12 +250,17 @@ impl Default for ChibiDatalog {
         ChibiDatalog {
             fact_store: Default::default(),
             interner: Default::default(),
+            explicit_facts: Default::default(),
             parallel: true,
             intern: true,
             index: true,
             program: vec![],
             sugared_program: vec![],
-            dred: false,
+            schema: Default::default(),
+            provenance: None,
+            maintenance_strategy: Default::default(),
+            derivation_counts: Default::default(),
+            subscriptions: Default::default(),
         }
     }
 }
@@ -244,51 +275,13 @@ impl ChibiDatalog {
         };
     }
     fn new_evaluation(
-        &self,
-        immediate_consequence_operator: Box<dyn ImmediateConsequenceOperator<HashSetDatabase>>,
+        &mut self,
+        program: &SugaredProgram,
+        deltas: bool,
+        log: &Option<RewritingLog>,
     ) -> IncrementalEvaluation<HashSetDatabase> {
-        return IncrementalEvaluation::new(immediate_consequence_operator);
-    }
-    fn update_materialization(&mut self) {
-        let evaluation = self.evaluate_program_bottom_up(&self.sugared_program.clone());
-
-        evaluation.into_iter().for_each(|(symbol, relation)| {
-            relation.into_iter().for_each(|row| {
-                self.insert_typed(&symbol, row);
-            });
-        });
-    }
-}
-
-impl Dynamic for ChibiDatalog {
-    fn insert(&mut self, table: &str, row: UntypedRow) {
-        self.insert_typed(table, ty_to_row(&row))
-    }
-
-    fn delete(&mut self, table: &str, row: &UntypedRow) {
-        self.delete_typed(table, &ty_to_row(row))
-    }
-}
-
-impl DynamicTyped for ChibiDatalog {
-    fn insert_typed(&mut self, table: &str, row: Row) {
-        let (relation_id, typed_row) =
-            idempotent_intern(&mut self.interner, self.intern, table, row);
-
-        self.fact_store.insert_at(relation_id, typed_row)
-    }
-    fn delete_typed(&mut self, table: &str, row: &Row) {
-        let (relation_id, typed_row) =
-            idempotent_intern(&mut self.interner, self.intern, table, row.clone());
-
-        self.fact_store.delete_at(relation_id, &typed_row)
-    }
-}
-
-impl BottomUpEvaluator for ChibiDatalog {
-    fn evaluate_program_bottom_up(&mut self, program: &Vec<SugaredRule>) -> EvaluationResult {
         let deltaifier = deltaify_idb(program);
-        let (nonrecursive, recursive) = if !self.dred {
+        let (nonrecursive, recursive) = if deltas {
             make_update_sne_programs(program)
         } else {
             make_sne_programs(program)
@@ -304,21 +297,38 @@ impl BottomUpEvaluator for ChibiDatalog {
             })
             .collect();
 
-        let im_op = Box::new(ParallelRewriting::new(
-            &programs[0],
-            &programs[1],
-            &programs[2],
-            self.index,
-        ));
-        let mut evaluation = self.new_evaluation(im_op);
         if !self.parallel {
-            evaluation.immediate_consequence_operator = Box::new(Rewriting::new(
+            return IncrementalEvaluation::new(Box::new(Rewriting::new(
                 &programs[0],
                 &programs[1],
                 &programs[2],
                 self.index,
-            ));
+                log,
+            )));
+        }
+
+        return IncrementalEvaluation::new(Box::new(ParallelRewriting::new(
+            &programs[0],
+            &programs[1],
+            &programs[2],
+            self.index,
+            log,
+        )));
+    }
+    fn evaluate(
+        &mut self,
+        program: &SugaredProgram,
+        deltas: bool,
+    ) -> Result<EvaluationResult, ValidationError> {
+        if stratify_by_negation(program).is_none() {
+            return Err(ValidationError::NotStratifiable);
         }
+        let log: Option<RewritingLog> = self.provenance.as_ref().map(|_| Default::default());
+        let evaluations = sort_program(program)
+            .iter()
+            .map(|stratum| self.new_evaluation(stratum, deltas, &log))
+            .collect();
+        let mut evaluation = StratifiedEvaluation::new(evaluations);
 
         let now = Instant::now();
         evaluation.semi_naive(&self.fact_store);
@@ -327,8 +337,11 @@ impl BottomUpEvaluator for ChibiDatalog {
             "inferencetime",
             now.elapsed().as_millis().to_string()
         );
+        if let Some(log) = log {
+            self.record_derivations(log);
+        }
 
-        return evaluation.output.storage.into_iter().fold(
+        return Ok(evaluation.output.storage.into_iter().fold(
             Default::default(),
             |mut acc: EvaluationResult, (relation_id, row_set)| {
                 let spur = Spur::try_from_usize(relation_id as usize - 1).unwrap();
@@ -337,15 +350,209 @@ impl BottomUpEvaluator for ChibiDatalog {
                 acc.insert(sym.to_string(), row_set);
                 acc
             },
-        );
+        ));
+    }
+    // Sends the recorded changes of every subscribed relation, un-interned
+    fn publish_changes(&mut self) {
+        let interner = &self.interner;
+
+        self.subscriptions.publish(|row| {
+            row.iter()
+                .map(|typed_value| interner.resolve_value(typed_value.clone()))
+                .collect()
+        })
+    }
+    // Whether the fact store holds the already interned row
+    fn stores(&self, relation_id: u32, typed_row: &Row) -> bool {
+        return self
+            .fact_store
+            .storage
+            .get(&relation_id)
+            .map_or(false, |relation| relation.contains(typed_row));
+    }
+    // Whether the database holds the row, without interning anything
+    fn holds(&self, database: &HashSetDatabase, table: &str, row: &Row) -> bool {
+        if let Some(relation_id) = self.interner.rodeo.get(table) {
+            let mut typed_row = row.clone();
+            if self.intern {
+                if let Some(existing_typed_row) = self.interner.try_intern_row(&typed_row) {
+                    typed_row = existing_typed_row
+                } else {
+                    return false;
+                }
+            }
+            return database
+                .storage
+                .get(&relation_id.into_inner().get())
+                .map_or(false, |relation| relation.contains(&typed_row));
+        }
+
+        return false;
+    }
+    fn mark_explicit(&mut self, table: &str, row: Row, explicit: bool) {
+        let (relation_id, typed_row) =
+            idempotent_intern(&mut self.interner, self.intern, table, row);
+        if explicit {
+            self.explicit_facts.insert_at(relation_id, typed_row);
+        } else {
+            self.explicit_facts.delete_at(relation_id, &typed_row);
+        }
+    }
+    fn record_derivations(&mut self, log: RewritingLog) {
+        let interner = &self.interner;
+        if let Some(provenance) = &mut self.provenance {
+            log.lock()
+                .unwrap()
+                .drain(..)
+                .for_each(|(rule, row, premises)| {
+                    provenance.record(
+                        &interner.resolve_rule_weak(&rule),
+                        row,
+                        premises
+                            .into_iter()
+                            .map(|premise| {
+                                (
+                                    interner.resolve_symbol(premise.relation_id),
+                                    terms_to_row(premise.terms),
+                                )
+                            })
+                            .collect(),
+                    )
+                });
+        }
+    }
+}
+
+impl Dynamic for ChibiDatalog {
+    fn insert(&mut self, table: &str, row: UntypedRow) {
+        if let Err(e) = self.try_insert(table, row) {
+            panic!("{}", e)
+        }
+    }
+
+    fn try_insert(&mut self, table: &str, row: UntypedRow) -> Result<(), ValidationError> {
+        let typed_row = ty_to_row(&row);
+        self.try_insert_typed(table, typed_row.clone())?;
+        self.mark_explicit(table, typed_row, true);
+
+        return Ok(());
+    }
+
+    fn delete(&mut self, table: &str, row: &UntypedRow) {
+        let typed_row = ty_to_row(row);
+        self.delete_typed(table, &typed_row);
+        self.mark_explicit(table, typed_row, false);
+    }
+}
+
+impl ExplicitFacts for ChibiDatalog {
+    fn is_explicit(&self, table: &str, row: &Row) -> bool {
+        return self.holds(&self.explicit_facts, table, row);
+    }
+}
+
+impl DynamicTyped for ChibiDatalog {
+    fn insert_typed(&mut self, table: &str, row: Row) {
+        if let Err(e) = self.try_insert_typed(table, row) {
+            panic!("{}", e)
+        }
+    }
+    fn try_insert_typed(&mut self, table: &str, row: Row) -> Result<(), ValidationError> {
+        self.schema.check_row(table, &row)?;
+        let (relation_id, typed_row) =
+            idempotent_intern(&mut self.interner, self.intern, table, row);
+        if self.subscriptions.is_recording(table) && !self.stores(relation_id, &typed_row) {
+            self.subscriptions.record(table, typed_row.clone(), true);
+        }
+        self.fact_store.insert_at(relation_id, typed_row);
+
+        return Ok(());
+    }
+    fn delete_typed(&mut self, table: &str, row: &Row) {
+        let (relation_id, typed_row) =
+            idempotent_intern(&mut self.interner, self.intern, table, row.clone());
+        if let Some(provenance) = &mut self.provenance {
+            provenance.forget(table, &typed_row);
+        }
+        if self.subscriptions.is_recording(table) && self.stores(relation_id, &typed_row) {
+            self.subscriptions.record(table, typed_row.clone(), false);
+        }
+
+        self.fact_store.delete_at(relation_id, &typed_row)
+    }
+}
+
+impl Declarable for ChibiDatalog {
+    fn declare(&mut self, declaration: RelationDeclaration) -> Result<(), ValidationError> {
+        let mut schema = self.schema.clone();
+        schema.declare(declaration)?;
+        schema.check_program(&self.sugared_program)?;
+        self.schema = schema;
+
+        return Ok(());
+    }
+
+    fn schema(&self) -> &Schema {
+        return &self.schema;
+    }
+}
+
+impl BottomUpEvaluator for ChibiDatalog {
+    fn evaluate_program_bottom_up(
+        &mut self,
+        program: &Vec<SugaredRule>,
+    ) -> Result<EvaluationResult, ValidationError> {
+        return self.evaluate(program, false);
+    }
+}
+
+impl DeltaEvaluator for ChibiDatalog {
+    fn evaluate_program_on_deltas(
+        &mut self,
+        program: &SugaredProgram,
+    ) -> Result<EvaluationResult, ValidationError> {
+        return self.evaluate(program, true);
+    }
+}
+
+impl TopDownEvaluator for ChibiDatalog {
+    fn evaluate_program_top_down(
+        &mut self,
+        program: &SugaredProgram,
+        query: &SugaredRule,
+    ) -> Result<EvaluationResult, ValidationError> {
+        let mut sugared_program = program.clone();
+        sugared_program.push(query.clone());
+        if stratify_by_negation(&sugared_program).is_none() {
+            return Err(ValidationError::NotStratifiable);
+        }
+        let program =
+            idempotent_program_strong_intern(&mut self.interner, self.intern, &sugared_program);
+        let goal = program.last().unwrap().head.clone();
+
+        let answers = TabledResolution::new(&program, &self.fact_store).query(&goal);
+
+        let mut out: EvaluationResult = Default::default();
+        out.insert(query.head.symbol.clone(), answers);
+
+        return Ok(out);
     }
 }
 
 impl Materializer for ChibiDatalog {
-    fn materialize(&mut self, program: &SugaredProgram) {
-        idempotent_program_weak_intern(&mut self.interner, self.intern, program)
-            .into_iter()
-            .for_each(|sugared_rule| self.sugared_program.push(sugared_rule));
+    fn materialize(&mut self, program: &SugaredProgram) -> Result<(), ValidationError> {
+        let mut validated_program = self.sugared_program.clone();
+        validated_program.extend(program.clone());
+        validate_program(&validated_program)?;
+        if self.intern {
+            validate_interned_builtins(program)?;
+        }
+        self.schema.check_program(program)?;
+        self.subscriptions.record_changes();
+
+        let fresh_program =
+            idempotent_program_weak_intern(&mut self.interner, self.intern, program);
+        self.sugared_program.extend(fresh_program.clone());
 
         self.program = self
             .sugared_program
@@ -353,15 +560,82 @@ impl Materializer for ChibiDatalog {
             .map(|sugared_rule| self.interner.intern_rule_weak(&sugared_rule))
             .collect();
 
-        self.update_materialization()
+        let program = self.sugared_program.clone();
+        match self.maintenance_strategy {
+            MaintenanceStrategy::DeleteRederive => {
+                stratified_delete_rederive(self, &program, &fresh_program, vec![], vec![])
+            }
+            MaintenanceStrategy::Counting => {
+                stratified_counting(self, &program, &fresh_program, vec![], vec![])
+            }
+        }?;
+        self.publish_changes();
+
+        return Ok(());
+    }
+
+    fn unmaterialize(&mut self, program: &SugaredProgram) -> Result<(), ValidationError> {
+        let removed_rules =
+            idempotent_program_weak_intern(&mut self.interner, self.intern, program);
+        let removed_positions =
+            find_rules(&self.sugared_program, &removed_rules).map_err(|rule_idx| {
+                ValidationError::UnknownRule {
+                    rule: program[rule_idx].to_string(),
+                }
+            })?;
+        self.subscriptions.record_changes();
+        let mut removed_program: SugaredProgram = vec![];
+        let mut sugared_program: SugaredProgram = vec![];
+        self.sugared_program
+            .drain(..)
+            .enumerate()
+            .for_each(|(idx, rule)| {
+                if removed_positions.contains(&idx) {
+                    removed_program.push(rule);
+                } else {
+                    sugared_program.push(rule);
+                }
+            });
+        self.sugared_program = sugared_program;
+        self.program = self
+            .sugared_program
+            .iter()
+            .map(|sugared_rule| self.interner.intern_rule_weak(&sugared_rule))
+            .collect();
+
+        let consequences = self
+            .evaluate_program_bottom_up(&make_consequence_retraction_program(&removed_program))?;
+        // Explicit facts hold even if the removed rules derived them too
+        let retractions: Vec<(&str, Row)> = consequences
+            .iter()
+            .flat_map(|(del_sym, row_set)| {
+                let sym = del_sym.strip_prefix(OVERDELETION_PREFIX).unwrap();
+                row_set.iter().map(move |row| (sym, row.clone()))
+            })
+            .filter(|(sym, row)| !self.is_explicit(sym, row))
+            .collect();
+
+        let program = self.sugared_program.clone();
+        match self.maintenance_strategy {
+            MaintenanceStrategy::DeleteRederive => {
+                stratified_delete_rederive(self, &program, &vec![], vec![], retractions)
+            }
+            MaintenanceStrategy::Counting => initialize_derivation_counts(self, &program)
+                .and_then(|_| stratified_counting(self, &program, &vec![], vec![], retractions)),
+        }?;
+        self.publish_changes();
+
+        return Ok(());
     }
 
     fn update(&mut self, changes: Vec<Diff>) {
+        self.subscriptions.record_changes();
         let mut additions: Vec<(&str, Row)> = vec![];
         let mut retractions: Vec<(&str, Row)> = vec![];
 
         changes.iter().for_each(|(sign, (sym, value))| {
             let typed_row: Row = ty_to_row(value);
+            self.mark_explicit(sym, typed_row.clone(), *sign);
 
             if *sign {
                 additions.push((sym, typed_row));
@@ -370,24 +644,17 @@ impl Materializer for ChibiDatalog {
             }
         });
 
-        if retractions.len() > 0 {
-            self.dred = true;
-            delete_rederive(self, &self.sugared_program.clone(), retractions);
-            self.dred = false;
-        }
-
-        if additions.len() > 0 {
-            additions.iter().for_each(|(sym, row)| {
-                self.insert_typed(&format!("{}{}", DELTA_PREFIX, sym), row.clone());
-            });
-
-            self.update_materialization();
-
-            additions.into_iter().for_each(|(sym, row)| {
-                self.insert_typed(sym, row.clone());
-                self.delete_typed(&format!("{}{}", DELTA_PREFIX, sym), &row);
-            });
+        let program = self.sugared_program.clone();
+        match self.maintenance_strategy {
+            MaintenanceStrategy::DeleteRederive => {
+                stratified_delete_rederive(self, &program, &vec![], additions, retractions)
+            }
+            MaintenanceStrategy::Counting => {
+                stratified_counting(self, &program, &vec![], additions, retractions)
+            }
         }
+        .expect("the materialized program is stratifiable");
+        self.publish_changes();
     }
 
     fn triple_count(&self) -> usize {
@@ -398,90 +665,144 @@ impl Materializer for ChibiDatalog {
             .map(|(_sym, rel)| return rel.len())
             .sum();
     }
+}
 
-    fn dump(&self) {
-        let mut file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open("mat.nt")
-            .unwrap();
-        let mut writer = BufWriter::new(file);
-
-        self.fact_store
+impl Exporter for ChibiDatalog {
+    fn export(
+        &self,
+        writer: &mut dyn Write,
+        relation_filter: &dyn Fn(&str) -> bool,
+        format: ExportFormat,
+    ) -> std::io::Result<()> {
+        let rows = self
+            .fact_store
             .storage
             .iter()
-            .for_each(|(_relation_id, relation)| {
-                relation.iter().for_each(|row| {
-                    let interner = &self.interner;
-
-                    let row0: u32 = row[0].clone().try_into().unwrap();
-                    let row1: u32 = row[1].clone().try_into().unwrap();
-                    let row2: u32 = row[2].clone().try_into().unwrap();
-                    let mut subject = interner
-                        .rodeo
-                        .resolve(&Spur::try_from_usize(row0 as usize - 1).unwrap());
-                    let mut predicate = interner
-                        .rodeo
-                        .resolve(&Spur::try_from_usize(row1 as usize - 1).unwrap());
-                    let mut object = interner
-                        .rodeo
-                        .resolve(&Spur::try_from_usize(row2 as usize - 1).unwrap());
-
-                    if let Some(alias) = OWL_INV.get(&subject) {
-                        subject = alias;
-                    }
-                    if let Some(alias) = OWL_INV.get(&predicate) {
-                        predicate = alias;
-                    }
-                    if let Some(alias) = OWL_INV.get(&object) {
-                        object = alias;
-                    }
-
-                    let spo = format!("{} {} {} .", subject, predicate, object);
-                    writeln!(writer, "{}", spo).unwrap();
+            .map(|(relation_id, relation)| {
+                let spur = Spur::try_from_usize(*relation_id as usize - 1).unwrap();
+
+                return (self.interner.rodeo.resolve(&spur), relation);
+            })
+            .filter(|(table, _relation)| relation_filter(table))
+            .flat_map(|(table, relation)| {
+                relation.iter().map(move |row| {
+                    let resolved_row = row
+                        .iter()
+                        .map(|typed_value| self.interner.resolve_value(typed_value.clone()))
+                        .collect();
+
+                    return (table, resolved_row);
                 })
             });
+
+        return export_rows(writer, format, rows);
     }
 }
 
 impl RelationDropper for ChibiDatalog {
     fn drop_relation(&mut self, table: &str) {
         let sym = self.interner.rodeo.get_or_intern(table);
+        if let Some(provenance) = &mut self.provenance {
+            provenance.forget_relation(table);
+        }
 
         self.fact_store.storage.remove(&sym.into_inner().get());
     }
 }
 
+impl Explainer for ChibiDatalog {
+    fn record_provenance(&mut self, record: bool) {
+        if !record {
+            self.provenance = None;
+        } else if self.provenance.is_none() {
+            self.provenance = Some(Default::default());
+        }
+    }
+
+    fn explain(&self, table: &str, row: &UntypedRow) -> Option<DerivationTree> {
+        if !self.contains_row(table, row) {
+            return None;
+        }
+
+        let mut typed_row = ty_to_row(row);
+        if self.intern {
+            typed_row = self.interner.try_intern_row(&typed_row)?;
+        }
+        let empty_provenance = Provenance::default();
+        let provenance = self.provenance.as_ref().unwrap_or(&empty_provenance);
+
+        return Some(provenance.explain(table, &typed_row, &|typed_value| {
+            self.interner.resolve_value(typed_value)
+        }));
+    }
+}
+
+impl CountingMaintainer for ChibiDatalog {
+    fn contains_typed(&self, table: &str, row: &Row) -> bool {
+        return self.holds(&self.fact_store, table, row);
+    }
+
+    fn derivation_counts(&mut self) -> &mut DerivationCounts {
+        return &mut self.derivation_counts;
+    }
+}
+
+impl Maintainer for ChibiDatalog {
+    fn set_maintenance_strategy(&mut self, strategy: MaintenanceStrategy) {
+        self.maintenance_strategy = strategy;
+        self.derivation_counts = Default::default();
+        if strategy == MaintenanceStrategy::Counting {
+            initialize_derivation_counts(self, &self.sugared_program.clone())
+                .expect("the materialized program is stratifiable");
+        }
+    }
+
+    fn maintenance_strategy(&self) -> MaintenanceStrategy {
+        return self.maintenance_strategy;
+    }
+}
+
+impl Subscribable for ChibiDatalog {
+    fn subscribe(&mut self, table: &str) -> ChangeFeed {
+        return self.subscriptions.subscribe(table);
+    }
+}
+
 impl Queryable for ChibiDatalog {
     fn contains_row(&self, table: &str, row: &UntypedRow) -> bool {
-        if let Some(relation_id) = self.interner.rodeo.get(table) {
-            let mut typed_row = ty_to_row(row);
-            if self.intern {
-                if let Some(existing_typed_row) = self.interner.try_intern_row(&typed_row) {
-                    typed_row = existing_typed_row
-                } else {
-                    return false;
-                }
-            }
-            return self
-                .fact_store
-                .storage
-                .get(&relation_id.into_inner().get())
-                .unwrap()
-                .contains(&typed_row);
-        }
+        return self.contains_typed(table, &ty_to_row(row));
+    }
+}
 
-        return false;
+impl PatternQueryable for ChibiDatalog {
+    fn query(&self, pattern: &Pattern) -> QueryResult<'_> {
+        return answer_pattern_query(&self.interner, self.intern, pattern, |symbol| {
+            let relation_id = self.interner.rodeo.get(symbol)?.into_inner();
+
+            return Some((
+                relation_id,
+                self.fact_store.storage.get(&relation_id.get())?,
+            ));
+        });
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::models::datalog::{SugaredRule, TypedValue};
-    use crate::models::reasoner::{BottomUpEvaluator, Dynamic, Materializer, Queryable};
+    use crate::misc::export::ExportFormat;
+    use crate::misc::validation::ValidationError;
+    use crate::models::datalog::{Pattern, SugaredRule, TypedValue};
+    use crate::models::reasoner::{
+        Bindings, BottomUpEvaluator, Declarable, Dynamic, DynamicTyped, Explainer, Exporter,
+        Maintainer, Materializer, PatternQueryable, Queryable, Subscribable, TopDownEvaluator,
+    };
     use crate::models::relational_algebra::Row;
+    use crate::models::schema::{ColumnType, RelationDeclaration};
+    use crate::parsers::rdf::Prefixes;
+    use crate::reasoning::algorithms::counting::MaintenanceStrategy;
     use crate::reasoning::reasoners::chibi::ChibiDatalog;
     use indexmap::IndexSet;
+    use std::collections::HashSet;
 
     #[test]
     fn test_chibi_operations() {
@@ -522,12 +843,14 @@ mod tests {
         reasoner.insert("edge", vec![Box::new("b"), Box::new("d")]);
 
         let query = vec![
-            SugaredRule::from("reachable(?x, ?y) <- [edge(?x, ?y)]"),
-            SugaredRule::from("reachable(?x, ?z) <- [reachable(?x, ?y), reachable(?y, ?z)]"),
+            SugaredRule::try_from("reachable(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reachable(?x, ?z) <- [reachable(?x, ?y), reachable(?y, ?z)]")
+                .unwrap(),
         ];
 
         let new_tuples = reasoner
             .evaluate_program_bottom_up(&query)
+            .unwrap()
             .get("reachable")
             .unwrap()
             .clone();
@@ -565,4 +888,776 @@ mod tests {
 
         assert_eq!(expected_new_tuples, new_tuples)
     }
+
+    #[test]
+    fn test_chibi_stratified_negation() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, false, true);
+        reasoner.insert("node", vec![Box::new("a")]);
+        reasoner.insert("node", vec![Box::new("b")]);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+
+        reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+                SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap(),
+                SugaredRule::try_from(
+                    "unreachable(?x, ?y) <- [node(?x), node(?y), !reach(?x, ?y)]",
+                )
+                .unwrap(),
+            ])
+            .unwrap();
+
+        assert!(reasoner.contains_row("unreachable", &vec![Box::new("b"), Box::new("a")]));
+        assert!(!reasoner.contains_row("unreachable", &vec![Box::new("a"), Box::new("b")]));
+
+        reasoner.update(vec![(true, ("edge", vec![Box::new("b"), Box::new("a")]))]);
+
+        assert!(!reasoner.contains_row("unreachable", &vec![Box::new("b"), Box::new("a")]));
+        assert!(!reasoner.contains_row("unreachable", &vec![Box::new("a"), Box::new("a")]));
+
+        reasoner.update(vec![(false, ("edge", vec![Box::new("a"), Box::new("b")]))]);
+
+        assert!(reasoner.contains_row("unreachable", &vec![Box::new("a"), Box::new("b")]));
+        assert!(reasoner.contains_row("unreachable", &vec![Box::new("a"), Box::new("a")]));
+        assert!(!reasoner.contains_row("unreachable", &vec![Box::new("b"), Box::new("a")]));
+    }
+
+    #[test]
+    fn test_chibi_incremental_materialization() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, false, true);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+
+        reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+                SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap(),
+            ])
+            .unwrap();
+        reasoner
+            .materialize(&vec![SugaredRule::try_from(
+                "reach_from_a(?y) <- [reach(a, ?y)]",
+            )
+            .unwrap()])
+            .unwrap();
+
+        assert!(reasoner.contains_row("reach_from_a", &vec![Box::new("b")]));
+
+        reasoner.update(vec![
+            (true, ("edge", vec![Box::new("b"), Box::new("c")])),
+            (true, ("edge", vec![Box::new("c"), Box::new("d")])),
+        ]);
+
+        assert!(reasoner.contains_row("reach", &vec![Box::new("b"), Box::new("d")]));
+        assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("d")]));
+        assert!(reasoner.contains_row("reach_from_a", &vec![Box::new("d")]));
+
+        reasoner.update(vec![(false, ("edge", vec![Box::new("b"), Box::new("c")]))]);
+
+        assert!(!reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("d")]));
+        assert!(!reasoner.contains_row("reach_from_a", &vec![Box::new("c")]));
+        assert!(reasoner.contains_row("reach_from_a", &vec![Box::new("b")]));
+        assert!(reasoner.contains_row("reach", &vec![Box::new("c"), Box::new("d")]));
+    }
+
+    #[test]
+    fn test_chibi_unmaterialize() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, true, true);
+        vec![("a", "b"), ("b", "c"), ("c", "d")]
+            .into_iter()
+            .for_each(|(source, destination)| {
+                reasoner.insert("edge", vec![Box::new(source), Box::new(destination)])
+            });
+        reasoner.insert("shortcut", vec![Box::new("a"), Box::new("c")]);
+
+        let recursive_rule =
+            SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap();
+        let shortcut_rule = SugaredRule::try_from("reach(?x, ?y) <- [shortcut(?x, ?y)]").unwrap();
+        reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+                recursive_rule.clone(),
+                shortcut_rule.clone(),
+            ])
+            .unwrap();
+
+        assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("d")]));
+
+        reasoner.unmaterialize(&vec![recursive_rule]).unwrap();
+
+        assert!(!reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("d")]));
+        assert!(!reasoner.contains_row("reach", &vec![Box::new("b"), Box::new("d")]));
+        assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("c")]));
+        assert!(reasoner.contains_row("reach", &vec![Box::new("c"), Box::new("d")]));
+
+        reasoner.unmaterialize(&vec![shortcut_rule]).unwrap();
+
+        assert!(!reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("c")]));
+        assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("b")]));
+
+        reasoner.update(vec![(true, ("edge", vec![Box::new("d"), Box::new("e")]))]);
+
+        assert!(reasoner.contains_row("reach", &vec![Box::new("d"), Box::new("e")]));
+        assert!(!reasoner.contains_row("reach", &vec![Box::new("c"), Box::new("e")]));
+    }
+
+    #[test]
+    fn test_chibi_unmaterialize_keeps_explicit_facts() {
+        vec![
+            MaintenanceStrategy::DeleteRederive,
+            MaintenanceStrategy::Counting,
+        ]
+        .into_iter()
+        .for_each(|strategy| {
+            let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, true, true);
+            reasoner.set_maintenance_strategy(strategy);
+            reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+            reasoner.insert("edge", vec![Box::new("b"), Box::new("c")]);
+            reasoner.insert("reach", vec![Box::new("a"), Box::new("b")]);
+
+            let base_rule = SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap();
+            reasoner
+                .materialize(&vec![
+                    base_rule.clone(),
+                    SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]")
+                        .unwrap(),
+                ])
+                .unwrap();
+
+            let hop_rule = SugaredRule::try_from("reach(?x, ?y) <- [hop(?x, ?y)]").unwrap();
+            assert_eq!(
+                Err(ValidationError::UnknownRule {
+                    rule: hop_rule.to_string()
+                }),
+                reasoner.unmaterialize(&vec![hop_rule])
+            );
+
+            reasoner.unmaterialize(&vec![base_rule]).unwrap();
+
+            assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("b")]));
+            assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("c")]));
+            assert!(!reasoner.contains_row("reach", &vec![Box::new("b"), Box::new("c")]));
+
+            reasoner.update(vec![(false, ("reach", vec![Box::new("a"), Box::new("b")]))]);
+
+            assert!(!reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("b")]));
+            assert!(!reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("c")]));
+        });
+    }
+
+    #[test]
+    fn test_chibi_export() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, true, true);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+        reasoner.insert(
+            "weight",
+            vec![Box::new("a"), Box::new(3u32), Box::new(true)],
+        );
+        reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("node(?x) <- [edge(?x, ?y)]").unwrap()
+            ])
+            .unwrap();
+
+        let mut output = vec![];
+        reasoner
+            .export(&mut output, &|table| table != "edge", ExportFormat::Csv)
+            .unwrap();
+        let mut lines: Vec<_> = std::str::from_utf8(&output).unwrap().lines().collect();
+        lines.sort();
+
+        assert_eq!(vec!["node,a", "weight,a,3,true"], lines);
+
+        let mut prefixes = Prefixes::default();
+        prefixes.insert("ex", "http://example.org/");
+        let n_triples = ExportFormat::NTriples(prefixes);
+        assert!(reasoner
+            .export(&mut vec![], &|table| table == "edge", n_triples.clone())
+            .is_err());
+
+        reasoner.insert("ex:name", vec![Box::new("ex:a"), Box::new("Alice Smith")]);
+        let mut output = vec![];
+        reasoner
+            .export(&mut output, &|table| table == "ex:name", n_triples)
+            .unwrap();
+
+        assert_eq!(
+            "<http://example.org/a> <http://example.org/name> \"Alice Smith\" .\n",
+            std::str::from_utf8(&output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_chibi_subscribe() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, true, true);
+        let reach_feed = reasoner.subscribe("reach");
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+        reasoner.insert("edge", vec![Box::new("b"), Box::new("c")]);
+
+        reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+                SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap(),
+            ])
+            .unwrap();
+
+        let reach = |source: &str, destination: &str| -> Row {
+            return vec![
+                TypedValue::Str(source.to_string()),
+                TypedValue::Str(destination.to_string()),
+            ]
+            .into_boxed_slice();
+        };
+        let expected_insertions: HashSet<(bool, Row)> = vec![
+            (true, reach("a", "b")),
+            (true, reach("b", "c")),
+            (true, reach("a", "c")),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(expected_insertions, reach_feed.try_iter().collect());
+
+        reasoner.update(vec![
+            (false, ("edge", vec![Box::new("b"), Box::new("c")])),
+            (true, ("edge", vec![Box::new("a"), Box::new("c")])),
+        ]);
+
+        let expected_changes: HashSet<(bool, Row)> =
+            vec![(false, reach("b", "c"))].into_iter().collect();
+        assert_eq!(expected_changes, reach_feed.try_iter().collect());
+    }
+
+    #[test]
+    fn test_chibi_top_down() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, false, true);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+        reasoner.insert("edge", vec![Box::new("b"), Box::new("c")]);
+        reasoner.insert("edge", vec![Box::new("c"), Box::new("a")]);
+        reasoner.insert("edge", vec![Box::new("d"), Box::new("e")]);
+        reasoner.insert("blocked", vec![Box::new("c")]);
+
+        let program = vec![
+            SugaredRule::try_from("reachable(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reachable(?x, ?z) <- [reachable(?x, ?y), edge(?y, ?z)]")
+                .unwrap(),
+        ];
+
+        let answers = reasoner
+            .evaluate_program_top_down(
+                &program,
+                &SugaredRule::try_from("query(?x) <- [reachable(b, ?x), !blocked(?x)]").unwrap(),
+            )
+            .unwrap()
+            .get("query")
+            .unwrap()
+            .clone();
+
+        let mut expected_answers: IndexSet<Row> = Default::default();
+        vec!["a", "b"].into_iter().for_each(|constant| {
+            expected_answers.insert(Box::new([TypedValue::Str(constant.to_string())]));
+        });
+
+        assert_eq!(
+            expected_answers,
+            answers.into_iter().collect::<IndexSet<Row>>()
+        )
+    }
+
+    #[test]
+    fn test_chibi_top_down_deep_recursion() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, false, true);
+        let length = 20000u32;
+        (0..length).for_each(|node| {
+            reasoner.insert("edge", vec![Box::new(node), Box::new(node + 1)]);
+        });
+        reasoner.insert("exit", vec![Box::new(length)]);
+        reasoner.insert("closed", vec![Box::new(length / 2)]);
+
+        let program = vec![
+            SugaredRule::try_from("escapes(?x) <- [exit(?x)]").unwrap(),
+            SugaredRule::try_from("escapes(?x) <- [edge(?x, ?y), escapes(?y), !closed(?y)]")
+                .unwrap(),
+        ];
+
+        let answers = reasoner
+            .evaluate_program_top_down(
+                &program,
+                &SugaredRule::try_from("query(?x) <- [escapes(?x), ?x < 10]").unwrap(),
+            )
+            .unwrap()
+            .get("query")
+            .unwrap()
+            .clone();
+
+        assert!(answers.is_empty());
+
+        let answers = reasoner
+            .evaluate_program_top_down(
+                &program,
+                &SugaredRule::try_from("query(?x) <- [escapes(?x), ?x > 19990]").unwrap(),
+            )
+            .unwrap()
+            .get("query")
+            .unwrap()
+            .clone();
+
+        assert_eq!(10, answers.len());
+    }
+
+    #[test]
+    fn test_chibi_pattern_query() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, true, true);
+        reasoner.insert(
+            "T",
+            vec![Box::new("alice"), Box::new("type"), Box::new("Student")],
+        );
+        reasoner.insert(
+            "T",
+            vec![Box::new("bob"), Box::new("type"), Box::new("Professor")],
+        );
+        reasoner.insert(
+            "T",
+            vec![
+                Box::new("Student"),
+                Box::new("subClassOf"),
+                Box::new("Person"),
+            ],
+        );
+        reasoner.insert(
+            "T",
+            vec![
+                Box::new("Professor"),
+                Box::new("subClassOf"),
+                Box::new("Person"),
+            ],
+        );
+        reasoner.insert("retired", vec![Box::new("bob")]);
+
+        reasoner
+            .materialize(&vec![SugaredRule::try_from(
+                "T(?x, type, ?z) <- [T(?x, type, ?y), T(?y, subClassOf, ?z)]",
+            )
+            .unwrap()])
+            .unwrap();
+
+        let person = |name: &str| {
+            Bindings::from_iter([("?x".to_string(), TypedValue::Str(name.to_string()))])
+        };
+
+        let mut answers: Vec<Bindings> = reasoner
+            .query(&Pattern::try_from("T(?x, type, Person)").unwrap())
+            .collect();
+        answers.sort_by_key(|bindings| bindings["?x"].clone());
+        assert_eq!(vec![person("alice"), person("bob")], answers);
+
+        let active_answers: Vec<Bindings> = reasoner
+            .query(&Pattern::try_from("[T(?x, type, Person), !retired(?x)]").unwrap())
+            .collect();
+        assert_eq!(vec![person("alice")], active_answers);
+
+        // Variables keep their names, whatever their order of appearance
+        let classes: Vec<Bindings> = reasoner
+            .query(
+                &Pattern::try_from("[T(?c, subClassOf, Person), T(?x, type, ?c), !retired(?x)]")
+                    .unwrap(),
+            )
+            .collect();
+        assert_eq!(
+            vec![Bindings::from_iter([
+                ("?c".to_string(), TypedValue::Str("Student".to_string())),
+                ("?x".to_string(), TypedValue::Str("alice".to_string()))
+            ])],
+            classes
+        );
+
+        assert_eq!(
+            0,
+            reasoner
+                .query(&Pattern::try_from("T(?x, type, Robot)").unwrap())
+                .count()
+        );
+        assert_eq!(
+            0,
+            reasoner
+                .query(&Pattern::try_from("unknown(?x)").unwrap())
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_chibi_builtins() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, false, true);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b"), Box::new(3u32)]);
+        reasoner.insert("edge", vec![Box::new("b"), Box::new("c"), Box::new(4u32)]);
+        reasoner.insert("edge", vec![Box::new("c"), Box::new("d"), Box::new(5u32)]);
+        reasoner.insert("person", vec![Box::new("alice")]);
+        reasoner.insert("person", vec![Box::new("albert")]);
+        reasoner.insert("person", vec![Box::new("bob")]);
+
+        reasoner
+            .materialize(&vec![
+            SugaredRule::try_from("dist(?x, ?y, ?w) <- [edge(?x, ?y, ?w)]").unwrap(),
+            SugaredRule::try_from(
+                "dist(?x, ?z, ?d) <- [dist(?x, ?y, ?v), edge(?y, ?z, ?w), ?d = ?v + ?w, ?d < 10]",
+            )
+            .unwrap(),
+            SugaredRule::try_from("al(?x) <- [person(?x), starts_with(?x, al), ?x != albert]")
+                .unwrap(),
+        ])
+            .unwrap();
+
+        assert!(reasoner.contains_row("dist", &vec![Box::new("a"), Box::new("c"), Box::new(7u32)]));
+        assert!(reasoner.contains_row("dist", &vec![Box::new("b"), Box::new("d"), Box::new(9u32)]));
+        assert!(
+            !reasoner.contains_row("dist", &vec![Box::new("a"), Box::new("d"), Box::new(12u32)])
+        );
+        assert!(reasoner.contains_row("al", &vec![Box::new("alice")]));
+        assert!(!reasoner.contains_row("al", &vec![Box::new("albert")]));
+
+        reasoner.update(vec![(
+            true,
+            ("edge", vec![Box::new("d"), Box::new("e"), Box::new(1u32)]),
+        )]);
+
+        assert!(reasoner.contains_row("dist", &vec![Box::new("c"), Box::new("e"), Box::new(6u32)]));
+        assert!(
+            !reasoner.contains_row("dist", &vec![Box::new("b"), Box::new("e"), Box::new(10u32)])
+        );
+
+        let answers: Vec<Bindings> = reasoner
+            .query(&Pattern::try_from("[?d > 5, dist(a, ?y, ?d)]").unwrap())
+            .collect();
+        assert_eq!(
+            vec![Bindings::from_iter([
+                ("?y".to_string(), TypedValue::Str("c".to_string())),
+                ("?d".to_string(), TypedValue::UInt(7))
+            ])],
+            answers
+        );
+    }
+
+    #[test]
+    fn test_chibi_interned_builtins() {
+        let mut reasoner = ChibiDatalog::new(false, true, true);
+        reasoner.insert("person", vec![Box::new("alice")]);
+        reasoner.insert("person", vec![Box::new("albert")]);
+        reasoner.insert("person", vec![Box::new("bob")]);
+        reasoner.insert("size", vec![Box::new("alice"), Box::new(3u32)]);
+
+        assert_eq!(
+            Err(ValidationError::Unsupported {
+                rule: "al(?0) <- [person(?0), starts_with(?0, al)]".to_string(),
+                feature: "ordering or matching interned strings"
+            }),
+            reasoner.materialize(&vec![SugaredRule::try_from(
+                "al(?x) <- [person(?x), starts_with(?x, al)]"
+            )
+            .unwrap()])
+        );
+        reasoner
+            .materialize(&vec![SugaredRule::try_from(
+                "small(?x) <- [size(?x, ?s), ?s < 10]",
+            )
+            .unwrap()])
+            .unwrap();
+        assert!(reasoner.contains_row("small", &vec![Box::new("alice")]));
+
+        // Queries resolve interned strings before ordering or matching them
+        let answers: Vec<Bindings> = reasoner
+            .query(&Pattern::try_from("[person(?x), ?x < b, ends_with(?x, ce)]").unwrap())
+            .collect();
+        assert_eq!(
+            vec![Bindings::from_iter([(
+                "?x".to_string(),
+                TypedValue::Str("alice".to_string())
+            )])],
+            answers
+        );
+    }
+
+    #[test]
+    fn test_chibi_integers() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, false, true);
+        reasoner.insert("offset", vec![Box::new("a"), Box::new(-3i64)]);
+        reasoner.insert("offset", vec![Box::new("b"), Box::new(2u32)]);
+        reasoner.insert("timestamp", vec![Box::new(-3i64), Box::new(5000000000u64)]);
+        reasoner.insert("timestamp", vec![Box::new(2u64), Box::new(7u64)]);
+        reasoner.insert_typed(
+            "offset",
+            Box::new([TypedValue::Str("c".to_string()), TypedValue::UInt64(3)]),
+        );
+        reasoner.insert_typed(
+            "timestamp",
+            Box::new([TypedValue::Int(3), TypedValue::Int(9)]),
+        );
+
+        reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("at(?x, ?t) <- [offset(?x, ?o), timestamp(?o, ?t)]").unwrap(),
+                SugaredRule::try_from("late(?x) <- [at(?x, ?t), ?t > 4294967295]").unwrap(),
+                SugaredRule::try_from("shifted(?x, ?s) <- [offset(?x, ?o), ?s = ?o - 1]").unwrap(),
+            ])
+            .unwrap();
+
+        assert!(reasoner.contains_row("at", &vec![Box::new("a"), Box::new(5000000000u64)]));
+        assert!(reasoner.contains_row("at", &vec![Box::new("b"), Box::new(7u32)]));
+        assert!(reasoner.contains_row("late", &vec![Box::new("a")]));
+        assert!(!reasoner.contains_row("late", &vec![Box::new("b")]));
+        assert!(reasoner.contains_row("shifted", &vec![Box::new("a"), Box::new(-4i64)]));
+        assert!(reasoner.contains_row("shifted", &vec![Box::new("b"), Box::new(1u32)]));
+        assert!(reasoner.contains_row("at", &vec![Box::new("c"), Box::new(9u32)]));
+    }
+
+    #[test]
+    fn test_chibi_aggregates() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, false, true);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b"), Box::new(3u32)]);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("c"), Box::new(4u32)]);
+        reasoner.insert("edge", vec![Box::new("b"), Box::new("c"), Box::new(5u32)]);
+
+        reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("deg(?x, count(?y)) <- [edge(?x, ?y, ?w)]").unwrap(),
+                SugaredRule::try_from(
+                    "weight(?x, sum(?w), min(?w), max(?w)) <- [edge(?x, ?y, ?w)]",
+                )
+                .unwrap(),
+                SugaredRule::try_from("hub(?x) <- [deg(?x, ?d), ?d > 1]").unwrap(),
+            ])
+            .unwrap();
+
+        assert!(reasoner.contains_row("deg", &vec![Box::new("a"), Box::new(2u32)]));
+        assert!(reasoner.contains_row("deg", &vec![Box::new("b"), Box::new(1u32)]));
+        assert!(reasoner.contains_row(
+            "weight",
+            &vec![
+                Box::new("a"),
+                Box::new(7u32),
+                Box::new(3u32),
+                Box::new(4u32)
+            ]
+        ));
+        assert!(reasoner.contains_row("hub", &vec![Box::new("a")]));
+        assert!(!reasoner.contains_row("hub", &vec![Box::new("b")]));
+
+        reasoner.update(vec![
+            (
+                true,
+                ("edge", vec![Box::new("b"), Box::new("d"), Box::new(1u32)]),
+            ),
+            (
+                false,
+                ("edge", vec![Box::new("a"), Box::new("c"), Box::new(4u32)]),
+            ),
+        ]);
+
+        assert!(reasoner.contains_row("deg", &vec![Box::new("a"), Box::new(1u32)]));
+        assert!(!reasoner.contains_row("deg", &vec![Box::new("a"), Box::new(2u32)]));
+        assert!(reasoner.contains_row("deg", &vec![Box::new("b"), Box::new(2u32)]));
+        assert!(!reasoner.contains_row("deg", &vec![Box::new("b"), Box::new(1u32)]));
+        assert!(reasoner.contains_row(
+            "weight",
+            &vec![
+                Box::new("b"),
+                Box::new(6u32),
+                Box::new(1u32),
+                Box::new(5u32)
+            ]
+        ));
+        assert!(!reasoner.contains_row("hub", &vec![Box::new("a")]));
+        assert!(reasoner.contains_row("hub", &vec![Box::new("b")]));
+
+        let answers = reasoner
+            .evaluate_program_top_down(
+                &vec![SugaredRule::try_from("deg(?x, count(?y)) <- [edge(?x, ?y, ?w)]").unwrap()],
+                &SugaredRule::try_from("query(?d) <- [deg(b, ?d)]").unwrap(),
+            )
+            .unwrap()
+            .get("query")
+            .unwrap()
+            .clone();
+
+        let mut expected_answers: IndexSet<Row> = Default::default();
+        expected_answers.insert(Box::new([TypedValue::UInt(2)]));
+        assert_eq!(expected_answers, answers);
+    }
+
+    #[test]
+    fn test_chibi_invalid_program() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, false, true);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+
+        let error = reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+                SugaredRule::try_from("reach(?x, ?z) <- [edge(?x, ?y)]").unwrap(),
+            ])
+            .unwrap_err();
+
+        assert_eq!(
+            ValidationError::UnsafeVariable {
+                rule: "reach(?0, ?1) <- [edge(?0, ?2)]".to_string(),
+                variable: 1
+            },
+            error
+        );
+        assert!(!reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("b")]));
+
+        let unstratifiable_program =
+            vec![SugaredRule::try_from("win(?x) <- [move(?x, ?y), !win(?y)]").unwrap()];
+        assert_eq!(
+            Err(ValidationError::NotStratifiable),
+            reasoner.evaluate_program_bottom_up(&unstratifiable_program)
+        );
+        assert_eq!(
+            Err(ValidationError::NotStratifiable),
+            reasoner.evaluate_program_top_down(
+                &unstratifiable_program,
+                &SugaredRule::try_from("query(?x) <- [win(?x)]").unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_chibi_declarations() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, true, true);
+        reasoner
+            .declare(
+                RelationDeclaration::try_from(".decl edge(from: str, to: str, weight: uint)")
+                    .unwrap(),
+            )
+            .unwrap();
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b"), Box::new(3u32)]);
+
+        assert_eq!(
+            Err(ValidationError::TypeMismatch {
+                symbol: "edge".to_string(),
+                column: "weight".to_string(),
+                expected: ColumnType::UInt,
+                found: TypedValue::Str("heavy".to_string()),
+            }),
+            reasoner.materialize(&vec![SugaredRule::try_from(
+                "heavy(?x) <- [edge(?x, ?y, heavy)]"
+            )
+            .unwrap()])
+        );
+
+        reasoner
+            .materialize(&vec![SugaredRule::try_from(
+                "reach(?x, ?y) <- [edge(?x, ?y, ?w)]",
+            )
+            .unwrap()])
+            .unwrap();
+        assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("b")]));
+
+        assert_eq!(
+            Err(ValidationError::ArityMismatch {
+                symbol: "reach".to_string(),
+                expected: 1,
+                found: 2,
+            }),
+            reasoner.declare(RelationDeclaration::try_from(".decl reach(from: str)").unwrap())
+        );
+        assert_eq!(
+            Err(ValidationError::ConflictingDeclaration {
+                symbol: "edge".to_string()
+            }),
+            reasoner.declare(RelationDeclaration::try_from(".decl edge(from: str)").unwrap())
+        );
+        assert_eq!(
+            vec![".decl edge(from: str, to: str, weight: uint)"],
+            reasoner
+                .schema()
+                .declarations
+                .values()
+                .map(|declaration| declaration.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert!(reasoner
+            .schema()
+            .check_row("edge", &[TypedValue::Int(-3)])
+            .is_err());
+
+        assert_eq!(
+            Err(ValidationError::TypeMismatch {
+                symbol: "edge".to_string(),
+                column: "to".to_string(),
+                expected: ColumnType::Str,
+                found: TypedValue::UInt(4),
+            }),
+            reasoner.try_insert("edge", vec![Box::new("b"), Box::new(4u32), Box::new(1u32)])
+        );
+        assert!(
+            !reasoner.contains_row("edge", &vec![Box::new("b"), Box::new(4u32), Box::new(1u32)])
+        );
+        assert_eq!(
+            Ok(()),
+            reasoner.try_insert_typed(
+                "edge",
+                Box::new([
+                    TypedValue::Str("b".to_string()),
+                    TypedValue::Str("c".to_string()),
+                    TypedValue::UInt(1)
+                ])
+            )
+        );
+        assert!(reasoner.contains_row("edge", &vec![Box::new("b"), Box::new("c"), Box::new(1u32)]));
+    }
+
+    #[test]
+    fn test_chibi_explain() {
+        let mut reasoner: ChibiDatalog = ChibiDatalog::new(false, true, true);
+        reasoner.record_provenance(true);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+        reasoner.insert("edge", vec![Box::new("b"), Box::new("c")]);
+
+        reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+                SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap(),
+            ])
+            .unwrap();
+
+        let expected_explanation =
+            "reach(a, c) by reach(?0, ?1) <- [reach(?0, ?2), edge(?2, ?1)]\n  \
+            reach(a, b) by reach(?0, ?1) <- [edge(?0, ?1)]\n    \
+            edge(a, b)\n  \
+            edge(b, c)\n";
+        assert_eq!(
+            expected_explanation,
+            reasoner
+                .explain("reach", &vec![Box::new("a"), Box::new("c")])
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "edge(b, c)\n",
+            reasoner
+                .explain("edge", &vec![Box::new("b"), Box::new("c")])
+                .unwrap()
+                .to_string()
+        );
+
+        reasoner.update(vec![
+            (false, ("edge", vec![Box::new("a"), Box::new("b")])),
+            (true, ("edge", vec![Box::new("a"), Box::new("d")])),
+            (true, ("edge", vec![Box::new("d"), Box::new("c")])),
+        ]);
+        assert!(reasoner
+            .explain("reach", &vec![Box::new("a"), Box::new("b")])
+            .is_none());
+
+        // Incremental maintenance evaluates the changed atoms first
+        let expected_explanation =
+            "reach(a, c) by reach(?0, ?1) <- [edge(?2, ?1), reach(?0, ?2)]\n  \
+            edge(d, c)\n  \
+            reach(a, d) by reach(?0, ?1) <- [edge(?0, ?1)]\n    \
+            edge(a, d)\n";
+        assert_eq!(
+            expected_explanation,
+            reasoner
+                .explain("reach", &vec![Box::new("a"), Box::new("c")])
+                .unwrap()
+                .to_string()
+        );
+    }
 }
@@ -1,8 +1,12 @@
 mod abomonated_model;
 mod abomonated_vertebra;
 
+use crate::misc::export::{export_rows, ExportFormat};
+use crate::misc::helpers::{find_rules, ty_to_row};
+use crate::misc::rule_graph::stratify_by_negation;
 use crate::misc::string_interning::Interner;
-use crate::models::datalog::{Program, SugaredProgram, TypedValue};
+use crate::misc::validation::{validate_program, ValidationError};
+use crate::models::datalog::{Pattern, SugaredProgram, TypedValue};
 use ahash::{AHasher, HashSet};
 use crossbeam_channel::{select, unbounded, Receiver, Sender};
 use differential_dataflow::algorithms::identifiers::Identifiers;
@@ -13,19 +17,27 @@ use differential_dataflow::operators::{iterate, Consolidate, Join, JoinCore, Thr
 use differential_dataflow::Collection;
 use std::clone::Clone;
 use std::hash::{Hash, Hasher};
+use std::io::Write;
 use std::num::NonZeroU32;
 use std::thread;
 use std::time::{Duration, Instant};
 
 use crate::models::instance::{Database, HashSetDatabase};
-use crate::models::reasoner::{Diff, DynamicTyped, Materializer};
+use crate::models::reasoner::{
+    Declarable, Diff, Dynamic, DynamicTyped, Exporter, Materializer, PatternQueryable, QueryResult,
+    Queryable, Subscribable, UntypedRow,
+};
 use crate::models::relational_algebra::Row;
+use crate::models::schema::{RelationDeclaration, Schema};
+use crate::reasoning::algorithms::rewriting::answer_pattern_query;
+use crate::reasoning::algorithms::subscriptions::{ChangeFeed, Subscriptions};
 use crate::reasoning::reasoners::differential::abomonated_model::{
     abomonate_rule, borrowing_mask, mask, permute_mask, AbomonatedAtom, AbomonatedRule,
     AbomonatedTerm, AbomonatedTypedValue, BorrowingMaskedAtom, MaskedAtom,
 };
 use crate::reasoning::reasoners::differential::abomonated_vertebra::AbomonatedSubstitutions;
 use colored::Colorize;
+use lasso::{Key, Spur};
 use timely::communication::allocator::Generic;
 use timely::dataflow::scopes::Child;
 use timely::dataflow::Scope;
@@ -36,10 +48,13 @@ pub type AtomCollection<'b> = Collection<Child<'b, Worker<Generic>, usize>, Abom
 pub type SubstitutionsCollection<'b> =
     Collection<Child<'b, Worker<Generic>, usize>, AbomonatedSubstitutions>;
 
-pub type RuleSink = Sender<(AbomonatedRule, usize, isize)>;
+// A rule, and the stratum that it belongs to
+pub type StratifiedRule = (AbomonatedRule, usize);
+
+pub type RuleSink = Sender<(StratifiedRule, usize, isize)>;
 pub type AtomSink = Sender<(AbomonatedAtom, usize, isize)>;
 
-pub type RuleSource = Receiver<(AbomonatedRule, usize, isize)>;
+pub type RuleSource = Receiver<(StratifiedRule, usize, isize)>;
 pub type AtomSource = Receiver<(AbomonatedAtom, usize, isize)>;
 
 pub type NotificationSink = Sender<usize>;
@@ -125,7 +140,7 @@ pub fn reason_arranged_by_relation(
                 .dataflow_named::<usize, _, _>("rule_ingestion", |local| {
                     let local_rule_output_sink = rule_output_sink.clone();
 
-                    let (rule_input, rule_collection) = local.new_collection::<AbomonatedRule, isize>();
+                    let (rule_input, rule_collection) = local.new_collection::<StratifiedRule, isize>();
                     rule_collection.inspect(move |x| {
                         local_rule_output_sink.send((x.0.clone(), x.1, x.2)).unwrap();
                     });
@@ -148,94 +163,123 @@ pub fn reason_arranged_by_relation(
                         .import(local)
                         .as_collection(|x, _y| x.clone());
 
-                    let facts_by_symbol = fact_collection
-                        .map(|ground_fact| (ground_fact.0, ground_fact));
-
-                    let indexed_rules = rule_collection.identifiers();
-                    let goals = indexed_rules
-                        .flat_map(|(rule, rule_id)| {
-                            rule
-                                .1
-                                .into_iter()
-                                .enumerate()
-                                .map(move |(atom_id, atom)| {
-                                    ((rule_id, atom_id), atom)
-                                })
-                        });
-
-                    let heads = indexed_rules
-                        .map(|rule_and_id| ((rule_and_id.1, rule_and_id.0.1.len()), rule_and_id.0.0));
-
-                    let subs_product = heads
-                        .map(|((rule_id, _rule_body_len), _head)| ((rule_id, 0), AbomonatedSubstitutions::default()));
-
+                    // Every round of the outer loop completes one more stratum, with the rules of all strata up to
+                    // it, such that negated atoms only ever look at complete relations
                     let output = local
-                        .iterative::<usize, _, _>(|inner| {
-                            let subs_product_var = iterate::Variable::new_from(subs_product.enter(inner), Product::new(Default::default(), 1));
-                            let facts_var = iterate::Variable::new_from(facts_by_symbol.enter(inner), Product::new(Default::default(), 1));
-
-                            let g = goals.enter(inner);
-
-                            let s_old_arr = subs_product_var.arrange_by_key();
-                            let facts = facts_var.distinct();
-                            let facts_by_symbol_arr = facts.arrange_by_key();
-
-                            let goal_x_subs = g
-                                .arrange_by_key()
-                                .join_core(&s_old_arr, |key, goal, sub| {
-                                    let rewrite_attempt = &attempt_to_rewrite(sub, goal);
-                                    if !is_ground(rewrite_attempt) {
-                                        return Some((goal.0, (key.clone(), rewrite_attempt.clone(), sub.clone())));
-                                    }
-                                    return None;
+                        .iterative::<usize, _, _>(|strata| {
+                            let strata_var = iterate::Variable::new_from(fact_collection.enter(strata), Product::new(Default::default(), 1));
+                            // Rules join in at the round of their stratum, and stay
+                            let stratum_rules = rule_collection
+                                .enter_at(strata, |(_rule, stratum)| *stratum)
+                                .map(|(rule, _stratum)| rule);
+
+                            let facts_by_symbol = strata_var
+                                .map(|ground_fact| (ground_fact.0, ground_fact));
+
+                            let indexed_rules = stratum_rules.identifiers();
+                            let goals = indexed_rules
+                                .flat_map(|(rule, rule_id)| {
+                                    rule
+                                        .1
+                                        .into_iter()
+                                        .enumerate()
+                                        .map(move |(atom_id, atom)| {
+                                            ((rule_id, atom_id), atom)
+                                        })
                                 });
 
-                            let current_goals = goal_x_subs
-                                .arrange_by_key();
+                            let heads = indexed_rules
+                                .map(|rule_and_id| ((rule_and_id.1, rule_and_id.0.1.len()), rule_and_id.0.0));
 
-                            let new_substitutions = facts_by_symbol_arr.join_core(&current_goals, |_interned_symbol, ground_fact: &AbomonatedAtom, (new_key, rewrite_attempt, old_sub)| {
-                                    let ground_terms = ground_fact
-                                        .2
-                                        .iter()
-                                        .map(|row_element| AbomonatedTerm::Constant(AbomonatedTypedValue::try_from(row_element.clone()).unwrap()))
-                                        .collect();
+                            let subs_product = heads
+                                .map(|((rule_id, _rule_body_len), _head)| ((rule_id, 0), AbomonatedSubstitutions::default()));
 
-                                    let proposed_atom = (rewrite_attempt.0.clone(), rewrite_attempt.1, ground_terms);
+                            let stratum_output = strata
+                                .iterative::<usize, _, _>(|inner| {
+                                    let subs_product_var = iterate::Variable::new_from(subs_product.enter(inner), Product::new(Default::default(), 1));
+                                    let facts_var = iterate::Variable::new_from(facts_by_symbol.enter(inner), Product::new(Default::default(), 1));
 
-                                    let sub = unify(
-                                        &rewrite_attempt,
-                                        &proposed_atom,
-                                    );
+                                    let g = goals.enter(inner);
 
-                                    match sub {
-                                        None => {
-                                            None
-                                        }
-                                        Some(sub) => {
-                                            let (previous_iter, new_sub) = ((new_key, old_sub.clone()), sub);
-                                            let (iter, mut previous_sub) = previous_iter;
-                                            previous_sub.inner.extend(&new_sub.inner);
-
-                                            Some(((iter.0, iter.1 + 1), previous_sub))
-                                        }
-                                    }
-                                });
+                                    let s_old_arr = subs_product_var.arrange_by_key();
+                                    let facts = facts_var.distinct();
+                                    let facts_by_symbol_arr = facts.arrange_by_key();
+
+                                    let g_arr = g.arrange_by_key();
+
+                                    let goal_x_subs = g_arr
+                                        .join_core(&s_old_arr, |key, goal, sub| {
+                                            if !goal.1 {
+                                                return None;
+                                            }
+                                            let rewrite_attempt = &attempt_to_rewrite(sub, goal);
+                                            return Some((goal.0, (key.clone(), rewrite_attempt.clone(), sub.clone())));
+                                        });
 
-                            let groundington = heads
-                                .enter(inner)
-                                .join(&new_substitutions)
-                                .map(|(_left, (atom, sub))| attempt_to_rewrite(&sub, &atom))
-                                .filter(|atom| is_ground(atom))
-                                .map(|atom| (atom.0, atom))
-                                .consolidate();
+                                    // Negated goals only let through the substitutions that do not ground them into a fact
+                                    let unblocked_substitutions = g_arr
+                                        .join_core(&s_old_arr, |key, goal, sub| {
+                                            if goal.1 {
+                                                return None;
+                                            }
+                                            let mut ground_goal = attempt_to_rewrite(sub, goal);
+                                            ground_goal.1 = true;
+                                            return Some((ground_goal, ((key.0, key.1 + 1), sub.clone())));
+                                        })
+                                        .antijoin(&facts.map(|(_interned_symbol, ground_fact)| ground_fact))
+                                        .map(|(_ground_goal, next_sub)| next_sub);
+
+                                    let current_goals = goal_x_subs
+                                        .arrange_by_key();
+
+                                    let new_substitutions = facts_by_symbol_arr.join_core(&current_goals, |_interned_symbol, ground_fact: &AbomonatedAtom, (new_key, rewrite_attempt, old_sub)| {
+                                            let ground_terms = ground_fact
+                                                .2
+                                                .iter()
+                                                .map(|row_element| AbomonatedTerm::Constant(AbomonatedTypedValue::try_from(row_element.clone()).unwrap()))
+                                                .collect();
+
+                                            let proposed_atom = (rewrite_attempt.0.clone(), rewrite_attempt.1, ground_terms);
+
+                                            let sub = unify(
+                                                &rewrite_attempt,
+                                                &proposed_atom,
+                                            );
+
+                                            match sub {
+                                                None => {
+                                                    None
+                                                }
+                                                Some(sub) => {
+                                                    let (previous_iter, new_sub) = ((new_key, old_sub.clone()), sub);
+                                                    let (iter, mut previous_sub) = previous_iter;
+                                                    previous_sub.inner.extend(&new_sub.inner);
+
+                                                    Some(((iter.0, iter.1 + 1), previous_sub))
+                                                }
+                                            }
+                                        })
+                                        .concat(&unblocked_substitutions);
+
+                                    let groundington = heads
+                                        .enter(inner)
+                                        .join(&new_substitutions)
+                                        .map(|(_left, (atom, sub))| attempt_to_rewrite(&sub, &atom))
+                                        .filter(|atom| is_ground(atom))
+                                        .map(|atom| (atom.0, atom))
+                                        .consolidate();
+
+                                    subs_product_var.set(&subs_product.enter(inner).concat(&new_substitutions));
+                                    facts_var.set(&facts_by_symbol.enter(inner).concat(&groundington)).leave()
+                                })
+                                .map(|(_interned_symbol, ground_fact)| ground_fact)
+                                .distinct();
 
-                            subs_product_var.set(&subs_product.enter(inner).concat(&new_substitutions));
-                            facts_var.set(&facts_by_symbol.enter(inner).concat(&groundington)).leave()
+                            strata_var.set(&stratum_output).leave()
                         })
-                        .consolidate()
                         .inspect_batch(move |_t, xs| {
                             for (atom, time, diff) in xs {
-                                local_fact_output_sink.send((atom.1.clone(), *time, *diff)).unwrap()
+                                local_fact_output_sink.send((atom.clone(), *time, *diff)).unwrap()
                             }
                         });
 
@@ -293,8 +337,8 @@ pub fn reason_arranged_by_relation(
 
 pub fn unique_column_combinations(rule: AbomonatedRule) -> Vec<(NonZeroU32, Vec<usize>)> {
     let mut out = vec![];
-    let mut variables: HashSet<u8> = Default::default();
-    let mut fresh_variables: HashSet<u8> = Default::default();
+    let mut variables: HashSet<u32> = Default::default();
+    let mut fresh_variables: HashSet<u32> = Default::default();
     for body_atom in rule.1 {
         let index: Vec<_> = body_atom
             .2
@@ -343,7 +387,7 @@ pub fn reason_with_masked_atoms(
                     let local_rule_output_sink = rule_output_sink.clone();
 
                     let (rule_input, rule_collection) =
-                        local.new_collection::<AbomonatedRule, isize>();
+                        local.new_collection::<StratifiedRule, isize>();
                     rule_collection.inspect(move |x| {
                         local_rule_output_sink
                             .send((x.0.clone(), x.1, x.2))
@@ -366,191 +410,232 @@ pub fn reason_with_masked_atoms(
 
                     let rule_collection = rule_trace.import(local).as_collection(|x, _y| x.clone());
 
-                    // (relation_id, [positions])
-                    let unique_column_combinations = rule_collection
-                        .flat_map(unique_column_combinations)
-                        .distinct()
-                        .arrange_by_key();
-
-                    let facts_by_relation_id = fact_collection
-                        .map(|(relation_id, sign, terms)| (relation_id, (sign, terms)));
-
-                    let facts_by_masked = unique_column_combinations.join_core(
-                        &facts_by_relation_id.arrange_by_key(),
-                        |&key, column_combination, right| {
-                            let mut projected_row = vec![None; right.1.len()];
-                            if !(column_combination.len() == 0) {
-                                column_combination.iter().for_each(|column_position| {
-                                    if let AbomonatedTerm::Constant(ref inner) =
-                                        right.1[*column_position]
-                                    {
-                                        projected_row[*column_position] = Some(inner)
-                                    }
-                                });
-                            }
-                            let masked_projected_row: BorrowingMaskedAtom = (key, projected_row);
-
-                            Some(hashisher((
-                                masked_projected_row,
-                                (key, right.0, right.1.clone()),
-                            )))
-                        },
-                    );
-
-                    let indexed_rules = rule_collection.identifiers();
-                    let goals = indexed_rules.flat_map(|(rule, rule_id)| {
-                        rule.1
-                            .into_iter()
-                            .enumerate()
-                            .map(move |(atom_id, atom)| ((rule_id, atom_id), atom))
-                    });
-
-                    // (rule_identifier, (abomonated_atom, rule_body_length))
-                    let heads = indexed_rules.map(|rule_and_id| {
-                        (rule_and_id.1, (rule_and_id.0 .0, rule_and_id.0 .1.len()))
-                    });
-
-                    let heads_x_ucc = heads
-                        .map(|(rule_identifier, (abo_atom, rule_body_length))| {
-                            (abo_atom.0, (rule_identifier, (abo_atom, rule_body_length)))
-                        })
-                        .join_core(
-                            &unique_column_combinations,
-                            |&key, heads_contents, positions| {
-                                Some((
-                                    (heads_contents.0, heads_contents.1 .1),
-                                    (heads_contents.1 .0.clone(), positions.clone()),
-                                ))
-                            },
-                        );
-
-                    let subs_product = heads
-                        .map(|(rule_id, _head)| ((rule_id, 0), AbomonatedSubstitutions::default()));
-
+                    // Every round of the outer loop completes one more stratum, with the rules of all
+                    // strata up to it, such that negated atoms only ever look at complete relations
                     let output = local
-                        .iterative::<usize, _, _>(|inner| {
-                            let subs_product_var = iterate::Variable::new_from(
-                                subs_product.enter(inner),
+                        .iterative::<usize, _, _>(|strata| {
+                            let strata_var = iterate::Variable::new_from(
+                                fact_collection.enter(strata),
                                 Product::new(Default::default(), 1),
                             );
-                            let facts_var = iterate::Variable::new_from(
-                                facts_by_masked.enter(inner),
-                                Product::new(Default::default(), 1),
-                            );
-
-                            let g = goals.enter(inner);
+                            // Rules join in at the round of their stratum, and stay
+                            let stratum_rules = rule_collection
+                                .enter_at(strata, |(_rule, stratum)| *stratum)
+                                .map(|(rule, _stratum)| rule);
+
+                            // (relation_id, [positions])
+                            let unique_column_combinations = stratum_rules
+                                .flat_map(unique_column_combinations)
+                                .distinct()
+                                .arrange_by_key();
 
-                            let s_old_arr = subs_product_var.arrange_by_key();
-                            let facts =
-                                facts_var.map(|(_, atom)| return hashisher((atom.clone(), atom)));
+                            let facts_by_relation_id = strata_var
+                                .map(|(relation_id, sign, terms)| (relation_id, (sign, terms)));
+
+                            let facts_by_masked = unique_column_combinations.join_core(
+                                &facts_by_relation_id.arrange_by_key(),
+                                |&key, column_combination, right| {
+                                    let mut projected_row = vec![None; right.1.len()];
+                                    if !(column_combination.len() == 0) {
+                                        column_combination.iter().for_each(|column_position| {
+                                            if let AbomonatedTerm::Constant(ref inner) =
+                                                right.1[*column_position]
+                                            {
+                                                projected_row[*column_position] = Some(inner)
+                                            }
+                                        });
+                                    }
+                                    let masked_projected_row: BorrowingMaskedAtom = (key, projected_row);
 
-                            let hashed_facts_by_hashed_masked = facts_var
-                                .map(|(hashed_masked_atom, atom)| {
-                                    let hashished = hashisher((atom, hashed_masked_atom));
+                                    Some(hashisher((
+                                        masked_projected_row,
+                                        (key, right.0, right.1.clone()),
+                                    )))
+                                },
+                            );
 
-                                    return (hashished.1, hashished.0);
+                            let indexed_rules = stratum_rules.identifiers();
+                            let goals = indexed_rules.flat_map(|(rule, rule_id)| {
+                                rule.1
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(move |(atom_id, atom)| ((rule_id, atom_id), atom))
+                            });
+
+                            // (rule_identifier, (abomonated_atom, rule_body_length))
+                            let heads = indexed_rules.map(|rule_and_id| {
+                                (rule_and_id.1, (rule_and_id.0 .0, rule_and_id.0 .1.len()))
+                            });
+
+                            let heads_x_ucc = heads
+                                .map(|(rule_identifier, (abo_atom, rule_body_length))| {
+                                    (abo_atom.0, (rule_identifier, (abo_atom, rule_body_length)))
                                 })
-                                .arrange_by_key();
+                                .join_core(
+                                    &unique_column_combinations,
+                                    |&key, heads_contents, positions| {
+                                        Some((
+                                            (heads_contents.0, heads_contents.1 .1),
+                                            (heads_contents.1 .0.clone(), positions.clone()),
+                                        ))
+                                    },
+                                );
 
-                            let facts_by_masked_arr = facts.arrange_by_key();
+                            let subs_product = heads
+                                .map(|(rule_id, _head)| ((rule_id, 0), AbomonatedSubstitutions::default()));
 
-                            let goal_x_subs =
-                                g.arrange_by_key().join_core(&s_old_arr, |key, goal, sub| {
-                                    let rewrite_attempt = attempt_to_rewrite(sub, goal);
-                                    let new_key = (key.clone(), sub.clone());
+                            let stratum_output = strata
+                                .iterative::<usize, _, _>(|inner| {
+                                    let subs_product_var = iterate::Variable::new_from(
+                                        subs_product.enter(inner),
+                                        Product::new(Default::default(), 1),
+                                    );
+                                    let facts_var = iterate::Variable::new_from(
+                                        facts_by_masked.enter(inner),
+                                        Product::new(Default::default(), 1),
+                                    );
 
-                                    Some((
-                                        hashisher((borrowing_mask(&rewrite_attempt), ())).0,
-                                        (new_key, rewrite_attempt),
-                                    ))
-                                });
+                                    let g = goals.enter(inner);
 
-                            let current_goals = goal_x_subs
-                                .arrange_by_key()
-                                .join_core(
-                                    &hashed_facts_by_hashed_masked,
-                                    |_hashed_masked_atom, left, hashed_atom| {
-                                        return Some((*hashed_atom, left.clone()));
-                                    },
-                                )
-                                .arrange_by_key();
+                                    let s_old_arr = subs_product_var.arrange_by_key();
+                                    let facts =
+                                        facts_var.map(|(_, atom)| return hashisher((atom.clone(), atom)));
+
+                                    let hashed_facts_by_hashed_masked = facts_var
+                                        .map(|(hashed_masked_atom, atom)| {
+                                            let hashished = hashisher((atom, hashed_masked_atom));
 
-                            let new_substitutions = facts_by_masked_arr.join_core(
-                                &current_goals,
-                                |_hashed_masked_atom,
-                                 ground_fact: &AbomonatedAtom,
-                                 (new_key, rewrite_attempt)| {
-                                    let ground_terms = ground_fact
-                                        .2
-                                        .iter()
-                                        .map(|row_element| {
-                                            AbomonatedTerm::Constant(
-                                                AbomonatedTypedValue::try_from(row_element.clone())
-                                                    .unwrap(),
-                                            )
+                                            return (hashished.1, hashished.0);
                                         })
-                                        .collect();
+                                        .arrange_by_key();
 
-                                    let proposed_atom = (
-                                        rewrite_attempt.0.clone(),
-                                        rewrite_attempt.1,
-                                        ground_terms,
-                                    );
+                                    let facts_by_masked_arr = facts.arrange_by_key();
 
-                                    let sub = unify(&rewrite_attempt, &proposed_atom);
+                                    let ground_facts = facts_var
+                                        .map(|(_hashed_masked_atom, atom)| atom)
+                                        .distinct();
 
-                                    match sub {
-                                        None => None,
-                                        Some(sub) => {
-                                            let (previous_iter, new) =
-                                                ((new_key.0, new_key.1.clone()), sub);
-                                            let (_iter, previous) = previous_iter;
-                                            let mut previous_sub = previous;
-                                            let new_sub = new;
-                                            previous_sub.inner.extend(&new_sub.inner);
+                                    let g_arr = g.arrange_by_key();
+
+                                    let goal_x_subs =
+                                        g_arr.join_core(&s_old_arr, |key, goal, sub| {
+                                            if !goal.1 {
+                                                return None;
+                                            }
+                                            let rewrite_attempt = attempt_to_rewrite(sub, goal);
+                                            let new_key = (key.clone(), sub.clone());
 
                                             Some((
-                                                (previous_iter.0 .0, previous_iter.0 .1 + 1),
-                                                previous_sub,
+                                                hashisher((borrowing_mask(&rewrite_attempt), ())).0,
+                                                (new_key, rewrite_attempt),
                                             ))
-                                        }
-                                    }
-                                },
-                            );
+                                        });
 
-                            let groundington = heads_x_ucc.enter(inner).join_core(
-                                &new_substitutions.arrange_by_key(),
-                                |&key, (head_atom, positions), fresh_subs| {
-                                    let attempt = attempt_to_rewrite(fresh_subs, head_atom);
-                                    if !is_ground(&attempt) {
-                                        return None;
-                                    }
+                                    // Negated goals only let through the substitutions that do not ground
+                                    // them into a fact
+                                    let unblocked_substitutions = g_arr
+                                        .join_core(&s_old_arr, |key, goal, sub| {
+                                            if goal.1 {
+                                                return None;
+                                            }
+                                            let mut ground_goal = attempt_to_rewrite(sub, goal);
+                                            ground_goal.1 = true;
 
-                                    let mut projected_row = vec![None; attempt.2.len()];
-                                    if !(positions.len() == 0) {
-                                        positions.iter().for_each(|column_position| {
-                                            // TODO we will delete the clone
-                                            if let AbomonatedTerm::Constant(inner) =
-                                                attempt.2[*column_position].clone()
-                                            {
-                                                projected_row[*column_position] = Some(inner)
+                                            Some((ground_goal, ((key.0, key.1 + 1), sub.clone())))
+                                        })
+                                        .antijoin(&ground_facts)
+                                        .map(|(_ground_goal, next_sub)| next_sub);
+
+                                    let current_goals = goal_x_subs
+                                        .arrange_by_key()
+                                        .join_core(
+                                            &hashed_facts_by_hashed_masked,
+                                            |_hashed_masked_atom, left, hashed_atom| {
+                                                return Some((*hashed_atom, left.clone()));
+                                            },
+                                        )
+                                        .arrange_by_key();
+
+                                    let new_substitutions = facts_by_masked_arr.join_core(
+                                        &current_goals,
+                                        |_hashed_masked_atom,
+                                         ground_fact: &AbomonatedAtom,
+                                         (new_key, rewrite_attempt)| {
+                                            let ground_terms = ground_fact
+                                                .2
+                                                .iter()
+                                                .map(|row_element| {
+                                                    AbomonatedTerm::Constant(
+                                                        AbomonatedTypedValue::try_from(row_element.clone())
+                                                            .unwrap(),
+                                                    )
+                                                })
+                                                .collect();
+
+                                            let proposed_atom = (
+                                                rewrite_attempt.0.clone(),
+                                                rewrite_attempt.1,
+                                                ground_terms,
+                                            );
+
+                                            let sub = unify(&rewrite_attempt, &proposed_atom);
+
+                                            match sub {
+                                                None => None,
+                                                Some(sub) => {
+                                                    let (previous_iter, new) =
+                                                        ((new_key.0, new_key.1.clone()), sub);
+                                                    let (_iter, previous) = previous_iter;
+                                                    let mut previous_sub = previous;
+                                                    let new_sub = new;
+                                                    previous_sub.inner.extend(&new_sub.inner);
+
+                                                    Some((
+                                                        (previous_iter.0 .0, previous_iter.0 .1 + 1),
+                                                        previous_sub,
+                                                    ))
+                                                }
+                                            }
+                                        },
+                                    )
+                                    .concat(&unblocked_substitutions);
+
+                                    let groundington = heads_x_ucc.enter(inner).join_core(
+                                        &new_substitutions.arrange_by_key(),
+                                        |&key, (head_atom, positions), fresh_subs| {
+                                            let attempt = attempt_to_rewrite(fresh_subs, head_atom);
+                                            if !is_ground(&attempt) {
+                                                return None;
                                             }
-                                        });
-                                    }
-                                    let masked_projected_row: MaskedAtom =
-                                        (attempt.0, projected_row);
 
-                                    Some(hashisher((masked_projected_row, attempt)))
-                                },
-                            );
-                            let groundington = groundington.distinct();
+                                            let mut projected_row = vec![None; attempt.2.len()];
+                                            if !(positions.len() == 0) {
+                                                positions.iter().for_each(|column_position| {
+                                                    // TODO we will delete the clone
+                                                    if let AbomonatedTerm::Constant(inner) =
+                                                        attempt.2[*column_position].clone()
+                                                    {
+                                                        projected_row[*column_position] = Some(inner)
+                                                    }
+                                                });
+                                            }
+                                            let masked_projected_row: MaskedAtom =
+                                                (attempt.0, projected_row);
+
+                                            Some(hashisher((masked_projected_row, attempt)))
+                                        },
+                                    );
+                                    let groundington = groundington.distinct();
 
-                            subs_product_var.set_concat(&new_substitutions);
-                            facts_var.set_concat(&groundington).leave()
+                                    subs_product_var.set_concat(&new_substitutions);
+                                    facts_var.set_concat(&groundington).leave()
+                                })
+                                .map(|(_mask, atom)| atom)
+                                .concat(&strata_var)
+                                .distinct();
+
+                            strata_var.set(&stratum_output).leave()
                         })
-                        .map(move |(mask, atom)| {atom})
-                        .concat(&fact_collection)
-                        .consolidate()
                         .inspect_batch(move |_t, xs| {
                             for (atom, time, diff) in xs {
                                 local_fact_output_sink
@@ -616,69 +701,18 @@ fn hashisher<K: Hash, T>((key, value): (K, T)) -> (u32, T) {
     (hashed_key as u32, value)
 }
 
-pub struct DifferentialDatalog {
-    epoch: usize,
-    pub fact_store: HashSetDatabase,
+// A single dataflow that evaluates every stratum of the program, one after the other, whose output is
+// its input alongside everything that it derives
+pub struct DifferentialDataflow {
     _ddflow: thread::JoinHandle<()>,
     pub rule_input_sink: RuleSink,
     pub rule_output_source: RuleSource,
     pub fact_input_sink: AtomSink,
     pub fact_output_source: AtomSource,
     pub notification_source: NotificationSource,
-    pub interner: Interner,
-    materialization: Program,
 }
 
-impl Default for DifferentialDatalog {
-    fn default() -> Self {
-        let (rule_input_sink, rule_input_source) = unbounded();
-        let (fact_input_sink, fact_input_source) = unbounded();
-
-        let (rule_output_sink, rule_output_source) = unbounded();
-        let (fact_output_sink, fact_output_source) = unbounded();
-
-        let cores = thread::available_parallelism().unwrap().get();
-        let (notification_sink, notification_source) = unbounded();
-
-        let handle = thread::spawn(move || {
-            reason_arranged_by_relation(
-                cores,
-                true,
-                rule_input_source,
-                fact_input_source,
-                rule_output_sink,
-                fact_output_sink,
-                notification_sink,
-            )
-        });
-
-        DifferentialDatalog {
-            epoch: 0,
-            rule_input_sink,
-            rule_output_source,
-            fact_input_sink,
-            fact_output_source,
-            notification_source,
-            _ddflow: handle,
-            fact_store: Default::default(),
-            interner: Default::default(),
-            materialization: vec![],
-        }
-    }
-}
-
-fn typed_row_to_abomonated_row(typed_row: Row, interner: &mut Interner) -> Vec<AbomonatedTerm> {
-    let typed_row = interner.intern_row(typed_row);
-
-    return typed_row
-        .into_iter()
-        .map(|typed_value| {
-            AbomonatedTerm::Constant(AbomonatedTypedValue::from(typed_value.clone()))
-        })
-        .collect();
-}
-
-impl DifferentialDatalog {
+impl DifferentialDataflow {
     pub fn new(parallel: bool, index: bool) -> Self {
         let (rule_input_sink, rule_input_source) = unbounded();
         let (fact_input_sink, fact_input_source) = unbounded();
@@ -713,72 +747,326 @@ impl DifferentialDatalog {
             }
         });
 
-        DifferentialDatalog {
-            epoch: 0,
+        DifferentialDataflow {
+            _ddflow: handle,
             rule_input_sink,
             rule_output_source,
             fact_input_sink,
             fact_output_source,
             notification_source,
-            _ddflow: handle,
-            ..Default::default()
         }
     }
-    fn noop_typed(&mut self, table: &str, row: Row) {
-        let abomonated_atom = (
-            self.interner.rodeo.get_or_intern(table).into_inner(),
+    fn advance_to(
+        &self,
+        epoch: usize,
+        noop_rule: &StratifiedRule,
+        noop_atom: &AbomonatedAtom,
+    ) -> Vec<(AbomonatedAtom, isize)> {
+        self.fact_input_sink
+            .send((noop_atom.clone(), epoch, 0))
+            .unwrap();
+        self.rule_input_sink
+            .send((noop_rule.clone(), epoch, 0))
+            .unwrap();
+
+        let mut output = vec![];
+        loop {
+            select! {
+                recv(self.notification_source) -> last_epoch => {
+                    let last_epoch_uw = last_epoch.unwrap();
+
+                    if last_epoch_uw == epoch {
+                        self
+                        .fact_output_source
+                        .try_iter()
+                        .for_each(|(fresh_atom, _time, diff)| output.push((fresh_atom, diff)));
+
+                        return output;
+                    }
+                }
+                recv(self.fact_output_source) -> fact => {
+                    let (fresh_atom, _time, diff) = fact.unwrap();
+
+                    output.push((fresh_atom, diff))
+                },
+            }
+        }
+    }
+}
+
+pub struct DifferentialDatalog {
+    epoch: usize,
+    parallel: bool,
+    intern: bool,
+    index: bool,
+    pub fact_store: HashSetDatabase,
+    dataflow: Option<DifferentialDataflow>,
+    pub interner: Interner,
+    fact_diffs: Vec<(AbomonatedAtom, isize)>,
+    sugared_program: SugaredProgram,
+    rule_strata: Vec<StratifiedRule>,
+    schema: Schema,
+    subscriptions: Subscriptions,
+}
+
+impl Default for DifferentialDatalog {
+    fn default() -> Self {
+        DifferentialDatalog {
+            epoch: 0,
+            parallel: true,
+            intern: true,
+            index: false,
+            fact_store: Default::default(),
+            dataflow: None,
+            interner: Default::default(),
+            fact_diffs: vec![],
+            sugared_program: vec![],
+            rule_strata: vec![],
+            schema: Default::default(),
+            subscriptions: Default::default(),
+        }
+    }
+}
+
+fn typed_row_to_abomonated_row(
+    typed_row: Row,
+    interner: &mut Interner,
+    intern: bool,
+) -> Vec<AbomonatedTerm> {
+    let typed_row = if intern {
+        interner.intern_row(typed_row)
+    } else {
+        typed_row
+    };
+
+    return typed_row
+        .into_iter()
+        .map(|typed_value| {
+            AbomonatedTerm::Constant(AbomonatedTypedValue::from(typed_value.clone()))
+        })
+        .collect();
+}
+
+impl DifferentialDatalog {
+    pub fn new(parallel: bool, intern: bool, index: bool) -> Self {
+        return Self {
+            parallel,
+            intern,
+            index,
+            ..Default::default()
+        };
+    }
+    fn noop_rule(&mut self) -> StratifiedRule {
+        return (
+            (
+                (
+                    self.interner
+                        .rodeo
+                        .get_or_intern(NOOP_DUMMY_LHS.to_string())
+                        .into_inner(),
+                    true,
+                    vec![AbomonatedTerm::Variable(0)],
+                ),
+                vec![(
+                    self.interner
+                        .rodeo
+                        .get_or_intern(NOOP_DUMMY_RHS.to_string())
+                        .into_inner(),
+                    true,
+                    vec![AbomonatedTerm::Variable(0)],
+                )],
+            ),
+            0,
+        );
+    }
+    fn noop_atom(&mut self) -> AbomonatedAtom {
+        let noop_row = vec![TypedValue::Bool(false)].into_boxed_slice();
+
+        return (
+            self.interner.rodeo.get_or_intern("noop").into_inner(),
             true,
-            typed_row_to_abomonated_row(row, &mut self.interner),
+            typed_row_to_abomonated_row(noop_row, &mut self.interner, self.intern),
         );
+    }
+    // The dataflow, which is started on first use
+    fn dataflow(&mut self) -> &DifferentialDataflow {
+        let (parallel, index) = (self.parallel, self.index);
 
-        self.fact_input_sink
-            .send((abomonated_atom, self.epoch, 0))
-            .unwrap();
+        return self
+            .dataflow
+            .get_or_insert_with(|| DifferentialDataflow::new(parallel, index));
+    }
+    // Pushes all pending changes through the dataflow
+    fn step(&mut self) {
+        let noop_rule = self.noop_rule();
+        let noop_atom = self.noop_atom();
+        let epoch = self.epoch;
+        let pending_fact_diffs = std::mem::take(&mut self.fact_diffs);
+
+        let now = Instant::now();
+        let dataflow = self.dataflow();
+        pending_fact_diffs.into_iter().for_each(|(atom, diff)| {
+            dataflow.fact_input_sink.send((atom, epoch, diff)).unwrap();
+        });
+        let fact_diffs = dataflow.advance_to(epoch + 1, &noop_rule, &noop_atom);
+
+        // The diffs that leave the dataflow are the changes of this step
+        let interner = &self.interner;
+        let subscriptions = &mut self.subscriptions;
+        let fact_store = &mut self.fact_store;
+        fact_diffs.into_iter().for_each(|(fresh_atom, diff)| {
+            let spur = Spur::try_from_usize(fresh_atom.0.get() as usize - 1).unwrap();
+            let table = interner.rodeo.resolve(&spur);
+            if subscriptions.is_recording(table) {
+                subscriptions.record(table, abomonated_atom_to_row(&fresh_atom), diff > 0);
+            }
+            insert_atom_with_diff(fresh_atom, diff, fact_store)
+        });
+        self.epoch += 1;
+
+        println!(
+            "{{{}: {}}}",
+            "inferencetime",
+            now.elapsed().as_millis().to_string()
+        );
+    }
+    // Sends the recorded changes of every subscribed relation, un-interned
+    fn publish_changes(&mut self) {
+        let interner = &self.interner;
+
+        self.subscriptions.publish(|row| {
+            row.iter()
+                .map(|typed_value| interner.resolve_value(typed_value.clone()))
+                .collect()
+        })
+    }
+    // Sends every rule of the program to the dataflow, alongside its stratum, retracting the rules
+    // that are no longer there, and steps
+    fn load_program(&mut self, sugared_program: SugaredProgram) {
+        let strata = stratify_by_negation(&sugared_program).unwrap();
+        self.sugared_program = sugared_program;
+
+        let mut rule_strata = vec![];
+        strata.iter().enumerate().for_each(|(stratum, rules)| {
+            rules.iter().for_each(|rule| {
+                let interned_rule = if self.intern {
+                    self.interner.intern_rule(rule)
+                } else {
+                    self.interner.intern_rule_weak(rule)
+                };
+
+                rule_strata.push((abomonate_rule(interned_rule), stratum));
+            })
+        });
+
+        // Rules that were removed, or moved to another stratum, are retracted from their old one
+        let rule_diffs: Vec<(StratifiedRule, isize)> = self
+            .rule_strata
+            .iter()
+            .filter(|rule_stratum| !rule_strata.contains(rule_stratum))
+            .map(|rule_stratum| (rule_stratum.clone(), -1))
+            .chain(
+                rule_strata
+                    .iter()
+                    .filter(|rule_stratum| !self.rule_strata.contains(rule_stratum))
+                    .map(|rule_stratum| (rule_stratum.clone(), 1)),
+            )
+            .collect();
+        let epoch = self.epoch;
+        let dataflow = self.dataflow();
+        rule_diffs.into_iter().for_each(|(rule_stratum, diff)| {
+            dataflow
+                .rule_input_sink
+                .send((rule_stratum, epoch, diff))
+                .unwrap();
+        });
+        self.rule_strata = rule_strata;
+
+        self.step();
+    }
+}
+
+// Changes are buffered, and only reach the dataflow, hence contains_row and query, on the next
+// materialize or update
+impl Dynamic for DifferentialDatalog {
+    fn insert(&mut self, table: &str, row: UntypedRow) {
+        self.insert_typed(table, ty_to_row(&row))
+    }
+
+    fn try_insert(&mut self, table: &str, row: UntypedRow) -> Result<(), ValidationError> {
+        return self.try_insert_typed(table, ty_to_row(&row));
+    }
+
+    fn delete(&mut self, table: &str, row: &UntypedRow) {
+        self.delete_typed(table, &ty_to_row(row))
     }
 }
 
 impl DynamicTyped for DifferentialDatalog {
     fn insert_typed(&mut self, table: &str, row: Row) {
+        if let Err(e) = self.try_insert_typed(table, row) {
+            panic!("{}", e)
+        }
+    }
+    fn try_insert_typed(&mut self, table: &str, row: Row) -> Result<(), ValidationError> {
+        self.schema.check_row(table, &row)?;
         let abomonated_atom = (
             self.interner.rodeo.get_or_intern(table).into_inner(),
             true,
-            typed_row_to_abomonated_row(row, &mut self.interner),
+            typed_row_to_abomonated_row(row, &mut self.interner, self.intern),
         );
 
-        self.fact_input_sink
-            .send((abomonated_atom, self.epoch, 1))
-            .unwrap();
+        self.fact_diffs.push((abomonated_atom, 1));
+
+        return Ok(());
     }
 
     fn delete_typed(&mut self, table: &str, row: &Row) {
         let abomonated_atom = (
             self.interner.rodeo.get_or_intern(table).into_inner(),
             true,
-            typed_row_to_abomonated_row(row.clone(), &mut self.interner),
+            typed_row_to_abomonated_row(row.clone(), &mut self.interner, self.intern),
         );
 
-        self.fact_input_sink
-            .send((abomonated_atom, self.epoch, -1))
-            .unwrap();
+        self.fact_diffs.push((abomonated_atom, -1));
+    }
+}
+
+impl Declarable for DifferentialDatalog {
+    fn declare(&mut self, declaration: RelationDeclaration) -> Result<(), ValidationError> {
+        let mut schema = self.schema.clone();
+        schema.declare(declaration)?;
+        schema.check_program(&self.sugared_program)?;
+        self.schema = schema;
+
+        return Ok(());
+    }
+
+    fn schema(&self) -> &Schema {
+        return &self.schema;
     }
 }
 
 const NOOP_DUMMY_LHS: &'static str = "NOOP";
 const NOOP_DUMMY_RHS: &'static str = "SKIP";
 
-fn insert_atom_with_diff(
-    fresh_intensional_atom: AbomonatedAtom,
-    multiplicity: isize,
-    instance: &mut HashSetDatabase,
-) {
-    let boxed_vec = fresh_intensional_atom
+fn abomonated_atom_to_row(abomonated_atom: &AbomonatedAtom) -> Row {
+    return abomonated_atom
         .2
-        .into_iter()
+        .iter()
         .map(|abomonated_term| match abomonated_term {
-            AbomonatedTerm::Constant(inner) => inner.into(),
+            AbomonatedTerm::Constant(inner) => inner.clone().into(),
             AbomonatedTerm::Variable(_) => unreachable!(),
         })
         .collect();
+}
+
+fn insert_atom_with_diff(
+    fresh_intensional_atom: AbomonatedAtom,
+    multiplicity: isize,
+    instance: &mut HashSetDatabase,
+) {
+    let boxed_vec = abomonated_atom_to_row(&fresh_intensional_atom);
 
     if multiplicity > 0 {
         instance.insert_at(fresh_intensional_atom.0.get(), boxed_vec)
@@ -788,42 +1076,55 @@ fn insert_atom_with_diff(
 }
 
 impl Materializer for DifferentialDatalog {
-    fn materialize(&mut self, program: &SugaredProgram) {
-        program.iter().for_each(|rule| {
-            let interned_rule = self.interner.intern_rule(rule);
+    fn materialize(&mut self, program: &SugaredProgram) -> Result<(), ValidationError> {
+        for rule in program {
+            if rule.body.iter().any(|body_atom| body_atom.is_builtin()) {
+                return Err(ValidationError::Unsupported {
+                    rule: rule.to_string(),
+                    feature: "built-in predicates",
+                });
+            }
+            if rule.head.has_aggregates() {
+                return Err(ValidationError::Unsupported {
+                    rule: rule.to_string(),
+                    feature: "aggregates",
+                });
+            }
+        }
+        let mut sugared_program = self.sugared_program.clone();
+        sugared_program.extend(program.clone());
+        validate_program(&sugared_program)?;
+        self.schema.check_program(program)?;
+        self.subscriptions.record_changes();
+        self.load_program(sugared_program);
+        self.publish_changes();
+
+        return Ok(());
+    }
 
-            self.materialization.push(interned_rule.clone());
-            self.rule_input_sink
-                .send((abomonate_rule(interned_rule), self.epoch, 1))
-                .unwrap();
-        });
-        self.epoch += 1;
-        let noop_rule: AbomonatedRule = (
-            (
-                self.interner
-                    .rodeo
-                    .get_or_intern(NOOP_DUMMY_LHS.to_string())
-                    .into_inner(),
-                true,
-                vec![AbomonatedTerm::Variable(0)],
-            ),
-            vec![(
-                self.interner
-                    .rodeo
-                    .get_or_intern(NOOP_DUMMY_RHS.to_string())
-                    .into_inner(),
-                true,
-                vec![AbomonatedTerm::Variable(0)],
-            )],
-        );
-        self.rule_input_sink
-            .send((noop_rule, self.epoch, 0))
-            .unwrap();
+    fn unmaterialize(&mut self, program: &SugaredProgram) -> Result<(), ValidationError> {
+        let removed_positions = find_rules(&self.sugared_program, program).map_err(|rule_idx| {
+            ValidationError::UnknownRule {
+                rule: program[rule_idx].to_string(),
+            }
+        })?;
+        self.subscriptions.record_changes();
+        let sugared_program = self
+            .sugared_program
+            .iter()
+            .enumerate()
+            .filter(|(idx, _rule)| !removed_positions.contains(idx))
+            .map(|(_idx, rule)| rule.clone())
+            .collect();
+
+        self.load_program(sugared_program);
+        self.publish_changes();
 
-        self.update(vec![]);
+        return Ok(());
     }
 
     fn update(&mut self, changes: Vec<Diff>) {
+        self.subscriptions.record_changes();
         changes.iter().for_each(|(sign, (sym, value))| {
             let typed_row: Box<[TypedValue]> = value
                 .iter()
@@ -836,62 +1137,9 @@ impl Materializer for DifferentialDatalog {
                 self.delete_typed(sym.clone(), &typed_row);
             }
         });
-        self.epoch += 1;
-        let noop_row = vec![TypedValue::Bool(false)].into_boxed_slice();
-        self.noop_typed("noop", noop_row);
-
-        let noop_rule: AbomonatedRule = (
-            (
-                self.interner
-                    .rodeo
-                    .get_or_intern(NOOP_DUMMY_LHS.to_string())
-                    .into_inner(),
-                true,
-                vec![AbomonatedTerm::Variable(0)],
-            ),
-            vec![(
-                self.interner
-                    .rodeo
-                    .get_or_intern(NOOP_DUMMY_RHS.to_string())
-                    .into_inner(),
-                true,
-                vec![AbomonatedTerm::Variable(0)],
-            )],
-        );
-        self.rule_input_sink
-            .send((noop_rule, self.epoch, 0))
-            .unwrap();
-
-        let now = Instant::now();
-        loop {
-            select! {
-                recv(self.notification_source) -> last_epoch => {
-                    let last_epoch_uw = last_epoch.unwrap();
-
-                    if last_epoch_uw == self.epoch {
-                        self
-                        .fact_output_source
-                        .try_iter()
-                        .for_each(|fresh_intensional_atom| {
-                            insert_atom_with_diff(fresh_intensional_atom.0, fresh_intensional_atom.2, &mut self.fact_store)
-                        });
 
-                        println!(
-                            "{{{}: {}}}",
-                            "inferencetime",
-                            now.elapsed().as_millis().to_string()
-                        );
-
-                        return;
-                    }
-                }
-                recv(self.fact_output_source) -> fact => {
-                    let fresh_intensional_atom = fact.unwrap();
-
-                    insert_atom_with_diff(fresh_intensional_atom.0, fresh_intensional_atom.2, &mut self.fact_store)
-                },
-            }
-        }
+        self.step();
+        self.publish_changes();
     }
 
     fn triple_count(&self) -> usize {
@@ -902,8 +1150,266 @@ impl Materializer for DifferentialDatalog {
             .map(|(_sym, rel)| return rel.len())
             .sum();
     }
+}
+
+impl Exporter for DifferentialDatalog {
+    fn export(
+        &self,
+        writer: &mut dyn Write,
+        relation_filter: &dyn Fn(&str) -> bool,
+        format: ExportFormat,
+    ) -> std::io::Result<()> {
+        let rows = self
+            .fact_store
+            .storage
+            .iter()
+            .map(|(relation_id, relation)| {
+                let spur = Spur::try_from_usize(*relation_id as usize - 1).unwrap();
+
+                return (self.interner.rodeo.resolve(&spur), relation);
+            })
+            .filter(|(table, _relation)| relation_filter(table))
+            .flat_map(|(table, relation)| {
+                relation.iter().map(move |row| {
+                    let resolved_row = row
+                        .iter()
+                        .map(|typed_value| self.interner.resolve_value(typed_value.clone()))
+                        .collect();
+
+                    return (table, resolved_row);
+                })
+            });
+
+        return export_rows(writer, format, rows);
+    }
+}
+
+impl Subscribable for DifferentialDatalog {
+    fn subscribe(&mut self, table: &str) -> ChangeFeed {
+        return self.subscriptions.subscribe(table);
+    }
+}
+
+impl Queryable for DifferentialDatalog {
+    fn contains_row(&self, table: &str, row: &UntypedRow) -> bool {
+        if let Some(relation_id) = self.interner.rodeo.get(table) {
+            // Every string is interned on its way in, hence one that never was can not be stored
+            let typed_row = if self.intern {
+                self.interner.try_intern_row(&ty_to_row(row))
+            } else {
+                Some(ty_to_row(row))
+            };
+            if let Some(typed_row) = typed_row {
+                return self
+                    .fact_store
+                    .storage
+                    .get(&relation_id.into_inner().get())
+                    .map_or(false, |relation| relation.contains(&typed_row));
+            }
+        }
+
+        return false;
+    }
+}
+
+impl PatternQueryable for DifferentialDatalog {
+    fn query(&self, pattern: &Pattern) -> QueryResult<'_> {
+        return answer_pattern_query(&self.interner, self.intern, pattern, |symbol| {
+            let relation_id = self.interner.rodeo.get(symbol)?.into_inner();
+
+            return Some((relation_id, self.fact_store.storage.get(&relation_id.get())?));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::datalog::{Pattern, SugaredRule, TypedValue};
+    use crate::models::reasoner::{
+        Diff, Dynamic, Materializer, PatternQueryable, Queryable, UntypedRow,
+    };
+    use crate::reasoning::reasoners::chibi::ChibiDatalog;
+    use crate::reasoning::reasoners::differential::DifferentialDatalog;
+    use ahash::HashSet;
+
+    #[test]
+    fn test_differential_stratified_negation() {
+        // Both dataflows evaluate negated atoms on their own
+        [false, true].into_iter().for_each(|index| {
+            let mut reasoner: DifferentialDatalog = DifferentialDatalog::new(false, true, index);
+            reasoner.insert("node", vec![Box::new("a")]);
+            reasoner.insert("node", vec![Box::new("b")]);
+            reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+
+            reasoner
+                .materialize(&vec![
+                    SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+                    SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]")
+                        .unwrap(),
+                    SugaredRule::try_from(
+                        "unreachable(?x, ?y) <- [node(?x), node(?y), !reach(?x, ?y)]",
+                    )
+                    .unwrap(),
+                ])
+                .unwrap();
+
+            assert!(reasoner.contains_row("unreachable", &vec![Box::new("b"), Box::new("a")]));
+            assert!(!reasoner.contains_row("unreachable", &vec![Box::new("a"), Box::new("b")]));
+
+            reasoner.update(vec![(true, ("edge", vec![Box::new("b"), Box::new("a")]))]);
+
+            assert!(!reasoner.contains_row("unreachable", &vec![Box::new("b"), Box::new("a")]));
+            assert!(!reasoner.contains_row("unreachable", &vec![Box::new("a"), Box::new("a")]));
+
+            // Retracting an edge retracts what it reached, which unblocks the negated atom
+            reasoner.update(vec![(false, ("edge", vec![Box::new("a"), Box::new("b")]))]);
+
+            assert!(reasoner.contains_row("unreachable", &vec![Box::new("a"), Box::new("b")]));
+            assert!(reasoner.contains_row("unreachable", &vec![Box::new("a"), Box::new("a")]));
+            assert!(!reasoner.contains_row("unreachable", &vec![Box::new("b"), Box::new("a")]));
+            assert!(!reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("b")]));
+        });
+    }
+
+    #[test]
+    fn test_differential_floats_and_strings() {
+        let program = vec![
+            SugaredRule::try_from("listed(?x, ?p) <- [item(?x, ?p), shelf(?x)]").unwrap(),
+            SugaredRule::try_from("label(?x, ?l) <- [item(?x, ?p), name(?x, ?l)]").unwrap(),
+        ];
+        let updates = || -> Vec<Vec<Diff<'static>>> {
+            return vec![
+                vec![
+                    (true, ("item", vec![Box::new("apple"), Box::new(1.5f64)])),
+                    (true, ("item", vec![Box::new("pear"), Box::new(2.25f64)])),
+                    (true, ("shelf", vec![Box::new("apple")])),
+                    (true, ("shelf", vec![Box::new("pear")])),
+                    (
+                        true,
+                        ("name", vec![Box::new("pear"), Box::new("Conference pear")]),
+                    ),
+                ],
+                vec![
+                    (false, ("shelf", vec![Box::new("apple")])),
+                    (false, ("item", vec![Box::new("pear"), Box::new(2.25f64)])),
+                    (true, ("item", vec![Box::new("pear"), Box::new(2.5f64)])),
+                ],
+            ];
+        };
+        let rows = || -> Vec<(&'static str, UntypedRow)> {
+            return vec![
+                ("listed", vec![Box::new("apple"), Box::new(1.5f64)]),
+                ("listed", vec![Box::new("pear"), Box::new(2.25f64)]),
+                ("listed", vec![Box::new("pear"), Box::new(2.5f64)]),
+                ("label", vec![Box::new("pear"), Box::new("Conference pear")]),
+                (
+                    "label",
+                    vec![Box::new("apple"), Box::new("Conference pear")],
+                ),
+            ];
+        };
+
+        // Floats and strings come out as they went in, whether they were interned or not
+        [false, true].into_iter().for_each(|intern| {
+            let mut differential = DifferentialDatalog::new(false, intern, false);
+            let mut chibi = ChibiDatalog::new(false, intern, true);
+            differential.materialize(&program).unwrap();
+            chibi.materialize(&program).unwrap();
+
+            updates()
+                .into_iter()
+                .zip(updates())
+                .for_each(|(update, same_update)| {
+                    differential.update(update);
+                    chibi.update(same_update);
+
+                    rows().into_iter().for_each(|(table, row)| {
+                        assert_eq!(
+                            chibi.contains_row(table, &row),
+                            differential.contains_row(table, &row)
+                        );
+                    });
+                });
+
+            assert!(differential.contains_row(
+                "label",
+                &vec![Box::new("pear"), Box::new("Conference pear")]
+            ));
+            assert!(differential.contains_row("listed", &vec![Box::new("pear"), Box::new(2.5f64)]));
+            assert!(
+                !differential.contains_row("listed", &vec![Box::new("apple"), Box::new(1.5f64)])
+            );
+        });
+    }
+
+    #[test]
+    fn test_differential_against_chibi() {
+        let program = vec![
+            SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+            SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap(),
+        ];
+        let nodes = ["a", "b", "c", "d"];
+        let mut differential = DifferentialDatalog::new(false, true, false);
+        let mut chibi = ChibiDatalog::new(false, true, true);
+        let assert_same_rows = |differential: &DifferentialDatalog, chibi: &ChibiDatalog| {
+            nodes.into_iter().for_each(|source| {
+                nodes.into_iter().for_each(|destination| {
+                    let row: UntypedRow = vec![Box::new(source), Box::new(destination)];
+                    assert_eq!(
+                        chibi.contains_row("reach", &row),
+                        differential.contains_row("reach", &row)
+                    );
+                })
+            });
+        };
+        let answers = |reasoner: &dyn PatternQueryable, pattern: &str| {
+            return reasoner
+                .query(&Pattern::try_from(pattern).unwrap())
+                .map(|bindings| {
+                    let mut bindings: Vec<(String, TypedValue)> = bindings.into_iter().collect();
+                    bindings.sort();
+                    bindings
+                })
+                .collect::<HashSet<_>>();
+        };
+
+        differential.insert("edge", vec![Box::new("a"), Box::new("b")]);
+        chibi.insert("edge", vec![Box::new("a"), Box::new("b")]);
+        differential.insert("edge", vec![Box::new("b"), Box::new("c")]);
+        chibi.insert("edge", vec![Box::new("b"), Box::new("c")]);
+        // Inserted rows only reach the dataflow on the next materialize or update
+        assert!(!differential.contains_row("edge", &vec![Box::new("a"), Box::new("b")]));
+
+        differential.materialize(&program).unwrap();
+        chibi.materialize(&program).unwrap();
+        assert!(differential.contains_row("reach", &vec![Box::new("a"), Box::new("c")]));
+        assert_same_rows(&differential, &chibi);
+
+        differential.insert("edge", vec![Box::new("c"), Box::new("d")]);
+        chibi.insert("edge", vec![Box::new("c"), Box::new("d")]);
+        assert!(!differential.contains_row("edge", &vec![Box::new("c"), Box::new("d")]));
+
+        differential.update(vec![]);
+        chibi.update(vec![]);
+        assert!(differential.contains_row("reach", &vec![Box::new("a"), Box::new("d")]));
+        assert_same_rows(&differential, &chibi);
+        assert_eq!(3, answers(&differential, "reach(a, ?y)").len());
+        assert_eq!(
+            answers(&chibi, "reach(a, ?y)"),
+            answers(&differential, "reach(a, ?y)")
+        );
+
+        differential.update(vec![(false, ("edge", vec![Box::new("b"), Box::new("c")]))]);
+        chibi.update(vec![(false, ("edge", vec![Box::new("b"), Box::new("c")]))]);
+        assert_same_rows(&differential, &chibi);
+        assert_eq!(
+            answers(&chibi, "reach(?x, d)"),
+            answers(&differential, "reach(?x, d)")
+        );
 
-    fn dump(&self) {
-        todo!()
+        // A string that was never interned can not be stored
+        assert!(!differential.contains_row("reach", &vec![Box::new("a"), Box::new("z")]));
+        assert!(!differential.contains_row("unknown", &vec![Box::new("a")]));
+        assert!(answers(&differential, "reach(z, ?y)").is_empty());
     }
 }
@@ -1,37 +1,53 @@
 use crate::models::datalog::{Atom, Rule, Term, TypedValue};
 use abomonation_derive::Abomonation;
 use itertools::Itertools;
+use ordered_float::OrderedFloat;
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 use std::num::NonZeroU32;
 
 #[derive(Eq, PartialEq, Clone, Debug, Hash, PartialOrd, Ord, Abomonation)]
 pub enum AbomonatedTypedValue {
-    //Str(String),
+    Str(String),
     Bool(bool),
     UInt(u32),
     InternedStr(NonZeroU32),
+    // A float owns no memory, hence it can be encoded as is
+    Float(#[unsafe_abomonate_ignore] OrderedFloat<f64>),
+    Int(i64),
+    UInt64(u64),
 }
 
 impl Display for AbomonatedTypedValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            //AbomonatedTypedValue::Str(inner) => write!(f, "{}", inner),
+            AbomonatedTypedValue::Str(inner) => write!(f, "{}", inner),
             AbomonatedTypedValue::Bool(inner) => write!(f, "{}", inner),
             AbomonatedTypedValue::UInt(inner) => write!(f, "{}", inner),
             AbomonatedTypedValue::InternedStr(inner) => write!(f, "Is{}", inner),
+            AbomonatedTypedValue::Float(inner) => write!(f, "{}", inner),
+            AbomonatedTypedValue::Int(inner) => write!(f, "{}", inner),
+            AbomonatedTypedValue::UInt64(inner) => write!(f, "{}", inner),
         }
     }
 }
 
 impl From<TypedValue> for AbomonatedTypedValue {
     fn from(value: TypedValue) -> Self {
+        // The derived equality tells the integer variants apart, hence integers are kept in the
+        // narrowest variant that fits them
+        let value = match value.as_integer() {
+            Some(integer) => TypedValue::from_integer(integer).unwrap(),
+            None => value,
+        };
         return match value {
-            //TypedValue::Str(inner) => AbomonatedTypedValue::Str(inner),
+            TypedValue::Str(inner) => AbomonatedTypedValue::Str(inner),
             TypedValue::Bool(inner) => AbomonatedTypedValue::Bool(inner),
             TypedValue::UInt(inner) => AbomonatedTypedValue::UInt(inner),
             TypedValue::InternedStr(inner) => AbomonatedTypedValue::InternedStr(inner),
-            _ => panic!("floats and strings are not supported by differential reasoner!"),
+            TypedValue::Float(inner) => AbomonatedTypedValue::Float(inner),
+            TypedValue::Int(inner) => AbomonatedTypedValue::Int(inner),
+            TypedValue::UInt64(inner) => AbomonatedTypedValue::UInt64(inner),
         };
     }
 }
@@ -39,10 +55,13 @@ impl From<TypedValue> for AbomonatedTypedValue {
 impl Into<TypedValue> for AbomonatedTypedValue {
     fn into(self) -> TypedValue {
         match self {
-            //AbomonatedTypedValue::Str(inner) => TypedValue::Str(inner),
+            AbomonatedTypedValue::Str(inner) => TypedValue::Str(inner),
             AbomonatedTypedValue::Bool(inner) => TypedValue::Bool(inner),
             AbomonatedTypedValue::UInt(inner) => TypedValue::UInt(inner),
             AbomonatedTypedValue::InternedStr(inner) => TypedValue::InternedStr(inner),
+            AbomonatedTypedValue::Float(inner) => TypedValue::Float(inner),
+            AbomonatedTypedValue::Int(inner) => TypedValue::Int(inner),
+            AbomonatedTypedValue::UInt64(inner) => TypedValue::UInt64(inner),
         }
     }
 }
@@ -72,7 +91,7 @@ impl TryInto<bool> for AbomonatedTypedValue {
 #[derive(Eq, PartialEq, Clone, Debug, Hash, PartialOrd, Ord, Abomonation)]
 pub enum AbomonatedTerm {
     Constant(AbomonatedTypedValue),
-    Variable(u8),
+    Variable(u32),
 }
 
 impl Display for AbomonatedTerm {
@@ -156,6 +175,7 @@ pub fn abomonate_atom(atom: Atom) -> AbomonatedAtom {
         .map(|term| match term {
             Term::Constant(inner) => AbomonatedTerm::Constant(AbomonatedTypedValue::from(inner)),
             Term::Variable(inner) => AbomonatedTerm::Variable(inner),
+            Term::Aggregate(..) => unreachable!(),
         })
         .collect();
 
@@ -166,11 +186,13 @@ pub type AbomonatedRule = (AbomonatedAtom, Vec<AbomonatedAtom>);
 
 pub fn abomonate_rule(rule: Rule) -> AbomonatedRule {
     let head = abomonate_atom(rule.head);
-    let body = rule
+    // Negated atoms go last, so that they are only ever checked against ground substitutions
+    let (positive_body, negative_body): (Vec<_>, Vec<_>) = rule
         .body
         .iter()
         .map(|atom| abomonate_atom(atom.clone()))
-        .collect();
+        .partition(|atom| atom.1);
+    let body = positive_body.into_iter().chain(negative_body).collect();
 
     return (head, body);
 }
@@ -3,7 +3,7 @@ use abomonation_derive::Abomonation;
 use itertools::Itertools;
 use std::fmt::{Display, Formatter};
 
-pub type AbomonatedSubstitution = (u8, AbomonatedTypedValue);
+pub type AbomonatedSubstitution = (u32, AbomonatedTypedValue);
 
 #[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Abomonation, Hash)]
 pub struct AbomonatedSubstitutions {
@@ -27,7 +27,7 @@ impl AbomonatedVertebra {
     pub fn new() -> Self {
         return Self { inner: Vec::new() };
     }
-    pub fn get(&self, key: u8) -> Option<AbomonatedTypedValue> {
+    pub fn get(&self, key: u32) -> Option<AbomonatedTypedValue> {
         let idx = self.inner.partition_point(|item| item.0 < key);
         if let Some(value_at_idx) = self.inner.get(idx) {
             if value_at_idx.0 == key {
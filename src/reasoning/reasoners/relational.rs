@@ -1,32 +1,75 @@
-use crate::misc::helpers::{idempotent_program_weak_intern, ty_to_row};
+use crate::misc::export::{export_rows, ExportFormat};
+use crate::misc::helpers::{find_rules, idempotent_program_weak_intern, ty_to_row};
+use crate::misc::rule_graph::{sort_program, stratify_by_negation};
 use crate::misc::string_interning::Interner;
-use crate::models::datalog::SugaredProgram;
+use crate::misc::validation::{validate_interned_builtins, validate_program, ValidationError};
+use crate::models::datalog::{Pattern, SugaredProgram, SugaredRule};
 use crate::models::index::IndexBacking;
-use crate::models::instance::{Database, SimpleDatabaseWithIndex};
+use crate::models::instance::{Database, HashSetDatabase, SimpleDatabaseWithIndex};
 use crate::models::reasoner::{
-    BottomUpEvaluator, Diff, Dynamic, DynamicTyped, EvaluationResult, Materializer, Queryable,
-    RelationDropper, UntypedRow,
+    BottomUpEvaluator, Declarable, DeltaEvaluator, Diff, Dynamic, DynamicTyped, EvaluationResult,
+    Explainer, ExplicitFacts, Exporter, Maintainer, Materializer, PatternQueryable, QueryResult,
+    Queryable, RelationDropper, Subscribable, UntypedRow,
+};
+use crate::models::relational_algebra::{
+    RelationalExpression, Row, SimpleRelationWithOneIndexBacking,
+};
+use crate::models::schema::{RelationDeclaration, Schema};
+use crate::reasoning::algorithms::counting::{
+    initialize_derivation_counts, stratified_counting, CountingMaintainer, DerivationCounts,
+    MaintenanceStrategy,
+};
+use crate::reasoning::algorithms::delete_rederive::{
+    make_consequence_retraction_program, stratified_delete_rederive, OVERDELETION_PREFIX,
 };
-use crate::models::relational_algebra::{RelationalExpression, Row};
-use crate::reasoning::algorithms::delete_rederive::delete_rederive;
 use crate::reasoning::algorithms::delta_rule_rewrite::{
-    deltaify_idb, make_sne_programs, make_update_sne_programs, DELTA_PREFIX,
+    deltaify_idb, make_sne_programs, make_update_sne_programs,
 };
 use crate::reasoning::algorithms::evaluation::{
-    ImmediateConsequenceOperator, IncrementalEvaluation,
+    ImmediateConsequenceOperator, IncrementalEvaluation, StratifiedEvaluation,
 };
+use crate::reasoning::algorithms::provenance::{DerivationLog, DerivationTree, Fact, Provenance};
+use crate::reasoning::algorithms::rewriting::answer_pattern_query;
+use crate::reasoning::algorithms::subscriptions::{ChangeFeed, Subscriptions};
 use colored::Colorize;
 use rayon::prelude::*;
+use std::io::Write;
 use std::time::Instant;
 
+pub type RelationalAlgebraLog = DerivationLog<(SugaredRule, Row, Vec<Fact>)>;
+
+// Evaluates the expression of the rule, logging every derivation of it if there is a log
+fn evaluate_and_log_rule<T: IndexBacking>(
+    instance: &SimpleDatabaseWithIndex<T>,
+    sym: &str,
+    expr: &RelationalExpression,
+    rule: &SugaredRule,
+    log: &Option<RelationalAlgebraLog>,
+) -> Option<SimpleRelationWithOneIndexBacking<T>> {
+    let log = match log {
+        Some(log) => log,
+        None => return instance.evaluate(expr, sym),
+    };
+
+    let (relation, derivations) = instance.evaluate_with_provenance(expr, sym, rule)?;
+    log.lock().unwrap().extend(
+        derivations
+            .into_iter()
+            .map(|(row, premises)| (rule.clone(), row, premises)),
+    );
+
+    return Some(relation);
+}
+
 pub fn evaluate_rules_sequentially<T: IndexBacking>(
-    sugared_program: &Vec<(String, RelationalExpression)>,
+    sugared_program: &Vec<(String, RelationalExpression, SugaredRule)>,
     instance: &SimpleDatabaseWithIndex<T>,
+    log: &Option<RelationalAlgebraLog>,
 ) -> SimpleDatabaseWithIndex<T> {
     let mut out: SimpleDatabaseWithIndex<T> = SimpleDatabaseWithIndex::new(Interner::default());
 
-    sugared_program.iter().for_each(|(sym, expr)| {
-        if let Some(eval) = instance.evaluate(&expr, sym) {
+    sugared_program.iter().for_each(|(sym, expr, rule)| {
+        if let Some(eval) = evaluate_and_log_rule(instance, sym, expr, rule, log) {
             let local_relation_id = out
                 .symbol_interner
                 .rodeo
@@ -44,15 +87,16 @@ pub fn evaluate_rules_sequentially<T: IndexBacking>(
 }
 
 pub fn evaluate_rules_in_parallel<T: IndexBacking>(
-    sugared_program: &Vec<(String, RelationalExpression)>,
+    sugared_program: &Vec<(String, RelationalExpression, SugaredRule)>,
     instance: &SimpleDatabaseWithIndex<T>,
+    log: &Option<RelationalAlgebraLog>,
 ) -> SimpleDatabaseWithIndex<T> {
     let mut out: SimpleDatabaseWithIndex<T> = SimpleDatabaseWithIndex::new(Interner::default());
 
     sugared_program
         .par_iter()
-        .filter_map(|(sym, expr)| {
-            if let Some(eval) = instance.evaluate(expr, sym) {
+        .filter_map(|(sym, expr, rule)| {
+            if let Some(eval) = evaluate_and_log_rule(instance, sym, expr, rule, log) {
                 return Some((sym, eval));
             }
             None
@@ -78,13 +122,14 @@ pub fn evaluate_rules_in_parallel<T: IndexBacking>(
 
 pub fn key_program_by_symbol(
     sugared_program: &SugaredProgram,
-) -> Vec<(String, RelationalExpression)> {
+) -> Vec<(String, RelationalExpression, SugaredRule)> {
     return sugared_program
         .iter()
         .map(|rule| {
             (
                 rule.head.symbol.to_string(),
                 RelationalExpression::from(rule),
+                rule.clone(),
             )
         })
         .collect();
@@ -106,9 +151,10 @@ pub fn deltaify_idb_by_renaming<T: IndexBacking>(
 }
 
 pub struct RelationalAlgebra {
-    pub nonrecursive_program: Vec<(String, RelationalExpression)>,
-    pub recursive_program: Vec<(String, RelationalExpression)>,
+    pub nonrecursive_program: Vec<(String, RelationalExpression, SugaredRule)>,
+    pub recursive_program: Vec<(String, RelationalExpression, SugaredRule)>,
     pub deltaifying_program: SugaredProgram,
+    pub log: Option<RelationalAlgebraLog>,
 }
 
 impl RelationalAlgebra {
@@ -116,11 +162,13 @@ impl RelationalAlgebra {
         nonrecursive_program: &SugaredProgram,
         recursive_program: &SugaredProgram,
         deltaifying_program: &SugaredProgram,
+        log: &Option<RelationalAlgebraLog>,
     ) -> Self {
         return RelationalAlgebra {
             nonrecursive_program: key_program_by_symbol(nonrecursive_program),
             recursive_program: key_program_by_symbol(recursive_program),
             deltaifying_program: deltaifying_program.clone(),
+            log: log.clone(),
         };
     }
 }
@@ -136,21 +184,22 @@ impl<T: IndexBacking> ImmediateConsequenceOperator<SimpleDatabaseWithIndex<T>>
         &self,
         fact_store: &SimpleDatabaseWithIndex<T>,
     ) -> SimpleDatabaseWithIndex<T> {
-        return evaluate_rules_sequentially(&self.nonrecursive_program, fact_store);
+        return evaluate_rules_sequentially(&self.nonrecursive_program, fact_store, &self.log);
     }
 
     fn recursive_program(
         &self,
         fact_store: &SimpleDatabaseWithIndex<T>,
     ) -> SimpleDatabaseWithIndex<T> {
-        return evaluate_rules_sequentially(&self.recursive_program, fact_store);
+        return evaluate_rules_sequentially(&self.recursive_program, fact_store, &self.log);
     }
 }
 
 pub struct ParallelRelationalAlgebra {
-    pub nonrecursive_program: Vec<(String, RelationalExpression)>,
-    pub recursive_program: Vec<(String, RelationalExpression)>,
+    pub nonrecursive_program: Vec<(String, RelationalExpression, SugaredRule)>,
+    pub recursive_program: Vec<(String, RelationalExpression, SugaredRule)>,
     pub deltaifying_program: SugaredProgram,
+    pub log: Option<RelationalAlgebraLog>,
 }
 
 impl ParallelRelationalAlgebra {
@@ -158,11 +207,13 @@ impl ParallelRelationalAlgebra {
         nonrecursive_program: &SugaredProgram,
         recursive_program: &SugaredProgram,
         deltaifying_program: &SugaredProgram,
+        log: &Option<RelationalAlgebraLog>,
     ) -> Self {
         return ParallelRelationalAlgebra {
             nonrecursive_program: key_program_by_symbol(nonrecursive_program),
             recursive_program: key_program_by_symbol(recursive_program),
             deltaifying_program: deltaifying_program.clone(),
+            log: log.clone(),
         };
     }
 }
@@ -178,14 +229,14 @@ impl<T: IndexBacking> ImmediateConsequenceOperator<SimpleDatabaseWithIndex<T>>
         &self,
         fact_store: &SimpleDatabaseWithIndex<T>,
     ) -> SimpleDatabaseWithIndex<T> {
-        return evaluate_rules_in_parallel(&self.nonrecursive_program, fact_store);
+        return evaluate_rules_in_parallel(&self.nonrecursive_program, fact_store, &self.log);
     }
 
     fn recursive_program(
         &self,
         fact_store: &SimpleDatabaseWithIndex<T>,
     ) -> SimpleDatabaseWithIndex<T> {
-        return evaluate_rules_in_parallel(&self.recursive_program, fact_store);
+        return evaluate_rules_in_parallel(&self.recursive_program, fact_store, &self.log);
     }
 }
 
@@ -195,10 +246,15 @@ where
 {
     pub fact_store: SimpleDatabaseWithIndex<T>,
     pub row_interner: Interner,
+    explicit_facts: HashSetDatabase,
     parallel: bool,
     intern: bool,
     sugared_program: SugaredProgram,
-    dred: bool,
+    schema: Schema,
+    provenance: Option<Provenance>,
+    maintenance_strategy: MaintenanceStrategy,
+    derivation_counts: DerivationCounts,
+    subscriptions: Subscriptions,
 }
 
 impl<T> Default for RelationalDatalog<T>
@@ -209,10 +265,15 @@ where
         RelationalDatalog {
             fact_store: SimpleDatabaseWithIndex::default(),
             row_interner: Default::default(),
+            explicit_facts: Default::default(),
             parallel: true,
             intern: true,
             sugared_program: Default::default(),
-            dred: false,
+            schema: Default::default(),
+            provenance: None,
+            maintenance_strategy: Default::default(),
+            derivation_counts: Default::default(),
+            subscriptions: Default::default(),
         }
     }
 }
@@ -232,11 +293,42 @@ where
 
 impl<T: IndexBacking + PartialEq> Dynamic for RelationalDatalog<T> {
     fn insert(&mut self, table: &str, row: UntypedRow) {
-        self.insert_typed(table, ty_to_row(&row))
+        if let Err(e) = self.try_insert(table, row) {
+            panic!("{}", e)
+        }
+    }
+
+    fn try_insert(&mut self, table: &str, row: UntypedRow) -> Result<(), ValidationError> {
+        let typed_row = ty_to_row(&row);
+        self.try_insert_typed(table, typed_row.clone())?;
+        self.mark_explicit(table, typed_row, true);
+
+        return Ok(());
     }
 
     fn delete(&mut self, table: &str, row: &UntypedRow) {
-        self.delete_typed(table, &ty_to_row(row))
+        let typed_row = ty_to_row(row);
+        self.delete_typed(table, &typed_row);
+        self.mark_explicit(table, typed_row, false);
+    }
+}
+
+impl<T: IndexBacking + PartialEq> ExplicitFacts for RelationalDatalog<T> {
+    fn is_explicit(&self, table: &str, row: &Row) -> bool {
+        if let Some(relation_id) = self.fact_store.symbol_interner.rodeo.get(table) {
+            let mut typed_row = Some(row.clone());
+            if self.intern {
+                typed_row = self.row_interner.try_intern_row(row);
+            }
+            return typed_row.map_or(false, |typed_row| {
+                self.explicit_facts
+                    .storage
+                    .get(&relation_id.into_inner().get())
+                    .map_or(false, |relation| relation.contains(&typed_row))
+            });
+        }
+
+        return false;
     }
 }
 
@@ -260,42 +352,22 @@ impl<T: IndexBacking + PartialEq> RelationalDatalog<T> {
     fn idempotent_program_weak_intern(&mut self, program: &SugaredProgram) -> SugaredProgram {
         return idempotent_program_weak_intern(&mut self.row_interner, self.intern, program);
     }
-    fn new_evaluation(
-        &self,
-        immediate_consequence_operator: Box<
-            dyn ImmediateConsequenceOperator<SimpleDatabaseWithIndex<T>>,
-        >,
-    ) -> IncrementalEvaluation<SimpleDatabaseWithIndex<T>> {
-        return IncrementalEvaluation::new(immediate_consequence_operator);
-    }
-    fn update_materialization(&mut self) {
-        let evaluation = self.evaluate_program_bottom_up(&self.sugared_program.clone());
-
-        evaluation.into_iter().for_each(|(symbol, relation)| {
-            relation.into_iter().for_each(|row| {
-                self.insert_typed(&symbol, row);
-            });
-        });
-    }
-}
-
-impl<T: IndexBacking + PartialEq> DynamicTyped for RelationalDatalog<T> {
-    fn insert_typed(&mut self, table: &str, row: Row) {
+    fn mark_explicit(&mut self, table: &str, row: Row, explicit: bool) {
         let (relation_id, typed_row) = self.idempotent_intern(table, row);
-
-        self.fact_store.insert_at(relation_id, typed_row)
-    }
-    fn delete_typed(&mut self, table: &str, row: &Row) {
-        let (relation_id, typed_row) = self.idempotent_intern(table, row.clone());
-
-        self.fact_store.delete_at(relation_id, &typed_row)
+        if explicit {
+            self.explicit_facts.insert_at(relation_id, typed_row);
+        } else {
+            self.explicit_facts.delete_at(relation_id, &typed_row);
+        }
     }
-}
-
-impl<T: IndexBacking + PartialEq> BottomUpEvaluator for RelationalDatalog<T> {
-    fn evaluate_program_bottom_up(&mut self, program: &SugaredProgram) -> EvaluationResult {
+    fn new_evaluation(
+        &mut self,
+        program: &SugaredProgram,
+        deltas: bool,
+        log: &Option<RelationalAlgebraLog>,
+    ) -> IncrementalEvaluation<SimpleDatabaseWithIndex<T>> {
         let deltaifier = deltaify_idb(program);
-        let (nonrecursive, recursive) = if !self.dred {
+        let (nonrecursive, recursive) = if deltas {
             make_update_sne_programs(program)
         } else {
             make_sne_programs(program)
@@ -312,20 +384,38 @@ impl<T: IndexBacking + PartialEq> BottomUpEvaluator for RelationalDatalog<T> {
             })
             .collect();
 
-        let im_op = Box::new(ParallelRelationalAlgebra::new(
-            &programs[0],
-            &programs[1],
-            &programs[2],
-        ));
-        let mut evaluation = self.new_evaluation(im_op);
         if !self.parallel {
-            evaluation.immediate_consequence_operator = Box::new(RelationalAlgebra::new(
+            return IncrementalEvaluation::new(Box::new(RelationalAlgebra::new(
                 &programs[0],
                 &programs[1],
                 &programs[2],
-            ));
+                log,
+            )));
         }
 
+        return IncrementalEvaluation::new(Box::new(ParallelRelationalAlgebra::new(
+            &programs[0],
+            &programs[1],
+            &programs[2],
+            log,
+        )));
+    }
+    fn evaluate(
+        &mut self,
+        program: &SugaredProgram,
+        deltas: bool,
+    ) -> Result<EvaluationResult, ValidationError> {
+        if stratify_by_negation(program).is_none() {
+            return Err(ValidationError::NotStratifiable);
+        }
+        let log: Option<RelationalAlgebraLog> =
+            self.provenance.as_ref().map(|_| Default::default());
+        let evaluations = sort_program(program)
+            .iter()
+            .map(|stratum| self.new_evaluation(stratum, deltas, &log))
+            .collect();
+        let mut evaluation = StratifiedEvaluation::new(evaluations);
+
         let now = Instant::now();
         evaluation.semi_naive(&self.fact_store);
         println!(
@@ -333,34 +423,187 @@ impl<T: IndexBacking + PartialEq> BottomUpEvaluator for RelationalDatalog<T> {
             "inferencetime",
             now.elapsed().as_millis().to_string()
         );
+        if let (Some(log), Some(provenance)) = (log, &mut self.provenance) {
+            log.lock()
+                .unwrap()
+                .drain(..)
+                .for_each(|(rule, row, premises)| provenance.record(&rule, row, premises));
+        }
 
-        return evaluation.output.storage.into_iter().fold(
+        return Ok(evaluation.output.storage.into_iter().fold(
             Default::default(),
             |mut acc: EvaluationResult, (sym, row)| {
                 acc.insert(sym, row.ward);
                 acc
             },
-        );
+        ));
+    }
+    // Sends the recorded changes of every subscribed relation, un-interned
+    fn publish_changes(&mut self) {
+        let row_interner = &self.row_interner;
+
+        self.subscriptions.publish(|row| {
+            row.iter()
+                .map(|typed_value| row_interner.resolve_value(typed_value.clone()))
+                .collect()
+        })
+    }
+    // Whether the fact store holds the already interned row
+    fn stores(&self, table: &str, typed_row: &Row) -> bool {
+        return self
+            .fact_store
+            .storage
+            .get(table)
+            .map_or(false, |relation| relation.ward.contains(typed_row));
+    }
+}
+
+impl<T: IndexBacking + PartialEq> DynamicTyped for RelationalDatalog<T> {
+    fn insert_typed(&mut self, table: &str, row: Row) {
+        if let Err(e) = self.try_insert_typed(table, row) {
+            panic!("{}", e)
+        }
+    }
+    fn try_insert_typed(&mut self, table: &str, row: Row) -> Result<(), ValidationError> {
+        self.schema.check_row(table, &row)?;
+        let (relation_id, typed_row) = self.idempotent_intern(table, row);
+        if self.subscriptions.is_recording(table) && !self.stores(table, &typed_row) {
+            self.subscriptions.record(table, typed_row.clone(), true);
+        }
+        self.fact_store.insert_at(relation_id, typed_row);
+
+        return Ok(());
+    }
+    fn delete_typed(&mut self, table: &str, row: &Row) {
+        let (relation_id, typed_row) = self.idempotent_intern(table, row.clone());
+        if let Some(provenance) = &mut self.provenance {
+            provenance.forget(table, &typed_row);
+        }
+        if self.subscriptions.is_recording(table) && self.stores(table, &typed_row) {
+            self.subscriptions.record(table, typed_row.clone(), false);
+        }
+
+        self.fact_store.delete_at(relation_id, &typed_row)
+    }
+}
+
+impl<T: IndexBacking + PartialEq> Declarable for RelationalDatalog<T> {
+    fn declare(&mut self, declaration: RelationDeclaration) -> Result<(), ValidationError> {
+        let mut schema = self.schema.clone();
+        schema.declare(declaration)?;
+        schema.check_program(&self.sugared_program)?;
+        self.schema = schema;
+
+        return Ok(());
+    }
+
+    fn schema(&self) -> &Schema {
+        return &self.schema;
+    }
+}
+
+impl<T: IndexBacking + PartialEq> BottomUpEvaluator for RelationalDatalog<T> {
+    fn evaluate_program_bottom_up(
+        &mut self,
+        program: &SugaredProgram,
+    ) -> Result<EvaluationResult, ValidationError> {
+        return self.evaluate(program, false);
+    }
+}
+
+impl<T: IndexBacking + PartialEq> DeltaEvaluator for RelationalDatalog<T> {
+    fn evaluate_program_on_deltas(
+        &mut self,
+        program: &SugaredProgram,
+    ) -> Result<EvaluationResult, ValidationError> {
+        return self.evaluate(program, true);
     }
 }
 
 impl<T: IndexBacking + PartialEq> Materializer for RelationalDatalog<T> {
-    fn materialize(&mut self, program: &SugaredProgram) {
-        self.idempotent_program_weak_intern(program)
-            .into_iter()
-            .for_each(|sugared_rule| {
-                self.sugared_program.push(sugared_rule);
+    fn materialize(&mut self, program: &SugaredProgram) -> Result<(), ValidationError> {
+        let mut validated_program = self.sugared_program.clone();
+        validated_program.extend(program.clone());
+        validate_program(&validated_program)?;
+        if self.intern {
+            validate_interned_builtins(program)?;
+        }
+        self.schema.check_program(program)?;
+        self.subscriptions.record_changes();
+
+        let fresh_program = self.idempotent_program_weak_intern(program);
+        self.sugared_program.extend(fresh_program.clone());
+
+        let program = self.sugared_program.clone();
+        match self.maintenance_strategy {
+            MaintenanceStrategy::DeleteRederive => {
+                stratified_delete_rederive(self, &program, &fresh_program, vec![], vec![])
+            }
+            MaintenanceStrategy::Counting => {
+                stratified_counting(self, &program, &fresh_program, vec![], vec![])
+            }
+        }?;
+        self.publish_changes();
+
+        return Ok(());
+    }
+
+    fn unmaterialize(&mut self, program: &SugaredProgram) -> Result<(), ValidationError> {
+        let removed_rules = self.idempotent_program_weak_intern(program);
+        let removed_positions =
+            find_rules(&self.sugared_program, &removed_rules).map_err(|rule_idx| {
+                ValidationError::UnknownRule {
+                    rule: program[rule_idx].to_string(),
+                }
+            })?;
+        self.subscriptions.record_changes();
+        let mut removed_program: SugaredProgram = vec![];
+        let mut sugared_program: SugaredProgram = vec![];
+        self.sugared_program
+            .drain(..)
+            .enumerate()
+            .for_each(|(idx, rule)| {
+                if removed_positions.contains(&idx) {
+                    removed_program.push(rule);
+                } else {
+                    sugared_program.push(rule);
+                }
             });
+        self.sugared_program = sugared_program;
 
-        self.update_materialization();
+        let consequences = self
+            .evaluate_program_bottom_up(&make_consequence_retraction_program(&removed_program))?;
+        // Explicit facts hold even if the removed rules derived them too
+        let retractions: Vec<(&str, Row)> = consequences
+            .iter()
+            .flat_map(|(del_sym, row_set)| {
+                let sym = del_sym.strip_prefix(OVERDELETION_PREFIX).unwrap();
+                row_set.iter().map(move |row| (sym, row.clone()))
+            })
+            .filter(|(sym, row)| !self.is_explicit(sym, row))
+            .collect();
+
+        let program = self.sugared_program.clone();
+        match self.maintenance_strategy {
+            MaintenanceStrategy::DeleteRederive => {
+                stratified_delete_rederive(self, &program, &vec![], vec![], retractions)
+            }
+            MaintenanceStrategy::Counting => initialize_derivation_counts(self, &program)
+                .and_then(|_| stratified_counting(self, &program, &vec![], vec![], retractions)),
+        }?;
+        self.publish_changes();
+
+        return Ok(());
     }
 
     fn update(&mut self, changes: Vec<Diff>) {
+        self.subscriptions.record_changes();
         let mut additions: Vec<(&str, Row)> = vec![];
         let mut retractions: Vec<(&str, Row)> = vec![];
 
         changes.iter().for_each(|(sign, (sym, value))| {
             let typed_row: Row = ty_to_row(value);
+            self.mark_explicit(sym, typed_row.clone(), *sign);
 
             if *sign {
                 additions.push((sym, typed_row));
@@ -369,24 +612,17 @@ impl<T: IndexBacking + PartialEq> Materializer for RelationalDatalog<T> {
             }
         });
 
-        if retractions.len() > 0 {
-            self.dred = true;
-            delete_rederive(self, &self.sugared_program.clone(), retractions);
-            self.dred = false;
-        }
-
-        if additions.len() > 0 {
-            additions.iter().for_each(|(sym, row)| {
-                self.insert_typed(&format!("{}{}", DELTA_PREFIX, sym), row.clone());
-            });
-
-            self.update_materialization();
-
-            additions.into_iter().for_each(|(sym, row)| {
-                self.delete_typed(&format!("{}{}", DELTA_PREFIX, sym), &row);
-                self.insert_typed(sym, row.clone());
-            });
+        let program = self.sugared_program.clone();
+        match self.maintenance_strategy {
+            MaintenanceStrategy::DeleteRederive => {
+                stratified_delete_rederive(self, &program, &vec![], additions, retractions)
+            }
+            MaintenanceStrategy::Counting => {
+                stratified_counting(self, &program, &vec![], additions, retractions)
+            }
         }
+        .expect("the materialized program is stratifiable");
+        self.publish_changes();
     }
 
     fn triple_count(&self) -> usize {
@@ -397,16 +633,39 @@ impl<T: IndexBacking + PartialEq> Materializer for RelationalDatalog<T> {
             .map(|(_sym, rel)| return rel.ward.len())
             .sum();
     }
+}
+
+impl<T: IndexBacking + PartialEq> Exporter for RelationalDatalog<T> {
+    fn export(
+        &self,
+        writer: &mut dyn Write,
+        relation_filter: &dyn Fn(&str) -> bool,
+        format: ExportFormat,
+    ) -> std::io::Result<()> {
+        let rows = self
+            .fact_store
+            .storage
+            .iter()
+            .filter(|(table, _relation)| relation_filter(table))
+            .flat_map(|(table, relation)| {
+                relation.ward.iter().map(move |row| {
+                    let resolved_row = row
+                        .iter()
+                        .map(|typed_value| self.row_interner.resolve_value(typed_value.clone()))
+                        .collect();
+
+                    return (table.as_str(), resolved_row);
+                })
+            });
 
-    fn dump(&self) {
-        todo!()
+        return export_rows(writer, format, rows);
     }
 }
 
-impl<T: IndexBacking + PartialEq> Queryable for RelationalDatalog<T> {
-    fn contains_row(&self, table: &str, row: &UntypedRow) -> bool {
+impl<T: IndexBacking + PartialEq> CountingMaintainer for RelationalDatalog<T> {
+    fn contains_typed(&self, table: &str, row: &Row) -> bool {
         if let Some(relation) = self.fact_store.storage.get(table) {
-            let mut typed_row = Some(ty_to_row(row));
+            let mut typed_row = Some(row.clone());
             if self.intern {
                 typed_row = self.row_interner.try_intern_row(&typed_row.unwrap());
                 if typed_row == None {
@@ -418,20 +677,102 @@ impl<T: IndexBacking + PartialEq> Queryable for RelationalDatalog<T> {
 
         return false;
     }
+
+    fn derivation_counts(&mut self) -> &mut DerivationCounts {
+        return &mut self.derivation_counts;
+    }
+}
+
+impl<T: IndexBacking + PartialEq> Maintainer for RelationalDatalog<T> {
+    fn set_maintenance_strategy(&mut self, strategy: MaintenanceStrategy) {
+        self.maintenance_strategy = strategy;
+        self.derivation_counts = Default::default();
+        if strategy == MaintenanceStrategy::Counting {
+            initialize_derivation_counts(self, &self.sugared_program.clone())
+                .expect("the materialized program is stratifiable");
+        }
+    }
+
+    fn maintenance_strategy(&self) -> MaintenanceStrategy {
+        return self.maintenance_strategy;
+    }
+}
+
+impl<T: IndexBacking + PartialEq> Subscribable for RelationalDatalog<T> {
+    fn subscribe(&mut self, table: &str) -> ChangeFeed {
+        return self.subscriptions.subscribe(table);
+    }
+}
+
+impl<T: IndexBacking + PartialEq> Queryable for RelationalDatalog<T> {
+    fn contains_row(&self, table: &str, row: &UntypedRow) -> bool {
+        return self.contains_typed(table, &ty_to_row(row));
+    }
+}
+
+impl<T: IndexBacking + PartialEq> PatternQueryable for RelationalDatalog<T> {
+    fn query(&self, pattern: &Pattern) -> QueryResult<'_> {
+        return answer_pattern_query(&self.row_interner, self.intern, pattern, |symbol| {
+            let relation_id = self
+                .fact_store
+                .symbol_interner
+                .rodeo
+                .get(symbol)?
+                .into_inner();
+            let relation = self.fact_store.storage.get(symbol)?;
+
+            return Some((relation_id, &relation.ward));
+        });
+    }
 }
 
 impl<T: IndexBacking + PartialEq> RelationDropper for RelationalDatalog<T> {
     fn drop_relation(&mut self, table: &str) {
+        if let Some(provenance) = &mut self.provenance {
+            provenance.forget_relation(table);
+        }
         self.fact_store.storage.remove(table);
     }
 }
 
+impl<T: IndexBacking + PartialEq> Explainer for RelationalDatalog<T> {
+    fn record_provenance(&mut self, record: bool) {
+        if !record {
+            self.provenance = None;
+        } else if self.provenance.is_none() {
+            self.provenance = Some(Default::default());
+        }
+    }
+
+    fn explain(&self, table: &str, row: &UntypedRow) -> Option<DerivationTree> {
+        if !self.contains_row(table, row) {
+            return None;
+        }
+
+        let mut typed_row = ty_to_row(row);
+        if self.intern {
+            typed_row = self.row_interner.try_intern_row(&typed_row)?;
+        }
+        let empty_provenance = Provenance::default();
+        let provenance = self.provenance.as_ref().unwrap_or(&empty_provenance);
+
+        return Some(provenance.explain(table, &typed_row, &|typed_value| {
+            self.row_interner.resolve_value(typed_value)
+        }));
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::models::datalog::{SugaredRule, TypedValue};
+    use crate::misc::validation::ValidationError;
+    use crate::models::datalog::{Pattern, SugaredRule, Ty, TypedValue};
     use crate::models::index::BTreeIndex;
-    use crate::models::reasoner::{BottomUpEvaluator, Dynamic, Materializer, Queryable};
+    use crate::models::reasoner::{
+        Bindings, BottomUpEvaluator, Dynamic, Explainer, Maintainer, Materializer,
+        PatternQueryable, Queryable, Subscribable,
+    };
     use crate::models::relational_algebra::Row;
+    use crate::reasoning::algorithms::counting::MaintenanceStrategy;
     use crate::reasoning::reasoners::relational::RelationalDatalog;
     use indexmap::IndexSet;
 
@@ -475,9 +816,13 @@ mod tests {
 
         let new_tuples = reasoner
             .evaluate_program_bottom_up(&vec![
-                SugaredRule::from("reachable(?x, ?y) <- [edge(?x, ?y)]"),
-                SugaredRule::from("reachable(?x, ?z) <- [reachable(?x, ?y), reachable(?y, ?z)]"),
+                SugaredRule::try_from("reachable(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+                SugaredRule::try_from(
+                    "reachable(?x, ?z) <- [reachable(?x, ?y), reachable(?y, ?z)]",
+                )
+                .unwrap(),
             ])
+            .unwrap()
             .get("reachable")
             .unwrap()
             .clone();
@@ -515,4 +860,391 @@ mod tests {
 
         assert_eq!(expected_new_tuples, new_tuples)
     }
+
+    #[test]
+    fn test_relational_stratified_negation() {
+        let mut reasoner: RelationalDatalog<BTreeIndex> = RelationalDatalog::new(false, false);
+        reasoner.insert("node", vec![Box::new("a")]);
+        reasoner.insert("node", vec![Box::new("b")]);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+
+        reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+                SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap(),
+                SugaredRule::try_from(
+                    "unreachable(?x, ?y) <- [node(?x), node(?y), !reach(?x, ?y)]",
+                )
+                .unwrap(),
+            ])
+            .unwrap();
+
+        assert!(reasoner.contains_row("unreachable", &vec![Box::new("b"), Box::new("a")]));
+        assert!(!reasoner.contains_row("unreachable", &vec![Box::new("a"), Box::new("b")]));
+
+        reasoner.update(vec![(true, ("edge", vec![Box::new("b"), Box::new("a")]))]);
+
+        assert!(!reasoner.contains_row("unreachable", &vec![Box::new("b"), Box::new("a")]));
+        assert!(!reasoner.contains_row("unreachable", &vec![Box::new("a"), Box::new("a")]));
+
+        reasoner.update(vec![(false, ("edge", vec![Box::new("a"), Box::new("b")]))]);
+
+        assert!(reasoner.contains_row("unreachable", &vec![Box::new("a"), Box::new("b")]));
+        assert!(reasoner.contains_row("unreachable", &vec![Box::new("a"), Box::new("a")]));
+        assert!(!reasoner.contains_row("unreachable", &vec![Box::new("b"), Box::new("a")]));
+        assert_eq!(
+            Err(ValidationError::NotStratifiable),
+            reasoner.evaluate_program_bottom_up(&vec![SugaredRule::try_from(
+                "win(?x) <- [move(?x, ?y), !win(?y)]"
+            )
+            .unwrap()])
+        );
+    }
+
+    #[test]
+    fn test_relational_unmaterialize() {
+        let mut reasoner: RelationalDatalog<BTreeIndex> = RelationalDatalog::new(false, true);
+        reasoner.set_maintenance_strategy(MaintenanceStrategy::Counting);
+        vec![("a", "b"), ("b", "c"), ("c", "d")]
+            .into_iter()
+            .for_each(|(source, destination)| {
+                reasoner.insert("edge", vec![Box::new(source), Box::new(destination)])
+            });
+        reasoner.insert("shortcut", vec![Box::new("a"), Box::new("c")]);
+
+        let recursive_rule =
+            SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]").unwrap();
+        let shortcut_rule = SugaredRule::try_from("reach(?x, ?y) <- [shortcut(?x, ?y)]").unwrap();
+        reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap(),
+                recursive_rule.clone(),
+                shortcut_rule.clone(),
+            ])
+            .unwrap();
+
+        assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("d")]));
+
+        reasoner.unmaterialize(&vec![recursive_rule]).unwrap();
+
+        assert!(!reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("d")]));
+        assert!(!reasoner.contains_row("reach", &vec![Box::new("b"), Box::new("d")]));
+        assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("c")]));
+        assert!(reasoner.contains_row("reach", &vec![Box::new("c"), Box::new("d")]));
+
+        reasoner.unmaterialize(&vec![shortcut_rule]).unwrap();
+
+        assert!(!reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("c")]));
+        assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("b")]));
+
+        reasoner.update(vec![(true, ("edge", vec![Box::new("d"), Box::new("e")]))]);
+
+        assert!(reasoner.contains_row("reach", &vec![Box::new("d"), Box::new("e")]));
+        assert!(!reasoner.contains_row("reach", &vec![Box::new("c"), Box::new("e")]));
+    }
+
+    #[test]
+    fn test_relational_unmaterialize_keeps_explicit_facts() {
+        vec![
+            MaintenanceStrategy::DeleteRederive,
+            MaintenanceStrategy::Counting,
+        ]
+        .into_iter()
+        .for_each(|strategy| {
+            let mut reasoner: RelationalDatalog<BTreeIndex> = RelationalDatalog::new(false, true);
+            reasoner.set_maintenance_strategy(strategy);
+            reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+            reasoner.insert("edge", vec![Box::new("b"), Box::new("c")]);
+            reasoner.insert("reach", vec![Box::new("a"), Box::new("b")]);
+
+            let base_rule = SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y)]").unwrap();
+            reasoner
+                .materialize(&vec![
+                    base_rule.clone(),
+                    SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z)]")
+                        .unwrap(),
+                ])
+                .unwrap();
+
+            let hop_rule = SugaredRule::try_from("reach(?x, ?y) <- [hop(?x, ?y)]").unwrap();
+            assert_eq!(
+                Err(ValidationError::UnknownRule {
+                    rule: hop_rule.to_string()
+                }),
+                reasoner.unmaterialize(&vec![hop_rule])
+            );
+
+            reasoner.unmaterialize(&vec![base_rule]).unwrap();
+
+            assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("b")]));
+            assert!(reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("c")]));
+            assert!(!reasoner.contains_row("reach", &vec![Box::new("b"), Box::new("c")]));
+
+            reasoner.update(vec![(false, ("reach", vec![Box::new("a"), Box::new("b")]))]);
+
+            assert!(!reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("b")]));
+            assert!(!reasoner.contains_row("reach", &vec![Box::new("a"), Box::new("c")]));
+        });
+    }
+
+    #[test]
+    fn test_relational_subscribe() {
+        let mut reasoner: RelationalDatalog<BTreeIndex> = RelationalDatalog::new(false, false);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+        reasoner
+            .materialize(&vec![SugaredRule::try_from(
+                "reach(?x, ?z) <- [edge(?x, ?y), edge(?y, ?z)]",
+            )
+            .unwrap()])
+            .unwrap();
+
+        let reach_feed = reasoner.subscribe("reach");
+        let unrelated_feed = reasoner.subscribe("unrelated");
+        reasoner.update(vec![(true, ("edge", vec![Box::new("b"), Box::new("c")]))]);
+
+        let expected_changes: Vec<(bool, Row)> = vec![(
+            true,
+            vec![
+                TypedValue::Str("a".to_string()),
+                TypedValue::Str("c".to_string()),
+            ]
+            .into_boxed_slice(),
+        )];
+        assert_eq!(expected_changes, reach_feed.try_iter().collect::<Vec<_>>());
+        assert_eq!(0, unrelated_feed.try_iter().count());
+
+        reasoner.update(vec![(false, ("edge", vec![Box::new("a"), Box::new("b")]))]);
+
+        assert!(!reach_feed.try_recv().unwrap().0);
+    }
+
+    #[test]
+    fn test_relational_pattern_query() {
+        let mut reasoner: RelationalDatalog<BTreeIndex> = RelationalDatalog::new(false, true);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b")]);
+        reasoner.insert("edge", vec![Box::new("b"), Box::new("c")]);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("c")]);
+
+        let answers: Vec<Bindings> = reasoner
+            .query(&Pattern::try_from("[edge(a, ?y), edge(?y, c)]").unwrap())
+            .collect();
+
+        assert_eq!(
+            vec![Bindings::from_iter([(
+                "?y".to_string(),
+                TypedValue::Str("b".to_string())
+            )])],
+            answers
+        );
+    }
+
+    #[test]
+    fn test_relational_builtins() {
+        let mut reasoner: RelationalDatalog<BTreeIndex> = RelationalDatalog::new(false, false);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b"), Box::new(3u32)]);
+        reasoner.insert("edge", vec![Box::new("b"), Box::new("c"), Box::new(4u32)]);
+        reasoner.insert("edge", vec![Box::new("c"), Box::new("d"), Box::new(5u32)]);
+        reasoner.insert("person", vec![Box::new("alice")]);
+        reasoner.insert("person", vec![Box::new("albert")]);
+        reasoner.insert("person", vec![Box::new("bob")]);
+
+        reasoner
+            .materialize(&vec![
+            SugaredRule::try_from("dist(?x, ?y, ?w) <- [edge(?x, ?y, ?w)]").unwrap(),
+            SugaredRule::try_from(
+                "dist(?x, ?z, ?d) <- [dist(?x, ?y, ?v), edge(?y, ?z, ?w), ?d = ?v + ?w, ?d < 10]",
+            )
+            .unwrap(),
+            SugaredRule::try_from("al(?x) <- [person(?x), starts_with(?x, al), ?x != albert]")
+                .unwrap(),
+        ])
+            .unwrap();
+
+        assert!(reasoner.contains_row("dist", &vec![Box::new("a"), Box::new("c"), Box::new(7u32)]));
+        assert!(reasoner.contains_row("dist", &vec![Box::new("b"), Box::new("d"), Box::new(9u32)]));
+        assert!(
+            !reasoner.contains_row("dist", &vec![Box::new("a"), Box::new("d"), Box::new(12u32)])
+        );
+        assert!(reasoner.contains_row("al", &vec![Box::new("alice")]));
+        assert!(!reasoner.contains_row("al", &vec![Box::new("albert")]));
+
+        reasoner.update(vec![(
+            true,
+            ("edge", vec![Box::new("d"), Box::new("e"), Box::new(1u32)]),
+        )]);
+
+        assert!(reasoner.contains_row("dist", &vec![Box::new("c"), Box::new("e"), Box::new(6u32)]));
+        assert!(
+            !reasoner.contains_row("dist", &vec![Box::new("b"), Box::new("e"), Box::new(10u32)])
+        );
+    }
+
+    #[test]
+    fn test_relational_interned_builtins() {
+        let mut reasoner = RelationalDatalog::<BTreeIndex>::new(false, true);
+        reasoner.insert("person", vec![Box::new("alice")]);
+        reasoner.insert("person", vec![Box::new("albert")]);
+        reasoner.insert("person", vec![Box::new("bob")]);
+        reasoner.insert("size", vec![Box::new("alice"), Box::new(3u32)]);
+
+        assert_eq!(
+            Err(ValidationError::Unsupported {
+                rule: "al(?0) <- [person(?0), starts_with(?0, al)]".to_string(),
+                feature: "ordering or matching interned strings"
+            }),
+            reasoner.materialize(&vec![SugaredRule::try_from(
+                "al(?x) <- [person(?x), starts_with(?x, al)]"
+            )
+            .unwrap()])
+        );
+        reasoner
+            .materialize(&vec![SugaredRule::try_from(
+                "small(?x) <- [size(?x, ?s), ?s < 10]",
+            )
+            .unwrap()])
+            .unwrap();
+        assert!(reasoner.contains_row("small", &vec![Box::new("alice")]));
+
+        // Queries resolve interned strings before ordering or matching them
+        let answers: Vec<Bindings> = reasoner
+            .query(&Pattern::try_from("[person(?x), ?x < b, ends_with(?x, ce)]").unwrap())
+            .collect();
+        assert_eq!(
+            vec![Bindings::from_iter([(
+                "?x".to_string(),
+                TypedValue::Str("alice".to_string())
+            )])],
+            answers
+        );
+    }
+
+    #[test]
+    fn test_relational_aggregates() {
+        let mut reasoner: RelationalDatalog<BTreeIndex> = RelationalDatalog::new(false, false);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b"), Box::new(3u32)]);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("c"), Box::new(4u32)]);
+        reasoner.insert("edge", vec![Box::new("b"), Box::new("c"), Box::new(5u32)]);
+
+        reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("deg(?x, count(?y)) <- [edge(?x, ?y, ?w)]").unwrap(),
+                SugaredRule::try_from(
+                    "weight(?x, sum(?w), min(?w), max(?w)) <- [edge(?x, ?y, ?w)]",
+                )
+                .unwrap(),
+                SugaredRule::try_from("hub(?x) <- [deg(?x, ?d), ?d > 1]").unwrap(),
+            ])
+            .unwrap();
+
+        assert!(reasoner.contains_row("deg", &vec![Box::new("a"), Box::new(2u32)]));
+        assert!(reasoner.contains_row("deg", &vec![Box::new("b"), Box::new(1u32)]));
+        assert!(reasoner.contains_row(
+            "weight",
+            &vec![
+                Box::new("a"),
+                Box::new(7u32),
+                Box::new(3u32),
+                Box::new(4u32)
+            ]
+        ));
+        assert!(reasoner.contains_row("hub", &vec![Box::new("a")]));
+        assert!(!reasoner.contains_row("hub", &vec![Box::new("b")]));
+
+        reasoner.update(vec![
+            (
+                true,
+                ("edge", vec![Box::new("b"), Box::new("d"), Box::new(1u32)]),
+            ),
+            (
+                false,
+                ("edge", vec![Box::new("a"), Box::new("c"), Box::new(4u32)]),
+            ),
+        ]);
+
+        assert!(reasoner.contains_row("deg", &vec![Box::new("a"), Box::new(1u32)]));
+        assert!(!reasoner.contains_row("deg", &vec![Box::new("a"), Box::new(2u32)]));
+        assert!(reasoner.contains_row("deg", &vec![Box::new("b"), Box::new(2u32)]));
+        assert!(!reasoner.contains_row("deg", &vec![Box::new("b"), Box::new(1u32)]));
+        assert!(reasoner.contains_row(
+            "weight",
+            &vec![
+                Box::new("b"),
+                Box::new(6u32),
+                Box::new(1u32),
+                Box::new(5u32)
+            ]
+        ));
+        assert!(!reasoner.contains_row("hub", &vec![Box::new("a")]));
+        assert!(reasoner.contains_row("hub", &vec![Box::new("b")]));
+    }
+
+    #[test]
+    fn test_relational_many_variables() {
+        let mut reasoner: RelationalDatalog<BTreeIndex> = RelationalDatalog::new(false, false);
+        let variables: Vec<String> = (0..300).map(|idx| format!("?v{}", idx)).collect();
+        reasoner.insert(
+            "wide",
+            (0..300u32)
+                .map(|idx| Box::new(idx) as Box<dyn Ty>)
+                .collect(),
+        );
+
+        reasoner
+            .materialize(&vec![SugaredRule::try_from(&*format!(
+                "ends(?v0, ?v299) <- [wide({})]",
+                variables.join(", ")
+            ))
+            .unwrap()])
+            .unwrap();
+
+        assert!(reasoner.contains_row("ends", &vec![Box::new(0u32), Box::new(299u32)]));
+    }
+
+    #[test]
+    fn test_relational_explain() {
+        let mut reasoner: RelationalDatalog<BTreeIndex> = RelationalDatalog::new(false, true);
+        reasoner.record_provenance(true);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("b"), Box::new(3u32)]);
+        reasoner.insert("edge", vec![Box::new("a"), Box::new("c"), Box::new(4u32)]);
+        reasoner.insert("edge", vec![Box::new("b"), Box::new("c"), Box::new(5u32)]);
+
+        reasoner
+            .materialize(&vec![
+                SugaredRule::try_from("reach(?x, ?y) <- [edge(?x, ?y, ?w)]").unwrap(),
+                SugaredRule::try_from("reach(?x, ?z) <- [reach(?x, ?y), edge(?y, ?z, ?w)]")
+                    .unwrap(),
+                SugaredRule::try_from("deg(?x, count(?y)) <- [edge(?x, ?y, ?w), ?w > 3]").unwrap(),
+            ])
+            .unwrap();
+
+        let expected_explanation =
+            "reach(b, c) by reach(?0, ?1) <- [edge(?0, ?1, ?2)]\n  edge(b, c, 5)\n";
+        assert_eq!(
+            expected_explanation,
+            reasoner
+                .explain("reach", &vec![Box::new("b"), Box::new("c")])
+                .unwrap()
+                .to_string()
+        );
+
+        let explanation = reasoner
+            .explain("deg", &vec![Box::new("a"), Box::new(1u32)])
+            .unwrap();
+        assert_eq!(
+            "deg(?0, count(?1)) <- [edge(?0, ?1, ?2), ?2 > 3]",
+            explanation.rule.unwrap().to_string()
+        );
+        assert_eq!(
+            vec!["edge(a, c, 4)"],
+            explanation
+                .premises
+                .iter()
+                .map(|premise| premise.fact.to_string())
+                .collect::<Vec<_>>()
+        );
+
+        assert!(reasoner
+            .explain("reach", &vec![Box::new("c"), Box::new("a")])
+            .is_none());
+    }
 }
@@ -0,0 +1,129 @@
+// Rule sets over T(subject, predicate, object) triples, whose rdf, rdfs and owl constants are
+// abbreviated the way that the RDF parsers abbreviate them, such that they run on their data as is.
+
+use crate::models::datalog::SugaredProgram;
+use crate::parsers::datalog::parse_program;
+
+pub static RDFS: &str = include_str!("../../data/rdfs.dl");
+
+// OWL 2 RL, but for the rules that only detect inconsistencies, the datatype rules, the axiomatic
+// triples and eq-ref. The list-based rules, such as those of owl:intersectionOf and
+// owl:propertyChainAxiom, walk rdf:first and rdf:rest with auxiliary relations.
+pub static OWL2_RL: &str = include_str!("../../data/owl2rl.dl");
+
+fn parse_rule_set(document: &str) -> SugaredProgram {
+    let (program, _, _) = parse_program(document).unwrap();
+
+    return program;
+}
+
+pub fn rdfs() -> SugaredProgram {
+    return parse_rule_set(RDFS);
+}
+
+pub fn owl2_rl() -> SugaredProgram {
+    return parse_rule_set(OWL2_RL);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::index::BTreeIndex;
+    use crate::models::reasoner::{Materializer, Queryable};
+    use crate::parsers::loader::{load_facts, TurtleParser};
+    use crate::parsers::rdf::Prefixes;
+    use crate::reasoning::reasoners::chibi::ChibiDatalog;
+    use crate::reasoning::reasoners::relational::RelationalDatalog;
+    use crate::reasoning::rule_sets::{owl2_rl, rdfs};
+
+    const ONTOLOGY: &str = r#"
+        @prefix ex: <http://a.org/> .
+        @prefix owl: <http://www.w3.org/2002/07/owl#> .
+        @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+
+        ex:hasUncle owl:propertyChainAxiom ( ex:hasParent ex:hasBrother ) .
+        ex:hasChild owl:inverseOf ex:hasParent .
+        ex:Mother owl:intersectionOf ( ex:Woman ex:Parent ) .
+        ex:Human owl:unionOf ( ex:Woman ex:Man ) .
+        ex:ancestorOf a owl:TransitiveProperty .
+        ex:hasMother a owl:FunctionalProperty .
+        ex:Person owl:hasKey ( ex:ssn ) .
+
+        ex:alice a ex:Woman, ex:Parent ; ex:hasParent ex:bob .
+        ex:bob ex:hasBrother ex:carl .
+        ex:frank a ex:Man .
+        ex:a1 ex:ancestorOf ex:a2 .
+        ex:a2 ex:ancestorOf ex:a3 .
+        ex:dan ex:hasMother ex:eve, ex:eva .
+        ex:eve ex:age 70 .
+        ex:p1 a ex:Person ; ex:ssn 1 .
+        ex:p2 a ex:Person ; ex:ssn 1 .
+    "#;
+
+    fn assert_owl2_rl_entailments(reasoner: &mut (impl Materializer + Queryable)) {
+        reasoner.materialize(&owl2_rl()).unwrap();
+        let mut prefixes = Prefixes::default();
+        prefixes.insert("ex", "http://a.org/");
+        load_facts(
+            ONTOLOGY.as_bytes(),
+            &mut TurtleParser::new(prefixes),
+            reasoner,
+            0,
+        )
+        .unwrap();
+
+        vec![
+            ("ex:alice", "ex:hasUncle", "ex:carl"),
+            ("ex:bob", "ex:hasChild", "ex:alice"),
+            ("ex:alice", "rdf:type", "ex:Mother"),
+            ("ex:Mother", "rdfs:subClassOf", "ex:Woman"),
+            ("ex:frank", "rdf:type", "ex:Human"),
+            ("ex:a1", "ex:ancestorOf", "ex:a3"),
+            ("ex:eve", "owl:sameAs", "ex:eva"),
+            ("ex:p2", "owl:sameAs", "ex:p1"),
+        ]
+        .into_iter()
+        .for_each(|(subject, predicate, object)| {
+            assert!(
+                reasoner.contains_row(
+                    "T",
+                    &vec![Box::new(subject), Box::new(predicate), Box::new(object)]
+                ),
+                "{} {} {}",
+                subject,
+                predicate,
+                object
+            )
+        });
+        assert!(reasoner.contains_row(
+            "T",
+            &vec![Box::new("ex:eva"), Box::new("ex:age"), Box::new(70u32)]
+        ));
+        assert!(!reasoner.contains_row(
+            "T",
+            &vec![
+                Box::new("ex:bob"),
+                Box::new("rdf:type"),
+                Box::new("ex:Mother")
+            ]
+        ));
+        assert!(!reasoner.contains_row(
+            "T",
+            &vec![
+                Box::new("ex:p1"),
+                Box::new("owl:sameAs"),
+                Box::new("ex:alice")
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_owl2_rl() {
+        assert_owl2_rl_entailments(&mut ChibiDatalog::new(false, true, true));
+        assert_owl2_rl_entailments(&mut RelationalDatalog::<BTreeIndex>::new(false, true));
+    }
+
+    #[test]
+    fn test_rdfs() {
+        assert_eq!(6, rdfs().len());
+    }
+}